@@ -5,6 +5,7 @@ use dialoguer::{Input, Select};
 use structopt::StructOpt;
 
 use crate::market::{Preset, PresetManager};
+use crate::payments::PRICING_MODELS;
 use crate::startup_config::{PresetNoInteractive, ProviderConfig, UpdateNames};
 
 #[derive(StructOpt, Clone, Debug)]
@@ -126,6 +127,15 @@ impl PresetUpdater {
             .default(prev_pricing)
             .interact()?;
         self.preset.pricing_model = self.pricing_models[pricing_idx].clone();
+
+        if self.preset.pricing_model == "capped" {
+            let max_cost = Input::<f64>::new()
+                .with_prompt("Max cost of an Agreement (GLM)")
+                .default(self.preset.max_cost.unwrap_or(0.))
+                .show_default(true)
+                .interact()?;
+            self.preset.max_cost = Some(max_cost);
+        }
         Ok(())
     }
 
@@ -184,7 +194,7 @@ pub fn create_interactive(config: ProviderConfig) -> anyhow::Result<()> {
     let registry = config.registry()?;
 
     let exeunits = registry.list().into_iter().map(|desc| desc.name).collect();
-    let pricing_models = vec!["linear".to_string()];
+    let pricing_models = PRICING_MODELS.iter().map(|m| m.to_string()).collect();
 
     let preset =
         PresetUpdater::new(Preset::default(), exeunits, pricing_models).interact(&config)?;
@@ -233,6 +243,15 @@ pub fn create(config: ProviderConfig, params: PresetNoInteractive) -> anyhow::Re
             preset.usage_coeffs.insert(usage_coefficient, *price);
         }
     }
+    for (name, tier) in params.price_tier.iter() {
+        let usage_coefficient = exe_unit_desc.resolve_coefficient(name)?;
+        preset
+            .usage_tiers
+            .entry(usage_coefficient)
+            .or_default()
+            .push(tier.clone());
+    }
+    preset.max_cost = params.max_cost;
 
     validate_preset(&config, &preset)?;
 
@@ -326,6 +345,19 @@ fn update_presets(
                         .insert(exe_unit_desc.resolve_coefficient(name)?, *price);
                 }
             }
+            if !params.price_tier.is_empty() {
+                preset.usage_tiers.clear();
+            }
+            for (name, tier) in params.price_tier.iter() {
+                preset
+                    .usage_tiers
+                    .entry(exe_unit_desc.resolve_coefficient(name)?)
+                    .or_default()
+                    .push(tier.clone());
+            }
+            if params.max_cost.is_some() {
+                preset.max_cost = params.max_cost;
+            }
 
             validate_preset(config, preset)?;
 
@@ -351,9 +383,12 @@ fn validate_preset(config: &ProviderConfig, preset: &Preset) -> anyhow::Result<(
     let registry = config.registry()?;
     registry.find_exeunit(&preset.exeunit_name)?;
 
-    if preset.pricing_model != "linear" {
+    if !PRICING_MODELS.contains(&preset.pricing_model.as_str()) {
         bail!("Not supported pricing model.")
     }
+    if preset.pricing_model == "capped" && preset.max_cost.is_none() {
+        bail!("Max cost is required for capped pricing model.")
+    }
 
     Ok(())
 }
@@ -367,7 +402,7 @@ fn update_preset_interactive(config: ProviderConfig, name: String) -> anyhow::Re
     let registry = config.registry()?;
 
     let exeunits = registry.list().into_iter().map(|desc| desc.name).collect();
-    let pricing_models = vec!["linear".to_string()];
+    let pricing_models = PRICING_MODELS.iter().map(|m| m.to_string()).collect();
 
     let preset =
         PresetUpdater::new(presets.get(&name)?, exeunits, pricing_models).interact(&config)?;
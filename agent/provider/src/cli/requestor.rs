@@ -0,0 +1,71 @@
+use structopt::StructOpt;
+
+use ya_client::model::NodeId;
+use ya_utils_cli::{CommandOutput, ResponseTable};
+
+use crate::cli::println_conditional;
+use crate::reputation::Reputation;
+use crate::startup_config::ProviderConfig;
+
+#[derive(StructOpt, Clone, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub enum RequestorConfig {
+    /// List Requestors with their reputation score
+    List,
+    /// Reject all Proposals from Requestor regardless of the score
+    Ban { node_id: NodeId },
+    /// Remove ban and evaluate Requestor by the score again
+    Unban { node_id: NodeId },
+}
+
+impl RequestorConfig {
+    pub fn run(self, config: ProviderConfig) -> anyhow::Result<()> {
+        match self {
+            RequestorConfig::List => list(config),
+            RequestorConfig::Ban { node_id } => set_banned(config, node_id, true),
+            RequestorConfig::Unban { node_id } => set_banned(config, node_id, false),
+        }
+    }
+}
+
+fn list(config: ProviderConfig) -> anyhow::Result<()> {
+    let reputation = Reputation::load_or_create(&config.reputation_file)?;
+    let columns = vec![
+        "Node ID".to_string(),
+        "Score".to_string(),
+        "Banned".to_string(),
+        "Closed".to_string(),
+        "Broken".to_string(),
+        "Paid on time".to_string(),
+        "Paid late".to_string(),
+    ];
+    let values = reputation
+        .list()
+        .map(|(node_id, record)| {
+            serde_json::json! {[
+                node_id,
+                record.score,
+                record.banned,
+                record.agreements_closed,
+                record.agreements_broken,
+                record.payments_on_time,
+                record.payments_late,
+            ]}
+        })
+        .collect();
+
+    CommandOutput::from(ResponseTable { columns, values }).print(config.json)?;
+    Ok(())
+}
+
+fn set_banned(config: ProviderConfig, node_id: NodeId, banned: bool) -> anyhow::Result<()> {
+    let mut reputation = Reputation::load_or_create(&config.reputation_file)?;
+    reputation.set_banned(&node_id, banned);
+    reputation.save(&config.reputation_file)?;
+
+    match banned {
+        true => println_conditional(&config, &format!("Requestor [{}] banned.", node_id)),
+        false => println_conditional(&config, &format!("Requestor [{}] unbanned.", node_id)),
+    }
+    Ok(())
+}
@@ -151,6 +151,8 @@ impl From<PresetV0> for Preset {
                     _ => None,
                 })
                 .collect(),
+            usage_tiers: Default::default(),
+            max_cost: None,
         }
     }
 }
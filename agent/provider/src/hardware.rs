@@ -126,6 +126,15 @@ impl Resources {
         })
     }
 
+    /// Resources not used by any process at the moment.
+    fn available<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Resources {
+            cpu_threads: num_cpus::get() as i32,
+            mem_gib: 1000. * sys_info::mem_info()?.avail as f64 / (1024. * 1024. * 1024.),
+            storage_gib: partition_space(path)? as f64 / (1024. * 1024. * 1024.),
+        })
+    }
+
     fn default_caps<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let res = Self::max_caps(path)?;
         Ok(Resources {
@@ -336,6 +345,21 @@ struct ManagerState {
     res_alloc: HashMap<String, Resources>,
 }
 
+impl Default for ManagerState {
+    fn default() -> Self {
+        ManagerState {
+            profiles: Profiles {
+                active: DEFAULT_PROFILE_NAME.to_string(),
+                profiles: HashMap::new(),
+            },
+            res_available: Resources::new_empty(),
+            res_cap: Resources::new_empty(),
+            res_remaining: Resources::new_empty(),
+            res_alloc: HashMap::new(),
+        }
+    }
+}
+
 impl ManagerState {
     #[inline]
     fn update(&mut self, profiles: Profiles) -> Result<bool, Error> {
@@ -415,6 +439,13 @@ impl Manager {
     pub fn event_receiver(&self) -> watch::Receiver<Event> {
         self.receiver.clone()
     }
+
+    #[inline]
+    pub fn allocation(&self) -> Allocation {
+        Allocation {
+            state: self.state.clone(),
+        }
+    }
 }
 
 impl Manager {
@@ -449,6 +480,46 @@ impl Manager {
     }
 }
 
+/// Read-only view of resources allocated by `Manager`, which can be shared
+/// with components living outside of the `ProviderAgent`.
+/// Default instance has no resources and reports no load.
+#[derive(Clone, Debug, Default)]
+pub struct Allocation {
+    state: Arc<Mutex<ManagerState>>,
+}
+
+impl Allocation {
+    /// Fraction of capped resources in use, in range [0, 1].
+    /// The most utilized resource determines the load.
+    pub fn load(&self) -> f64 {
+        let state = self.state.lock().unwrap();
+        let cap = state.res_cap;
+        let remaining = state.res_remaining;
+        used_fraction(cap.cpu_threads as f64, remaining.cpu_threads as f64)
+            .max(used_fraction(cap.mem_gib, remaining.mem_gib))
+            .max(used_fraction(cap.storage_gib, remaining.storage_gib))
+    }
+
+    /// Resources, which are neither allocated nor used by other processes.
+    /// Storage is measured on the partition containing `path`.
+    pub fn free<P: AsRef<Path>>(&self, path: P) -> Result<Resources, Error> {
+        let remaining = self.state.lock().unwrap().res_remaining;
+        let available = Resources::available(path)?;
+        Ok(Resources {
+            cpu_threads: remaining.cpu_threads.min(available.cpu_threads).max(0),
+            mem_gib: remaining.mem_gib.min(available.mem_gib).max(0.),
+            storage_gib: remaining.storage_gib.min(available.storage_gib).max(0.),
+        })
+    }
+}
+
+fn used_fraction(cap: f64, remaining: f64) -> f64 {
+    if cap <= 0. {
+        return 0.;
+    }
+    ((cap - remaining) / cap).clamp(0., 1.)
+}
+
 #[cfg(windows)]
 fn to_wstring(value: impl AsRef<OsStr>) -> Vec<u16> {
     use std::os::windows::ffi::OsStrExt;
@@ -654,4 +725,33 @@ mod tests {
             )
             .is_err());
     }
+
+    #[test]
+    fn free_limited_by_remaining() {
+        let res = Resources {
+            cpu_threads: 1,
+            mem_gib: 0.001,
+            storage_gib: 0.001,
+        };
+        let state = ManagerState {
+            res_available: res,
+            res_cap: res,
+            res_remaining: res,
+            res_alloc: HashMap::new(),
+            profiles: profiles(),
+        };
+        let allocation = Allocation {
+            state: Arc::new(Mutex::new(state)),
+        };
+
+        let free = allocation.free(std::env::temp_dir()).unwrap();
+        assert_eq!(free.cpu_threads, 1);
+        assert!(free.mem_gib <= res.mem_gib);
+        assert!(free.storage_gib <= res.storage_gib);
+
+        let free = Allocation::default().free(std::env::temp_dir()).unwrap();
+        assert_eq!(free.cpu_threads, 0);
+        assert_eq!(free.mem_gib, 0.);
+        assert_eq!(free.storage_gib, 0.);
+    }
 }
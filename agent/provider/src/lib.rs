@@ -9,6 +9,7 @@ mod interval;
 pub mod market;
 pub mod payments;
 pub mod provider_agent;
+pub mod reputation;
 pub mod rules;
 pub mod signal;
 pub mod startup_config;
@@ -27,6 +27,8 @@ async fn main() -> anyhow::Result<()> {
     config.presets_file = data_dir.join(config.presets_file);
     config.hardware_file = data_dir.join(config.hardware_file);
     config.rules_file = data_dir.join(config.rules_file);
+    config.reputation_file = data_dir.join(config.reputation_file);
+    config.paid_prices_file = data_dir.join(config.paid_prices_file);
 
     match cli_args.commands {
         Commands::Run(args) => {
@@ -51,5 +53,6 @@ async fn main() -> anyhow::Result<()> {
         Commands::Whitelist(whitelist_cmd) => whitelist_cmd.run(config),
         Commands::Clean(clean_cmd) => clean_cmd.run(config),
         Commands::Rule(outbound_cmd) => outbound_cmd.run(config),
+        Commands::Requestor(requestor_cmd) => requestor_cmd.run(config),
     }
 }
@@ -0,0 +1,84 @@
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ya_client::model::market::MARKET_API_PATH;
+use ya_client::web::{WebClient, WebInterface};
+use ya_market_resolver::dynamic::{
+    FREE_CPU_THREADS_PROPERTY, FREE_MEM_PROPERTY, FREE_STORAGE_PROPERTY,
+};
+
+use crate::hardware::Allocation;
+
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Properties declared as value-less in every Offer. Market resolves them
+/// at the time of matching, using values published by `FreeCapacity`.
+pub const FREE_CAPACITY_PROPERTIES: [&str; 3] = [
+    FREE_CPU_THREADS_PROPERTY,
+    FREE_MEM_PROPERTY,
+    FREE_STORAGE_PROPERTY,
+];
+
+/// Market REST endpoint setting values of local dynamic properties.
+#[derive(Clone)]
+pub struct DynamicPropertiesApi {
+    client: WebClient,
+}
+
+impl WebInterface for DynamicPropertiesApi {
+    const API_URL_ENV_VAR: &'static str = "YAGNA_MARKET_URL";
+    const API_SUFFIX: &'static str = MARKET_API_PATH;
+
+    fn from_client(client: WebClient) -> Self {
+        DynamicPropertiesApi { client }
+    }
+}
+
+impl DynamicPropertiesApi {
+    pub async fn set(&self, name: &str, value: Option<Value>) -> Result<(), ya_client::Error> {
+        let url = format!("dynamic-properties/{}", name);
+        self.client.put(&url).send_json(&value).json().await
+    }
+}
+
+/// Periodically publishes free capacity of the node to the Market.
+pub struct FreeCapacity {
+    api: DynamicPropertiesApi,
+    allocation: Allocation,
+    path: PathBuf,
+}
+
+impl FreeCapacity {
+    /// Free storage is measured on the partition containing `path`.
+    pub fn new(api: DynamicPropertiesApi, allocation: Allocation, path: PathBuf) -> Self {
+        FreeCapacity {
+            api,
+            allocation,
+            path,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(PUBLISH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.publish().await {
+                log::debug!("Failed to publish free capacity to the Market: {}", e);
+            }
+        }
+    }
+
+    async fn publish(&self) -> anyhow::Result<()> {
+        let free = self.allocation.free(&self.path)?;
+        let values = [
+            (FREE_CPU_THREADS_PROPERTY, Value::from(free.cpu_threads)),
+            (FREE_MEM_PROPERTY, Value::from(free.mem_gib)),
+            (FREE_STORAGE_PROPERTY, Value::from(free.storage_gib)),
+        ];
+        for (name, value) in values {
+            self.api.set(name, Some(value)).await?;
+        }
+        Ok(())
+    }
+}
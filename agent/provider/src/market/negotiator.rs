@@ -3,6 +3,7 @@ pub mod builtin;
 mod common;
 mod component;
 mod composite;
+pub mod external;
 pub mod factory;
 
 pub use accept_all::AcceptAllNegotiator;
@@ -1,14 +1,20 @@
 pub mod demand_validation;
+pub mod dynamic_price;
 pub mod expiration;
 pub mod manifest;
 pub mod max_agreements;
 pub mod note_interval;
+pub mod overdue_invoices;
 pub mod payment_timeout;
 pub mod price;
+pub mod reputation;
 
+pub use dynamic_price::DynamicPrice;
 pub use expiration::LimitExpiration;
 pub use manifest::ManifestSignature;
 pub use max_agreements::MaxAgreements;
 pub use note_interval::DebitNoteInterval;
+pub use overdue_invoices::OverdueInvoices;
 pub use payment_timeout::PaymentTimeout;
 pub use price::PriceNego;
+pub use reputation::RequestorReputation;
@@ -0,0 +1,532 @@
+use anyhow::{anyhow, bail};
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fs, io};
+
+use ya_agreement_utils::agreement::expand;
+use ya_agreement_utils::template::patch;
+use ya_agreement_utils::OfferDefinition;
+use ya_client_model::market::proposal::State;
+use ya_client_model::NodeId;
+use ya_utils_path::SwapSave;
+
+use super::price::price_property;
+use crate::hardware;
+use crate::market::negotiator::factory::DynamicPriceNegotiatorConfig;
+use crate::market::negotiator::{
+    AgreementResult, NegotiationResult, NegotiatorComponent, ProposalView,
+};
+
+pub(crate) const PAID_PRICES_JSON: &str = "paid-prices.json";
+
+static USAGE_VECTOR_PROPERTY: &str = "/golem/com/usage/vector";
+/// Number of counter-proposals sent in the negotiation. Stored in our own
+/// Proposals, so it follows the Proposal chain and Requestor can't alter it.
+static PRICE_ROUND_PROPERTY: &str = "golem.com.pricing.round";
+static PRICE_ROUND_POINTER: &str = "/golem/com/pricing/round";
+
+/// Name of the constant (last) price coefficient in `PriceFloor`.
+pub static INITIAL_PRICE_COEFF: &str = "initial";
+
+/// Minimal price for single usage coefficient in form `coefficient=price`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriceFloor {
+    pub coeff: String,
+    pub price: f64,
+}
+
+impl FromStr for PriceFloor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (coeff, price) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected `coefficient=price`, got: {}", s))?;
+        Ok(PriceFloor {
+            coeff: coeff.trim().to_string(),
+            price: price.trim().parse()?,
+        })
+    }
+}
+
+/// Range of hours in form `start-end`. End is exclusive and range can wrap
+/// around midnight, for example `22-6`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HourRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl HourRange {
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            self.start <= hour && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+impl FromStr for HourRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Expected `start-end` hours, got: {}", s))?;
+        let range = HourRange {
+            start: start.trim().parse()?,
+            end: end.trim().parse()?,
+        };
+        if range.start > 24 || range.end > 24 {
+            bail!("Hours out of range: {}", s);
+        }
+        Ok(range)
+    }
+}
+
+/// Prices paid by Requestors in successfully finished Agreements.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PaidPrices {
+    requestors: BTreeMap<String, Vec<f64>>,
+}
+
+impl PaidPrices {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if path.exists() {
+            log::debug!("Loading prices paid by Requestors from: {}", path.display());
+            Ok(serde_json::from_reader(io::BufReader::new(
+                fs::OpenOptions::new().read(true).open(path)?,
+            ))?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(path.swap_save(serde_json::to_string_pretty(self)?)?)
+    }
+
+    pub fn get(&self, node_id: &NodeId) -> Option<&Vec<f64>> {
+        self.requestors.get(&node_id.to_string())
+    }
+
+    pub fn insert(&mut self, node_id: &NodeId, prices: Vec<f64>) {
+        self.requestors.insert(node_id.to_string(), prices);
+    }
+}
+
+/// Negotiator, that instead of rejecting Proposals with prices lower than
+/// ours, makes counter-proposals meeting Requestor in the middle, but never
+/// going below configured floor prices.
+/// Prices are scaled by time-of-day and hardware load multipliers. Requestors,
+/// who successfully finished Agreement with us, are quoted the price they paid.
+pub struct DynamicPrice {
+    floors: HashMap<String, f64>,
+    concession: f64,
+    max_rounds: u32,
+    peak_hours: Option<HourRange>,
+    peak_multiplier: f64,
+    load_multiplier: f64,
+    hardware: hardware::Allocation,
+
+    /// Prices of the Agreement checked in last negotiation step. Assigned to the Agreement
+    /// in `on_agreement_approved`, which follows the step directly.
+    accepted: Option<(NodeId, Vec<f64>)>,
+    agreements: HashMap<String, (NodeId, Vec<f64>)>,
+    paid: PaidPrices,
+    paid_file: PathBuf,
+}
+
+impl DynamicPrice {
+    pub fn new(
+        config: &DynamicPriceNegotiatorConfig,
+        hardware: hardware::Allocation,
+        paid_file: PathBuf,
+    ) -> anyhow::Result<DynamicPrice> {
+        if config.price_concession <= 0.0 || config.price_concession > 1.0 {
+            bail!(
+                "Price concession should be in range (0, 1], got: {}",
+                config.price_concession
+            );
+        }
+        if config.peak_price_multiplier <= 0.0 || config.load_price_multiplier < 0.0 {
+            bail!(
+                "Peak price multiplier should be positive and load price multiplier non-negative"
+            );
+        }
+
+        Ok(DynamicPrice {
+            floors: config
+                .price_floor
+                .iter()
+                .map(|floor| (floor.coeff.clone(), floor.price))
+                .collect(),
+            concession: config.price_concession,
+            max_rounds: config.max_price_rounds,
+            peak_hours: config.peak_hours,
+            peak_multiplier: config.peak_price_multiplier,
+            load_multiplier: config.load_price_multiplier,
+            hardware,
+            accepted: None,
+            agreements: HashMap::new(),
+            paid: PaidPrices::load(&paid_file)?,
+            paid_file,
+        })
+    }
+
+    fn multiplier(&self, hour: u32) -> f64 {
+        let peak = match self.peak_hours {
+            Some(range) if range.contains(hour) => self.peak_multiplier,
+            _ => 1.0,
+        };
+        peak * (1.0 + self.load_multiplier * self.hardware.load())
+    }
+
+    /// Floor prices ordered as coefficients in price vector. The last
+    /// coefficient is the constant price.
+    fn floors(&self, offer: &ProposalView, len: usize) -> Vec<f64> {
+        let usage = offer
+            .pointer_typed::<Vec<String>>(USAGE_VECTOR_PROPERTY)
+            .unwrap_or_default();
+        (0..len)
+            .map(|idx| {
+                let name = match idx + 1 == len {
+                    true => INITIAL_PRICE_COEFF,
+                    false => usage.get(idx).map(String::as_str).unwrap_or_default(),
+                };
+                self.floors.get(name).cloned().unwrap_or(0.0)
+            })
+            .collect()
+    }
+}
+
+fn set_prices(offer: &mut ProposalView, prices: &[f64]) {
+    let price_property = price_property(offer);
+    if let Some(p) = offer.pointer_mut(&price_property) {
+        *p = serde_json::json!(prices);
+    }
+}
+
+/// Counter-proposals already sent in the negotiation `offer` belongs to.
+/// Initial Offer starts a new negotiation.
+fn price_round(offer: &ProposalView) -> u32 {
+    match offer.state {
+        State::Initial => 0,
+        _ => offer.pointer_typed(PRICE_ROUND_POINTER).unwrap_or(0),
+    }
+}
+
+fn set_price_round(offer: &mut ProposalView, round: u32) {
+    patch(
+        &mut offer.content.properties,
+        expand(serde_json::json!({ PRICE_ROUND_PROPERTY: round })),
+    );
+}
+
+impl NegotiatorComponent for DynamicPrice {
+    fn negotiate_step(
+        &mut self,
+        demand: &ProposalView,
+        mut offer: ProposalView,
+    ) -> anyhow::Result<NegotiationResult> {
+        let price_property = price_property(&offer);
+        let (bid, ask) = match (
+            demand.pointer_typed::<Vec<f64>>(&price_property),
+            offer.pointer_typed::<Vec<f64>>(&price_property),
+        ) {
+            (Ok(bid), Ok(ask)) => (bid, ask),
+            _ => return Ok(NegotiationResult::Ready { offer }),
+        };
+        if bid.len() != ask.len() {
+            return Ok(NegotiationResult::Reject {
+                message: "invalid price vector".to_string(),
+                is_final: false,
+            });
+        }
+
+        let requestor = demand.issuer;
+        let floors = self.floors(&offer, ask.len());
+        let (floors, ask) = match self.paid.get(&requestor) {
+            // Repeat customers are quoted the price they paid, unless floors were raised since.
+            Some(paid) if paid.len() == ask.len() => {
+                let ask = match offer.state {
+                    State::Initial => paid.iter().zip(&floors).map(|(p, f)| p.max(*f)).collect(),
+                    _ => ask,
+                };
+                (floors, ask)
+            }
+            _ => {
+                let multiplier = self.multiplier(chrono::Local::now().hour());
+                let floors = floors.iter().map(|f| f * multiplier).collect::<Vec<_>>();
+                let ask = match offer.state {
+                    State::Initial => ask
+                        .iter()
+                        .zip(&floors)
+                        .map(|(a, f)| (a * multiplier).max(*f))
+                        .collect(),
+                    _ => ask,
+                };
+                (floors, ask)
+            }
+        };
+        if bid.iter().zip(&ask).all(|(b, a)| b >= a) {
+            set_prices(&mut offer, &bid);
+            if offer.state == State::Accepted {
+                self.accepted = Some((requestor, bid));
+            }
+            return Ok(NegotiationResult::Ready { offer });
+        }
+
+        let round = price_round(&offer);
+        if round >= self.max_rounds {
+            log::info!(
+                "'DynamicPrice' negotiator: Reject proposal [{}]. No agreement on price after {} rounds.",
+                demand.id,
+                self.max_rounds,
+            );
+            return Ok(NegotiationResult::Reject {
+                message: format!("{:?} < {:?}", bid, ask),
+                is_final: true,
+            });
+        }
+
+        let concession = self.concession;
+        let counter = ask
+            .iter()
+            .zip(&bid)
+            .zip(&floors)
+            .map(|((a, b), f)| match b >= a {
+                true => *b,
+                false => (a - concession * (a - b)).max(*f),
+            })
+            .collect::<Vec<_>>();
+
+        log::debug!(
+            "'DynamicPrice' negotiator: Counter-offer {:?} for proposal [{}] with price {:?}.",
+            counter,
+            demand.id,
+            bid,
+        );
+        set_prices(&mut offer, &counter);
+        set_price_round(&mut offer, round + 1);
+        Ok(NegotiationResult::Negotiating { offer })
+    }
+
+    fn fill_template(&mut self, template: OfferDefinition) -> anyhow::Result<OfferDefinition> {
+        Ok(template)
+    }
+
+    fn on_agreement_terminated(
+        &mut self,
+        agreement_id: &str,
+        result: &AgreementResult,
+    ) -> anyhow::Result<()> {
+        if let Some((requestor, prices)) = self.agreements.remove(agreement_id) {
+            match result {
+                AgreementResult::ClosedByUs | AgreementResult::ClosedByRequestor => {
+                    self.paid.insert(&requestor, prices);
+                    self.paid.save(&self.paid_file)?;
+                }
+                AgreementResult::ApprovalFailed | AgreementResult::Broken { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn on_agreement_approved(&mut self, agreement_id: &str) -> anyhow::Result<()> {
+        if let Some(accepted) = self.accepted.take() {
+            self.agreements.insert(agreement_id.to_string(), accepted);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use tempdir::TempDir;
+    use ya_agreement_utils::OfferTemplate;
+
+    fn config() -> DynamicPriceNegotiatorConfig {
+        DynamicPriceNegotiatorConfig {
+            dynamic_pricing: true,
+            price_floor: vec![
+                "golem.usage.cpu_sec=0.6".parse().unwrap(),
+                "initial=0".parse().unwrap(),
+            ],
+            price_concession: 0.5,
+            max_price_rounds: 2,
+            peak_hours: None,
+            peak_price_multiplier: 1.0,
+            load_price_multiplier: 0.0,
+        }
+    }
+
+    fn proposal(prices: &[f64], state: State, issuer: NodeId) -> ProposalView {
+        ProposalView {
+            content: OfferTemplate {
+                properties: expand(serde_json::json!({
+                    "golem.com.pricing.model.linear.coeffs": prices,
+                    "golem.com.usage.vector": ["golem.usage.cpu_sec"],
+                })),
+                constraints: "()".to_string(),
+            },
+            id: "proposal-id".to_string(),
+            issuer,
+            state,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn new_negotiator(config: &DynamicPriceNegotiatorConfig, dir: &TempDir) -> DynamicPrice {
+        DynamicPrice::new(
+            config,
+            Default::default(),
+            dir.path().join(PAID_PRICES_JSON),
+        )
+        .unwrap()
+    }
+
+    /// Our Proposal sent in response, as seen in the next negotiation step.
+    fn sent(result: NegotiationResult) -> ProposalView {
+        match result {
+            NegotiationResult::Negotiating { mut offer } => {
+                offer.state = State::Draft;
+                offer
+            }
+            result => panic!("Expected counter-proposal, got: {:?}", result),
+        }
+    }
+
+    fn prices(result: &NegotiationResult) -> Vec<f64> {
+        match result {
+            NegotiationResult::Ready { offer } | NegotiationResult::Negotiating { offer } => {
+                offer.pointer_typed(&price_property(offer)).unwrap()
+            }
+            NegotiationResult::Reject { .. } => panic!("Unexpected rejection"),
+        }
+    }
+
+    #[test]
+    fn test_counter_offer_meets_in_the_middle() {
+        let dir = TempDir::new("dynamic_price").unwrap();
+        let mut negotiator = new_negotiator(&config(), &dir);
+        let requestor = NodeId::default();
+
+        let demand = proposal(&[0.5, 0.0], State::Draft, requestor);
+        let offer = proposal(&[1.0, 0.0], State::Initial, Default::default());
+        let result = negotiator.negotiate_step(&demand, offer).unwrap();
+        assert!(matches!(result, NegotiationResult::Negotiating { .. }));
+        assert_eq!(prices(&result), vec![0.75, 0.0]);
+
+        let demand = proposal(&[0.75, 0.0], State::Draft, requestor);
+        let result = negotiator.negotiate_step(&demand, sent(result)).unwrap();
+        assert!(matches!(result, NegotiationResult::Ready { .. }));
+        assert_eq!(prices(&result), vec![0.75, 0.0]);
+    }
+
+    #[test]
+    fn test_never_below_floor() {
+        let dir = TempDir::new("dynamic_price").unwrap();
+        let mut negotiator = new_negotiator(&config(), &dir);
+        let demand = proposal(&[0.1, 0.0], State::Draft, NodeId::default());
+
+        let mut offer = proposal(&[1.0, 0.0], State::Initial, Default::default());
+        for expected in [0.6, 0.6] {
+            let result = negotiator.negotiate_step(&demand, offer).unwrap();
+            assert_eq!(prices(&result), vec![expected, 0.0]);
+            offer = sent(result);
+        }
+
+        match negotiator.negotiate_step(&demand, offer).unwrap() {
+            NegotiationResult::Reject { is_final, .. } => assert!(is_final),
+            result => panic!("Expected rejection, got: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_rounds_counted_per_proposal_chain() {
+        let dir = TempDir::new("dynamic_price").unwrap();
+        let mut negotiator = new_negotiator(&config(), &dir);
+        let demand = proposal(&[0.1, 0.0], State::Draft, NodeId::default());
+
+        // Requestor negotiates two subscriptions in parallel.
+        let initial = proposal(&[1.0, 0.0], State::Initial, Default::default());
+        let first = sent(negotiator.negotiate_step(&demand, initial.clone()).unwrap());
+        let second = sent(negotiator.negotiate_step(&demand, initial).unwrap());
+        let first = sent(negotiator.negotiate_step(&demand, first).unwrap());
+
+        assert!(matches!(
+            negotiator.negotiate_step(&demand, first).unwrap(),
+            NegotiationResult::Reject { .. }
+        ));
+        assert!(matches!(
+            negotiator.negotiate_step(&demand, second).unwrap(),
+            NegotiationResult::Negotiating { .. }
+        ));
+    }
+
+    #[test]
+    fn test_repeat_customer_quoted_paid_price() {
+        let mut config = config();
+        config.peak_hours = Some("0-24".parse().unwrap());
+        config.peak_price_multiplier = 2.0;
+        let dir = TempDir::new("dynamic_price").unwrap();
+        let mut negotiator = new_negotiator(&config, &dir);
+        let requestor = NodeId::default();
+
+        // Agreement with prices, which Requestor accepted.
+        let agreed = proposal(&[2.0, 0.0], State::Accepted, requestor);
+        let result = negotiator.negotiate_step(&agreed, agreed.clone()).unwrap();
+        assert!(matches!(result, NegotiationResult::Ready { .. }));
+        negotiator.on_agreement_approved("agreement-id").unwrap();
+        negotiator
+            .on_agreement_terminated("agreement-id", &AgreementResult::ClosedByRequestor)
+            .unwrap();
+        assert!(negotiator.agreements.is_empty());
+
+        // Paid prices survive restart.
+        let mut negotiator = new_negotiator(&config, &dir);
+
+        // New customer gets peak multiplier applied to the Offer price.
+        let demand = proposal(
+            &[1.0, 0.0],
+            State::Draft,
+            "0x0000000000000000000000000000000000000001"
+                .parse()
+                .unwrap(),
+        );
+        let offer = proposal(&[1.5, 0.0], State::Initial, Default::default());
+        let result = negotiator.negotiate_step(&demand, offer).unwrap();
+        assert_eq!(prices(&result), vec![2.0, 0.0]);
+
+        // Repeat customer is quoted the price paid previously.
+        let demand = proposal(&[1.0, 0.0], State::Draft, requestor);
+        let offer = proposal(&[1.5, 0.0], State::Initial, Default::default());
+        let result = negotiator.negotiate_step(&demand, offer).unwrap();
+        assert_eq!(prices(&result), vec![1.5, 0.0]);
+    }
+
+    #[test]
+    fn test_hour_range() {
+        let day: HourRange = "8-18".parse().unwrap();
+        assert!(day.contains(8));
+        assert!(!day.contains(18));
+
+        let night: HourRange = "22-6".parse().unwrap();
+        assert!(night.contains(23));
+        assert!(night.contains(0));
+        assert!(!night.contains(12));
+
+        assert!("25-3".parse::<HourRange>().is_err());
+    }
+}
@@ -146,11 +146,14 @@ mod tests {
     use structopt::StructOpt;
     use tempdir::TempDir;
 
+    use crate::reputation::ReputationManager;
+
     fn build_policy<S: AsRef<str>>(args: S) -> (ManifestSignature, TempDir) {
         let tempdir = TempDir::new("test_dir").unwrap();
         let rules_file = tempdir.path().join("rules.json");
         let whitelist_file = tempdir.path().join("whitelist.json");
         let cert_dir = tempdir.path().join("cert_dir");
+        let reputation_file = tempdir.path().join("reputation.json");
 
         let arguments = shlex::split(args.as_ref()).expect("failed to parse arguments");
 
@@ -164,6 +167,10 @@ mod tests {
                         &cert_dir,
                     )
                     .unwrap(),
+                    hardware: Default::default(),
+                    reputation: ReputationManager::load_or_create(&reputation_file).unwrap(),
+                    dunning: Default::default(),
+                    paid_prices_file: tempdir.path().join("paid-prices.json"),
                 },
             ),
             tempdir,
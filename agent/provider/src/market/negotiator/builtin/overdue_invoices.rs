@@ -0,0 +1,135 @@
+use bigdecimal::BigDecimal;
+
+use ya_agreement_utils::OfferDefinition;
+
+use crate::market::negotiator::factory::OverdueInvoicesNegotiatorConfig;
+use crate::market::negotiator::{
+    AgreementResult, NegotiationResult, NegotiatorComponent, ProposalView,
+};
+use crate::payments::DunningManager;
+
+/// Negotiator rejecting Requestors, whose overdue Invoices exceed configured amount.
+pub struct OverdueInvoices {
+    max_amount: BigDecimal,
+    dunning: DunningManager,
+}
+
+impl OverdueInvoices {
+    /// Returns `None`, if overdue amount limit isn't configured.
+    pub fn new(
+        config: &OverdueInvoicesNegotiatorConfig,
+        dunning: DunningManager,
+    ) -> Option<OverdueInvoices> {
+        Some(OverdueInvoices {
+            max_amount: config.max_overdue_amount.clone()?,
+            dunning,
+        })
+    }
+}
+
+impl NegotiatorComponent for OverdueInvoices {
+    fn negotiate_step(
+        &mut self,
+        demand: &ProposalView,
+        offer: ProposalView,
+    ) -> anyhow::Result<NegotiationResult> {
+        let requestor = demand.issuer;
+        let overdue = self.dunning.overdue_amount(&requestor);
+        if overdue > self.max_amount {
+            let message = format!(
+                "Requestor [{}] has overdue invoices for {}, above {}",
+                requestor, overdue, self.max_amount
+            );
+            log::info!(
+                "'OverdueInvoices' negotiator: Reject proposal [{}]. {}",
+                demand.id,
+                message
+            );
+            return Ok(NegotiationResult::Reject {
+                message,
+                is_final: true,
+            });
+        }
+        Ok(NegotiationResult::Ready { offer })
+    }
+
+    fn fill_template(
+        &mut self,
+        offer_template: OfferDefinition,
+    ) -> anyhow::Result<OfferDefinition> {
+        Ok(offer_template)
+    }
+
+    fn on_agreement_terminated(
+        &mut self,
+        _agreement_id: &str,
+        _result: &AgreementResult,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn on_agreement_approved(&mut self, _agreement_id: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{Duration, Utc};
+    use ya_agreement_utils::OfferTemplate;
+    use ya_client_model::market::proposal::State;
+    use ya_client_model::payment::{DocumentStatus, Invoice};
+    use ya_client_model::NodeId;
+
+    fn proposal() -> ProposalView {
+        ProposalView {
+            content: OfferTemplate::default(),
+            id: "proposal-id".to_string(),
+            issuer: NodeId::default(),
+            state: State::Accepted,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn overdue_invoice(invoice_id: &str, amount: u32) -> Invoice {
+        Invoice {
+            invoice_id: invoice_id.to_string(),
+            issuer_id: NodeId::default(),
+            recipient_id: NodeId::default(),
+            payee_addr: String::new(),
+            payer_addr: String::new(),
+            payment_platform: String::new(),
+            timestamp: Utc::now(),
+            agreement_id: "agreement-id".to_string(),
+            activity_ids: vec![],
+            amount: BigDecimal::from(amount),
+            payment_due_date: Utc::now() - Duration::days(1),
+            status: DocumentStatus::Received,
+        }
+    }
+
+    #[test]
+    fn test_reject_above_overdue_amount() {
+        let dunning = DunningManager::default();
+        let config = OverdueInvoicesNegotiatorConfig {
+            max_overdue_amount: Some(BigDecimal::from(5)),
+        };
+        let mut negotiator = OverdueInvoices::new(&config, dunning.clone()).unwrap();
+
+        dunning.track(&overdue_invoice("first", 3));
+        let result = negotiator.negotiate_step(&proposal(), proposal()).unwrap();
+        assert!(matches!(result, NegotiationResult::Ready { .. }));
+
+        dunning.track(&overdue_invoice("second", 3));
+        match negotiator.negotiate_step(&proposal(), proposal()).unwrap() {
+            NegotiationResult::Reject { is_final, .. } => assert!(is_final),
+            result => panic!("Expected rejection, got: {:?}", result),
+        }
+
+        dunning.settled("second");
+        let result = negotiator.negotiate_step(&proposal(), proposal()).unwrap();
+        assert!(matches!(result, NegotiationResult::Ready { .. }));
+    }
+}
@@ -7,7 +7,16 @@ use crate::market::negotiator::{
 
 pub struct PriceNego {}
 
-static PRICE_PROPERTY: &str = "/golem/com/pricing/model/linear/coeffs";
+static PRICING_MODEL_PROPERTY: &str = "/golem/com/pricing/model/@tag";
+
+/// Pointer to price coefficients of pricing model declared in Proposal.
+/// Proposals without pricing model are treated as `linear`.
+pub fn price_property(proposal: &ProposalView) -> String {
+    let model = proposal
+        .pointer_typed::<String>(PRICING_MODEL_PROPERTY)
+        .unwrap_or_else(|_| "linear".to_string());
+    format!("/golem/com/pricing/model/{}/coeffs", model)
+}
 
 impl PriceNego {
     pub fn new(_config: &AgreementExpirationNegotiatorConfig) -> anyhow::Result<Self> {
@@ -21,10 +30,24 @@ impl NegotiatorComponent for PriceNego {
         demand: &ProposalView,
         mut offer: ProposalView,
     ) -> anyhow::Result<NegotiationResult> {
+        let price_property = price_property(&offer);
         if let (Ok(demand_prices), Ok(offer_prices)) = (
-            demand.pointer_typed::<Vec<f64>>(PRICE_PROPERTY),
-            offer.pointer_typed::<Vec<f64>>(PRICE_PROPERTY),
+            demand.pointer_typed::<Vec<f64>>(&price_property),
+            offer.pointer_typed::<Vec<f64>>(&price_property),
         ) {
+            // Tiers and max cost are other parameters of the model, which
+            // Requestor has to accept as they are.
+            let model_property = price_property.trim_end_matches("/coeffs");
+            let same_params = |name: &str| {
+                let pointer = format!("{}/{}", model_property, name);
+                demand.pointer(&pointer) == offer.pointer(&pointer)
+            };
+            if !same_params("tiers") || !same_params("max-cost") {
+                return Ok(NegotiationResult::Reject {
+                    message: "pricing model parameters don't match".to_string(),
+                    is_final: false,
+                });
+            }
             if demand_prices == offer_prices {
                 return Ok(NegotiationResult::Ready { offer });
             }
@@ -39,8 +62,8 @@ impl NegotiatorComponent for PriceNego {
                 .zip(&offer_prices)
                 .all(|(dp, op)| dp >= op)
             {
-                if let Some(p) = offer.pointer_mut(PRICE_PROPERTY) {
-                    *p = demand.pointer(PRICE_PROPERTY).unwrap().clone();
+                if let Some(p) = offer.pointer_mut(&price_property) {
+                    *p = demand.pointer(&price_property).unwrap().clone();
                 }
                 Ok(NegotiationResult::Negotiating { offer })
             } else {
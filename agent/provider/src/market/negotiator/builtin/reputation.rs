@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use ya_agreement_utils::OfferDefinition;
+use ya_client_model::market::proposal::State;
+use ya_client_model::NodeId;
+
+use crate::market::negotiator::factory::RequestorReputationNegotiatorConfig;
+use crate::market::negotiator::{
+    AgreementResult, NegotiationResult, NegotiatorComponent, ProposalView,
+};
+use crate::reputation::{ReputationEvent, ReputationManager};
+
+/// Negotiator rejecting Requestors, who were banned or whose reputation score
+/// dropped below threshold. Updates reputation based on Agreements termination.
+pub struct RequestorReputation {
+    min_score: i64,
+    reputation: ReputationManager,
+
+    /// Requestor of the Agreement approved in last negotiation step. Steps
+    /// negotiating Agreements are made on accepted Proposals, so other Proposals
+    /// negotiated before `on_agreement_approved` don't change it.
+    approved: Option<NodeId>,
+    agreements: HashMap<String, NodeId>,
+}
+
+impl RequestorReputation {
+    pub fn new(
+        config: &RequestorReputationNegotiatorConfig,
+        reputation: ReputationManager,
+    ) -> RequestorReputation {
+        RequestorReputation {
+            min_score: config.min_requestor_score,
+            reputation,
+            approved: None,
+            agreements: HashMap::new(),
+        }
+    }
+}
+
+impl NegotiatorComponent for RequestorReputation {
+    fn negotiate_step(
+        &mut self,
+        demand: &ProposalView,
+        offer: ProposalView,
+    ) -> anyhow::Result<NegotiationResult> {
+        let requestor = demand.issuer;
+        if let Some(record) = self.reputation.get(&requestor) {
+            let message = if record.banned {
+                Some(format!("Requestor [{}] is banned", requestor))
+            } else if record.score < self.min_score {
+                Some(format!(
+                    "Requestor [{}] reputation score {} is below {}",
+                    requestor, record.score, self.min_score
+                ))
+            } else {
+                None
+            };
+
+            if let Some(message) = message {
+                log::info!(
+                    "'RequestorReputation' negotiator: Reject proposal [{}]. {}",
+                    demand.id,
+                    message
+                );
+                return Ok(NegotiationResult::Reject {
+                    message,
+                    is_final: true,
+                });
+            }
+        }
+
+        if demand.state == State::Accepted {
+            self.approved = Some(requestor);
+        }
+        Ok(NegotiationResult::Ready { offer })
+    }
+
+    fn fill_template(
+        &mut self,
+        offer_template: OfferDefinition,
+    ) -> anyhow::Result<OfferDefinition> {
+        Ok(offer_template)
+    }
+
+    fn on_agreement_terminated(
+        &mut self,
+        agreement_id: &str,
+        result: &AgreementResult,
+    ) -> anyhow::Result<()> {
+        let requestor = match self.agreements.remove(agreement_id) {
+            Some(requestor) => requestor,
+            None => return Ok(()),
+        };
+
+        let event = match result {
+            AgreementResult::ClosedByUs | AgreementResult::ClosedByRequestor => {
+                ReputationEvent::AgreementClosed
+            }
+            AgreementResult::Broken { reason } => ReputationEvent::AgreementBroken(reason.clone()),
+            AgreementResult::ApprovalFailed => return Ok(()),
+        };
+        self.reputation.record(&requestor, event);
+        Ok(())
+    }
+
+    fn on_agreement_approved(&mut self, agreement_id: &str) -> anyhow::Result<()> {
+        if let Some(requestor) = self.approved.take() {
+            self.agreements.insert(agreement_id.to_string(), requestor);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use ya_agreement_utils::OfferTemplate;
+
+    use crate::market::termination_reason::BreakReason;
+
+    fn proposal() -> ProposalView {
+        ProposalView {
+            content: OfferTemplate::default(),
+            id: "proposal-id".to_string(),
+            issuer: NodeId::default(),
+            state: State::Accepted,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn negotiator(tempdir: &tempdir::TempDir) -> (RequestorReputation, ReputationManager) {
+        let path = tempdir.path().join("reputation.json");
+        let reputation = ReputationManager::load_or_create(&path).unwrap();
+        let config = RequestorReputationNegotiatorConfig {
+            min_requestor_score: -4,
+        };
+        (
+            RequestorReputation::new(&config, reputation.clone()),
+            reputation,
+        )
+    }
+
+    fn broken() -> AgreementResult {
+        AgreementResult::Broken {
+            reason: BreakReason::DebitNotesDeadline(chrono::Duration::seconds(10)),
+        }
+    }
+
+    #[test]
+    fn test_reject_below_threshold() {
+        let tempdir = tempdir::TempDir::new("reputation").unwrap();
+        let (mut negotiator, reputation) = negotiator(&tempdir);
+
+        // Agreement broken due to Requestor's fault lowers score below threshold.
+        let result = negotiator.negotiate_step(&proposal(), proposal()).unwrap();
+        assert!(matches!(result, NegotiationResult::Ready { .. }));
+        negotiator.on_agreement_approved("agreement-id").unwrap();
+        negotiator
+            .on_agreement_terminated("agreement-id", &broken())
+            .unwrap();
+
+        match negotiator.negotiate_step(&proposal(), proposal()).unwrap() {
+            NegotiationResult::Reject { is_final, .. } => assert!(is_final),
+            result => panic!("Expected rejection, got: {:?}", result),
+        }
+        assert_eq!(reputation.get(&NodeId::default()).unwrap().score, -5);
+    }
+    #[test]
+    fn test_agreement_assigned_to_accepted_proposal_issuer() {
+        let tempdir = tempdir::TempDir::new("reputation").unwrap();
+        let (mut negotiator, reputation) = negotiator(&tempdir);
+        let other = NodeId::from(&[1u8; 20][..]);
+
+        // Other Requestor negotiates, before Agreement is approved.
+        negotiator.negotiate_step(&proposal(), proposal()).unwrap();
+        let draft = ProposalView {
+            issuer: other,
+            state: State::Draft,
+            ..proposal()
+        };
+        negotiator.negotiate_step(&draft, proposal()).unwrap();
+        negotiator.on_agreement_approved("agreement-id").unwrap();
+        negotiator
+            .on_agreement_terminated("agreement-id", &broken())
+            .unwrap();
+
+        assert_eq!(reputation.get(&NodeId::default()).unwrap().score, -5);
+        assert!(reputation.get(&other).is_none());
+    }
+}
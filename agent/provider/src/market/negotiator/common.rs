@@ -108,10 +108,10 @@ pub struct AgreementFinalized {
 ///   as plugable components.
 pub trait Negotiator:
     Actor
-    + Handler<CreateOffer, Result = <CreateOffer as Message>::Result>
-    + Handler<AgreementFinalized, Result = <AgreementFinalized as Message>::Result>
-    + Handler<ReactToProposal, Result = <ReactToProposal as Message>::Result>
-    + Handler<ReactToAgreement, Result = <ReactToAgreement as Message>::Result>
+    + Handler<CreateOffer>
+    + Handler<AgreementFinalized>
+    + Handler<ReactToProposal>
+    + Handler<ReactToAgreement>
 {
 }
 
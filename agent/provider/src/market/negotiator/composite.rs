@@ -1,5 +1,6 @@
-use actix::{Actor, Addr, Context, Handler};
+use actix::prelude::*;
 use anyhow::anyhow;
+use futures::FutureExt;
 use serde_json::Value;
 use std::convert::TryFrom;
 
@@ -9,9 +10,11 @@ use ya_client::model::market::NewOffer;
 use ya_client_model::market::proposal::State;
 
 use super::builtin::{
-    DebitNoteInterval, LimitExpiration, ManifestSignature, MaxAgreements, PaymentTimeout,
+    DebitNoteInterval, DynamicPrice, LimitExpiration, ManifestSignature, MaxAgreements,
+    OverdueInvoices, PaymentTimeout, RequestorReputation,
 };
 use super::common::{offer_definition_to_offer, AgreementResponse, Negotiator, ProposalResponse};
+use super::external::ExternalNegotiator;
 use super::{NegotiationResult, NegotiatorsPack};
 use crate::market::negotiator::builtin::demand_validation::DemandValidation;
 use crate::market::negotiator::builtin::PriceNego;
@@ -26,6 +29,8 @@ use crate::provider_agent::AgentNegotiatorsConfig;
 /// Negotiator that can limit number of running agreements.
 pub struct CompositeNegotiator {
     components: NegotiatorsPack,
+    /// Asynchronous, so it is called after all other components decided.
+    external: Option<ExternalNegotiator>,
 }
 
 impl CompositeNegotiator {
@@ -34,6 +39,10 @@ impl CompositeNegotiator {
         config: &CompositeNegotiatorConfig,
         agent_negotiators_cfg: AgentNegotiatorsConfig,
     ) -> anyhow::Result<CompositeNegotiator> {
+        let hardware = agent_negotiators_cfg.hardware.clone();
+        let reputation = agent_negotiators_cfg.reputation.clone();
+        let dunning = agent_negotiators_cfg.dunning.clone();
+        let paid_prices_file = agent_negotiators_cfg.paid_prices_file.clone();
         let components = NegotiatorsPack::default()
             .add_component(
                 "Validation",
@@ -55,35 +64,89 @@ impl CompositeNegotiator {
                 "PaymentTimeout",
                 Box::new(PaymentTimeout::new(&config.payment_timeout_config)?),
             )
+            .add_component(
+                "RequestorReputation",
+                Box::new(RequestorReputation::new(
+                    &config.reputation_config,
+                    reputation,
+                )),
+            )
             .add_component(
                 "ManifestSignature",
                 Box::new(ManifestSignature::new(
                     &config.policy_config.clone(),
                     agent_negotiators_cfg,
                 )),
-            )
-            .add_component(
+            );
+
+        let components = match config.dynamic_price_config.dynamic_pricing {
+            true => components.add_component(
+                "Price",
+                Box::new(DynamicPrice::new(
+                    &config.dynamic_price_config,
+                    hardware,
+                    paid_prices_file,
+                )?),
+            ),
+            false => components.add_component(
                 "Price",
                 Box::new(PriceNego::new(&config.expire_agreements_config)?),
-            );
+            ),
+        };
 
-        Ok(CompositeNegotiator { components })
+        let components = match OverdueInvoices::new(&config.overdue_invoices_config, dunning) {
+            Some(overdue) => components.add_component("OverdueInvoices", Box::new(overdue)),
+            None => components,
+        };
+
+        Ok(CompositeNegotiator {
+            components,
+            external: ExternalNegotiator::new(&config.external_config),
+        })
     }
 }
 
 impl Handler<CreateOffer> for CompositeNegotiator {
-    type Result = anyhow::Result<NewOffer>;
+    type Result = ResponseFuture<anyhow::Result<NewOffer>>;
 
     fn handle(&mut self, msg: CreateOffer, _: &mut Context<Self>) -> Self::Result {
-        let offer = self.components.fill_template(msg.offer_definition)?;
-        Ok(offer_definition_to_offer(offer))
+        let offer = self.components.fill_template(msg.offer_definition);
+        let external = self.external.clone();
+        async move {
+            let mut offer = offer?;
+            if let Some(external) = external {
+                offer = external.fill_template(offer).await?;
+            }
+            Ok(offer_definition_to_offer(offer))
+        }
+        .boxed_local()
     }
 }
 
 impl Handler<ReactToProposal> for CompositeNegotiator {
-    type Result = anyhow::Result<ProposalResponse>;
+    type Result = ResponseFuture<anyhow::Result<ProposalResponse>>;
 
     fn handle(&mut self, msg: ReactToProposal, _: &mut Context<Self>) -> Self::Result {
+        let result = self.react_to_proposal(msg);
+        let external = self.external.clone();
+        async move {
+            let (their, constraints, result) = result?;
+            let result = match external {
+                Some(external) => external.negotiate_step(&their, result).await,
+                None => result,
+            };
+            Ok(proposal_response(result, constraints))
+        }
+        .boxed_local()
+    }
+}
+
+impl CompositeNegotiator {
+    /// Negotiates Proposal using synchronous components.
+    fn react_to_proposal(
+        &mut self,
+        msg: ReactToProposal,
+    ) -> anyhow::Result<(ProposalView, String, NegotiationResult)> {
         // In current implementation we don't allow to change constraints, so we take
         // them from initial Offer.
         let constraints = msg.prev_proposal.constraints;
@@ -101,23 +164,25 @@ impl Handler<ReactToProposal> for CompositeNegotiator {
         };
 
         let result = self.components.negotiate_step(&their, template)?;
-        match result {
-            NegotiationResult::Reject { message, is_final } => {
-                Ok(ProposalResponse::RejectProposal {
-                    reason: Some(reason_with_extra(
-                        message,
-                        serde_json::json!({ "golem.proposal.rejection.is-final": is_final }),
-                    )),
-                    is_final,
-                })
-            }
-            NegotiationResult::Ready { offer } | NegotiationResult::Negotiating { offer } => {
-                let offer = NewOffer {
-                    properties: flatten_value(offer.content.properties),
-                    constraints,
-                };
-                Ok(ProposalResponse::CounterProposal { offer })
-            }
+        Ok((their, constraints, result))
+    }
+}
+
+fn proposal_response(result: NegotiationResult, constraints: String) -> ProposalResponse {
+    match result {
+        NegotiationResult::Reject { message, is_final } => ProposalResponse::RejectProposal {
+            reason: Some(reason_with_extra(
+                message,
+                serde_json::json!({ "golem.proposal.rejection.is-final": is_final }),
+            )),
+            is_final,
+        },
+        NegotiationResult::Ready { offer } | NegotiationResult::Negotiating { offer } => {
+            let offer = NewOffer {
+                properties: flatten_value(offer.content.properties),
+                constraints,
+            };
+            ProposalResponse::CounterProposal { offer }
         }
     }
 }
@@ -165,25 +230,55 @@ pub fn to_proposal_views(
 }
 
 impl Handler<ReactToAgreement> for CompositeNegotiator {
-    type Result = anyhow::Result<AgreementResponse>;
+    type Result = ActorResponse<Self, anyhow::Result<AgreementResponse>>;
 
     fn handle(&mut self, msg: ReactToAgreement, _: &mut Context<Self>) -> Self::Result {
         let agreement_id = msg.agreement.id.clone();
-        let (demand_proposal, offer_proposal) = to_proposal_views(msg.agreement).map_err(|e| {
-            anyhow!(
-                "Negotiator failed to extract Proposals from Agreement. {}",
-                e
-            )
-        })?;
+        let (demand_proposal, offer_proposal) = match to_proposal_views(msg.agreement) {
+            Ok(proposals) => proposals,
+            Err(e) => {
+                return ActorResponse::reply(Err(anyhow!(
+                    "Negotiator failed to extract Proposals from Agreement. {}",
+                    e
+                )))
+            }
+        };
 
-        // We expect that all `NegotiatorComponents` should return ready state.
-        // Otherwise we must reject Agreement proposals, because negotiations didn't end.
-        match self
+        let result = match self
             .components
-            .negotiate_step(&demand_proposal, offer_proposal)?
+            .negotiate_step(&demand_proposal, offer_proposal)
         {
+            Ok(result) => result,
+            Err(e) => return ActorResponse::reply(Err(e)),
+        };
+        let external = match self.external.clone() {
+            Some(external) => external,
+            None => return ActorResponse::reply(self.react_to_agreement(&agreement_id, result)),
+        };
+
+        let future = async move { external.negotiate_step(&demand_proposal, result).await };
+        ActorResponse::r#async(
+            future
+                .into_actor(self)
+                .map(move |result, myself, _| myself.react_to_agreement(&agreement_id, result)),
+        )
+    }
+}
+
+impl CompositeNegotiator {
+    fn react_to_agreement(
+        &mut self,
+        agreement_id: &str,
+        result: NegotiationResult,
+    ) -> anyhow::Result<AgreementResponse> {
+        // We expect that all `NegotiatorComponents` should return ready state.
+        // Otherwise we must reject Agreement proposals, because negotiations didn't end.
+        match result {
             NegotiationResult::Ready { .. } => {
-                self.components.on_agreement_approved(&agreement_id)?;
+                self.components.on_agreement_approved(agreement_id)?;
+                if let Some(external) = &self.external {
+                    external.on_agreement_approved(agreement_id);
+                }
                 Ok(AgreementResponse::ApproveAgreement)
             }
             NegotiationResult::Reject { message, is_final } => {
@@ -210,6 +305,9 @@ impl Handler<AgreementFinalized> for CompositeNegotiator {
     type Result = anyhow::Result<()>;
 
     fn handle(&mut self, msg: AgreementFinalized, _: &mut Context<Self>) -> Self::Result {
+        if let Some(external) = &self.external {
+            external.on_agreement_terminated(&msg.agreement_id, &msg.result);
+        }
         self.components
             .on_agreement_terminated(&msg.agreement_id, &msg.result)
     }
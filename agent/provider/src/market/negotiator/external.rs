@@ -0,0 +1,638 @@
+//! Negotiator delegating negotiations to external process.
+//!
+//! Provider communicates with the process using JSON-lines protocol, either over
+//! stdin/stdout of spawned executable or over Unix socket. Each request is a single line:
+//! `{"id": 1, "method": "negotiate_step", "params": {...}}`
+//! and the process must respond with a single line containing the same `id`:
+//! `{"id": 1, "result": ...}` or `{"id": 1, "error": "message"}`.
+//!
+//! Methods:
+//! - `negotiate_step` with params `{"demand": ProposalView, "offer": ProposalView}`,
+//!   result is `NegotiationResult`, for example `{"Ready": {"offer": ProposalView}}`.
+//! - `fill_template` with params `{"template": OfferTemplate}`, result is `OfferTemplate`.
+//! - `on_agreement_approved` with params `{"agreement_id": String}`, result is ignored.
+//! - `on_agreement_terminated` with params `{"agreement_id": String, "result": String,
+//!   "reason": Option<String>}`, result is ignored.
+//!
+//! All I/O is asynchronous and happens in separate task, so slow process doesn't block
+//! `CompositeNegotiator`. If the process doesn't respond in time or exits, the connection
+//! is dropped (process is killed) and re-established with exponential backoff. Proposals
+//! are handled according to `FallbackPolicy` in the meantime, and also when the process
+//! responds with error. Agreement notifications can't change the Agreement anymore, so
+//! undelivered ones are queued and re-sent in order, when the connection is re-established.
+//! Notifications answered with error were delivered, so they are only logged.
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use strum::{Display, EnumString};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+use ya_agreement_utils::{OfferDefinition, OfferTemplate};
+
+use crate::market::negotiator::factory::ExternalNegotiatorConfig;
+use crate::market::negotiator::{AgreementResult, NegotiationResult, ProposalView};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Oldest notifications are dropped, when the process is unavailable for long time.
+const MAX_PENDING_NOTIFICATIONS: usize = 1000;
+
+/// Decides how Proposals are handled, when external negotiator misbehaves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, EnumString)]
+pub enum FallbackPolicy {
+    /// Leave negotiated Proposal unchanged, as if external negotiator was ready.
+    Accept,
+    /// Reject Proposal with non-final reason.
+    Reject,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+enum Request {
+    NegotiateStep {
+        demand: ProposalView,
+        offer: ProposalView,
+    },
+    FillTemplate {
+        template: OfferTemplate,
+    },
+    OnAgreementTerminated {
+        agreement_id: String,
+        result: String,
+        reason: Option<String>,
+    },
+    OnAgreementApproved {
+        agreement_id: String,
+    },
+}
+
+#[derive(Serialize)]
+struct RequestLine<'a> {
+    id: u64,
+    #[serde(flatten)]
+    request: &'a Request,
+}
+
+#[derive(Deserialize)]
+struct ResponseLine {
+    id: u64,
+    #[serde(default)]
+    result: Value,
+    error: Option<String>,
+}
+
+type Reader = Lines<BufReader<Box<dyn AsyncRead + Unpin + Send>>>;
+type Writer = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Connection to external negotiator process. Spawned process is killed,
+/// when connection is dropped.
+struct Connection {
+    writer: Writer,
+    lines: Reader,
+    _child: Option<Child>,
+}
+
+impl Connection {
+    fn spawn(config: &ExternalNegotiatorConfig, path: &Path) -> anyhow::Result<Self> {
+        let mut child = Command::new(path)
+            .args(&config.external_negotiator_args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to spawn [{}]", path.display()))?;
+
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("No stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("No stdout"))?;
+        Ok(Connection {
+            writer: Box::new(stdin),
+            lines: BufReader::new(Box::new(stdout) as Box<dyn AsyncRead + Unpin + Send>).lines(),
+            _child: Some(child),
+        })
+    }
+
+    #[cfg(unix)]
+    async fn connect(socket: &Path) -> anyhow::Result<Self> {
+        let stream = tokio::net::UnixStream::connect(socket)
+            .await
+            .with_context(|| format!("Failed to connect to [{}]", socket.display()))?;
+        let (reader, writer) = stream.into_split();
+        Ok(Connection {
+            writer: Box::new(writer),
+            lines: BufReader::new(Box::new(reader) as Box<dyn AsyncRead + Unpin + Send>).lines(),
+            _child: None,
+        })
+    }
+
+    #[cfg(not(unix))]
+    async fn connect(_socket: &Path) -> anyhow::Result<Self> {
+        bail!("Unix sockets are not supported on this platform")
+    }
+
+    /// Outer error means, that the request couldn't be delivered or answered. Inner error
+    /// is the error responded by the process.
+    async fn call(&mut self, id: u64, request: &Request) -> anyhow::Result<Result<Value, String>> {
+        let mut line = serde_json::to_string(&RequestLine { id, request })?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.flush().await?;
+
+        loop {
+            let line = self
+                .lines
+                .next_line()
+                .await?
+                .ok_or_else(|| anyhow!("Process closed connection"))?;
+
+            let response: ResponseLine = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid response line: {}", line))?;
+            // Skip responses for previous requests, which timed out.
+            if response.id != id {
+                continue;
+            }
+            return Ok(match response.error {
+                Some(error) => Err(error),
+                None => Ok(response.result),
+            });
+        }
+    }
+}
+
+struct Call {
+    request: Request,
+    /// `None` for notifications, which are queued in case of failure.
+    respond: Option<oneshot::Sender<anyhow::Result<Value>>>,
+}
+
+/// Owns connection to external process. Runs in separate task and handles
+/// requests one by one.
+struct Worker {
+    config: ExternalNegotiatorConfig,
+    connection: Option<Connection>,
+    next_id: u64,
+    failures: u32,
+    next_attempt: Instant,
+    pending: VecDeque<Request>,
+}
+
+impl Worker {
+    async fn run(mut self, mut calls: mpsc::UnboundedReceiver<Call>) {
+        while let Some(call) = calls.recv().await {
+            match call.respond {
+                Some(respond) => {
+                    let result = match self.flush_pending().await {
+                        Ok(()) => self.call(&call.request).await.and_then(|result| {
+                            result.map_err(|e| anyhow!("Process responded with error: {}", e))
+                        }),
+                        Err(e) => Err(e),
+                    };
+                    respond.send(result).ok();
+                }
+                None => {
+                    self.enqueue(call.request);
+                    if let Err(e) = self.flush_pending().await {
+                        log::debug!(
+                            "External negotiator notifications not delivered ({} pending). {}",
+                            self.pending.len(),
+                            e
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn enqueue(&mut self, request: Request) {
+        if self.pending.len() >= MAX_PENDING_NOTIFICATIONS {
+            if let Some(dropped) = self.pending.pop_front() {
+                log::warn!(
+                    "Too many undelivered external negotiator notifications. Dropping: {:?}",
+                    dropped
+                );
+            }
+        }
+        self.pending.push_back(request);
+    }
+
+    /// Delivers queued notifications in order. Stops on the first failure
+    /// to deliver. Notifications answered with error aren't re-sent.
+    async fn flush_pending(&mut self) -> anyhow::Result<()> {
+        while let Some(request) = self.pending.front().cloned() {
+            if let Err(error) = self.call(&request).await? {
+                log::warn!(
+                    "External negotiator failed to handle notification {:?}: {}",
+                    request,
+                    error
+                );
+            }
+            self.pending.pop_front();
+        }
+        Ok(())
+    }
+
+    async fn connect(&self) -> anyhow::Result<Connection> {
+        if Instant::now() < self.next_attempt {
+            bail!(
+                "Reconnecting in {:?}",
+                self.next_attempt.saturating_duration_since(Instant::now())
+            );
+        }
+
+        match (
+            &self.config.external_negotiator_path,
+            &self.config.external_negotiator_socket,
+        ) {
+            (Some(path), _) => Connection::spawn(&self.config, path),
+            (None, Some(socket)) => Connection::connect(socket).await,
+            (None, None) => bail!("External negotiator not configured"),
+        }
+    }
+
+    async fn call(&mut self, request: &Request) -> anyhow::Result<Result<Value, String>> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let timeout = self.config.external_negotiator_timeout;
+
+        let result = async {
+            let mut connection = match self.connection.take() {
+                Some(connection) => connection,
+                None => self.connect().await?,
+            };
+            let value = tokio::time::timeout(timeout, connection.call(id, request))
+                .await
+                .map_err(|_| anyhow!("No response within {:?}", timeout))??;
+            // Connection is dropped on I/O errors and timeouts, so misbehaving
+            // process will be restarted with the next request. Process responding
+            // with error is still alive.
+            self.connection = Some(connection);
+            Ok::<_, anyhow::Error>(value)
+        }
+        .await;
+
+        match &result {
+            Ok(_) => self.failures = 0,
+            Err(_) if Instant::now() < self.next_attempt => {}
+            Err(_) => self.failed(Instant::now()),
+        }
+        result
+    }
+
+    /// Delay before next connection attempt doubles with every failure, up to `MAX_BACKOFF`.
+    fn failed(&mut self, now: Instant) {
+        let delay = MIN_BACKOFF
+            .checked_mul(2u32.saturating_pow(self.failures))
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF);
+        self.failures = self.failures.saturating_add(1);
+        self.next_attempt = now + delay;
+    }
+}
+
+/// Handle to external negotiator process. Cheap to clone.
+#[derive(Clone)]
+pub struct ExternalNegotiator {
+    calls: mpsc::UnboundedSender<Call>,
+    fallback: FallbackPolicy,
+}
+
+impl ExternalNegotiator {
+    /// Returns `None`, if neither executable nor socket was configured.
+    /// Must be called inside tokio runtime, which drives communication with the process.
+    pub fn new(config: &ExternalNegotiatorConfig) -> Option<ExternalNegotiator> {
+        if config.external_negotiator_path.is_none() && config.external_negotiator_socket.is_none()
+        {
+            return None;
+        }
+
+        let (calls, receiver) = mpsc::unbounded_channel();
+        let worker = Worker {
+            config: config.clone(),
+            connection: None,
+            next_id: 0,
+            failures: 0,
+            next_attempt: Instant::now(),
+            pending: VecDeque::new(),
+        };
+        tokio::spawn(worker.run(receiver));
+
+        Some(ExternalNegotiator {
+            calls,
+            fallback: config.external_negotiator_fallback,
+        })
+    }
+
+    async fn call<T: for<'de> Deserialize<'de>>(&self, request: Request) -> anyhow::Result<T> {
+        let (respond, response) = oneshot::channel();
+        self.calls
+            .send(Call {
+                request,
+                respond: Some(respond),
+            })
+            .map_err(|_| anyhow!("External negotiator stopped"))?;
+        let result = response
+            .await
+            .map_err(|_| anyhow!("External negotiator stopped"))??;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    fn notify(&self, request: Request) {
+        self.calls
+            .send(Call {
+                request,
+                respond: None,
+            })
+            .map_err(|_| log::warn!("External negotiator stopped. Notification dropped."))
+            .ok();
+    }
+
+    /// Negotiates Proposal, after it was processed by other negotiator components.
+    /// Rejected Proposals aren't sent to external negotiator and it can't make
+    /// Proposal ready, if other components are still negotiating.
+    pub async fn negotiate_step(
+        &self,
+        demand: &ProposalView,
+        result: NegotiationResult,
+    ) -> NegotiationResult {
+        let (offer, ready) = match result {
+            NegotiationResult::Ready { offer } => (offer, true),
+            NegotiationResult::Negotiating { offer } => (offer, false),
+            reject => return reject,
+        };
+
+        let request = Request::NegotiateStep {
+            demand: demand.clone(),
+            offer: offer.clone(),
+        };
+        let result = match self.call(request).await {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!(
+                    "External negotiator failed to negotiate Proposal [{}]. Using fallback policy: {}. {}",
+                    demand.id,
+                    self.fallback,
+                    e
+                );
+                match self.fallback {
+                    FallbackPolicy::Accept => NegotiationResult::Ready { offer },
+                    FallbackPolicy::Reject => NegotiationResult::Reject {
+                        message: "Provider negotiator unavailable".to_string(),
+                        is_final: false,
+                    },
+                }
+            }
+        };
+
+        match result {
+            NegotiationResult::Ready { offer } if !ready => {
+                NegotiationResult::Negotiating { offer }
+            }
+            result => result,
+        }
+    }
+
+    pub async fn fill_template(
+        &self,
+        mut offer_template: OfferDefinition,
+    ) -> anyhow::Result<OfferDefinition> {
+        let request = Request::FillTemplate {
+            template: offer_template.offer.clone(),
+        };
+        match self.call(request).await {
+            Ok(template) => offer_template.offer = template,
+            Err(e) => match self.fallback {
+                FallbackPolicy::Accept => {
+                    log::warn!("External negotiator failed to fill Offer template. {}", e)
+                }
+                FallbackPolicy::Reject => return Err(e),
+            },
+        }
+        Ok(offer_template)
+    }
+
+    /// Notification is delivered in background. Failures don't affect the Agreement.
+    pub fn on_agreement_terminated(&self, agreement_id: &str, result: &AgreementResult) {
+        let (result, reason) = match result {
+            AgreementResult::ApprovalFailed => ("ApprovalFailed", None),
+            AgreementResult::ClosedByUs => ("ClosedByUs", None),
+            AgreementResult::ClosedByRequestor => ("ClosedByRequestor", None),
+            AgreementResult::Broken { reason } => ("Broken", Some(reason.to_string())),
+        };
+        self.notify(Request::OnAgreementTerminated {
+            agreement_id: agreement_id.to_string(),
+            result: result.to_string(),
+            reason,
+        });
+    }
+
+    /// Notification is delivered in background. Failures don't affect the Agreement.
+    pub fn on_agreement_approved(&self, agreement_id: &str) {
+        self.notify(Request::OnAgreementApproved {
+            agreement_id: agreement_id.to_string(),
+        });
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+    use ya_agreement_utils::agreement::expand;
+    use ya_client_model::market::proposal::State;
+
+    fn config(script: &str, fallback: FallbackPolicy) -> ExternalNegotiatorConfig {
+        ExternalNegotiatorConfig {
+            external_negotiator_path: Some("/bin/sh".into()),
+            external_negotiator_args: vec!["-c".to_string(), script.to_string()],
+            external_negotiator_socket: None,
+            external_negotiator_timeout: Duration::from_millis(500),
+            external_negotiator_fallback: fallback,
+        }
+    }
+
+    fn proposal() -> ProposalView {
+        ProposalView {
+            content: OfferTemplate {
+                properties: expand(json!({ "golem.com.pricing.model": "linear" })),
+                constraints: "()".to_string(),
+            },
+            id: "proposalId".to_string(),
+            issuer: Default::default(),
+            state: State::Initial,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn ready() -> NegotiationResult {
+        NegotiationResult::Ready { offer: proposal() }
+    }
+
+    /// Responds to each request with final rejection, echoing request id.
+    const REJECTING_SCRIPT: &str = r#"while read -r line; do
+        id=$(echo "$line" | sed 's/^{"id":\([0-9]*\).*/\1/')
+        echo "{\"id\":$id,\"result\":{\"Reject\":{\"message\":\"too cheap\",\"is_final\":true}}}"
+    done"#;
+
+    /// Responds to each request with ready Proposal, echoing request id.
+    const READY_SCRIPT: &str = r#"while read -r line; do
+        id=$(echo "$line" | sed 's/^{"id":\([0-9]*\).*/\1/')
+        offer=$(echo "$line" | sed 's/.*"offer":\({.*}\)}}$/\1/')
+        echo "{\"id\":$id,\"result\":{\"Ready\":{\"offer\":$offer}}}"
+    done"#;
+
+    #[actix_rt::test]
+    async fn test_external_negotiator_result() {
+        let negotiator =
+            ExternalNegotiator::new(&config(REJECTING_SCRIPT, FallbackPolicy::Accept)).unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(
+                negotiator.negotiate_step(&proposal(), ready()).await,
+                NegotiationResult::Reject {
+                    message: "too cheap".to_string(),
+                    is_final: true,
+                }
+            );
+        }
+    }
+
+    /// Responds with error to Agreement notifications and with final rejection
+    /// to other requests. Each spawn is recorded in file given as first argument.
+    const FAILING_NOTIFICATIONS_SCRIPT: &str = r#"echo spawned >> "$0"
+    while read -r line; do
+        id=$(echo "$line" | sed 's/^{"id":\([0-9]*\).*/\1/')
+        case "$line" in
+            *on_agreement*) echo "{\"id\":$id,\"error\":\"unknown agreement\"}" ;;
+            *) echo "{\"id\":$id,\"result\":{\"Reject\":{\"message\":\"too cheap\",\"is_final\":true}}}" ;;
+        esac
+    done"#;
+
+    #[actix_rt::test]
+    async fn test_external_negotiator_notification_answered_with_error() {
+        let dir = std::env::temp_dir().join(format!("ya-external-notify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let spawns = dir.join("spawns");
+        std::fs::remove_file(&spawns).ok();
+
+        let mut config = config(FAILING_NOTIFICATIONS_SCRIPT, FallbackPolicy::Accept);
+        config
+            .external_negotiator_args
+            .push(spawns.display().to_string());
+        let negotiator = ExternalNegotiator::new(&config).unwrap();
+
+        // Notifications answered with error are delivered and don't block negotiations.
+        for _ in 0..3 {
+            negotiator.on_agreement_approved("agreementId");
+            assert_eq!(
+                negotiator.negotiate_step(&proposal(), ready()).await,
+                NegotiationResult::Reject {
+                    message: "too cheap".to_string(),
+                    is_final: true,
+                }
+            );
+        }
+
+        let spawned = std::fs::read_to_string(&spawns).unwrap();
+        assert_eq!(spawned.lines().count(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[actix_rt::test]
+    async fn test_external_negotiator_cant_finish_negotiations() {
+        let negotiator =
+            ExternalNegotiator::new(&config(READY_SCRIPT, FallbackPolicy::Reject)).unwrap();
+        let offer = proposal();
+
+        assert_eq!(
+            negotiator
+                .negotiate_step(
+                    &proposal(),
+                    NegotiationResult::Negotiating {
+                        offer: offer.clone()
+                    }
+                )
+                .await,
+            NegotiationResult::Negotiating { offer }
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_external_negotiator_fallback_on_timeout() {
+        let negotiator =
+            ExternalNegotiator::new(&config("sleep 10", FallbackPolicy::Reject)).unwrap();
+
+        assert_eq!(
+            negotiator.negotiate_step(&proposal(), ready()).await,
+            NegotiationResult::Reject {
+                message: "Provider negotiator unavailable".to_string(),
+                is_final: false,
+            }
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_external_negotiator_fallback_on_exit() {
+        let negotiator =
+            ExternalNegotiator::new(&config("exit 1", FallbackPolicy::Accept)).unwrap();
+
+        assert_eq!(
+            negotiator.negotiate_step(&proposal(), ready()).await,
+            ready()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_external_negotiator_respawn_backoff() {
+        let dir = std::env::temp_dir().join(format!("ya-external-nego-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let spawns = dir.join("spawns");
+        std::fs::remove_file(&spawns).ok();
+
+        let script = format!("echo spawned >> {}; exit 1", spawns.display());
+        let negotiator = ExternalNegotiator::new(&config(&script, FallbackPolicy::Accept)).unwrap();
+
+        for _ in 0..5 {
+            assert_eq!(
+                negotiator.negotiate_step(&proposal(), ready()).await,
+                ready()
+            );
+        }
+
+        // Next attempts happen only after backoff delay.
+        let spawned = std::fs::read_to_string(&spawns).unwrap();
+        assert_eq!(spawned.lines().count(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let now = Instant::now();
+        let mut worker = Worker {
+            config: config("exit 1", FallbackPolicy::Accept),
+            connection: None,
+            next_id: 0,
+            failures: 0,
+            next_attempt: now,
+            pending: VecDeque::new(),
+        };
+
+        let delays: Vec<_> = (0..10)
+            .map(|_| {
+                worker.failed(now);
+                worker.next_attempt - now
+            })
+            .collect();
+        assert_eq!(delays[0], MIN_BACKOFF);
+        assert_eq!(delays[1], MIN_BACKOFF * 2);
+        assert_eq!(delays[3], MIN_BACKOFF * 8);
+        assert_eq!(delays[9], MAX_BACKOFF);
+    }
+}
@@ -1,11 +1,15 @@
 use actix::Addr;
+use bigdecimal::BigDecimal;
 use humantime;
+use std::path::PathBuf;
 use std::sync::Arc;
 use structopt::StructOpt;
 
 use ya_manifest_utils::PolicyConfig;
 
+use super::builtin::dynamic_price::{HourRange, PriceFloor};
 use super::common::NegotiatorAddr;
+use super::external::FallbackPolicy;
 use crate::market::config::MarketConfig;
 use crate::market::negotiator::{AcceptAllNegotiator, CompositeNegotiator};
 use crate::market::ProviderMarket;
@@ -62,6 +66,69 @@ pub struct PaymentTimeoutConfig {
     pub payment_timeout_required_duration: std::time::Duration,
 }
 
+/// Configuration for DynamicPrice negotiator, which replaces default Price negotiator
+/// when enabled.
+#[derive(StructOpt, Clone, Debug)]
+pub struct DynamicPriceNegotiatorConfig {
+    /// Counter-offer prices instead of rejecting Proposals below Offer price
+    #[structopt(long, env)]
+    pub dynamic_pricing: bool,
+    /// Minimal acceptable price per usage coefficient (`coefficient=price`).
+    /// Constant part of the price is named `initial`.
+    #[structopt(long, env, use_delimiter = true)]
+    pub price_floor: Vec<PriceFloor>,
+    /// Part of the difference between our and Requestor's price conceded in every round
+    #[structopt(long, env, default_value = "0.5")]
+    pub price_concession: f64,
+    #[structopt(long, env, default_value = "5")]
+    pub max_price_rounds: u32,
+    /// Hours of the day (local time), when `peak_price_multiplier` applies, for example `18-22`
+    #[structopt(long, env)]
+    pub peak_hours: Option<HourRange>,
+    #[structopt(long, env, default_value = "1.0")]
+    pub peak_price_multiplier: f64,
+    /// Prices are multiplied by `1 + load_price_multiplier * load`, where load is
+    /// a fraction of allocated hardware resources
+    #[structopt(long, env, default_value = "0.0")]
+    pub load_price_multiplier: f64,
+}
+
+/// Configuration for RequestorReputation negotiator
+#[derive(StructOpt, Clone, Debug)]
+pub struct RequestorReputationNegotiatorConfig {
+    /// Proposals from Requestors with lower reputation score are rejected
+    #[structopt(long, env, allow_hyphen_values = true, default_value = "-10")]
+    pub min_requestor_score: i64,
+}
+
+/// Configuration for OverdueInvoices negotiator
+#[derive(StructOpt, Clone, Debug)]
+pub struct OverdueInvoicesNegotiatorConfig {
+    /// Proposals from Requestors with overdue invoices for a higher total amount are rejected
+    #[structopt(long, env)]
+    pub max_overdue_amount: Option<BigDecimal>,
+}
+
+/// Configuration for External negotiator component, which delegates negotiations
+/// to process speaking JSON-lines protocol (see `negotiator::external`).
+#[derive(StructOpt, Clone, Debug)]
+pub struct ExternalNegotiatorConfig {
+    /// Executable started by the Provider, communicating over stdin/stdout
+    #[structopt(long, env)]
+    pub external_negotiator_path: Option<PathBuf>,
+    /// Arguments passed to `external_negotiator_path` executable
+    #[structopt(long, env, use_delimiter = true)]
+    pub external_negotiator_args: Vec<String>,
+    /// Unix socket of already running negotiator process
+    #[structopt(long, env, conflicts_with = "external-negotiator-path")]
+    pub external_negotiator_socket: Option<PathBuf>,
+    #[structopt(long, env, parse(try_from_str = humantime::parse_duration), default_value = "5s")]
+    pub external_negotiator_timeout: std::time::Duration,
+    /// Policy used, when external negotiator doesn't respond correctly (Accept | Reject)
+    #[structopt(long, env, default_value = "Reject")]
+    pub external_negotiator_fallback: FallbackPolicy,
+}
+
 /// Configuration for LimitAgreements Negotiator.
 #[derive(StructOpt, Clone, Debug)]
 pub struct CompositeNegotiatorConfig {
@@ -77,6 +144,14 @@ pub struct CompositeNegotiatorConfig {
     pub payment_timeout_config: PaymentTimeoutConfig,
     #[structopt(flatten)]
     pub policy_config: PolicyConfig,
+    #[structopt(flatten)]
+    pub reputation_config: RequestorReputationNegotiatorConfig,
+    #[structopt(flatten)]
+    pub overdue_invoices_config: OverdueInvoicesNegotiatorConfig,
+    #[structopt(flatten)]
+    pub dynamic_price_config: DynamicPriceNegotiatorConfig,
+    #[structopt(flatten)]
+    pub external_config: ExternalNegotiatorConfig,
 }
 
 #[derive(StructOpt, Clone, Debug)]
@@ -11,6 +11,7 @@ use tokio::sync::watch;
 pub use crate::config::presets::Presets;
 use crate::events::Event;
 use crate::execution::ExeUnitsRegistry;
+use crate::payments::PriceTier;
 use crate::startup_config::FileMonitor;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -23,6 +24,12 @@ pub struct Preset {
     pub initial_price: f64,
     // It's important that all values are sorted, so that other tools can easily detect changes.
     pub usage_coeffs: BTreeMap<String, f64>,
+    /// Usage price tiers for `tiered` pricing model.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub usage_tiers: BTreeMap<String, Vec<PriceTier>>,
+    /// Maximal total cost of an Agreement for `capped` pricing model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
 }
 
 impl Preset {
@@ -226,6 +233,8 @@ impl Default for Preset {
             exeunit_name: "wasmtime".to_string(),
             pricing_model: "linear".to_string(),
             usage_coeffs,
+            usage_tiers: Default::default(),
+            max_cost: None,
         }
     }
 }
@@ -236,6 +245,8 @@ impl PartialEq for Preset {
             && self.exeunit_name == other.exeunit_name
             && self.pricing_model == other.pricing_model
             && self.usage_coeffs == other.usage_coeffs
+            && self.usage_tiers == other.usage_tiers
+            && self.max_cost == other.max_cost
     }
 }
 
@@ -289,6 +300,19 @@ fn display_preset(
             coeff,
             width = align_coeff
         )?;
+        for tier in preset.usage_tiers.get(name).into_iter().flatten() {
+            writeln!(
+                f,
+                "      {:width$}{} GLM",
+                format!("above {}", tier.from),
+                tier.price,
+                width = align_coeff - 2
+            )?;
+        }
+    }
+
+    if let Some(max_cost) = preset.max_cost {
+        writeln!(f, "{:width$}{} GLM", "Max cost:", max_cost, width = align)?;
     }
 
     Ok(())
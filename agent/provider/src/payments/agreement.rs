@@ -193,6 +193,10 @@ impl AgreementPayment {
                 });
 
         let cost: BigDecimal = filtered_activities.clone().map(|(cost, _)| cost).sum();
+        let cost = match self.payment_model.max_cost() {
+            Some(max_cost) if cost > max_cost => max_cost,
+            _ => cost,
+        };
 
         let usage_len = self.payment_model.expected_usage_len();
         let usage: Vec<f64> = filtered_activities.map(|(_, usage)| usage).fold(
@@ -0,0 +1,445 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ya_client::model::payment::{DocumentStatus, Invoice};
+use ya_client::model::NodeId;
+
+/// Target of structured dunning log lines, which can be filtered
+/// with `RUST_LOG=dunning=info`.
+pub const DUNNING_LOG_TARGET: &str = "dunning";
+
+/// Event related to collecting payment for issued Invoice.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum DunningEvent {
+    #[serde(rename_all = "camelCase")]
+    InvoiceResent {
+        invoice_id: String,
+        requestor: NodeId,
+        attempt: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    InvoiceOverdue {
+        invoice_id: String,
+        requestor: NodeId,
+        amount: BigDecimal,
+        days_overdue: i64,
+    },
+    #[serde(rename_all = "camelCase")]
+    InvoiceRejected {
+        invoice_id: String,
+        requestor: NodeId,
+        amount: BigDecimal,
+    },
+    #[serde(rename_all = "camelCase")]
+    InvoicePaid {
+        invoice_id: String,
+        requestor: NodeId,
+        days_overdue: i64,
+    },
+}
+
+impl DunningEvent {
+    /// Logs event as a single line of JSON.
+    pub fn emit(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => match self {
+                DunningEvent::InvoiceOverdue { .. } | DunningEvent::InvoiceRejected { .. } => {
+                    log::warn!(target: DUNNING_LOG_TARGET, "{}", json)
+                }
+                _ => log::info!(target: DUNNING_LOG_TARGET, "{}", json),
+            },
+            Err(e) => log::error!("Failed to serialize dunning event {:?}: {}", self, e),
+        }
+    }
+}
+
+/// Invoice issued to Requestor and not paid yet.
+#[derive(Clone, Debug)]
+struct OutstandingInvoice {
+    requestor: NodeId,
+    amount: BigDecimal,
+    due_date: DateTime<Utc>,
+    accepted: bool,
+    rejected: bool,
+    /// Last time Invoice was delivered to Requestor. `None` until first send succeeds.
+    last_sent: Option<DateTime<Utc>>,
+    resends: u32,
+    /// Days overdue reported in last `InvoiceOverdue` event.
+    reported_overdue: Option<i64>,
+}
+
+impl OutstandingInvoice {
+    fn days_overdue(&self, now: DateTime<Utc>) -> Option<i64> {
+        (now > self.due_date).then(|| (now - self.due_date).num_days())
+    }
+}
+
+/// Overdue Invoices of single Requestor.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestorOverdue {
+    pub invoices: u32,
+    pub amount: BigDecimal,
+    pub max_days_overdue: i64,
+}
+
+/// Invoices waiting for payment, tracked to remind Requestors about them
+/// and to find Requestors with overdue payments.
+#[derive(Clone, Debug, Default)]
+pub struct Dunning {
+    invoices: HashMap<String, OutstandingInvoice>,
+}
+
+impl Dunning {
+    pub fn track(&mut self, invoice: &Invoice) {
+        self.invoices
+            .entry(invoice.invoice_id.clone())
+            .or_insert_with(|| OutstandingInvoice {
+                requestor: invoice.recipient_id,
+                amount: invoice.amount.clone(),
+                due_date: invoice.payment_due_date,
+                accepted: false,
+                rejected: false,
+                last_sent: None,
+                resends: 0,
+                reported_overdue: None,
+            });
+    }
+
+    /// Tracks Invoice in status reported by payment service. Rebuilds state after
+    /// restart and catches up with status changes, which events were missed.
+    /// `InvoiceRejected` is returned only for Invoices tracked before.
+    pub fn update(&mut self, invoice: &Invoice, now: DateTime<Utc>) -> Option<DunningEvent> {
+        let invoice_id = invoice.invoice_id.as_str();
+        let known = self.invoices.contains_key(invoice_id);
+        match invoice.status {
+            DocumentStatus::Settled => self.settled(invoice_id, now),
+            DocumentStatus::Cancelled => {
+                self.invoices.remove(invoice_id);
+                None
+            }
+            status => {
+                self.track(invoice);
+                let tracked = self.invoices.get_mut(invoice_id)?;
+                match status {
+                    DocumentStatus::Accepted => tracked.accepted = true,
+                    DocumentStatus::Rejected if known && !tracked.rejected => {
+                        return self.rejected(invoice_id)
+                    }
+                    DocumentStatus::Rejected => tracked.rejected = true,
+                    // Invoice issued before restart is sent again after resend interval.
+                    _ => {
+                        tracked.last_sent.get_or_insert(invoice.timestamp);
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    pub fn sent(&mut self, invoice_id: &str, now: DateTime<Utc>) {
+        if let Some(invoice) = self.invoices.get_mut(invoice_id) {
+            invoice.last_sent = Some(now);
+        }
+    }
+
+    pub fn resent(&mut self, invoice_id: &str, now: DateTime<Utc>) -> Option<DunningEvent> {
+        let invoice = self.invoices.get_mut(invoice_id)?;
+        invoice.last_sent = Some(now);
+        invoice.resends += 1;
+        Some(DunningEvent::InvoiceResent {
+            invoice_id: invoice_id.to_string(),
+            requestor: invoice.requestor,
+            attempt: invoice.resends,
+        })
+    }
+
+    pub fn accepted(&mut self, invoice_id: &str) {
+        if let Some(invoice) = self.invoices.get_mut(invoice_id) {
+            invoice.accepted = true;
+        }
+    }
+
+    pub fn rejected(&mut self, invoice_id: &str) -> Option<DunningEvent> {
+        let invoice = self.invoices.get_mut(invoice_id)?;
+        invoice.rejected = true;
+        Some(DunningEvent::InvoiceRejected {
+            invoice_id: invoice_id.to_string(),
+            requestor: invoice.requestor,
+            amount: invoice.amount.clone(),
+        })
+    }
+
+    pub fn settled(&mut self, invoice_id: &str, now: DateTime<Utc>) -> Option<DunningEvent> {
+        let invoice = self.invoices.remove(invoice_id)?;
+        Some(DunningEvent::InvoicePaid {
+            invoice_id: invoice_id.to_string(),
+            requestor: invoice.requestor,
+            days_overdue: invoice.days_overdue(now).unwrap_or(0),
+        })
+    }
+
+    /// Delivered Invoices, which Requestor neither accepted nor rejected
+    /// within `interval` since they were sent last time.
+    pub fn to_resend(&self, now: DateTime<Utc>, interval: Duration) -> Vec<String> {
+        self.invoices
+            .iter()
+            .filter(|(_, invoice)| !invoice.accepted && !invoice.rejected)
+            .filter(|(_, invoice)| {
+                invoice
+                    .last_sent
+                    .map(|last_sent| now - last_sent >= interval)
+                    .unwrap_or(false)
+            })
+            .map(|(invoice_id, _)| invoice_id.clone())
+            .collect()
+    }
+
+    /// Returns `InvoiceOverdue` event for every Invoice, which became overdue
+    /// or whose days overdue counter grew since last check.
+    pub fn check_overdue(&mut self, now: DateTime<Utc>) -> Vec<DunningEvent> {
+        let mut events = Vec::new();
+        for (invoice_id, invoice) in self.invoices.iter_mut() {
+            let days_overdue = match invoice.days_overdue(now) {
+                Some(days) => days,
+                None => continue,
+            };
+            if !matches!(invoice.reported_overdue, Some(days) if days >= days_overdue) {
+                invoice.reported_overdue = Some(days_overdue);
+                events.push(DunningEvent::InvoiceOverdue {
+                    invoice_id: invoice_id.clone(),
+                    requestor: invoice.requestor,
+                    amount: invoice.amount.clone(),
+                    days_overdue,
+                });
+            }
+        }
+        events
+    }
+
+    pub fn overdue(&self, now: DateTime<Utc>) -> HashMap<NodeId, RequestorOverdue> {
+        let mut overdue = HashMap::<NodeId, RequestorOverdue>::new();
+        for invoice in self.invoices.values() {
+            if let Some(days_overdue) = invoice.days_overdue(now) {
+                let entry = overdue.entry(invoice.requestor).or_default();
+                entry.invoices += 1;
+                entry.amount += invoice.amount.clone();
+                entry.max_days_overdue = entry.max_days_overdue.max(days_overdue);
+            }
+        }
+        overdue
+    }
+
+    pub fn overdue_amount(&self, requestor: &NodeId, now: DateTime<Utc>) -> BigDecimal {
+        self.invoices
+            .values()
+            .filter(|invoice| invoice.requestor == *requestor)
+            .filter(|invoice| invoice.days_overdue(now).is_some())
+            .fold(BigDecimal::zero(), |sum, invoice| sum + &invoice.amount)
+    }
+}
+
+/// `Dunning` shared between Payments actor, which tracks Invoices,
+/// and negotiators checking Requestors' overdue payments.
+/// State is kept in memory and rebuilt from payment service on startup.
+#[derive(Clone, Debug, Default)]
+pub struct DunningManager {
+    state: Arc<Mutex<Dunning>>,
+}
+
+impl DunningManager {
+    pub fn track(&self, invoice: &Invoice) {
+        self.state.lock().unwrap().track(invoice)
+    }
+
+    pub fn update(&self, invoice: &Invoice) -> Option<DunningEvent> {
+        self.state.lock().unwrap().update(invoice, Utc::now())
+    }
+
+    pub fn sent(&self, invoice_id: &str) {
+        self.state.lock().unwrap().sent(invoice_id, Utc::now())
+    }
+
+    pub fn resent(&self, invoice_id: &str) -> Option<DunningEvent> {
+        self.state.lock().unwrap().resent(invoice_id, Utc::now())
+    }
+
+    pub fn accepted(&self, invoice_id: &str) {
+        self.state.lock().unwrap().accepted(invoice_id)
+    }
+
+    pub fn rejected(&self, invoice_id: &str) -> Option<DunningEvent> {
+        self.state.lock().unwrap().rejected(invoice_id)
+    }
+
+    pub fn settled(&self, invoice_id: &str) -> Option<DunningEvent> {
+        self.state.lock().unwrap().settled(invoice_id, Utc::now())
+    }
+
+    pub fn to_resend(&self, interval: Duration) -> Vec<String> {
+        self.state.lock().unwrap().to_resend(Utc::now(), interval)
+    }
+
+    pub fn check_overdue(&self) -> Vec<DunningEvent> {
+        self.state.lock().unwrap().check_overdue(Utc::now())
+    }
+
+    pub fn overdue(&self) -> HashMap<NodeId, RequestorOverdue> {
+        self.state.lock().unwrap().overdue(Utc::now())
+    }
+
+    pub fn overdue_amount(&self, requestor: &NodeId) -> BigDecimal {
+        self.state
+            .lock()
+            .unwrap()
+            .overdue_amount(requestor, Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice(invoice_id: &str, amount: i64, due_date: DateTime<Utc>) -> Invoice {
+        Invoice {
+            invoice_id: invoice_id.to_string(),
+            issuer_id: NodeId::default(),
+            recipient_id: NodeId::default(),
+            payee_addr: String::new(),
+            payer_addr: String::new(),
+            payment_platform: String::new(),
+            timestamp: Utc::now(),
+            agreement_id: "agreement-id".to_string(),
+            activity_ids: vec![],
+            amount: BigDecimal::from(amount),
+            payment_due_date: due_date,
+            status: DocumentStatus::Issued,
+        }
+    }
+
+    #[test]
+    fn test_overdue_reported_once_per_day() {
+        let now = Utc::now();
+        let requestor = NodeId::default();
+        let mut dunning = Dunning::default();
+        dunning.track(&invoice("due", 5, now + Duration::days(1)));
+        dunning.track(&invoice("overdue", 3, now - Duration::days(2)));
+        dunning.track(&invoice("paid", 7, now - Duration::days(1)));
+        dunning.settled("paid", now);
+
+        let events = dunning.check_overdue(now);
+        assert_eq!(
+            events,
+            vec![DunningEvent::InvoiceOverdue {
+                invoice_id: "overdue".to_string(),
+                requestor,
+                amount: BigDecimal::from(3),
+                days_overdue: 2,
+            }]
+        );
+        assert!(dunning.check_overdue(now + Duration::hours(1)).is_empty());
+        assert_eq!(dunning.check_overdue(now + Duration::days(2)).len(), 2);
+
+        assert_eq!(dunning.overdue_amount(&requestor, now), BigDecimal::from(3));
+        let overdue = dunning.overdue(now + Duration::days(2));
+        assert_eq!(
+            overdue.get(&requestor),
+            Some(&RequestorOverdue {
+                invoices: 2,
+                amount: BigDecimal::from(8),
+                max_days_overdue: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_resend_only_unaccepted() {
+        let now = Utc::now();
+        let interval = Duration::hours(1);
+        let mut dunning = Dunning::default();
+        for invoice_id in &["not-sent", "sent", "accepted", "rejected"] {
+            dunning.track(&invoice(invoice_id, 1, now));
+        }
+        for invoice_id in &["sent", "accepted", "rejected"] {
+            dunning.sent(invoice_id, now);
+        }
+        dunning.accepted("accepted");
+        dunning.rejected("rejected");
+
+        assert!(dunning.to_resend(now, interval).is_empty());
+        let later = now + interval;
+        assert_eq!(dunning.to_resend(later, interval), vec!["sent".to_string()]);
+
+        dunning.resent("sent", later);
+        assert!(dunning.to_resend(later, interval).is_empty());
+    }
+
+    #[test]
+    fn test_update_from_invoice_status() {
+        let now = Utc::now();
+        let interval = Duration::hours(1);
+        let mut dunning = Dunning::default();
+        let with_status = |invoice_id: &str, status: DocumentStatus| Invoice {
+            status,
+            timestamp: now - interval,
+            ..invoice(invoice_id, 1, now - Duration::days(1))
+        };
+
+        // Restored after restart.
+        for (invoice_id, status) in [
+            ("issued", DocumentStatus::Issued),
+            ("received", DocumentStatus::Received),
+            ("accepted", DocumentStatus::Accepted),
+            ("rejected", DocumentStatus::Rejected),
+            ("settled", DocumentStatus::Settled),
+            ("cancelled", DocumentStatus::Cancelled),
+        ] {
+            assert_eq!(dunning.update(&with_status(invoice_id, status), now), None);
+        }
+        let mut to_resend = dunning.to_resend(now, interval);
+        to_resend.sort();
+        assert_eq!(
+            to_resend,
+            vec!["issued".to_string(), "received".to_string()]
+        );
+        assert_eq!(
+            dunning
+                .overdue(now)
+                .get(&NodeId::default())
+                .unwrap()
+                .invoices,
+            4
+        );
+
+        // Requestor's decision reported by payment service stops resending.
+        assert_eq!(
+            dunning.update(&with_status("issued", DocumentStatus::Rejected), now),
+            Some(DunningEvent::InvoiceRejected {
+                invoice_id: "issued".to_string(),
+                requestor: NodeId::default(),
+                amount: BigDecimal::from(1),
+            })
+        );
+        assert_eq!(
+            dunning.update(&with_status("received", DocumentStatus::Accepted), now),
+            None
+        );
+        assert!(dunning.to_resend(now, interval).is_empty());
+
+        assert!(matches!(
+            dunning.update(&with_status("accepted", DocumentStatus::Settled), now),
+            Some(DunningEvent::InvoicePaid { .. })
+        ));
+        assert_eq!(
+            dunning
+                .overdue(now)
+                .get(&NodeId::default())
+                .unwrap()
+                .invoices,
+            3
+        );
+    }
+}
@@ -1,13 +1,18 @@
 use super::model::{PaymentDescription, PaymentModel};
-use super::pricing::LinearPricing;
+use super::pricing::{CappedPricing, LinearPricing, TieredPricing};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use std::sync::Arc;
 
 pub struct PaymentModelFactory;
 
 impl PaymentModelFactory {
     pub fn create<'a>(commercials: &'a PaymentDescription<'a>) -> Result<Arc<dyn PaymentModel>> {
-        Ok(Arc::new(LinearPricing::new(commercials)?))
+        Ok(match commercials.get_pricing_model()?.as_str() {
+            "linear" => Arc::new(LinearPricing::new(commercials)?),
+            "tiered" => Arc::new(TieredPricing::new(commercials)?),
+            "capped" => Arc::new(CappedPricing::new(commercials)?),
+            other => bail!("Unsupported pricing model: {}", other),
+        })
     }
 }
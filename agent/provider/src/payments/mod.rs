@@ -1,10 +1,15 @@
 mod agreement;
+mod dunning;
 mod factory;
 mod model;
 #[allow(clippy::module_inception)]
 mod payments;
 mod pricing;
 
+pub use dunning::{DunningEvent, DunningManager};
 pub use factory::PaymentModelFactory;
 pub use payments::{Payments, PaymentsConfig};
-pub use pricing::{AccountView, LinearPricing, LinearPricingOffer, PricingOffer};
+pub use pricing::{
+    AccountView, CappedPricingOffer, LinearPricing, LinearPricingOffer, PriceTier, PricingOffer,
+    TieredPricingOffer, PRICING_MODELS,
+};
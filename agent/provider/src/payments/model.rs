@@ -1,5 +1,6 @@
 use anyhow::Result;
 use bigdecimal::BigDecimal;
+use serde::de::DeserializeOwned;
 use std::time::Duration;
 
 use ya_agreement_utils::{AgreementView, Error};
@@ -15,6 +16,11 @@ use crate::market::negotiator::builtin::payment_timeout::PAYMENT_TIMEOUT_PROPERT
 pub trait PaymentModel {
     fn compute_cost(&self, usage: &[f64]) -> Result<BigDecimal>;
     fn expected_usage_len(&self) -> usize;
+
+    /// Maximal total cost of all activities in Agreement.
+    fn max_cost(&self) -> Option<BigDecimal> {
+        None
+    }
 }
 
 /// Extracted commercial part of agreement.
@@ -27,9 +33,28 @@ impl<'a> PaymentDescription<'a> {
         Ok(PaymentDescription::<'a> { agreement })
     }
 
+    /// Offers without pricing model tag are treated as linear.
+    pub fn get_pricing_model(&self) -> Result<String> {
+        let model_addr = "/offer/properties/golem/com/pricing/model/@tag";
+        match self.agreement.pointer_typed::<String>(model_addr) {
+            Ok(model) => Ok(model),
+            Err(Error::NoKey(_)) => Ok("linear".to_string()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
     pub fn get_usage_coefficients(&self) -> Result<Vec<f64>> {
-        let coeffs_addr = "/offer/properties/golem/com/pricing/model/linear/coeffs";
-        Ok(self.agreement.pointer_typed::<Vec<f64>>(coeffs_addr)?)
+        self.get_pricing_param("coeffs")
+    }
+
+    /// Returns parameter of Agreement's pricing model, for example `coeffs`.
+    pub fn get_pricing_param<T: DeserializeOwned>(&self, name: &str) -> Result<T> {
+        let param_addr = format!(
+            "/offer/properties/golem/com/pricing/model/{}/{}",
+            self.get_pricing_model()?,
+            name
+        );
+        Ok(self.agreement.pointer_typed::<T>(&param_addr)?)
     }
 
     pub fn get_update_interval(&self) -> Result<Duration> {
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::sync::Arc;
 use std::time::Duration;
@@ -14,7 +14,7 @@ use log;
 use serde_json::json;
 use structopt::StructOpt;
 use ya_client::activity::ActivityProviderApi;
-use ya_client::model::payment::{DebitNote, Invoice, NewDebitNote, NewInvoice};
+use ya_client::model::payment::{DebitNote, DocumentStatus, Invoice, NewDebitNote, NewInvoice};
 use ya_client::model::payment::{DebitNoteEvent, DebitNoteEventType, InvoiceEventType};
 use ya_client::payment::PaymentApi;
 
@@ -30,9 +30,11 @@ use crate::execution::{ActivityDestroyed, CreateActivity};
 use crate::interval::RelativeInterval;
 use crate::market::provider_market::NewAgreement;
 use crate::market::termination_reason::BreakReason;
+use crate::reputation::{ReputationEvent, ReputationManager};
 use crate::tasks::{AgreementBroken, AgreementClosed, BreakAgreement};
 
 use super::agreement::{compute_cost, ActivityPayment, AgreementPayment, CostInfo};
+use super::dunning::{DunningEvent, DunningManager};
 use super::model::PaymentModel;
 
 // =========================================== //
@@ -79,6 +81,13 @@ struct InvoiceAccepted {
     pub invoice_id: String,
 }
 
+/// Message sent when invoice is rejected by requestor.
+#[derive(Message, Clone)]
+#[rtype(result = "Result<()>")]
+struct InvoiceRejected {
+    pub invoice_id: String,
+}
+
 /// Message sent when invoice is settled (fully paid).
 #[derive(Message, Clone)]
 #[rtype(result = "Result<()>")]
@@ -86,6 +95,11 @@ struct InvoiceSettled {
     pub invoice_id: String,
 }
 
+/// Re-sends unaccepted invoices and reports overdue ones.
+#[derive(Message, Clone)]
+#[rtype(result = "()")]
+struct CheckDunning;
+
 /// Gets costs summary for agreement.
 #[derive(Message, Clone)]
 #[rtype(result = "Result<CostsSummary>")]
@@ -122,6 +136,12 @@ pub struct PaymentsConfig {
     pub get_events_error_timeout: Duration,
     #[structopt(long, env, parse(try_from_str = humantime::parse_duration), default_value = "5s")]
     pub invoice_reissue_interval: Duration,
+    /// Invoices not accepted by Requestor are sent again after this time
+    #[structopt(long, env, parse(try_from_str = humantime::parse_duration), default_value = "1h")]
+    pub invoice_resend_interval: Duration,
+    /// How often unpaid invoices are checked for reminders and overdue payments
+    #[structopt(long, env, parse(try_from_str = humantime::parse_duration), default_value = "5min")]
+    pub invoice_dunning_interval: Duration,
     #[structopt(skip = "you-forgot-to-set-session-id")]
     pub session_id: String,
 }
@@ -143,6 +163,8 @@ pub struct Payments {
 
     invoices_to_pay: Vec<Invoice>,
     earnings: BigDecimal,
+    reputation: ReputationManager,
+    dunning: DunningManager,
 
     break_agreement_signal: SignalSlot<BreakAgreement>,
 }
@@ -154,6 +176,8 @@ impl Payments {
         activity_api: ActivityProviderApi,
         payment_api: PaymentApi,
         config: PaymentsConfig,
+        reputation: ReputationManager,
+        dunning: DunningManager,
     ) -> Payments {
         let provider_ctx = ProviderCtx {
             activity_api: Arc::new(activity_api),
@@ -168,6 +192,8 @@ impl Payments {
             context: Arc::new(provider_ctx),
             invoices_to_pay: vec![],
             earnings: BigDecimal::zero(),
+            reputation,
+            dunning,
             break_agreement_signal: SignalSlot::<BreakAgreement>::default(),
         }
     }
@@ -305,6 +331,28 @@ async fn send_debit_note(
     Ok(debit_note)
 }
 
+/// Rebuilds dunning state from Invoices issued before restart. Invoices issued
+/// by Provider are paid to one of its accounts.
+async fn restore_dunning(provider_ctx: Arc<ProviderCtx>, dunning: DunningManager) -> Result<()> {
+    let addresses: HashSet<String> = provider_ctx
+        .payment_api
+        .get_provider_accounts()
+        .await?
+        .into_iter()
+        .map(|account| account.address)
+        .collect();
+    let invoices = provider_ctx
+        .payment_api
+        .get_invoices::<Utc>(None, None)
+        .await?;
+    for invoice in invoices {
+        if addresses.contains(&invoice.payee_addr) {
+            dunning.update(&invoice);
+        }
+    }
+    Ok(())
+}
+
 async fn check_invoice_events(provider_ctx: Arc<ProviderCtx>, payments_addr: Addr<Payments>) {
     let config = &provider_ctx.config;
     let mut after_timestamp = Utc::now();
@@ -339,11 +387,10 @@ async fn check_invoice_events(provider_ctx: Arc<ProviderCtx>, payments_addr: Add
                     log::info!("Invoice [{}] settled by requestor.", invoice_id);
                     payments_addr.do_send(InvoiceSettled { invoice_id })
                 }
-                // InvoiceEventType::InvoiceRejectedEvent {} => {
-                //     log::warn!("Invoice [{}] rejected by requestor.", invoice_id)
-                //     // TODO: Send signal to other provider's modules to react to this situation.
-                //     //       Probably we don't want to cooperate with this Requestor anymore.
-                // }
+                InvoiceEventType::InvoiceRejectedEvent { .. } => {
+                    log::warn!("Invoice [{}] rejected by requestor.", invoice_id);
+                    payments_addr.do_send(InvoiceRejected { invoice_id })
+                }
                 _ => log::warn!("Unexpected event received: {:?}", event.event_type),
             }
             after_timestamp = event.event_date;
@@ -761,6 +808,7 @@ impl Handler<AgreementClosed> for Payments {
             let payment_timeout = agreement.payment_timeout;
             let myself = ctx.address();
             let ctx = self.context.clone();
+            let dunning = self.dunning.clone();
 
             let future = async move {
                 let stop_tracking = StopTrackingCategory {
@@ -778,6 +826,7 @@ impl Handler<AgreementClosed> for Payments {
                         payment_timeout,
                     })
                     .await??;
+                dunning.track(&invoice);
                 // We do not want to wait for sending Invoice, as we are eager to start new
                 // negotiations. Waiting for invoice to be sent to Requestor could result in
                 // hanging Provider waiting for Requestor to appear in the net and receive the Invoice
@@ -846,6 +895,7 @@ impl Handler<SendInvoice> for Payments {
 
     fn handle(&mut self, msg: SendInvoice, _ctx: &mut Context<Self>) -> Self::Result {
         let provider_ctx = self.context.clone();
+        let dunning = self.dunning.clone();
         async move {
             log::info!("Sending invoice [{}] to requestor...", msg.invoice_id);
 
@@ -854,6 +904,7 @@ impl Handler<SendInvoice> for Payments {
                 match provider_ctx.payment_api.send_invoice(&msg.invoice_id).await {
                     Ok(_) => {
                         log::info!("Invoice [{}] sent.", msg.invoice_id);
+                        dunning.sent(&msg.invoice_id);
                         return Ok(());
                     }
                     Err(e) => {
@@ -904,6 +955,7 @@ impl Handler<InvoiceAccepted> for Payments {
             .into_actor(self)
             .map(|result, myself, _ctx| match result {
                 Ok(invoice) => {
+                    myself.dunning.accepted(&invoice.invoice_id);
                     myself.invoices_to_pay.push(invoice);
                     Ok(())
                 }
@@ -936,6 +988,16 @@ impl Handler<InvoiceSettled> for Payments {
                         .retain(|x| x.invoice_id != invoice.invoice_id);
                     myself.earnings += invoice.amount;
                     log::info!("Current earnings: {}", myself.earnings);
+
+                    if let Some(event) = myself.dunning.settled(&invoice.invoice_id) {
+                        event.emit();
+                    }
+
+                    let event = match Utc::now() <= invoice.payment_due_date {
+                        true => ReputationEvent::PaymentOnTime,
+                        false => ReputationEvent::PaymentLate(invoice.invoice_id.clone()),
+                    };
+                    myself.reputation.record(&invoice.recipient_id, event);
                     Ok(())
                 }
                 Err(e) => Err(anyhow!("Cannot get invoice: {}", e)),
@@ -945,6 +1007,93 @@ impl Handler<InvoiceSettled> for Payments {
     }
 }
 
+impl Handler<InvoiceRejected> for Payments {
+    type Result = Result<(), Error>;
+
+    fn handle(&mut self, msg: InvoiceRejected, _ctx: &mut Context<Self>) -> Self::Result {
+        match self.dunning.rejected(&msg.invoice_id) {
+            Some(event) => event.emit(),
+            None => log::debug!("Rejected invoice [{}] isn't tracked.", msg.invoice_id),
+        }
+        Ok(())
+    }
+}
+
+/// Reminds Requestors about invoices they didn't accept and reports
+/// overdue invoices together with days overdue per Requestor.
+impl Handler<CheckDunning> for Payments {
+    type Result = ();
+
+    fn handle(&mut self, _msg: CheckDunning, ctx: &mut Context<Self>) -> Self::Result {
+        for event in self.dunning.check_overdue() {
+            // Requestor not paying at all is penalized like the one paying late.
+            if let DunningEvent::InvoiceOverdue {
+                invoice_id,
+                requestor,
+                ..
+            } = &event
+            {
+                let event = ReputationEvent::PaymentOverdue(invoice_id.clone());
+                self.reputation.record(requestor, event);
+            }
+            event.emit();
+        }
+        for (requestor, overdue) in self.dunning.overdue() {
+            log::warn!(
+                "Requestor [{}] has {} overdue invoice(s) for {}, up to {} day(s) overdue.",
+                requestor,
+                overdue.invoices,
+                overdue.amount,
+                overdue.max_days_overdue
+            );
+        }
+
+        let interval = chrono::Duration::from_std(self.context.config.invoice_resend_interval)
+            .unwrap_or_else(|_| chrono::Duration::max_value());
+        for invoice_id in self.dunning.to_resend(interval) {
+            let provider_ctx = self.context.clone();
+            let dunning = self.dunning.clone();
+            let future = async move {
+                // Requestor's decision could be delivered without us noticing the event.
+                match provider_ctx.payment_api.get_invoice(&invoice_id).await {
+                    Ok(invoice)
+                        if !matches!(
+                            invoice.status,
+                            DocumentStatus::Issued | DocumentStatus::Received
+                        ) =>
+                    {
+                        log::info!(
+                            "Invoice [{}] is {}. Not sending it again.",
+                            invoice_id,
+                            invoice.status
+                        );
+                        if let Some(event) = dunning.update(&invoice) {
+                            event.emit();
+                        }
+                        return;
+                    }
+                    Ok(_) => (),
+                    Err(e) => {
+                        log::warn!("Error getting invoice [{}]: {}", invoice_id, e);
+                        return;
+                    }
+                }
+
+                log::info!("Invoice [{}] not accepted. Sending it again...", invoice_id);
+                match provider_ctx.payment_api.send_invoice(&invoice_id).await {
+                    Ok(_) => {
+                        if let Some(event) = dunning.resent(&invoice_id) {
+                            event.emit();
+                        }
+                    }
+                    Err(e) => log::warn!("Error re-sending invoice [{}]: {}", invoice_id, e),
+                }
+            };
+            ctx.spawn(future.into_actor(self));
+        }
+    }
+}
+
 impl Handler<DeadlineElapsed> for Payments {
     type Result = ();
 
@@ -1047,6 +1196,13 @@ impl Actor for Payments {
             provider_ctx.clone(),
             payment_addr.clone(),
         ));
+        let dunning = self.dunning.clone();
+        let restore_ctx = provider_ctx.clone();
+        tokio::task::spawn_local(async move {
+            if let Err(e) = restore_dunning(restore_ctx, dunning).await {
+                log::warn!("Failed to restore unpaid invoices: {}", e);
+            }
+        });
         tokio::task::spawn_local(async move {
             for checker in &[&provider_ctx.debit_checker, &provider_ctx.payment_checker] {
                 let _ = checker
@@ -1056,6 +1212,10 @@ impl Actor for Payments {
             }
             check_debit_notes_events(provider_ctx, provider_signal).await;
         });
+
+        ctx.run_interval(self.context.config.invoice_dunning_interval, |_, ctx| {
+            ctx.notify(CheckDunning)
+        });
     }
 }
 
@@ -1,6 +1,8 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use bigdecimal::{BigDecimal, FromPrimitive};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::BTreeMap;
 
 use ya_agreement_utils::ComInfo;
 use ya_client::model::{payment::Account, NodeId};
@@ -26,6 +28,9 @@ impl From<Account> for AccountView {
     }
 }
 
+/// Pricing models, which can be chosen in presets.
+pub const PRICING_MODELS: &[&str] = &["linear", "tiered", "capped"];
+
 pub trait PricingOffer {
     fn prices(&self, preset: &Preset) -> Vec<(String, f64)>;
     fn build(
@@ -43,18 +48,7 @@ pub struct LinearPricing {
 
 impl PaymentModel for LinearPricing {
     fn compute_cost(&self, usage: &[f64]) -> Result<BigDecimal> {
-        // Note: last element of usage_coeffs contains constant initial cost
-        // of computing task, so we don't multiply it.
-        let const_coeff_idx = self.usage_coeffs.len() - 1;
-        let cost: f64 = self.usage_coeffs[const_coeff_idx]
-            + self.usage_coeffs[0..const_coeff_idx]
-                .iter()
-                .zip(usage.iter())
-                .map(|(coeff, usage_value)| coeff * usage_value)
-                .sum::<f64>();
-
-        BigDecimal::from_f64(cost)
-            .ok_or_else(|| anyhow!("Failed to convert to BigDecimal: {}", cost))
+        to_big_decimal(linear_cost(&self.usage_coeffs, usage))
     }
 
     fn expected_usage_len(&self) -> usize {
@@ -106,39 +100,337 @@ impl PricingOffer for LinearPricingOffer {
         initial_price: f64,
         prices: Vec<(String, f64)>,
     ) -> Result<ComInfo> {
-        let mut usage_vector = Vec::new();
-        let coefficients = prices
-            .into_iter()
-            .map(|(p, v)| {
-                usage_vector.push(p);
-                v
-            })
-            .chain(std::iter::once(initial_price))
-            .collect::<Vec<_>>();
+        let (usage_vector, coefficients) = split_prices(initial_price, prices);
+        Ok(build_com_info(
+            accounts,
+            "linear",
+            json!({ "coeffs": coefficients }),
+            usage_vector,
+        ))
+    }
+}
+
+/// Usage price, which applies above given amount of usage.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PriceTier {
+    pub from: f64,
+    pub price: f64,
+}
+
+/// Linear pricing, where price per unit of usage changes after exceeding
+/// thresholds defined by `PriceTier`s. Coefficients apply below the first threshold.
+pub struct TieredPricing {
+    usage_coeffs: Vec<f64>,
+    tiers: Vec<Vec<PriceTier>>,
+}
 
-        let mut params = json!({
-            "scheme": "payu".to_string(),
-            "scheme.payu": json!({}),
-            "pricing": json!({
-                "model": "linear".to_string(),
-                "model.linear": json!({
-                    "coeffs": coefficients
+impl PaymentModel for TieredPricing {
+    fn compute_cost(&self, usage: &[f64]) -> Result<BigDecimal> {
+        let const_coeff_idx = self.usage_coeffs.len() - 1;
+        let cost: f64 = self.usage_coeffs[const_coeff_idx]
+            + self.usage_coeffs[0..const_coeff_idx]
+                .iter()
+                .zip(usage.iter())
+                .enumerate()
+                .map(|(idx, (coeff, usage_value))| {
+                    let tiers = self.tiers.get(idx).map(Vec::as_slice).unwrap_or_default();
+                    tiered_cost(*coeff, tiers, *usage_value)
                 })
-            }),
-            "usage": json!({
-                "vector": usage_vector
+                .sum::<f64>();
+
+        to_big_decimal(cost)
+    }
+
+    fn expected_usage_len(&self) -> usize {
+        self.usage_coeffs.len() - 1
+    }
+}
+
+impl TieredPricing {
+    pub fn new<'a>(commercials: &'a PaymentDescription<'a>) -> Result<TieredPricing> {
+        let usage: Vec<f64> = commercials.get_usage_coefficients()?;
+        let mut tiers: Vec<Vec<PriceTier>> = commercials.get_pricing_param("tiers")?;
+        tiers.iter_mut().for_each(|tiers| sort_tiers(tiers));
+
+        log::info!(
+            "Creating TieredPricing payment model. Usage coefficients vector: {:?}, tiers: {:?}.",
+            usage,
+            tiers
+        );
+        Ok(TieredPricing {
+            usage_coeffs: usage,
+            tiers,
+        })
+    }
+}
+
+/// Linear pricing with maximal total cost of the Agreement.
+pub struct CappedPricing {
+    usage_coeffs: Vec<f64>,
+    max_cost: f64,
+}
+
+impl PaymentModel for CappedPricing {
+    fn compute_cost(&self, usage: &[f64]) -> Result<BigDecimal> {
+        to_big_decimal(linear_cost(&self.usage_coeffs, usage).min(self.max_cost))
+    }
+
+    fn expected_usage_len(&self) -> usize {
+        self.usage_coeffs.len() - 1
+    }
+
+    fn max_cost(&self) -> Option<BigDecimal> {
+        BigDecimal::from_f64(self.max_cost)
+    }
+}
+
+impl CappedPricing {
+    pub fn new<'a>(commercials: &'a PaymentDescription<'a>) -> Result<CappedPricing> {
+        let usage: Vec<f64> = commercials.get_usage_coefficients()?;
+        let max_cost: f64 = commercials.get_pricing_param("max-cost")?;
+
+        log::info!(
+            "Creating CappedPricing payment model. Usage coefficients vector: {:?}, max cost: {}.",
+            usage,
+            max_cost
+        );
+        Ok(CappedPricing {
+            usage_coeffs: usage,
+            max_cost,
+        })
+    }
+}
+
+/// Helper for building offer with `TieredPricing`.
+pub struct TieredPricingOffer {
+    tiers: BTreeMap<String, Vec<PriceTier>>,
+}
+
+impl TieredPricingOffer {
+    pub fn new(tiers: BTreeMap<String, Vec<PriceTier>>) -> Self {
+        TieredPricingOffer { tiers }
+    }
+}
+
+impl PricingOffer for TieredPricingOffer {
+    fn prices(&self, preset: &Preset) -> Vec<(String, f64)> {
+        preset.usage_coeffs.clone().into_iter().collect()
+    }
+
+    fn build(
+        &self,
+        accounts: &[AccountView],
+        initial_price: f64,
+        prices: Vec<(String, f64)>,
+    ) -> Result<ComInfo> {
+        let (usage_vector, coefficients) = split_prices(initial_price, prices);
+        let tiers = usage_vector
+            .iter()
+            .map(|usage| {
+                let mut tiers = self.tiers.get(usage).cloned().unwrap_or_default();
+                sort_tiers(&mut tiers);
+                tiers
             })
-        });
-
-        for account in accounts {
-            params.as_object_mut().unwrap().insert(
-                format!("payment.platform.{}", account.platform),
-                json!({
-                    "address".to_string(): account.address,
-                }),
-            );
+            .collect::<Vec<_>>();
+
+        Ok(build_com_info(
+            accounts,
+            "tiered",
+            json!({ "coeffs": coefficients, "tiers": tiers }),
+            usage_vector,
+        ))
+    }
+}
+
+/// Helper for building offer with `CappedPricing`.
+pub struct CappedPricingOffer {
+    max_cost: f64,
+}
+
+impl CappedPricingOffer {
+    pub fn new(max_cost: f64) -> Result<Self> {
+        if max_cost < 0.0 {
+            bail!("Max cost can't be negative: {}", max_cost);
+        }
+        Ok(CappedPricingOffer { max_cost })
+    }
+}
+
+impl PricingOffer for CappedPricingOffer {
+    fn prices(&self, preset: &Preset) -> Vec<(String, f64)> {
+        preset.usage_coeffs.clone().into_iter().collect()
+    }
+
+    fn build(
+        &self,
+        accounts: &[AccountView],
+        initial_price: f64,
+        prices: Vec<(String, f64)>,
+    ) -> Result<ComInfo> {
+        let (usage_vector, coefficients) = split_prices(initial_price, prices);
+        Ok(build_com_info(
+            accounts,
+            "capped",
+            json!({ "coeffs": coefficients, "max-cost": self.max_cost }),
+            usage_vector,
+        ))
+    }
+}
+
+/// Note: last element of usage_coeffs contains constant initial cost
+/// of computing task, so we don't multiply it.
+fn linear_cost(usage_coeffs: &[f64], usage: &[f64]) -> f64 {
+    let const_coeff_idx = usage_coeffs.len() - 1;
+    usage_coeffs[const_coeff_idx]
+        + usage_coeffs[0..const_coeff_idx]
+            .iter()
+            .zip(usage.iter())
+            .map(|(coeff, usage_value)| coeff * usage_value)
+            .sum::<f64>()
+}
+
+/// Cost of single usage counter, where `base_price` applies below the first tier.
+fn tiered_cost(base_price: f64, tiers: &[PriceTier], usage: f64) -> f64 {
+    let mut cost = 0.0;
+    let mut price = base_price;
+    let mut from = 0.0;
+    for tier in tiers {
+        if usage <= tier.from {
+            break;
         }
+        cost += price * (tier.from - from);
+        from = tier.from;
+        price = tier.price;
+    }
+    cost + price * (usage - from).max(0.0)
+}
+
+fn sort_tiers(tiers: &mut [PriceTier]) {
+    tiers.sort_by(|a, b| a.from.total_cmp(&b.from));
+}
+
+fn to_big_decimal(cost: f64) -> Result<BigDecimal> {
+    BigDecimal::from_f64(cost).ok_or_else(|| anyhow!("Failed to convert to BigDecimal: {}", cost))
+}
+
+/// Splits prices into usage vector and coefficients with initial price as the last element.
+fn split_prices(initial_price: f64, prices: Vec<(String, f64)>) -> (Vec<String>, Vec<f64>) {
+    let mut usage_vector = Vec::new();
+    let coefficients = prices
+        .into_iter()
+        .map(|(p, v)| {
+            usage_vector.push(p);
+            v
+        })
+        .chain(std::iter::once(initial_price))
+        .collect::<Vec<_>>();
+    (usage_vector, coefficients)
+}
+
+fn build_com_info(
+    accounts: &[AccountView],
+    model: &str,
+    model_params: serde_json::Value,
+    usage_vector: Vec<String>,
+) -> ComInfo {
+    let mut params = json!({
+        "scheme": "payu".to_string(),
+        "scheme.payu": json!({}),
+        "pricing": json!({
+            "model": model.to_string(),
+            format!("model.{}", model): model_params
+        }),
+        "usage": json!({
+            "vector": usage_vector
+        })
+    });
+
+    for account in accounts {
+        params.as_object_mut().unwrap().insert(
+            format!("payment.platform.{}", account.platform),
+            json!({
+                "address".to_string(): account.address,
+            }),
+        );
+    }
+
+    ComInfo { params }
+}
 
-        Ok(ComInfo { params })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiers() -> Vec<PriceTier> {
+        vec![
+            PriceTier {
+                from: 100.0,
+                price: 0.5,
+            },
+            PriceTier {
+                from: 200.0,
+                price: 0.25,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_tiered_cost() {
+        assert_eq!(tiered_cost(1.0, &tiers(), 50.0), 50.0);
+        assert_eq!(tiered_cost(1.0, &tiers(), 150.0), 100.0 + 25.0);
+        assert_eq!(tiered_cost(1.0, &tiers(), 300.0), 100.0 + 50.0 + 25.0);
+        assert_eq!(tiered_cost(1.0, &[], 300.0), 300.0);
+    }
+
+    #[test]
+    fn test_tiered_and_capped_pricing() {
+        let tiered = TieredPricing {
+            usage_coeffs: vec![1.0, 0.1, 2.0],
+            tiers: vec![tiers()],
+        };
+        assert_eq!(
+            tiered.compute_cost(&[300.0, 10.0]).unwrap(),
+            to_big_decimal(2.0 + 175.0 + 1.0).unwrap()
+        );
+
+        let capped = CappedPricing {
+            usage_coeffs: vec![1.0, 0.1, 2.0],
+            max_cost: 100.0,
+        };
+        assert_eq!(
+            capped.compute_cost(&[50.0, 10.0]).unwrap(),
+            to_big_decimal(53.0).unwrap()
+        );
+        assert_eq!(
+            capped.compute_cost(&[300.0, 10.0]).unwrap(),
+            to_big_decimal(100.0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_build_tiered_offer() {
+        let offer = TieredPricingOffer::new(BTreeMap::from([(
+            "golem.usage.cpu_sec".to_string(),
+            tiers(),
+        )]));
+        let com_info = offer
+            .build(
+                &[],
+                2.0,
+                vec![
+                    ("golem.usage.cpu_sec".to_string(), 1.0),
+                    ("golem.usage.duration_sec".to_string(), 0.1),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(com_info.params["pricing"]["model"], "tiered");
+        assert_eq!(
+            com_info.params["pricing"]["model.tiered"],
+            json!({
+                "coeffs": [1.0, 0.1, 2.0],
+                "tiers": [tiers(), []],
+            })
+        );
     }
 }
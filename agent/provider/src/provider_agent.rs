@@ -12,6 +12,7 @@ use tokio_stream::wrappers::WatchStream;
 use ya_agreement_utils::agreement::TypedArrayPointer;
 use ya_agreement_utils::*;
 use ya_client::cli::ProviderApi;
+use ya_client::web::WebClient;
 use ya_core_model::payment::local::NetworkName;
 use ya_file_logging::{start_logger, LoggerHandle};
 use ya_manifest_utils::{manifest, Feature};
@@ -24,9 +25,14 @@ use crate::execution::{
     UpdateActivity,
 };
 use crate::hardware;
+use crate::market::free_capacity::{DynamicPropertiesApi, FreeCapacity, FREE_CAPACITY_PROPERTIES};
 use crate::market::provider_market::{OfferKind, Shutdown as MarketShutdown, Unsubscribe};
 use crate::market::{CreateOffer, Preset, PresetManager, ProviderMarket};
-use crate::payments::{AccountView, LinearPricingOffer, Payments, PricingOffer};
+use crate::payments::{
+    AccountView, CappedPricingOffer, DunningManager, LinearPricingOffer, Payments, PricingOffer,
+    TieredPricingOffer,
+};
+use crate::reputation::ReputationManager;
 use crate::rules::RulesManager;
 use crate::startup_config::{FileMonitor, NodeConfig, ProviderConfig, RunConfig};
 use crate::tasks::task_manager::{InitializeTaskManager, TaskManager};
@@ -68,6 +74,10 @@ impl GlobalsManager {
 #[derive(Clone)]
 pub struct AgentNegotiatorsConfig {
     pub rules_manager: RulesManager,
+    pub hardware: hardware::Allocation,
+    pub reputation: ReputationManager,
+    pub dunning: DunningManager,
+    pub paid_prices_file: PathBuf,
 }
 
 pub struct ProviderAgent {
@@ -83,7 +93,9 @@ pub struct ProviderAgent {
     rulestore_monitor: FileMonitor,
     keystore_monitor: FileMonitor,
     whitelist_monitor: FileMonitor,
+    reputation_monitor: FileMonitor,
     net_api: NetApi,
+    free_capacity: Option<FreeCapacity>,
 }
 
 impl ProviderAgent {
@@ -114,6 +126,8 @@ impl ProviderAgent {
         }
 
         let api = ProviderApi::try_from(&args.api)?;
+        let dynamic_api: DynamicPropertiesApi =
+            WebClient::with_token(&args.api.app_key).interface()?;
 
         log::info!("Loading payment accounts...");
         let accounts: Vec<AccountView> = api
@@ -170,10 +184,28 @@ impl ProviderAgent {
         let (rulestore_monitor, keystore_monitor, whitelist_monitor) =
             rules_manager.spawn_file_monitors()?;
 
-        let agent_negotiators_cfg = AgentNegotiatorsConfig { rules_manager };
+        let reputation = ReputationManager::load_or_create(&config.reputation_file)?;
+        let reputation_monitor = reputation.spawn_monitor()?;
+        let dunning = DunningManager::default();
+
+        let agent_negotiators_cfg = AgentNegotiatorsConfig {
+            rules_manager,
+            hardware: hardware.allocation(),
+            reputation: reputation.clone(),
+            dunning: dunning.clone(),
+            paid_prices_file: config.paid_prices_file.clone(),
+        };
 
         let market = ProviderMarket::new(api.market, args.market, agent_negotiators_cfg).start();
-        let payments = Payments::new(api.activity.clone(), api.payment, args.payment).start();
+        let payments = Payments::new(
+            api.activity.clone(),
+            api.payment,
+            args.payment,
+            reputation,
+            dunning,
+        )
+        .start();
+        let free_capacity = FreeCapacity::new(dynamic_api, hardware.allocation(), data_dir.clone());
         let runner = TaskRunner::new(api.activity, args.runner, registry, data_dir)?.start();
         let task_manager =
             TaskManager::new(market.clone(), runner.clone(), payments, args.tasks)?.start();
@@ -192,7 +224,9 @@ impl ProviderAgent {
             rulestore_monitor,
             keystore_monitor,
             whitelist_monitor,
+            reputation_monitor,
             net_api,
+            free_capacity: Some(free_capacity),
         })
     }
 
@@ -253,6 +287,10 @@ impl ProviderAgent {
     ) -> anyhow::Result<CreateOffer> {
         let pricing_model: Box<dyn PricingOffer> = match preset.pricing_model.as_str() {
             "linear" => Box::<LinearPricingOffer>::default(),
+            "tiered" => Box::new(TieredPricingOffer::new(preset.usage_tiers.clone())),
+            "capped" => Box::new(CappedPricingOffer::new(preset.max_cost.ok_or_else(
+                || anyhow!("Preset [{}] is missing the max cost", preset.name),
+            )?)?),
             other => return Err(anyhow!("Unsupported pricing model: {}", other)),
         };
         let (initial_price, prices) = get_prices(pricing_model.as_ref(), &preset, &offer)?;
@@ -261,6 +299,9 @@ impl ProviderAgent {
             "golem.com.payment.protocol.version",
             node_info.protocol_version.into(),
         );
+        for name in FREE_CAPACITY_PROPERTIES {
+            offer.set_property(name, serde_json::Value::Null);
+        }
         offer.add_constraints(Self::build_constraints(node_info.subnet.clone())?);
         let com_info = pricing_model.build(accounts, initial_price, prices)?;
         let srv_info = Self::build_service_info(inf_node_info, exeunit_desc, &offer)?;
@@ -282,7 +323,8 @@ impl ProviderAgent {
         if let Some(subnet) = subnet {
             cnts = cnts.and(constraints!["golem.node.debug.subnet" == subnet,]);
         }
-        Ok(cnts.to_string())
+        ya_market_resolver::simplify_constraints(&cnts.to_string())
+            .map_err(|e| anyhow!("Invalid offer constraints: {e}"))
     }
 
     async fn build_node_info(globals: GlobalsState, net_api: NetApi) -> anyhow::Result<NodeInfo> {
@@ -502,6 +544,10 @@ impl Handler<Initialize> for ProviderAgent {
             .await;
         });
 
+        if let Some(free_capacity) = self.free_capacity.take() {
+            tokio::task::spawn_local(free_capacity.run());
+        }
+
         let agent = ctx.address();
         let task_manager = self.task_manager.clone();
         async move {
@@ -523,6 +569,7 @@ impl Handler<Shutdown> for ProviderAgent {
         self.keystore_monitor.stop();
         self.rulestore_monitor.stop();
         self.whitelist_monitor.stop();
+        self.reputation_monitor.stop();
 
         async move {
             market.send(MarketShutdown).await??;
@@ -587,8 +634,8 @@ mod tests {
     use ya_manifest_utils::manifest;
 
     use crate::{
-        execution::ExeUnitDesc, market::Preset, payments::AccountView,
-        provider_agent::ProviderAgent,
+        execution::ExeUnitDesc, market::free_capacity::FREE_CAPACITY_PROPERTIES, market::Preset,
+        payments::AccountView, provider_agent::ProviderAgent,
     };
 
     #[test_case(true,  r#"["inet", "vpn", "manifest-support"]"#  ; "Supported with 'inet', 'vpn', and 'manifest-support'")]
@@ -626,6 +673,25 @@ mod tests {
         assert_eq!(payload_manifest_prop, expected_manifest_suport);
     }
 
+    #[test]
+    fn offer_declares_free_capacity_as_dynamic() {
+        let fake = fake_data();
+        let offer = ProviderAgent::build_offer(
+            fake.node_info,
+            fake.inf_node_info,
+            &fake.accounts,
+            fake.preset,
+            fake.offer_template,
+            fake.exeunit_desc,
+        )
+        .expect("Failed to build offer");
+
+        let offer_definition = offer.offer_definition.into_json();
+        for name in FREE_CAPACITY_PROPERTIES {
+            assert_eq!(offer_definition.get(name), Some(&serde_json::Value::Null));
+        }
+    }
+
     /// Test utilities
 
     struct FakeData {
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::{fs, io};
+
+use ya_client::model::NodeId;
+use ya_utils_path::SwapSave;
+
+use crate::market::termination_reason::BreakReason;
+use crate::startup_config::FileMonitor;
+
+pub(crate) const REPUTATION_JSON: &str = "reputation.json";
+
+/// Score changes applied for events related to Requestor.
+const AGREEMENT_CLOSED_SCORE: i64 = 1;
+const PAYMENT_ON_TIME_SCORE: i64 = 1;
+const PAYMENT_LATE_SCORE: i64 = -2;
+const AGREEMENT_BROKEN_SCORE: i64 = -5;
+
+/// Event, which affects Requestor's reputation.
+#[derive(Clone, Debug)]
+pub enum ReputationEvent {
+    AgreementClosed,
+    AgreementBroken(BreakReason),
+    PaymentOnTime,
+    /// Invoice with given id wasn't paid until its due date.
+    PaymentOverdue(String),
+    /// Invoice with given id was paid after its due date.
+    PaymentLate(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestorRecord {
+    pub score: i64,
+    pub banned: bool,
+    pub agreements_closed: u32,
+    pub agreements_broken: u32,
+    pub payments_on_time: u32,
+    pub payments_late: u32,
+}
+
+impl RequestorRecord {
+    fn record(&mut self, event: &ReputationEvent) {
+        match event {
+            ReputationEvent::AgreementClosed => {
+                self.agreements_closed += 1;
+                self.score += AGREEMENT_CLOSED_SCORE;
+            }
+            ReputationEvent::AgreementBroken(reason) => {
+                self.agreements_broken += 1;
+                if requestor_fault(reason) {
+                    self.score += AGREEMENT_BROKEN_SCORE;
+                }
+            }
+            ReputationEvent::PaymentOnTime => {
+                self.payments_on_time += 1;
+                self.score += PAYMENT_ON_TIME_SCORE;
+            }
+            ReputationEvent::PaymentOverdue(_) | ReputationEvent::PaymentLate(_) => {
+                self.payments_late += 1;
+                self.score += PAYMENT_LATE_SCORE;
+            }
+        }
+    }
+}
+
+/// Agreements broken due to our problems shouldn't lower Requestor's score.
+fn requestor_fault(reason: &BreakReason) -> bool {
+    match reason {
+        BreakReason::DebitNotesDeadline(_)
+        | BreakReason::DebitNoteRejected(_)
+        | BreakReason::DebitNoteNotPaid(_)
+        | BreakReason::RequestorUnreachable(_) => true,
+        BreakReason::InitializationError { .. }
+        | BreakReason::Expired(_)
+        | BreakReason::NoActivity(_)
+        | BreakReason::DebitNoteCancelled => false,
+    }
+}
+
+/// Per-Requestor scores built from Agreement termination reasons and
+/// payment timeliness.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Reputation {
+    requestors: BTreeMap<String, RequestorRecord>,
+    /// Overdue Invoices, which already lowered the score. They are counted
+    /// once, even if they stay unpaid across restarts or are paid later.
+    #[serde(default)]
+    overdue_invoices: BTreeSet<String>,
+}
+
+impl Reputation {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if path.exists() {
+            log::debug!("Loading Requestors reputation from: {}", path.display());
+            Ok(serde_json::from_reader(io::BufReader::new(
+                fs::OpenOptions::new().read(true).open(path)?,
+            ))?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn load_or_create(path: &Path) -> anyhow::Result<Self> {
+        if path.exists() {
+            Self::load(path)
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let state = Self::default();
+            state.save(path)?;
+            Ok(state)
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        Ok(path.swap_save(serde_json::to_string_pretty(self)?)?)
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = (&String, &RequestorRecord)> {
+        self.requestors.iter()
+    }
+
+    pub fn get(&self, node_id: &NodeId) -> Option<&RequestorRecord> {
+        self.requestors.get(&node_id.to_string())
+    }
+
+    pub fn record(&mut self, node_id: &NodeId, event: &ReputationEvent) {
+        let counted = match event {
+            ReputationEvent::PaymentOverdue(invoice_id) => {
+                !self.overdue_invoices.insert(invoice_id.clone())
+            }
+            ReputationEvent::PaymentLate(invoice_id) => self.overdue_invoices.remove(invoice_id),
+            _ => false,
+        };
+        if !counted {
+            self.entry(node_id).record(event)
+        }
+    }
+
+    pub fn set_banned(&mut self, node_id: &NodeId, banned: bool) {
+        self.entry(node_id).banned = banned;
+    }
+
+    fn entry(&mut self, node_id: &NodeId) -> &mut RequestorRecord {
+        self.requestors.entry(node_id.to_string()).or_default()
+    }
+}
+
+/// `Reputation` shared between Provider agent components and kept in sync
+/// with reputation file, which can be modified by `ya-provider requestor` command.
+#[derive(Clone, Debug)]
+pub struct ReputationManager {
+    path: PathBuf,
+    state: Arc<Mutex<Reputation>>,
+}
+
+impl ReputationManager {
+    pub fn load_or_create(path: &Path) -> anyhow::Result<Self> {
+        Ok(ReputationManager {
+            path: path.to_path_buf(),
+            state: Arc::new(Mutex::new(Reputation::load_or_create(path)?)),
+        })
+    }
+
+    pub fn spawn_monitor(&self) -> anyhow::Result<FileMonitor> {
+        let state = self.state.clone();
+        let handler = move |p: PathBuf| match Reputation::load(&p) {
+            Ok(new_state) => {
+                *state.lock().unwrap() = new_state;
+            }
+            Err(e) => log::warn!("Error updating Requestors reputation from {:?}: {:?}", p, e),
+        };
+        Ok(FileMonitor::spawn(
+            &self.path,
+            FileMonitor::on_modified(handler),
+        )?)
+    }
+
+    pub fn get(&self, node_id: &NodeId) -> Option<RequestorRecord> {
+        self.state.lock().unwrap().get(node_id).cloned()
+    }
+
+    /// Records event and saves reputation file. File is reloaded before,
+    /// to avoid overwriting changes made by the cli in meantime.
+    pub fn record(&self, node_id: &NodeId, event: ReputationEvent) {
+        log::debug!("Requestor [{}] reputation event: {:?}", node_id, event);
+
+        let mut state = self.state.lock().unwrap();
+        match Reputation::load(&self.path) {
+            Ok(reloaded) => *state = reloaded,
+            Err(e) => log::warn!("Error reloading Requestors reputation: {}", e),
+        }
+        state.record(node_id, &event);
+        if let Err(e) = state.save(&self.path) {
+            log::error!("Error saving Requestors reputation: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_counts_only_requestor_faults() {
+        let node_id = NodeId::default();
+        let mut reputation = Reputation::default();
+
+        reputation.record(&node_id, &ReputationEvent::AgreementClosed);
+        reputation.record(
+            &node_id,
+            &ReputationEvent::PaymentLate("invoice".to_string()),
+        );
+        reputation.record(
+            &node_id,
+            &ReputationEvent::AgreementBroken(BreakReason::DebitNoteNotPaid(
+                chrono::Duration::seconds(10),
+            )),
+        );
+        reputation.record(
+            &node_id,
+            &ReputationEvent::AgreementBroken(BreakReason::DebitNoteCancelled),
+        );
+
+        let record = reputation.get(&node_id).unwrap();
+        assert_eq!(record.score, 1 - 2 - 5);
+        assert_eq!(record.agreements_closed, 1);
+        assert_eq!(record.agreements_broken, 2);
+        assert_eq!(record.payments_late, 1);
+        assert!(!record.banned);
+    }
+
+    #[test]
+    fn test_overdue_invoice_counted_once() {
+        let node_id = NodeId::default();
+        let mut reputation = Reputation::default();
+        let overdue = ReputationEvent::PaymentOverdue("invoice".to_string());
+
+        reputation.record(&node_id, &overdue);
+        reputation.record(&node_id, &overdue);
+        assert_eq!(reputation.get(&node_id).unwrap().score, PAYMENT_LATE_SCORE);
+
+        // Overdue Invoice paid later doesn't lower the score again.
+        reputation.record(
+            &node_id,
+            &ReputationEvent::PaymentLate("invoice".to_string()),
+        );
+        let record = reputation.get(&node_id).unwrap();
+        assert_eq!(record.score, PAYMENT_LATE_SCORE);
+        assert_eq!(record.payments_late, 1);
+        assert!(reputation.overdue_invoices.is_empty());
+
+        reputation.record(&node_id, &ReputationEvent::PaymentLate("other".to_string()));
+        assert_eq!(reputation.get(&node_id).unwrap().payments_late, 2);
+    }
+
+    #[test]
+    fn test_record_keeps_bans_saved_in_meantime() {
+        let tempdir = tempdir::TempDir::new("reputation").unwrap();
+        let path = tempdir.path().join(REPUTATION_JSON);
+        let node_id = NodeId::default();
+
+        let manager = ReputationManager::load_or_create(&path).unwrap();
+
+        let mut reputation = Reputation::load(&path).unwrap();
+        reputation.set_banned(&node_id, true);
+        reputation.save(&path).unwrap();
+
+        manager.record(&node_id, ReputationEvent::PaymentOnTime);
+
+        let record = Reputation::load(&path).unwrap().get(&node_id).cloned();
+        let record = record.unwrap();
+        assert!(record.banned);
+        assert_eq!(record.score, 1);
+        assert_eq!(manager.get(&node_id), Some(record));
+    }
+}
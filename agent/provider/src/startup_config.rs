@@ -22,12 +22,15 @@ use crate::cli::keystore::KeystoreConfig;
 use crate::cli::pre_install::PreInstallConfig;
 pub use crate::cli::preset::PresetsConfig;
 use crate::cli::profile::ProfileConfig;
+use crate::cli::requestor::RequestorConfig;
 use crate::cli::rule::RuleCommand;
 use crate::cli::whitelist::WhitelistConfig;
 pub(crate) use crate::config::globals::GLOBALS_JSON;
 use crate::execution::{ExeUnitsRegistry, TaskRunnerConfig};
 use crate::market::config::MarketConfig;
-use crate::payments::PaymentsConfig;
+use crate::payments::{PaymentsConfig, PriceTier};
+pub(crate) use crate::market::negotiator::builtin::dynamic_price::PAID_PRICES_JSON;
+pub(crate) use crate::reputation::REPUTATION_JSON;
 use crate::tasks::config::TaskConfig;
 
 lazy_static::lazy_static! {
@@ -87,6 +90,10 @@ pub struct ProviderConfig {
     pub hardware_file: PathBuf,
     #[structopt(skip = RULES_JSON)]
     pub rules_file: PathBuf,
+    #[structopt(skip = REPUTATION_JSON)]
+    pub reputation_file: PathBuf,
+    #[structopt(skip = PAID_PRICES_JSON)]
+    pub paid_prices_file: PathBuf,
     /// Max number of available CPU cores
     #[structopt(
         long,
@@ -200,6 +207,12 @@ pub struct PresetNoInteractive {
     pub pricing: Option<String>,
     #[structopt(long, parse(try_from_str = parse_key_val))]
     pub price: Vec<(String, f64)>,
+    /// Price tier for `tiered` pricing model in form `coefficient=from:price`
+    #[structopt(long, parse(try_from_str = parse_price_tier))]
+    pub price_tier: Vec<(String, PriceTier)>,
+    /// Maximal total cost of an Agreement for `capped` pricing model
+    #[structopt(long)]
+    pub max_cost: Option<f64>,
 }
 
 #[derive(StructOpt, Clone, Debug)]
@@ -248,6 +261,8 @@ pub enum Commands {
     Clean(CleanConfig),
     /// Manage Rule config
     Rule(RuleCommand),
+    /// Manage Requestors reputation
+    Requestor(RequestorConfig),
 }
 
 #[derive(Debug)]
@@ -394,6 +409,18 @@ where
     Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
 }
 
+fn parse_price_tier(s: &str) -> std::result::Result<(String, PriceTier), Box<dyn Error>> {
+    let (name, tier): (String, String) = parse_key_val(s)?;
+    let pos = tier
+        .find(':')
+        .ok_or_else(|| format!("invalid price tier: no `:` found in `{}`", tier))?;
+    let tier = PriceTier {
+        from: tier[..pos].parse()?,
+        price: tier[pos + 1..].parse()?,
+    };
+    Ok((name, tier))
+}
+
 fn default_data_dir() -> String {
     DataDir::new(clap::crate_name!()).to_string()
 }
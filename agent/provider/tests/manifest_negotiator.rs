@@ -19,6 +19,7 @@ use ya_manifest_utils::{Policy, PolicyConfig};
 use ya_provider::market::negotiator::builtin::ManifestSignature;
 use ya_provider::market::negotiator::*;
 use ya_provider::provider_agent::AgentNegotiatorsConfig;
+use ya_provider::reputation::ReputationManager;
 use ya_provider::rules::RulesManager;
 
 static MANIFEST_TEST_RESOURCES: TestResources = TestResources {
@@ -70,7 +71,13 @@ fn manifest_negotiator_test_accepted_because_of_no_payload() {
             .expect("Can't load RulesManager");
 
     let config = create_manifest_signature_validating_policy_config();
-    let negotiator_cfg = AgentNegotiatorsConfig { rules_manager };
+    let negotiator_cfg = AgentNegotiatorsConfig {
+        rules_manager,
+        hardware: Default::default(),
+        reputation: ReputationManager::load_or_create(&test_cert_dir.join("reputation.json"))
+            .expect("Can't load ReputationManager"),
+        dunning: Default::default(),
+    };
     let mut manifest_negotiator = ManifestSignature::new(&config, negotiator_cfg);
     // Current implementation does not verify content of certificate permissions incoming in demand.
 
@@ -571,7 +578,13 @@ fn manifest_negotiator_test_encoded_manifest_sign_and_cert_and_cert_dir_files(
             .expect("Can't load RulesManager");
 
     let config = create_manifest_signature_validating_policy_config();
-    let negotiator_cfg = AgentNegotiatorsConfig { rules_manager };
+    let negotiator_cfg = AgentNegotiatorsConfig {
+        rules_manager,
+        hardware: Default::default(),
+        reputation: ReputationManager::load_or_create(&test_cert_dir.join("reputation.json"))
+            .expect("Can't load ReputationManager"),
+        dunning: Default::default(),
+    };
     let mut manifest_negotiator = ManifestSignature::new(&config, negotiator_cfg);
     // Current implementation does not verify content of certificate permissions incoming in demand.
 
@@ -0,0 +1,193 @@
+use chrono::{SecondsFormat, Utc};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+pub const CURRENT_TIME_PROPERTY: &str = "golem.node.current.time";
+pub const UPTIME_PROPERTY: &str = "golem.node.uptime.sec";
+pub const FREE_CPU_THREADS_PROPERTY: &str = "golem.node.free.cpu.threads";
+pub const FREE_MEM_PROPERTY: &str = "golem.node.free.mem.gib";
+pub const FREE_STORAGE_PROPERTY: &str = "golem.node.free.storage.gib";
+
+/// Properties, which can be declared dynamic. Other properties set to `null`
+/// have no value and are ignored during matching.
+pub const DECLARED_PROPERTIES: &[&str] = &[
+    CURRENT_TIME_PROPERTY,
+    UPTIME_PROPERTY,
+    FREE_CPU_THREADS_PROPERTY,
+    FREE_MEM_PROPERTY,
+    FREE_STORAGE_PROPERTY,
+];
+
+pub fn is_declared(name: &str) -> bool {
+    DECLARED_PROPERTIES.contains(&name)
+}
+
+/// Property which value is evaluated at the moment of matching, instead of being
+/// published once together with Offer or Demand.
+///
+/// Subscriptions declare dynamic properties by setting one of `DECLARED_PROPERTIES`
+/// to `null`. Such value-less
+/// properties can be resolved only on the node, which owns the subscription.
+pub trait DynamicProperty: Send + Sync {
+    /// Full name of the property, for example `golem.node.free.mem.gib`.
+    fn name(&self) -> &str;
+    /// Current value of the property. `None` if value is unknown at the moment.
+    fn value(&self) -> Option<Value>;
+}
+
+/// Current time on the node (RFC 3339).
+pub struct CurrentTime;
+
+impl DynamicProperty for CurrentTime {
+    fn name(&self) -> &str {
+        CURRENT_TIME_PROPERTY
+    }
+
+    fn value(&self) -> Option<Value> {
+        Some(Value::String(
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        ))
+    }
+}
+
+/// Number of seconds since the node was started.
+pub struct Uptime {
+    started: Instant,
+}
+
+impl Default for Uptime {
+    fn default() -> Self {
+        Uptime {
+            started: Instant::now(),
+        }
+    }
+}
+
+impl DynamicProperty for Uptime {
+    fn name(&self) -> &str {
+        UPTIME_PROPERTY
+    }
+
+    fn value(&self) -> Option<Value> {
+        Some(Value::from(self.started.elapsed().as_secs()))
+    }
+}
+
+/// Property with value set externally, for example by provider agent,
+/// which knows current free capacity of the node.
+pub struct StoredProperty {
+    name: String,
+    value: RwLock<Option<Value>>,
+}
+
+impl StoredProperty {
+    pub fn new(name: &str, value: Option<Value>) -> Self {
+        StoredProperty {
+            name: name.to_string(),
+            value: RwLock::new(value),
+        }
+    }
+
+    pub fn set(&self, value: Option<Value>) {
+        *self.value.write().unwrap() = value;
+    }
+}
+
+impl DynamicProperty for StoredProperty {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self) -> Option<Value> {
+        self.value.read().unwrap().clone()
+    }
+}
+
+/// Registry of dynamic properties of the local node.
+#[derive(Clone, Default)]
+pub struct DynamicProperties {
+    properties: Arc<RwLock<BTreeMap<String, Arc<dyn DynamicProperty>>>>,
+    stored: Arc<RwLock<BTreeMap<String, Arc<StoredProperty>>>>,
+}
+
+impl DynamicProperties {
+    /// Registry with builtin properties: current time and node uptime.
+    pub fn with_builtin() -> Self {
+        let dynamic = DynamicProperties::default();
+        dynamic.register(Arc::new(CurrentTime));
+        dynamic.register(Arc::new(Uptime::default()));
+        dynamic
+    }
+
+    pub fn register(&self, property: Arc<dyn DynamicProperty>) {
+        let name = property.name().to_string();
+        self.stored.write().unwrap().remove(&name);
+        self.properties.write().unwrap().insert(name, property);
+    }
+
+    pub fn unregister(&self, name: &str) -> bool {
+        self.stored.write().unwrap().remove(name);
+        self.properties.write().unwrap().remove(name).is_some()
+    }
+
+    /// Sets value of externally managed property. Registers the property if needed.
+    /// Only declared properties can be set. Builtin and other computed properties
+    /// can't be overridden this way.
+    pub fn set_value(&self, name: &str, value: Option<Value>) -> Result<(), String> {
+        if !is_declared(name) {
+            return Err(format!("Property [{}] can't be declared dynamic", name));
+        }
+
+        if let Some(stored) = self.stored.read().unwrap().get(name) {
+            stored.set(value);
+            return Ok(());
+        }
+
+        let mut properties = self.properties.write().unwrap();
+        if properties.contains_key(name) {
+            return Err(format!("Property [{}] is computed and can't be set", name));
+        }
+
+        let stored = Arc::new(StoredProperty::new(name, value));
+        properties.insert(name.to_string(), stored.clone());
+        self.stored
+            .write()
+            .unwrap()
+            .insert(name.to_string(), stored);
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.properties.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Evaluates all properties and returns them in flat form (`<name>=<value>`).
+    /// Properties without value are skipped.
+    pub fn evaluate(&self) -> Vec<String> {
+        self.properties
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(name, property)| flat_property(name, property.value()?))
+            .collect()
+    }
+
+    /// Evaluates single property in flat form. `None` if property isn't registered
+    /// or has no value at the moment.
+    pub fn evaluate_property(&self, name: &str) -> Option<String> {
+        let property = self.properties.read().unwrap().get(name)?.clone();
+        flat_property(name, property.value()?)
+    }
+}
+
+fn flat_property(name: &str, value: Value) -> Option<String> {
+    match serde_json::to_string(&value) {
+        Ok(value) => Some(format!("{}={}", name, value)),
+        Err(e) => {
+            log::debug!("Can't serialize dynamic property [{}]: {}", name, e);
+            None
+        }
+    }
+}
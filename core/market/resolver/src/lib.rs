@@ -1,12 +1,18 @@
 #[macro_use]
 extern crate nom;
 
+pub mod dynamic;
 pub mod flatten;
 pub mod resolver;
 
 use resolver::error::MatchError as InternalMatchErorr;
 
-use crate::resolver::properties::PropertyRef;
+use crate::resolver::expression::build_expression;
+use crate::resolver::expression::{Expression, ResolveResult};
+use crate::resolver::ldap_parser;
+use crate::resolver::prop_parser;
+use crate::resolver::properties::{Property, PropertyRef, PropertySet};
+use dynamic::DynamicProperties;
 use flatten::{flatten_properties, FlattenError};
 use resolver::error::PrepareError;
 pub use resolver::matching::{match_weak, MatchResult};
@@ -23,6 +29,12 @@ pub enum Match {
         offer_mismatch: Vec<String>,
         demand_mismatch: Vec<String>,
     },
+    /// Match depends only on value-less (dynamic) properties, which can be
+    /// resolved only by the owner of the subscription.
+    Dynamic {
+        offer_dynamic: Vec<String>,
+        demand_dynamic: Vec<String>,
+    },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -42,9 +54,14 @@ pub fn match_demand_offer(
     offer_constraints: &str,
 ) -> Result<Match, MatchError> {
     let demand = Demand::from(demand_properties, demand_constraints)?;
-    let prep_demand_result = PreparedDemand::from(&demand)?;
     let offer = Offer::from(offer_properties, offer_constraints)?;
-    let prep_offer_result = PreparedOffer::from(&offer)?;
+    match_demand_offer_with(&demand, &offer)
+}
+
+// Match already flattened Demand and Offer, eg. extended with dynamic properties.
+pub fn match_demand_offer_with(demand: &Demand, offer: &Offer) -> Result<Match, MatchError> {
+    let prep_demand_result = PreparedDemand::from(demand)?;
+    let prep_offer_result = PreparedOffer::from(offer)?;
 
     match match_weak(&prep_demand_result, &prep_offer_result)? {
         MatchResult::True => Ok(Match::Yes),
@@ -52,14 +69,161 @@ pub fn match_demand_offer(
             offer_mismatch: extract_names(&from_offer),
             demand_mismatch: extract_names(&from_demand),
         }),
-        MatchResult::Undefined((from_offer, _), (from_demand, _)) => Ok(Match::Undefined {
-            offer_mismatch: extract_names(&from_offer),
-            demand_mismatch: extract_names(&from_demand),
-        }),
+        MatchResult::Undefined((from_offer, offer_expr), (from_demand, demand_expr)) => {
+            if only_dynamic(&from_offer, &offer_expr, &prep_offer_result.properties)
+                && only_dynamic(&from_demand, &demand_expr, &prep_demand_result.properties)
+            {
+                Ok(Match::Dynamic {
+                    offer_dynamic: extract_names(&from_offer),
+                    demand_dynamic: extract_names(&from_demand),
+                })
+            } else {
+                Ok(Match::Undefined {
+                    offer_mismatch: extract_names(&from_offer),
+                    demand_mismatch: extract_names(&from_demand),
+                })
+            }
+        }
         MatchResult::Err(e) => Err(e.into()),
     }
 }
 
+// Checks if constraints resolved to true, or are undefined only because
+// of value-less properties.
+fn only_dynamic(
+    props_vec: &[&PropertyRef],
+    reduced: &Expression,
+    properties: &PropertySet,
+) -> bool {
+    if *reduced == Expression::Empty(true) {
+        return true;
+    }
+    !props_vec.is_empty()
+        && props_vec.iter().all(|prop| {
+            let name = match prop {
+                PropertyRef::Value(name, _) => name,
+                PropertyRef::Aspect(..) => return false,
+            };
+            matches!(
+                properties.properties.get(&name[..]),
+                Some(Property::Implicit(_))
+            )
+        })
+}
+
+// Parse constraints and render them back in normalized form
+// (see: `Expression::simplify`).
+pub fn simplify_constraints(constraints: &str) -> Result<String, MatchError> {
+    Ok(parse_constraints(constraints)?.simplify().to_string())
+}
+
+fn parse_constraints(constraints: &str) -> Result<Expression, PrepareError> {
+    let tags = ldap_parser::parse(constraints)
+        .map_err(|error| PrepareError::new(&format!("Error parsing constraints: {}", error)))?;
+    build_expression(&tags).map_err(|error| {
+        PrepareError::new(&format!("Error building constraints expression: {}", error))
+    })
+}
+
+/// Constraints parsed once, to be resolved against properties of many
+/// subscriptions, eg. to filter Offers without creating Proposals.
+#[derive(Debug, Clone)]
+pub struct Constraints {
+    expression: Expression,
+}
+
+impl Constraints {
+    pub fn parse(constraints: &str) -> Result<Self, MatchError> {
+        Ok(Constraints {
+            expression: parse_constraints(constraints)?,
+        })
+    }
+
+    /// Checks if constraints resolve to true with Offer properties.
+    /// Offer constraints are ignored. Constraints, which can't be resolved, don't match.
+    pub fn matches(&self, offer: &Offer) -> Result<bool, MatchError> {
+        let property_set = PropertySet::from_flat_props(&offer.properties);
+        match self.expression.resolve(&property_set) {
+            ResolveResult::True => Ok(true),
+            ResolveResult::False(..) | ResolveResult::Undefined(..) => Ok(false),
+            ResolveResult::Err(e) => Err(InternalMatchErorr::new(&e.to_string()).into()),
+        }
+    }
+}
+
+/// Constraints of one side resolved against properties of the other side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintsExplanation {
+    /// `None` if constraints couldn't be resolved to boolean value.
+    pub result: Option<bool>,
+    /// Constraints reduced with known properties and normalized.
+    pub reduced: String,
+    /// Properties referenced by comparisons, which didn't hold.
+    pub failed: Vec<String>,
+    /// Properties referenced by comparisons, which couldn't be resolved.
+    pub undefined: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchExplanation {
+    /// Demand constraints resolved against Offer properties.
+    pub demand: ConstraintsExplanation,
+    /// Offer constraints resolved against Demand properties.
+    pub offer: ConstraintsExplanation,
+}
+
+// Explain the result of matching, by resolving constraints of both sides
+// and listing properties responsible for mismatch.
+pub fn explain_match(demand: &Demand, offer: &Offer) -> Result<MatchExplanation, MatchError> {
+    let prep_demand = PreparedDemand::from(demand)?;
+    let prep_offer = PreparedOffer::from(offer)?;
+
+    Ok(MatchExplanation {
+        demand: explain_constraints(&prep_demand.constraints, &prep_offer.properties).map_err(
+            |e| InternalMatchErorr::new(&format!("Error resolving Demand constraints: {}", e)),
+        )?,
+        offer: explain_constraints(&prep_offer.constraints, &prep_demand.properties).map_err(
+            |e| InternalMatchErorr::new(&format!("Error resolving Offer constraints: {}", e)),
+        )?,
+    })
+}
+
+fn explain_constraints(
+    constraints: &Expression,
+    properties: &PropertySet,
+) -> Result<ConstraintsExplanation, String> {
+    let (result, reduced) = match constraints.resolve(properties) {
+        ResolveResult::True => (Some(true), Expression::Empty(true)),
+        ResolveResult::False(_, expr) => (Some(false), expr),
+        ResolveResult::Undefined(_, expr) => (None, expr),
+        ResolveResult::Err(e) => return Err(e.to_string()),
+    };
+    let (failed, undefined) = constraints.explain(properties);
+
+    Ok(ConstraintsExplanation {
+        result,
+        reduced: reduced.simplify().to_string(),
+        failed: extract_names(&failed),
+        undefined: extract_names(&undefined),
+    })
+}
+
+// Replace value-less declarations of dynamic properties with their current values.
+// Properties not declared dynamic, and published values are left unchanged.
+fn fill_dynamic_properties(properties: &mut [String], dynamic: &DynamicProperties) {
+    for prop in properties.iter_mut() {
+        let name = match prop_parser::parse_prop_def(prop) {
+            Ok((name, None)) | Ok((name, Some("null"))) if dynamic::is_declared(name) => {
+                name.to_string()
+            }
+            _ => continue,
+        };
+        if let Some(value) = dynamic.evaluate_property(&name) {
+            *prop = value;
+        }
+    }
+}
+
 fn extract_names(props_vec: &[&PropertyRef]) -> Vec<String> {
     props_vec
         .iter()
@@ -86,6 +250,12 @@ impl Offer {
             constraints: constraints.to_string(),
         })
     }
+
+    // Fill properties declared dynamic with their current values.
+    pub fn with_dynamic_properties(mut self, dynamic: &DynamicProperties) -> Self {
+        fill_dynamic_properties(&mut self.properties, dynamic);
+        self
+    }
 }
 
 #[derive(Debug, Default)]
@@ -104,4 +274,10 @@ impl Demand {
             constraints: constraints.to_string(),
         })
     }
+
+    // Fill properties declared dynamic with their current values.
+    pub fn with_dynamic_properties(mut self, dynamic: &DynamicProperties) -> Self {
+        fill_dynamic_properties(&mut self.properties, dynamic);
+        self
+    }
 }
@@ -6,7 +6,7 @@ pub mod prepare;
 pub mod prop_parser;
 pub mod properties;
 
-pub use self::expression::Expression;
+pub use self::expression::{Expression, Operand};
 pub use self::matching::match_weak;
 pub use self::prepare::{PreparedDemand, PreparedOffer};
 pub use self::properties::PropertySet;
@@ -1,6 +1,12 @@
+use std::cmp::Ordering;
+use std::fmt;
 use std::str;
 
+use asnom::common::TagClass;
 use asnom::structures::{ExplicitTag, OctetString, Tag};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use semver::Version;
 
 use super::error::{ExpressionError, ResolveError};
 use super::ldap_parser;
@@ -15,19 +21,56 @@ pub enum ResolveResult<'a> {
     Err(ResolveError),
 }
 
+// Operand of a comparison expression.
+// Untyped values are interpreted according to the type of the referenced property,
+// typed literals (v"...", d"...", t"...") require the property to be of matching type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    Untyped(String),
+    Version(Version),
+    Decimal(BigDecimal),
+    DateTime(DateTime<Utc>),
+}
+
+impl Operand {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Operand::Untyped(_) => "Untyped",
+            Operand::Version(_) => "Version",
+            Operand::Decimal(_) => "Decimal",
+            Operand::DateTime(_) => "DateTime",
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Untyped(val) => write!(f, "{}", val),
+            Operand::Version(val) => write!(f, "v\"{}\"", val),
+            Operand::Decimal(val) => write!(f, "d\"{}\"", val),
+            Operand::DateTime(val) => write!(
+                f,
+                "t\"{}\"",
+                val.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+            ),
+        }
+    }
+}
+
 // Expression structure is the vehicle for LDAP filter expression resolution
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expression {
-    Equals(PropertyRef, String),       // property ref, value
-    Greater(PropertyRef, String),      // property ref, value
-    GreaterEqual(PropertyRef, String), // property ref, value
-    Less(PropertyRef, String),         // property ref, value
-    LessEqual(PropertyRef, String),    // property ref, value
-    Present(PropertyRef),              // property ref
-    Or(Vec<Expression>),               // operands
-    And(Vec<Expression>),              // operands
-    Not(Box<Expression>),              // operand
-    Empty(bool),                       // empty expression of specific logical value (true/false)
+    Equals(PropertyRef, Operand),       // property ref, value
+    Greater(PropertyRef, Operand),      // property ref, value
+    GreaterEqual(PropertyRef, Operand), // property ref, value
+    Less(PropertyRef, Operand),         // property ref, value
+    LessEqual(PropertyRef, Operand),    // property ref, value
+    Present(PropertyRef),               // property ref
+    Or(Vec<Expression>),                // operands
+    And(Vec<Expression>),               // operands
+    Not(Box<Expression>),               // operand
+    Empty(bool),                        // empty expression of specific logical value (true/false)
 }
 
 impl Expression {
@@ -80,7 +123,108 @@ impl Expression {
         }
     }
 
-    // TODO: Implement ultimate reduction of AND and OR expressions where only one factor remains
+    // Resolve each comparison of the expression separately and return references
+    // to properties, for which comparison failed and couldn't be resolved respectively.
+    // Comparison negated odd number of times fails, when it resolves to true.
+    pub fn explain<'a>(
+        &'a self,
+        property_set: &'a PropertySet,
+    ) -> (Vec<&'a PropertyRef>, Vec<&'a PropertyRef>) {
+        let mut failed = vec![];
+        let mut undefined = vec![];
+        self.explain_into(property_set, false, &mut failed, &mut undefined);
+        (failed, undefined)
+    }
+
+    fn explain_into<'a>(
+        &'a self,
+        property_set: &'a PropertySet,
+        negated: bool,
+        failed: &mut Vec<&'a PropertyRef>,
+        undefined: &mut Vec<&'a PropertyRef>,
+    ) {
+        let push_unique = |refs: &mut Vec<&'a PropertyRef>, prop: &'a PropertyRef| {
+            if !refs.contains(&prop) {
+                refs.push(prop);
+            }
+        };
+
+        match self {
+            // Satisfied alternative (OR, or negated AND) doesn't fail because of its
+            // other operands, so they are reported only when the whole clause fails.
+            Expression::Or(_)
+                if !negated && matches!(self.resolve(property_set), ResolveResult::True) => {}
+            Expression::And(_)
+                if negated && matches!(self.resolve(property_set), ResolveResult::False(..)) => {}
+            Expression::And(exprs) | Expression::Or(exprs) => exprs
+                .iter()
+                .for_each(|expr| expr.explain_into(property_set, negated, failed, undefined)),
+            Expression::Not(expr) => expr.explain_into(property_set, !negated, failed, undefined),
+            Expression::Empty(_) => {}
+            _ => {
+                let refs = self.property_refs().into_iter();
+                match self.resolve(property_set) {
+                    ResolveResult::True if !negated => {}
+                    ResolveResult::False(..) if negated => {}
+                    ResolveResult::Undefined(..) => {
+                        refs.for_each(|prop| push_unique(undefined, prop))
+                    }
+                    _ => refs.for_each(|prop| push_unique(failed, prop)),
+                }
+            }
+        }
+    }
+
+    // Normalize the expression:
+    // - flatten nested AND/OR expressions of the same kind,
+    // - remove Empty(true/false) identities and short-circuit on absorbing elements,
+    // - collapse single-operand AND/OR expressions,
+    // - de-duplicate equal sub-expressions,
+    // - remove double negations.
+    // The result is logically equivalent to the original expression.
+    pub fn simplify(self) -> Expression {
+        match self {
+            Expression::And(exprs) => Expression::simplify_clause(exprs, true),
+            Expression::Or(exprs) => Expression::simplify_clause(exprs, false),
+            Expression::Not(expr) => match expr.simplify() {
+                Expression::Empty(val) => Expression::Empty(!val),
+                Expression::Not(inner) => *inner,
+                inner => Expression::Not(Box::new(inner)),
+            },
+            expr => expr,
+        }
+    }
+
+    // Simplify AND (is_and == true) or OR (is_and == false) clause.
+    // Empty(is_and) is the identity element of the clause, Empty(!is_and) is its absorbing element.
+    fn simplify_clause(exprs: Vec<Expression>, is_and: bool) -> Expression {
+        let mut operands: Vec<Expression> = vec![];
+
+        for expr in exprs.into_iter().map(Expression::simplify) {
+            let flattened = match expr {
+                Expression::And(inner) if is_and => inner,
+                Expression::Or(inner) if !is_and => inner,
+                Expression::Empty(val) if val == is_and => continue,
+                Expression::Empty(val) => return Expression::Empty(val),
+                expr => vec![expr],
+            };
+
+            for expr in flattened {
+                if !operands.contains(&expr) {
+                    operands.push(expr);
+                }
+            }
+        }
+
+        match operands.len() {
+            0 => Expression::Empty(is_and),
+            1 => operands.pop().unwrap(),
+            _ if is_and => Expression::And(operands),
+            _ => Expression::Or(operands),
+        }
+    }
+
+    // (DONE) Implement ultimate reduction of AND and OR expressions where only one factor remains
 
     // (DONE) Rework for adjusted property definition syntax (property types derived form literals)
     // (DONE) Implement strong resolution and expression 'reduce' (ie. undefined results are propagated rather than ignored)
@@ -93,12 +237,12 @@ impl Expression {
     // (DONE) Handling of List property type
     // (DONE) equals operator for List property type with single operand (ignore other comparison operators)
     // (DONE) equals operator for List property type with List operand (list equivalence operator)
-    // TODO: Handling of types in constraint filter expressions
+    // (DONE) Handling of types in constraint filter expressions
     // TODO: Implement allowed characters in property names
     // (DONE) Rework resolve so that ResolveResult is based on strs and not Strings
     // (DONE) wildcard matching of property values
-    // TODO: wildcard matching of value-less properties
-    //       - Implement dynamic property "handler" - via trait?
+    // (DONE) wildcard matching of value-less properties
+    //       - (DONE) Implement dynamic property "handler" - via trait (see: `dynamic::DynamicProperty`)
     // (DONE) aspects
     // TODO: finalize and review the matching relation implementations
     pub fn resolve<'a>(&'a self, property_set: &'a PropertySet) -> ResolveResult {
@@ -107,31 +251,56 @@ impl Expression {
                 attr,
                 val,
                 property_set,
-                |prop_value: &PropertyValue, val: &str| -> bool { prop_value.equals(val) },
+                |prop_value: &PropertyValue, val: &Operand| -> Result<bool, String> {
+                    match val {
+                        Operand::Untyped(val) => Ok(prop_value.equals(val)),
+                        typed => prop_value.equals_typed(typed),
+                    }
+                },
             ),
             Expression::Less(attr, val) => self.resolve_with_function(
                 attr,
                 val,
                 property_set,
-                |prop_value: &PropertyValue, val: &str| -> bool { prop_value.less(val) },
+                |prop_value: &PropertyValue, val: &Operand| -> Result<bool, String> {
+                    match val {
+                        Operand::Untyped(val) => Ok(prop_value.less(val)),
+                        typed => Ok(prop_value.compare_typed(typed)? == Ordering::Less),
+                    }
+                },
             ),
             Expression::LessEqual(attr, val) => self.resolve_with_function(
                 attr,
                 val,
                 property_set,
-                |prop_value: &PropertyValue, val: &str| -> bool { prop_value.less_equal(val) },
+                |prop_value: &PropertyValue, val: &Operand| -> Result<bool, String> {
+                    match val {
+                        Operand::Untyped(val) => Ok(prop_value.less_equal(val)),
+                        typed => Ok(prop_value.compare_typed(typed)? != Ordering::Greater),
+                    }
+                },
             ),
             Expression::Greater(attr, val) => self.resolve_with_function(
                 attr,
                 val,
                 property_set,
-                |prop_value: &PropertyValue, val: &str| -> bool { prop_value.greater(val) },
+                |prop_value: &PropertyValue, val: &Operand| -> Result<bool, String> {
+                    match val {
+                        Operand::Untyped(val) => Ok(prop_value.greater(val)),
+                        typed => Ok(prop_value.compare_typed(typed)? == Ordering::Greater),
+                    }
+                },
             ),
             Expression::GreaterEqual(attr, val) => self.resolve_with_function(
                 attr,
                 val,
                 property_set,
-                |prop_value: &PropertyValue, val: &str| -> bool { prop_value.greater_equal(val) },
+                |prop_value: &PropertyValue, val: &Operand| -> Result<bool, String> {
+                    match val {
+                        Operand::Untyped(val) => Ok(prop_value.greater_equal(val)),
+                        typed => Ok(prop_value.compare_typed(typed)? != Ordering::Less),
+                    }
+                },
             ),
             // other binary operators here if needed...
             Expression::And(inner_expressions) => self.resolve_and(inner_expressions, property_set),
@@ -158,9 +327,9 @@ impl Expression {
     fn resolve_with_function<'a>(
         &'a self,
         prop_ref: &'a PropertyRef,
-        val_string: &str,
+        operand: &Operand,
         property_set: &'a PropertySet,
-        oper_function: impl Fn(&PropertyValue, &str) -> bool,
+        oper_function: impl Fn(&PropertyValue, &Operand) -> Result<bool, String>,
     ) -> ResolveResult {
         // TODO this requires rewrite to cater for implicit properties...
         // test if property exists and then if the value matches
@@ -181,16 +350,23 @@ impl Expression {
                                 match value.to_prop_ref_type(impl_type) {
                                     Ok(conv_result) => {
                                         let resolve_result = match conv_result {
-                                            Some(val) => oper_function(&val, val_string),
-                                            None => oper_function(value, val_string),
+                                            Some(val) => oper_function(&val, operand),
+                                            None => oper_function(value, operand),
                                         };
 
                                         // resolve against prop value
-                                        if resolve_result {
-                                            ResolveResult::True
-                                        } else {
-                                            ResolveResult::False(vec![], Expression::Empty(false))
-                                            // if resolved to false - return Empty as reduced expression
+                                        match resolve_result {
+                                            Ok(true) => ResolveResult::True,
+                                            Ok(false) => {
+                                                ResolveResult::False(
+                                                    vec![],
+                                                    Expression::Empty(false),
+                                                )
+                                                // if resolved to false - return Empty as reduced expression
+                                            }
+                                            Err(error) => ResolveResult::Err(
+                                                Expression::type_mismatch(name, operand, error),
+                                            ),
                                         }
                                     }
                                     Err(_) => {
@@ -203,11 +379,26 @@ impl Expression {
                                 // resolve against prop aspect
                                 match aspects.get(&aspect[..]) {
                                     Some(aspect_value) => {
-                                        if val_string == *aspect_value {
-                                            ResolveResult::True
-                                        } else {
-                                            ResolveResult::False(vec![], Expression::Empty(false))
-                                            // if resolved to false - return Empty as reduced expression
+                                        let resolve_result = match operand {
+                                            Operand::Untyped(val) => Ok(val == *aspect_value),
+                                            typed => oper_function(
+                                                &PropertyValue::Str(*aspect_value),
+                                                typed,
+                                            ),
+                                        };
+
+                                        match resolve_result {
+                                            Ok(true) => ResolveResult::True,
+                                            Ok(false) => {
+                                                ResolveResult::False(
+                                                    vec![],
+                                                    Expression::Empty(false),
+                                                )
+                                                // if resolved to false - return Empty as reduced expression
+                                            }
+                                            Err(error) => ResolveResult::Err(
+                                                Expression::type_mismatch(name, operand, error),
+                                            ),
                                         }
                                     }
                                     None => {
@@ -229,6 +420,16 @@ impl Expression {
         }
     }
 
+    fn type_mismatch(prop_name: &str, operand: &Operand, error: String) -> ResolveError {
+        ResolveError::new(&format!(
+            "Type mismatch: property '{}' can't be compared with {} literal {}: {}",
+            prop_name,
+            operand.type_name(),
+            operand,
+            error
+        ))
+    }
+
     fn resolve_and<'a>(
         &'a self,
         seq: &'a Vec<Expression>,
@@ -269,7 +470,7 @@ impl Expression {
             ResolveResult::Undefined(
                 unresolved_refs,
                 match unresolved_exprs.len().cmp(&1) {
-                    std::cmp::Ordering::Greater => Expression::And(unresolved_exprs),
+                    std::cmp::Ordering::Greater => Expression::And(unresolved_exprs).simplify(),
                     std::cmp::Ordering::Equal => unresolved_exprs.pop().unwrap(),
                     std::cmp::Ordering::Less => Expression::Empty(true),
                 },
@@ -320,7 +521,7 @@ impl Expression {
             ResolveResult::Undefined(
                 all_un_props,
                 match unresolved_exprs.len().cmp(&1) {
-                    std::cmp::Ordering::Greater => Expression::Or(unresolved_exprs),
+                    std::cmp::Ordering::Greater => Expression::Or(unresolved_exprs).simplify(),
                     std::cmp::Ordering::Equal => unresolved_exprs.pop().unwrap(),
                     std::cmp::Ordering::Less => Expression::Empty(true),
                 },
@@ -329,7 +530,7 @@ impl Expression {
             ResolveResult::False(
                 all_un_props,
                 match unresolved_exprs.len().cmp(&1) {
-                    std::cmp::Ordering::Greater => Expression::Or(unresolved_exprs),
+                    std::cmp::Ordering::Greater => Expression::Or(unresolved_exprs).simplify(),
                     std::cmp::Ordering::Equal => unresolved_exprs.pop().unwrap(),
                     std::cmp::Ordering::Less => Expression::Empty(false),
                 },
@@ -375,6 +576,34 @@ impl Expression {
     }
 }
 
+// Render expression back to LDAP filter syntax.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Equals(prop_ref, val) => write!(f, "({}={})", prop_ref, val),
+            Expression::Greater(prop_ref, val) => write!(f, "({}>{})", prop_ref, val),
+            Expression::GreaterEqual(prop_ref, val) => write!(f, "({}>={})", prop_ref, val),
+            Expression::Less(prop_ref, val) => write!(f, "({}<{})", prop_ref, val),
+            Expression::LessEqual(prop_ref, val) => write!(f, "({}<={})", prop_ref, val),
+            Expression::Present(prop_ref) => write!(f, "({}=*)", prop_ref),
+            Expression::And(exprs) | Expression::Or(exprs) => {
+                let oper = match self {
+                    Expression::And(_) => '&',
+                    _ => '|',
+                };
+                write!(f, "({}", oper)?;
+                for expr in exprs {
+                    write!(f, "{}", expr)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Not(expr) => write!(f, "(!{})", expr),
+            Expression::Empty(true) => write!(f, "()"),
+            Expression::Empty(false) => write!(f, "(|)"),
+        }
+    }
+}
+
 // #region Expression building
 
 pub fn build_expression(root: &Tag) -> Result<Expression, ExpressionError> {
@@ -468,6 +697,7 @@ fn build_simple_expression(
 ) -> Result<Expression, ExpressionError> {
     match extract_two_octet_strings(sequence) {
         Ok(result) => {
+            let operand = build_operand(&sequence[1], result.1)?;
             let prop_ref = match parse_prop_ref(result.0) {
                 Ok(prop_ref) => prop_ref,
                 Err(prop_err) => {
@@ -478,17 +708,11 @@ fn build_simple_expression(
                 }
             };
             match expr_type {
-                ldap_parser::TAG_EQUAL => Ok(Expression::Equals(prop_ref, String::from(result.1))),
-                ldap_parser::TAG_GREATER => {
-                    Ok(Expression::Greater(prop_ref, String::from(result.1)))
-                }
-                ldap_parser::TAG_GREATER_EQUAL => {
-                    Ok(Expression::GreaterEqual(prop_ref, String::from(result.1)))
-                }
-                ldap_parser::TAG_LESS => Ok(Expression::Less(prop_ref, String::from(result.1))),
-                ldap_parser::TAG_LESS_EQUAL => {
-                    Ok(Expression::LessEqual(prop_ref, String::from(result.1)))
-                }
+                ldap_parser::TAG_EQUAL => Ok(Expression::Equals(prop_ref, operand)),
+                ldap_parser::TAG_GREATER => Ok(Expression::Greater(prop_ref, operand)),
+                ldap_parser::TAG_GREATER_EQUAL => Ok(Expression::GreaterEqual(prop_ref, operand)),
+                ldap_parser::TAG_LESS => Ok(Expression::Less(prop_ref, operand)),
+                ldap_parser::TAG_LESS_EQUAL => Ok(Expression::LessEqual(prop_ref, operand)),
                 // add other binary operators handling here
                 _ => Err(ExpressionError::new(&format!(
                     "Unknown expression type {}",
@@ -500,6 +724,46 @@ fn build_simple_expression(
     }
 }
 
+// Build comparison operand, taking into account the type of literal recognized by the parser.
+fn build_operand(tag: &Tag, value: &str) -> Result<Operand, ExpressionError> {
+    let value_type = match tag {
+        Tag::OctetString(oct) if oct.class == TagClass::Context => oct.id,
+        _ => return Ok(Operand::Untyped(String::from(value))),
+    };
+
+    match value_type {
+        ldap_parser::TAG_VALUE_VERSION => {
+            Version::parse(value).map(Operand::Version).map_err(|e| {
+                ExpressionError::new(&format!(
+                    "Error parsing Version literal v\"{}\": {}",
+                    value, e
+                ))
+            })
+        }
+        ldap_parser::TAG_VALUE_DECIMAL => value
+            .parse::<BigDecimal>()
+            .map(Operand::Decimal)
+            .map_err(|e| {
+                ExpressionError::new(&format!(
+                    "Error parsing Decimal literal d\"{}\": {}",
+                    value, e
+                ))
+            }),
+        ldap_parser::TAG_VALUE_DATETIME => DateTime::parse_from_rfc3339(value)
+            .map(|dt| Operand::DateTime(Utc.from_utc_datetime(&dt.naive_utc())))
+            .map_err(|e| {
+                ExpressionError::new(&format!(
+                    "Error parsing DateTime literal t\"{}\": {}",
+                    value, e
+                ))
+            }),
+        _ => Err(ExpressionError::new(&format!(
+            "Unknown literal type {}",
+            value_type
+        ))),
+    }
+}
+
 fn extract_str_from_octet_string(tag: &Tag) -> Result<&str, ExpressionError> {
     match tag {
         Tag::OctetString(oct) => match str::from_utf8(&oct.inner) {
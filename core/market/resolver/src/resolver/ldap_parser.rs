@@ -18,6 +18,12 @@ pub const TAG_GREATER_EQUAL: u64 = 9;
 pub const TAG_LESS: u64 = 10;
 pub const TAG_LESS_EQUAL: u64 = 11;
 
+// Typed value literal tag constants (Context class OctetStrings)
+
+pub const TAG_VALUE_VERSION: u64 = 12;
+pub const TAG_VALUE_DECIMAL: u64 = 13;
+pub const TAG_VALUE_DATETIME: u64 = 14;
+
 // Parse function
 
 pub fn parse(input: &str) -> Result<Tag, String> {
@@ -103,7 +109,7 @@ named!(
     do_parse!(
         attr: take_till!(is_delimiter)
             >> filtertype: filtertype
-            >> value: take_until!(")")
+            >> value: value
             >> (Tag::Sequence(Sequence {
                 class: TagClass::Context,
                 id: filtertype,
@@ -112,15 +118,64 @@ named!(
                         inner: attr.to_vec(),
                         ..Default::default()
                     }),
-                    Tag::OctetString(OctetString {
-                        inner: value.to_vec(),
-                        ..Default::default()
-                    })
+                    value
                 ]
             }))
     )
 );
 
+// Value of a simple filter - either a typed literal (eg. v"1.2.0", d"0.01", t"2020-01-01T00:00:00Z")
+// or an untyped value, which is interpreted according to the type of referenced property.
+
+named!(value<Tag>, alt!(complete!(typed_value) | untyped_value));
+
+named!(
+    typed_value<Tag>,
+    do_parse!(
+        value_type: value_type
+            >> char!('"')
+            >> value: take_until!("\"")
+            >> char!('"')
+            >> peek!(char!(')'))
+            >> (Tag::OctetString(OctetString {
+                class: TagClass::Context,
+                id: value_type,
+                inner: value.to_vec(),
+            }))
+    )
+);
+
+named!(
+    untyped_value<Tag>,
+    do_parse!(
+        value: take_until!(")")
+            >> (Tag::OctetString(OctetString {
+                inner: value.to_vec(),
+                ..Default::default()
+            }))
+    )
+);
+
+named!(
+    value_type<u64>,
+    alt!(value_type_version | value_type_decimal | value_type_datetime)
+);
+
+named!(
+    value_type_version<u64>,
+    do_parse!(char!('v') >> (TAG_VALUE_VERSION))
+);
+
+named!(
+    value_type_decimal<u64>,
+    do_parse!(char!('d') >> (TAG_VALUE_DECIMAL))
+);
+
+named!(
+    value_type_datetime<u64>,
+    do_parse!(char!('t') >> (TAG_VALUE_DATETIME))
+);
+
 //named!(filtertype <u64>, call!(equal));
 
 named!(
@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+use std::fmt;
 use std::str;
 
 use bigdecimal::BigDecimal;
@@ -7,8 +9,10 @@ use semver::Version;
 use std::collections::HashMap;
 
 use super::error::ParseError;
+use super::expression::Operand;
 use super::prop_parser;
 use super::prop_parser::Literal;
+use crate::dynamic;
 
 #[allow(non_camel_case_types)]
 type d128 = BigDecimal;
@@ -159,6 +163,67 @@ impl<'a> PropertyValue<'a> {
         }
     }
 
+    // Equality with a typed literal operand.
+    // For List properties the "IN" operator is applied, ie. any item of the list
+    // convertible to the literal type and equal to the literal satisfies the condition.
+    pub fn equals_typed(&self, other: &Operand) -> Result<bool, String> {
+        match self {
+            PropertyValue::List(items) => Ok(items.iter().any(|item| {
+                item.compare_typed(other)
+                    .map(|ord| ord == Ordering::Equal)
+                    .unwrap_or(false)
+            })),
+            _ => Ok(self.compare_typed(other)? == Ordering::Equal),
+        }
+    }
+
+    // Compare with a typed literal operand.
+    // Returns error if the property value can't be interpreted as a value of the literal type.
+    pub fn compare_typed(&self, other: &Operand) -> Result<Ordering, String> {
+        match other {
+            Operand::Version(other) => Ok(self.as_version()?.cmp(other)),
+            Operand::Decimal(other) => Ok(self.as_decimal()?.cmp(other)),
+            Operand::DateTime(other) => Ok(self.as_datetime()?.cmp(other)),
+            Operand::Untyped(other) => Err(format!(
+                "Untyped value '{}' can't be compared as typed literal",
+                other
+            )),
+        }
+    }
+
+    fn as_version(&self) -> Result<Version, String> {
+        match self {
+            PropertyValue::Version(value) => Ok(value.clone()),
+            PropertyValue::Str(value) => Version::parse(value)
+                .map_err(|_| format!("Unable to interpret '{}' as Version", value)),
+            _ => Err(format!("Unable to convert {:?} to Version", self)),
+        }
+    }
+
+    fn as_decimal(&self) -> Result<BigDecimal, String> {
+        match self {
+            PropertyValue::Decimal(value) => Ok(value.clone()),
+            // Use shortest string representation of f64 to avoid binary rounding artifacts.
+            PropertyValue::Number(value) => value
+                .to_string()
+                .parse::<d128>()
+                .map_err(|_| format!("Unable to interpret {} as Decimal", value)),
+            PropertyValue::Str(value) => value
+                .parse::<d128>()
+                .map_err(|_| format!("Unable to interpret '{}' as Decimal", value)),
+            _ => Err(format!("Unable to convert {:?} to Decimal", self)),
+        }
+    }
+
+    fn as_datetime(&self) -> Result<DateTime<Utc>, String> {
+        match self {
+            PropertyValue::DateTime(value) => Ok(*value),
+            PropertyValue::Str(value) => PropertyValue::parse_date(value)
+                .map_err(|_| format!("Unable to interpret '{}' as DateTime", value)),
+            _ => Err(format!("Unable to convert {:?} to DateTime", self)),
+        }
+    }
+
     // Implement string equality with * wildcard
     // Note: Only str1 may contain wildcard
     // TODO my be sensible to move the Regex building to the point where property is parsed...
@@ -388,6 +453,8 @@ impl<'a> PropertySet<'a> {
         // Parse the property string to extract: property name and property value(s) - also detecting the property types
         match prop_parser::parse_prop_def(prop_flat) {
             Ok((name, value)) => match value {
+                // Declared dynamic properties set to null are value-less properties.
+                Some("null") if dynamic::is_declared(name) => Ok((name, Property::Implicit(name))),
                 Some(val) => match PropertyValue::from_value(val) {
                     Ok(prop_value) => {
                         Ok((name, Property::Explicit(name, prop_value, HashMap::new())))
@@ -439,6 +506,28 @@ pub enum PropertyRefType {
     DateTime,
 }
 
+impl fmt::Display for PropertyRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertyRef::Value(name, impl_type) => write!(f, "{}{}", name, impl_type),
+            PropertyRef::Aspect(name, aspect, impl_type) => {
+                write!(f, "{}[{}]{}", name, aspect, impl_type)
+            }
+        }
+    }
+}
+
+impl fmt::Display for PropertyRefType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertyRefType::Any => Ok(()),
+            PropertyRefType::Decimal => write!(f, "$d"),
+            PropertyRefType::Version => write!(f, "$v"),
+            PropertyRefType::DateTime => write!(f, "$t"),
+        }
+    }
+}
+
 pub fn parse_prop_ref(flat_prop: &str) -> Result<PropertyRef, ParseError> {
     // TODO parse the flat_prop using prop_parser and repack to PropertyRef
     match prop_parser::parse_prop_ref_with_aspect(flat_prop) {
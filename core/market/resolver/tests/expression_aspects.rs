@@ -111,7 +111,7 @@ fn resolve_equals_aspect() {
                     String::from("aspect"),
                     PropertyRefType::Any,
                 ),
-                String::from("asp_value"),
+                Operand::Untyped(String::from("asp_value")),
             ),
         ),
     );
@@ -131,7 +131,7 @@ fn resolve_equals_aspect() {
                     String::from("aspect"),
                     PropertyRefType::Any,
                 ),
-                String::from("asp_value"),
+                Operand::Untyped(String::from("asp_value")),
             ),
         ),
     );
@@ -177,7 +177,7 @@ fn resolve_not_aspect() {
                     String::from("aspect"),
                     PropertyRefType::Any,
                 ),
-                String::from("asp_value"),
+                Operand::Untyped(String::from("asp_value")),
             ))),
         ),
     );
@@ -45,7 +45,7 @@ fn build_expression_equals() {
 
     let expression = Expression::Equals(
         PropertyRef::Value(String::from("cn"), PropertyRefType::Any),
-        String::from("Babs Jensen"),
+        Operand::Untyped(String::from("Babs Jensen")),
     );
 
     assert_eq!(build_expression(&parse(f).unwrap()), Ok(expression));
@@ -57,7 +57,7 @@ fn build_expression_not() {
 
     let expression = Expression::Not(Box::new(Expression::Equals(
         PropertyRef::Value(String::from("cn"), PropertyRefType::Any),
-        String::from("Tim Howes"),
+        Operand::Untyped(String::from("Tim Howes")),
     )));
 
     assert_eq!(build_expression(&parse(f).unwrap()), Ok(expression));
@@ -70,17 +70,48 @@ fn build_expression_and() {
     let expression = Expression::And(vec![
         Expression::Equals(
             PropertyRef::Value(String::from("a"), PropertyRefType::Any),
-            String::from("b"),
+            Operand::Untyped(String::from("b")),
         ),
         Expression::Equals(
             PropertyRef::Value(String::from("b"), PropertyRefType::Any),
-            String::from("c"),
+            Operand::Untyped(String::from("c")),
         ),
         Expression::Equals(
             PropertyRef::Value(String::from("c"), PropertyRefType::Any),
-            String::from("d"),
+            Operand::Untyped(String::from("d")),
         ),
     ]);
 
     assert_eq!(build_expression(&parse(f).unwrap()), Ok(expression));
 }
+
+#[test]
+fn build_expression_typed_version() {
+    let f = r#"(golem.runtime.version>=v"1.2.0")"#;
+
+    let expression = Expression::GreaterEqual(
+        PropertyRef::Value(String::from("golem.runtime.version"), PropertyRefType::Any),
+        Operand::Version(semver::Version::parse("1.2.0").unwrap()),
+    );
+
+    assert_eq!(build_expression(&parse(f).unwrap()), Ok(expression));
+}
+
+#[test]
+fn build_expression_typed_decimal() {
+    let f = r#"(price<=d"0.0001")"#;
+
+    let expression = Expression::LessEqual(
+        PropertyRef::Value(String::from("price"), PropertyRefType::Any),
+        Operand::Decimal("0.0001".parse().unwrap()),
+    );
+
+    assert_eq!(build_expression(&parse(f).unwrap()), Ok(expression));
+}
+
+#[test]
+fn build_expression_typed_literal_error() {
+    let f = r#"(golem.runtime.version>=v"dblah")"#;
+
+    assert!(build_expression(&parse(f).unwrap()).is_err());
+}
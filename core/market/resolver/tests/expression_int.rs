@@ -33,7 +33,7 @@ fn resolve_multistep_with_supplemented_props() {
             vec![&PropertyRef::Value(String::from("a"), PropertyRefType::Any)],
             Expression::Equals(
                 PropertyRef::Value(String::from("a"), PropertyRefType::Any),
-                String::from("b")
+                Operand::Untyped(String::from("b"))
             )
         )
     );
@@ -97,7 +97,7 @@ fn resolve_equals() {
             )],
             Expression::Equals(
                 PropertyRef::Value(String::from("cn"), PropertyRefType::Any),
-                String::from("Babs Jensen"),
+                Operand::Untyped(String::from("Babs Jensen")),
             ),
         ),
     );
@@ -135,7 +135,7 @@ fn resolve_equals_list() {
             )],
             Expression::Equals(
                 PropertyRef::Value(String::from("cn"), PropertyRefType::Any),
-                String::from("Babs Jensen"),
+                Operand::Untyped(String::from("Babs Jensen")),
             ),
         ),
     );
@@ -169,7 +169,7 @@ fn resolve_equals_with_wildcard() {
             )],
             Expression::Equals(
                 PropertyRef::Value(String::from("cn"), PropertyRefType::Any),
-                String::from("Babs *"),
+                Operand::Untyped(String::from("Babs *")),
             ),
         ),
     );
@@ -311,7 +311,7 @@ fn resolve_greater_equal_version_with_implied_type() {
             vec![],
             Expression::GreaterEqual(
                 PropertyRef::Value(String::from("cn"), PropertyRefType::Version),
-                String::from("1.5.0"),
+                Operand::Untyped(String::from("1.5.0")),
             ),
         ),
     );
@@ -336,7 +336,7 @@ fn resolve_greater_equal_decimal_with_implied_type() {
             vec![],
             Expression::GreaterEqual(
                 PropertyRef::Value(String::from("cn"), PropertyRefType::Decimal),
-                String::from("10"),
+                Operand::Untyped(String::from("10")),
             ),
         ),
     );
@@ -370,7 +370,7 @@ fn resolve_not() {
             )],
             Expression::Not(Box::new(Expression::Equals(
                 PropertyRef::Value(String::from("cn"), PropertyRefType::Any),
-                String::from("Tim Howes"),
+                Operand::Untyped(String::from("Tim Howes")),
             ))),
         ),
     );
@@ -405,7 +405,7 @@ fn resolve_and() {
             vec![&PropertyRef::Value(String::from("a"), PropertyRefType::Any)],
             Expression::Equals(
                 PropertyRef::Value(String::from("a"), PropertyRefType::Any),
-                String::from("b"),
+                Operand::Untyped(String::from("b")),
             ),
         ),
     );
@@ -440,7 +440,7 @@ fn resolve_or() {
             vec![&PropertyRef::Value(String::from("a"), PropertyRefType::Any)],
             Expression::Equals(
                 PropertyRef::Value(String::from("a"), PropertyRefType::Any),
-                String::from("b"),
+                Operand::Untyped(String::from("b")),
             ),
         ),
     );
@@ -541,7 +541,7 @@ fn resolve_pseudo_function_prop_sample_undefined() {
                     String::from("golem.com.pricing.est{30}"),
                     PropertyRefType::Any,
                 ),
-                String::from("20"),
+                Operand::Untyped(String::from("20")),
             ),
         ),
     );
@@ -573,7 +573,7 @@ fn resolve_pseudo_function_array_sample_undefined() {
                     String::from("golem.com.pricing.est{[30]}"),
                     PropertyRefType::Any,
                 ),
-                String::from("20"),
+                Operand::Untyped(String::from("20")),
             ),
         ),
     );
@@ -591,7 +591,7 @@ fn resolve_wildcard_prop_sample_undefined() {
                             ], ResolveResult::Undefined(
                                 vec![&PropertyRef::Value(String::from("golem.srv.comp.task_package"), PropertyRefType::Any)],
                                 Expression::Equals(PropertyRef::Value(String::from("golem.srv.comp.task_package"), PropertyRefType::Any),
-                                                   String::from("hash://sha3:D5E31B2EED628572A5898BF8C34447644BFC4B5130CFC1E4F10AEAA1:http://12.34.56.78:8000/rust-wasi-tutorial.zip"))
+                                                   Operand::Untyped(String::from("hash://sha3:D5E31B2EED628572A5898BF8C34447644BFC4B5130CFC1E4F10AEAA1:http://12.34.56.78:8000/rust-wasi-tutorial.zip")))
                             ));
 }
 
@@ -608,7 +608,7 @@ fn resolve_complex_or_undefined() {
             vec![&PropertyRef::Value(String::from("a"), PropertyRefType::Any)],
             Expression::Equals(
                 PropertyRef::Value(String::from("a"), PropertyRefType::Any),
-                String::from("b"),
+                Operand::Untyped(String::from("b")),
             ),
         ),
     );
@@ -656,11 +656,11 @@ fn resolve_complex_and_undefined() {
             Expression::And(vec![
                 Expression::Equals(
                     PropertyRef::Value(String::from("a"), PropertyRefType::Any),
-                    String::from("b"),
+                    Operand::Untyped(String::from("b")),
                 ),
                 Expression::Equals(
                     PropertyRef::Value(String::from("b"), PropertyRefType::Any),
-                    String::from("c"),
+                    Operand::Untyped(String::from("c")),
                 ),
             ]),
         ),
@@ -684,3 +684,103 @@ fn resolve_complex_and_undefined_reduced() {
         ResolveResult::False(vec![], Expression::Empty(false)),
     );
 }
+
+#[test]
+fn resolve_typed_version() {
+    // test positive - compared as Version, not as string
+
+    run_resolve_test(
+        r#"(cn>=v"1.5.0")"#,
+        &vec![r#"cn="1.10.0""#],
+        ResolveResult::True,
+    );
+    run_resolve_test(
+        r#"(cn>=v"1.5.0")"#,
+        &vec![r#"cn=v"1.10.0""#],
+        ResolveResult::True,
+    );
+
+    // test negative
+
+    run_resolve_test(
+        r#"(cn<v"1.5.0")"#,
+        &vec![r#"cn="1.10.0""#],
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+}
+
+#[test]
+fn resolve_typed_decimal() {
+    // test positive - Number property compared as Decimal
+
+    run_resolve_test(
+        r#"(price<=d"0.0003")"#,
+        &vec!["price=0.0003"],
+        ResolveResult::True,
+    );
+    run_resolve_test(
+        r#"(price=d"0.1")"#,
+        &vec![r#"price=d"0.10""#],
+        ResolveResult::True,
+    );
+
+    // test negative
+
+    run_resolve_test(
+        r#"(price>d"0.0003")"#,
+        &vec!["price=0.0003"],
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+}
+
+#[test]
+fn resolve_typed_datetime() {
+    let f = r#"(expiration<t"2026-10-01T00:00:00Z")"#;
+
+    // test positive
+
+    run_resolve_test(
+        f,
+        &vec![r#"expiration=t"2026-09-30T23:59:59Z""#],
+        ResolveResult::True,
+    );
+
+    // test negative
+
+    run_resolve_test(
+        f,
+        &vec![r#"expiration="2026-10-01T00:00:00+00:00""#],
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+}
+
+#[test]
+fn resolve_typed_list_in() {
+    run_resolve_test(
+        r#"(cn=v"1.2.0")"#,
+        &vec![r#"cn=[v"1.0.0",v"1.2.0"]"#],
+        ResolveResult::True,
+    );
+}
+
+#[test]
+fn resolve_typed_type_mismatch() {
+    let expression = build_expression(&parse(r#"(cn>=v"1.5.0")"#).unwrap()).unwrap();
+
+    let properties = vec![String::from("cn=true")];
+    let property_set = PropertySet::from_flat_props(&properties);
+
+    match expression.resolve(&property_set) {
+        ResolveResult::Err(error) => assert!(error.msg.contains("Type mismatch")),
+        result => panic!("Expected type mismatch error, got: {:?}", result),
+    }
+
+    // string not parseable as Version
+    let properties = vec![String::from(r#"cn="dblah""#)];
+    let property_set = PropertySet::from_flat_props(&properties);
+
+    assert!(matches!(
+        expression.resolve(&property_set),
+        ResolveResult::Err(_)
+    ));
+}
@@ -0,0 +1,156 @@
+use ya_market_resolver::resolver::expression::*;
+use ya_market_resolver::resolver::ldap_parser::parse;
+use ya_market_resolver::resolver::properties::*;
+use ya_market_resolver::simplify_constraints;
+
+use ya_agreement_utils::{
+    constraints,
+    ClauseOperator::{And, Or},
+    ConstraintKey, ConstraintValue, Constraints,
+};
+
+fn run_simplify_test(expr: &str, expected: &str) {
+    let expression = build_expression(&parse(expr).unwrap()).unwrap();
+    let expected = build_expression(&parse(expected).unwrap()).unwrap();
+
+    assert_eq!(expression.simplify(), expected);
+}
+
+#[test]
+fn simplify_flatten_nested() {
+    run_simplify_test("(&(a=1)(&(b=2)(&(c=3))))", "(&(a=1)(b=2)(c=3))");
+    run_simplify_test("(|(a=1)(|(b=2)(c=3)))", "(|(a=1)(b=2)(c=3))");
+    run_simplify_test("(&(a=1)(|(b=2)(c=3)))", "(&(a=1)(|(b=2)(c=3)))");
+}
+
+#[test]
+fn simplify_empty_identities() {
+    run_simplify_test("(&(a=1)()(b=2))", "(&(a=1)(b=2))");
+    run_simplify_test("(|(a=1)())", "()");
+    assert_eq!(
+        build_expression(&parse("(&(a=1)(|))").unwrap())
+            .unwrap()
+            .simplify(),
+        Expression::Empty(false)
+    );
+    run_simplify_test("(&()())", "()");
+}
+
+#[test]
+fn simplify_single_operand() {
+    run_simplify_test("(&(|(a=1)))", "(a=1)");
+    run_simplify_test("(!(!(a=1)))", "(a=1)");
+}
+
+#[test]
+fn simplify_duplicates() {
+    run_simplify_test("(&(a=1)(b=2)(a=1))", "(&(a=1)(b=2))");
+    run_simplify_test("(|(a=1)(&(a=1)))", "(a=1)");
+}
+
+#[test]
+fn simplify_resolved_undefined() {
+    let expression = build_expression(&parse("(&(a=b)(&(b=c)(a=b))(c=d))").unwrap()).unwrap();
+
+    let properties = vec![String::from(r#"c="d""#)];
+    let property_set = PropertySet::from_flat_props(&properties);
+
+    assert_eq!(
+        expression.resolve_reduce(&property_set),
+        Ok(Expression::And(vec![
+            Expression::Equals(
+                PropertyRef::Value(String::from("a"), PropertyRefType::Any),
+                Operand::Untyped(String::from("b")),
+            ),
+            Expression::Equals(
+                PropertyRef::Value(String::from("b"), PropertyRefType::Any),
+                Operand::Untyped(String::from("c")),
+            ),
+        ]))
+    );
+}
+
+#[test]
+fn simplify_constraints_to_string() {
+    assert_eq!(
+        simplify_constraints(r#"(&(golem.inf.mem.gib>=8)(&(golem.runtime.version>=v"1.2.0"))(&))"#)
+            .unwrap(),
+        r#"(&(golem.inf.mem.gib>=8)(golem.runtime.version>=v"1.2.0"))"#
+    );
+    assert_eq!(simplify_constraints("(&(a$v=1.0.0)(|))").unwrap(), "(|)");
+}
+
+#[test]
+fn simplify_constraints_flattens_nested_clauses() {
+    let nested = Constraints::new_clause(
+        And,
+        vec![
+            Constraints::new_clause(And, vec![constraints!["a" == 1], constraints!["b" == 2]]),
+            constraints!["c" == 3],
+        ],
+    );
+
+    assert_eq!(
+        simplify_constraints(&nested.to_string()).unwrap(),
+        "(&(a=1)(b=2)(c=3))"
+    );
+}
+
+#[test]
+fn simplify_constraints_empty_or_is_false() {
+    let constraints = Constraints::new_clause(
+        And,
+        vec![
+            constraints!["a" == 1],
+            Constraints::new_clause(Or, Vec::<Constraints>::new()),
+            constraints!["a" == 1],
+        ],
+    );
+
+    let simplified = simplify_constraints(&constraints.to_string()).unwrap();
+    assert_eq!(simplified, "(|)");
+    assert_eq!(
+        build_expression(&parse(&simplified).unwrap()).unwrap(),
+        Expression::Empty(false)
+    );
+}
+
+#[test]
+fn simplify_constraints_removes_duplicates() {
+    let constraints = Constraints::new_clause(
+        And,
+        vec![
+            constraints!["a" == 1],
+            Constraints::new_clause(And, Vec::<Constraints>::new()),
+            constraints!["a" == 1],
+        ],
+    );
+
+    assert_eq!(
+        simplify_constraints(&constraints.to_string()).unwrap(),
+        "(a=1)"
+    );
+}
+
+#[test]
+fn simplify_constraints_keeps_clauses_with_other_operator() {
+    let alternative = Constraints::new_clause(
+        Or,
+        vec![
+            ConstraintKey::new("x").less_than(ConstraintValue::new(1)),
+            ConstraintKey::new("y").greater_than(ConstraintValue::new(2)),
+        ],
+    );
+    let constraints = Constraints::new_clause(
+        And,
+        vec![
+            constraints!["a" == 1],
+            Constraints::new_clause(Or, vec![alternative]),
+        ],
+    );
+
+    assert_eq!(
+        simplify_constraints(&constraints.to_string()).unwrap(),
+        "(&(a=1)(|(x<1)(y>2)))"
+    );
+}
@@ -302,3 +302,92 @@ fn and_whitespace() {
 
     assert_eq!(parse(f), Ok(tag));
 }
+
+#[test]
+fn typed_version() {
+    let f = r#"(cn>=v"1.2.0")"#;
+
+    let tag = Tag::Sequence(Sequence {
+        class: TagClass::Context,
+        id: TAG_GREATER_EQUAL,
+        inner: vec![
+            Tag::OctetString(OctetString {
+                inner: vec![0x63, 0x6e],
+                ..Default::default()
+            }),
+            Tag::OctetString(OctetString {
+                class: TagClass::Context,
+                id: TAG_VALUE_VERSION,
+                inner: b"1.2.0".to_vec(),
+            }),
+        ],
+    });
+
+    assert_eq!(parse(f), Ok(tag));
+}
+
+#[test]
+fn typed_decimal_and_datetime() {
+    let f = r#"(&(price<=d"0.0001")(expiration<t"2026-10-01T00:00:00Z"))"#;
+
+    let tag = Tag::Sequence(Sequence {
+        class: TagClass::Context,
+        id: TAG_AND,
+        inner: vec![
+            Tag::Sequence(Sequence {
+                class: TagClass::Context,
+                id: TAG_LESS_EQUAL,
+                inner: vec![
+                    Tag::OctetString(OctetString {
+                        inner: b"price".to_vec(),
+                        ..Default::default()
+                    }),
+                    Tag::OctetString(OctetString {
+                        class: TagClass::Context,
+                        id: TAG_VALUE_DECIMAL,
+                        inner: b"0.0001".to_vec(),
+                    }),
+                ],
+            }),
+            Tag::Sequence(Sequence {
+                class: TagClass::Context,
+                id: TAG_LESS,
+                inner: vec![
+                    Tag::OctetString(OctetString {
+                        inner: b"expiration".to_vec(),
+                        ..Default::default()
+                    }),
+                    Tag::OctetString(OctetString {
+                        class: TagClass::Context,
+                        id: TAG_VALUE_DATETIME,
+                        inner: b"2026-10-01T00:00:00Z".to_vec(),
+                    }),
+                ],
+            }),
+        ],
+    });
+
+    assert_eq!(parse(f), Ok(tag));
+}
+
+#[test]
+fn untyped_value_with_type_prefix() {
+    let f = "(cn=value)";
+
+    let tag = Tag::Sequence(Sequence {
+        class: TagClass::Context,
+        id: TAG_EQUAL,
+        inner: vec![
+            Tag::OctetString(OctetString {
+                inner: b"cn".to_vec(),
+                ..Default::default()
+            }),
+            Tag::OctetString(OctetString {
+                inner: b"value".to_vec(),
+                ..Default::default()
+            }),
+        ],
+    });
+
+    assert_eq!(parse(f), Ok(tag));
+}
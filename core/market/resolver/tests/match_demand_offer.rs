@@ -1,4 +1,8 @@
-use ya_market_resolver::{match_demand_offer, Match, MatchError};
+use ya_market_resolver::dynamic::{DynamicProperties, CURRENT_TIME_PROPERTY, UPTIME_PROPERTY};
+use ya_market_resolver::{
+    explain_match, match_demand_offer, match_demand_offer_with, Constraints, Demand, Match,
+    MatchError, Offer,
+};
 
 mod sample;
 
@@ -119,3 +123,197 @@ fn match_poc_offer_demand_samples_deep() {
         Match::Yes
     );
 }
+
+#[test]
+fn match_value_less_property_should_be_dynamic() {
+    assert_eq!(
+        match_demand_offer(
+            "{}",
+            "(golem.node.free.mem.gib>=8)",
+            r#"{"golem.node.free.mem.gib": null}"#,
+            "()",
+        )
+        .unwrap(),
+        Match::Dynamic {
+            offer_dynamic: vec!["golem.node.free.mem.gib".to_string()],
+            demand_dynamic: vec![],
+        }
+    );
+}
+
+#[test]
+fn match_undeclared_null_property_should_be_ignored() {
+    assert_eq!(
+        match_demand_offer(
+            "{}",
+            "(golem.inf.mem.gib>=8)",
+            r#"{"golem.inf.mem.gib": null}"#,
+            "()",
+        )
+        .unwrap(),
+        match_demand_offer("{}", "(golem.inf.mem.gib>=8)", "{}", "()").unwrap()
+    );
+}
+
+#[test]
+fn match_dynamic_property_resolved_by_owner() {
+    let dynamic = DynamicProperties::default();
+    let demand = Demand::from("{}", "(golem.node.free.mem.gib>=8)").unwrap();
+    let offer = || {
+        Offer::from(r#"{"golem.node.free.mem.gib": null}"#, "()")
+            .unwrap()
+            .with_dynamic_properties(&dynamic)
+    };
+
+    dynamic
+        .set_value("golem.node.free.mem.gib", Some(serde_json::json!(16)))
+        .unwrap();
+    assert_eq!(
+        match_demand_offer_with(&demand, &offer()).unwrap(),
+        Match::Yes
+    );
+
+    dynamic
+        .set_value("golem.node.free.mem.gib", Some(serde_json::json!(4)))
+        .unwrap();
+    assert_eq!(
+        match_demand_offer_with(&demand, &offer()).unwrap(),
+        Match::No {
+            demand_mismatch: vec![],
+            offer_mismatch: vec![],
+        }
+    );
+}
+
+#[test]
+fn dynamic_properties_fill_only_declared_placeholders() {
+    let dynamic = DynamicProperties::with_builtin();
+    dynamic
+        .set_value("golem.node.free.mem.gib", Some(serde_json::json!(16)))
+        .unwrap();
+    dynamic
+        .set_value("golem.node.free.storage.gib", Some(serde_json::json!(100)))
+        .unwrap();
+
+    let offer = Offer::from(
+        r#"{"golem.node.free.mem.gib": null, "golem.node.free.storage.gib": 10}"#,
+        "()",
+    )
+    .unwrap()
+    .with_dynamic_properties(&dynamic);
+
+    let mut properties = offer.properties;
+    properties.sort();
+    assert_eq!(
+        properties,
+        vec![
+            "golem.node.free.mem.gib=16".to_string(),
+            "golem.node.free.storage.gib=10".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn builtin_dynamic_properties_cant_be_set() {
+    let dynamic = DynamicProperties::with_builtin();
+
+    assert!(dynamic.set_value(UPTIME_PROPERTY, None).is_err());
+    assert!(dynamic
+        .set_value("golem.inf.mem.gib", Some(serde_json::json!(4)))
+        .is_err());
+    assert!(dynamic
+        .evaluate()
+        .iter()
+        .any(|prop| prop.starts_with(CURRENT_TIME_PROPERTY)));
+}
+
+#[test]
+fn explain_match_lists_failed_and_undefined_properties() {
+    let demand = Demand::from(
+        r#"{"golem.srv.comp.expiration": 1600000000000}"#,
+        "(&(golem.inf.mem.gib>=8)(golem.runtime.name=wasmtime)(golem.com.pricing.model=linear))",
+    )
+    .unwrap();
+    let offer = Offer::from(
+        r#"{"golem.inf.mem.gib": 4, "golem.runtime.name": "wasmtime"}"#,
+        "(&(golem.srv.comp.expiration>0)(!(golem.node.debug.subnet=*)))",
+    )
+    .unwrap();
+
+    let explanation = explain_match(&demand, &offer).unwrap();
+
+    assert_eq!(explanation.demand.result, Some(false));
+    assert_eq!(explanation.demand.reduced, "(|)");
+    assert_eq!(
+        explanation.demand.failed,
+        vec!["golem.inf.mem.gib".to_string()]
+    );
+    assert_eq!(
+        explanation.demand.undefined,
+        vec!["golem.com.pricing.model".to_string()]
+    );
+
+    assert_eq!(explanation.offer.result, Some(true));
+    assert_eq!(explanation.offer.reduced, "()");
+    assert!(explanation.offer.failed.is_empty());
+    assert!(explanation.offer.undefined.is_empty());
+}
+
+#[test]
+fn constraints_match_offer_properties_only() {
+    let constraints =
+        Constraints::parse("(&(golem.inf.mem.gib>=8)(golem.runtime.name=wasmtime))").unwrap();
+    let offer = |mem: u32| {
+        Offer::from(
+            &format!(
+                r#"{{"golem.inf.mem.gib": {}, "golem.runtime.name": "wasmtime"}}"#,
+                mem
+            ),
+            "(golem.srv.comp.expiration>0)",
+        )
+        .unwrap()
+    };
+
+    assert!(constraints.matches(&offer(16)).unwrap());
+    assert!(!constraints.matches(&offer(4)).unwrap());
+    assert!(!Constraints::parse("(golem.com.pricing.model=linear)")
+        .unwrap()
+        .matches(&offer(16))
+        .unwrap());
+    assert!(Constraints::parse("(golem.inf.mem.gib>=").is_err());
+}
+
+#[test]
+fn explain_match_skips_alternatives_of_satisfied_or() {
+    let demand = Demand::from(
+        "{}",
+        "(&(|(golem.runtime.name=wasmtime)(golem.runtime.name=vm))(!(&(golem.inf.mem.gib>=8)(golem.inf.cpu.threads>=4))))",
+    )
+    .unwrap();
+    let offer = Offer::from(
+        r#"{"golem.inf.mem.gib": 16, "golem.inf.cpu.threads": 2, "golem.runtime.name": "vm"}"#,
+        "()",
+    )
+    .unwrap();
+
+    let explanation = explain_match(&demand, &offer).unwrap();
+    assert_eq!(explanation.demand.result, Some(true));
+    assert!(explanation.demand.failed.is_empty());
+
+    let offer = Offer::from(
+        r#"{"golem.inf.mem.gib": 16, "golem.inf.cpu.threads": 8, "golem.runtime.name": "docker"}"#,
+        "()",
+    )
+    .unwrap();
+
+    let explanation = explain_match(&demand, &offer).unwrap();
+    assert_eq!(explanation.demand.result, Some(false));
+    assert_eq!(
+        explanation.demand.failed,
+        vec![
+            "golem.runtime.name".to_string(),
+            "golem.inf.mem.gib".to_string(),
+            "golem.inf.cpu.threads".to_string(),
+        ]
+    );
+}
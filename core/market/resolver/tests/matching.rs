@@ -86,7 +86,7 @@ fn match_weak_simple_undefined() {
                 )],
                 Expression::Equals(
                     PropertyRef::Value(String::from("o3"), PropertyRefType::Any),
-                    String::from("v2")
+                    Operand::Untyped(String::from("v2"))
                 )
             ),
             (vec![], Expression::Empty(false))
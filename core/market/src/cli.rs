@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use structopt::StructOpt;
 use ya_client::model::market::{agreement::State, Role};
-use ya_core_model::market::{GetAgreement, ListAgreements};
+use ya_core_model::market::{ExplainMatch, GetAgreement, ListAgreements, ScanOffers};
 use ya_service_api::{CliCtx, CommandOutput, ResponseTable};
 use ya_service_bus::{typed as bus, RpcEndpoint};
 
@@ -9,12 +9,56 @@ use ya_service_bus::{typed as bus, RpcEndpoint};
 #[derive(StructOpt, Debug)]
 pub enum Command {
     Agreements(AgreementsCommand),
+    /// Explain why Demand matches or doesn't match the Offer
+    Explain {
+        #[structopt(long, help = "Subscription ID of local Demand")]
+        demand_id: String,
+        #[structopt(long, help = "Offer ID")]
+        offer_id: String,
+    },
+    /// Find Offers known to this node, which properties match constraints
+    Scan {
+        #[structopt(long, help = "Constraints expression, eg. \"(golem.inf.mem.gib>=8)\"")]
+        constraints: String,
+        #[structopt(long, default_value = "0", help = "Number of matching Offers to skip")]
+        offset: usize,
+        #[structopt(long, default_value = "100", help = "Maximum number of Offers to show")]
+        limit: usize,
+    },
 }
 
 impl Command {
     pub async fn run_command(self, ctx: &CliCtx) -> anyhow::Result<CommandOutput> {
         match self {
             Command::Agreements(agreements_cmd) => agreements_cmd.run_command(ctx).await,
+            Command::Explain {
+                demand_id,
+                offer_id,
+            } => {
+                let explanation = bus::service(ya_core_model::market::local::BUS_ID)
+                    .send(ExplainMatch {
+                        demand_id,
+                        offer_id,
+                    })
+                    .await??;
+
+                CommandOutput::object(explanation)
+            }
+            Command::Scan {
+                constraints,
+                offset,
+                limit,
+            } => {
+                let offers = bus::service(ya_core_model::market::local::BUS_ID)
+                    .send(ScanOffers {
+                        constraints,
+                        offset,
+                        limit,
+                    })
+                    .await??;
+
+                CommandOutput::object(offers)
+            }
         }
     }
 }
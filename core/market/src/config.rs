@@ -62,6 +62,14 @@ pub struct DbConfig {
     /// Number of days to persist Negotiation Events
     #[structopt(env = "MARKET_EVENT_STORE_DAYS", default_value = "1")]
     pub event_store_days: i32,
+    /// Keep subscriptions, Proposals and unread Negotiation Events on disk,
+    /// so they survive daemon restart
+    #[structopt(
+        env = "MARKET_PERSISTENT_STATE",
+        parse(try_from_str),
+        default_value = "false"
+    )]
+    pub persistent_state: bool,
 }
 
 impl Config {
@@ -99,5 +107,6 @@ mod test {
         assert_eq!(4 * 3600, c.db.cleanup_interval.as_secs());
         assert_eq!(90, c.db.agreement_store_days);
         assert_eq!(1, c.db.event_store_days);
+        assert!(!c.db.persistent_state);
     }
 }
@@ -139,6 +139,13 @@ impl MarketService {
         Ok(())
     }
 
+    /// Checks if Market state should survive daemon restart. In this case
+    /// volatile part of `DbMixedExecutor` must be created on disk.
+    /// Uses the same config, which will be used to start Market Service.
+    pub fn persistent_state() -> Result<bool, MarketInitError> {
+        Ok(MARKET.config()?.db.persistent_state)
+    }
+
     pub async fn gsb<Context: Provider<Self, DbMixedExecutor>>(
         ctx: &Context,
     ) -> anyhow::Result<()> {
@@ -331,12 +338,27 @@ impl Service for MarketService {
 
 struct StaticMarket {
     locked_market: Mutex<Option<Arc<MarketService>>>,
+    locked_config: Mutex<Option<Arc<Config>>>,
 }
 
 impl StaticMarket {
     pub fn new() -> StaticMarket {
         StaticMarket {
             locked_market: Mutex::new(None),
+            locked_config: Mutex::new(None),
+        }
+    }
+
+    /// Config is loaded once, so decisions made before Market initialization
+    /// are consistent with config Market is started with.
+    pub fn config(&self) -> Result<Arc<Config>, MarketInitError> {
+        let mut guarded_config = self.locked_config.lock().unwrap();
+        if let Some(config) = &*guarded_config {
+            Ok(config.clone())
+        } else {
+            let config = Arc::new(Config::from_env()?);
+            *guarded_config = Some(config.clone());
+            Ok(config)
         }
     }
 
@@ -349,7 +371,7 @@ impl StaticMarket {
             Ok(market.clone())
         } else {
             let identity_api = IdentityGSB::new();
-            let config = Arc::new(Config::from_env()?);
+            let config = self.config()?;
             let market = Arc::new(MarketService::new(db, identity_api, config)?);
             *guarded_market = Some(market.clone());
             Ok(market)
@@ -5,7 +5,8 @@ use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 
-use ya_client::model::market::{NewDemand, NewOffer};
+use ya_client::model::market::{NewDemand, NewOffer, Offer as ClientOffer};
+use ya_client::model::NodeId;
 use ya_service_api_web::middleware::Identity;
 use ya_utils_actix::deadline_checker::{
     bind_deadline_reaction, DeadlineChecker, StopTracking, TrackDeadline,
@@ -23,15 +24,23 @@ pub(crate) mod resolver;
 pub(crate) mod store;
 
 use crate::db::dao::{DemandDao, DemandState};
-use error::{MatcherError, MatcherInitError, QueryOfferError, QueryOffersError};
+use error::{
+    DemandError, MatcherError, MatcherInitError, QueryOfferError, QueryOffersError, ScanOffersError,
+};
 use futures::FutureExt;
 use log::debug;
 use resolver::Resolver;
 use store::SubscriptionStore;
+use ya_core_model::market::{
+    ExplainMatch, MatchExplanation, RpcMessageError, ScanOffers, SetDynamicProperty,
+};
 use ya_core_model::net::local::{
     BindBroadcastError, BroadcastMessage, NewNeighbour, SendBroadcastMessage,
 };
+use ya_market_resolver::dynamic::DynamicProperties;
+use ya_market_resolver::Constraints;
 use ya_net::bind_broadcast_with_caller;
+use ya_service_bus::typed::ServiceBinder;
 
 /// Stores proposal generated from resolver.
 #[derive(Debug)]
@@ -116,10 +125,25 @@ impl Matcher {
 
         self.bind_neighbourhood_bcast(local_prefix).await.ok();
 
+        ServiceBinder::new(local_prefix, &self.store.dynamic_properties, ())
+            .bind(set_dynamic_property);
+        ServiceBinder::new(local_prefix, self, ())
+            .bind(explain_match)
+            .bind(scan_offers);
+
         self.bind_expiration_tracker()
             .await
             .map_err(|e| MatcherInitError::ExpirationTrackerError(e.to_string()))?;
 
+        if self.config.db.persistent_state {
+            let myself = self.clone();
+            tokio::task::spawn_local(async move {
+                if let Err(e) = myself.restore_subscriptions().await {
+                    log::warn!("Failed to restore persisted subscriptions. Error: {}", e);
+                }
+            });
+        }
+
         Ok(())
     }
 
@@ -140,6 +164,11 @@ impl Matcher {
         .await
     }
 
+    /// Properties of local subscriptions, which values are evaluated at the time of matching.
+    pub fn dynamic_properties(&self) -> DynamicProperties {
+        self.store.dynamic_properties.clone()
+    }
+
     pub async fn bind_expiration_tracker(&self) -> anyhow::Result<()> {
         let store = self.store.clone();
         bind_deadline_reaction(self.expiration_tracker.clone(), move |msg| {
@@ -170,6 +199,47 @@ impl Matcher {
         Ok(())
     }
 
+    /// Restores in-memory state of our subscriptions, which were persisted
+    /// in database before restart. Offers are tracked for expiration again and
+    /// re-broadcasted. Demands and negotiation Events don't need any in-memory
+    /// state: they are read from database and notifiers are created on demand.
+    async fn restore_subscriptions(&self) -> Result<(), MatcherError> {
+        let offer_ids = self.get_our_active_offer_ids().await?;
+        for offer in self.store.get_offers(offer_ids.clone()).await? {
+            self.track_offer_expiration(&offer).await;
+        }
+
+        let demands = self
+            .store
+            .get_demands_before(Utc::now().naive_utc())
+            .await?;
+        log::info!(
+            "Restored {} Offers and {} Demands from persistent Market state.",
+            offer_ids.len(),
+            demands.len()
+        );
+
+        if !offer_ids.is_empty() {
+            self.discovery
+                .bcast_offers(offer_ids)
+                .await
+                .map_err(|e| log::warn!("Failed to bcast restored Offers. Error: {}.", e))
+                .ok();
+        }
+        Ok(())
+    }
+
+    async fn track_offer_expiration(&self, offer: &Offer) {
+        self.expiration_tracker
+            .send(TrackDeadline {
+                category: "Offer".to_string(),
+                deadline: Utc.from_utc_datetime(&offer.expiration_ts),
+                id: offer.id.to_string(),
+            })
+            .await
+            .ok();
+    }
+
     // =========================================== //
     // Offer/Demand subscription
     // =========================================== //
@@ -189,14 +259,7 @@ impl Matcher {
             id.identity
         );
 
-        self.expiration_tracker
-            .send(TrackDeadline {
-                category: "Offer".to_string(),
-                deadline: Utc.from_utc_datetime(&offer.expiration_ts),
-                id: offer.id.to_string(),
-            })
-            .await
-            .ok();
+        self.track_offer_expiration(&offer).await;
 
         // Ignore error and don't retry to broadcast Offer. It will be broadcasted
         // anyway during random broadcast, so nothing bad happens here in case of error.
@@ -294,6 +357,56 @@ impl Matcher {
         Ok(())
     }
 
+    /// Explains why our Demand matches or doesn't match the Offer.
+    /// If `owner` is given, the Demand must belong to this identity.
+    pub async fn explain_match(
+        &self,
+        demand_id: &SubscriptionId,
+        offer_id: &SubscriptionId,
+        owner: Option<NodeId>,
+    ) -> Result<MatchExplanation, MatcherError> {
+        let demand = self.store.get_demand(demand_id).await?;
+        if owner.map_or(false, |owner| owner != demand.node_id) {
+            return Err(DemandError::NotFound(demand_id.clone()).into());
+        }
+        let offer = self.store.get_offer(offer_id).await?;
+
+        Ok(resolver::explain_local_demand(
+            &offer,
+            &demand,
+            &self.store.dynamic_properties,
+        )?)
+    }
+
+    /// Returns active Offers known to this node, which properties match
+    /// ad-hoc `constraints`, in order of creation. Doesn't create Proposals.
+    pub async fn scan_offers(
+        &self,
+        constraints: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<ClientOffer>, ScanOffersError> {
+        let constraints =
+            Constraints::parse(constraints).map_err(ScanOffersError::InvalidConstraints)?;
+
+        Ok(self
+            .store
+            .get_all_offers()
+            .await?
+            .into_iter()
+            .filter(|offer| resolver::scan_matches(&constraints, offer))
+            .skip(offset)
+            .take(limit)
+            .filter_map(|offer| match offer.into_client_offer() {
+                Err(e) => {
+                    log::error!("Skipping Offer because of: {}", e);
+                    None
+                }
+                Ok(offer) => Some(offer),
+            })
+            .collect())
+    }
+
     pub async fn get_our_active_offer_ids(&self) -> Result<Vec<SubscriptionId>, QueryOffersError> {
         let our_node_ids = self.identity.list().await?;
         self.store.get_active_offer_ids(Some(our_node_ids)).await
@@ -308,3 +421,55 @@ impl Matcher {
             .await
     }
 }
+
+async fn set_dynamic_property(
+    dynamic: DynamicProperties,
+    _caller: String,
+    msg: SetDynamicProperty,
+) -> Result<(), RpcMessageError> {
+    log::debug!(
+        "Setting dynamic property [{}] to: {:?}",
+        msg.name,
+        msg.value
+    );
+    dynamic
+        .set_value(&msg.name, msg.value)
+        .map_err(RpcMessageError::BadRequest)
+}
+
+async fn explain_match(
+    matcher: Matcher,
+    _caller: String,
+    msg: ExplainMatch,
+) -> Result<MatchExplanation, RpcMessageError> {
+    let parse_id = |id: &str| {
+        SubscriptionId::from_str(id).map_err(|e| RpcMessageError::BadRequest(e.to_string()))
+    };
+    let demand_id = parse_id(&msg.demand_id)?;
+    let offer_id = parse_id(&msg.offer_id)?;
+
+    matcher
+        .explain_match(&demand_id, &offer_id, None)
+        .await
+        .map_err(|e| match e {
+            MatcherError::Demand(DemandError::NotFound(_))
+            | MatcherError::QueryOffer(QueryOfferError::NotFound(_)) => {
+                RpcMessageError::NotFound(e.to_string())
+            }
+            e => RpcMessageError::Market(e.to_string()),
+        })
+}
+
+async fn scan_offers(
+    matcher: Matcher,
+    _caller: String,
+    msg: ScanOffers,
+) -> Result<Vec<ClientOffer>, RpcMessageError> {
+    matcher
+        .scan_offers(&msg.constraints, msg.offset, msg.limit)
+        .await
+        .map_err(|e| match e {
+            ScanOffersError::InvalidConstraints(_) => RpcMessageError::BadRequest(e.to_string()),
+            e => RpcMessageError::Market(e.to_string()),
+        })
+}
@@ -27,6 +27,14 @@ pub enum QueryOffersError {
     IdentityError(#[from] IdentityError),
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum ScanOffersError {
+    #[error("Invalid constraints. Error: {0}.")]
+    InvalidConstraints(ya_market_resolver::MatchError),
+    #[error(transparent)]
+    QueryOffers(#[from] QueryOffersError),
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum QueryDemandsError {
     #[error("Failed to get Demands. Error: {0}.")]
@@ -107,6 +115,8 @@ pub enum MatcherError {
     SaveOffer(#[from] SaveOfferError),
     #[error(transparent)]
     ModifyOffer(#[from] ModifyOfferError),
+    #[error("Failed to resolve match. Error: {0}.")]
+    Resolve(#[from] ya_market_resolver::MatchError),
 }
 
 #[derive(thiserror::Error, Debug)]
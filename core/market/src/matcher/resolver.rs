@@ -1,6 +1,8 @@
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
-use ya_market_resolver::{match_demand_offer, Match};
+use ya_core_model::market::{ConstraintsExplanation, MatchExplanation};
+use ya_market_resolver::dynamic::DynamicProperties;
+use ya_market_resolver::{explain_match, match_demand_offer_with, Constraints, Match, MatchError};
 
 use super::{error::ResolverError, RawProposal, SubscriptionStore};
 use crate::db::model::{Demand, Offer, SubscriptionId};
@@ -79,7 +81,7 @@ impl Resolver {
                     .get_demands_before(offer.insertion_ts.unwrap())
                     .await?
                     .into_iter()
-                    .filter(|demand| matches(&offer, demand))
+                    .filter(|demand| matches(&offer, demand, &self.store.dynamic_properties))
                     .for_each(|demand| self.emit_proposal(offer.clone(), demand));
             }
             Subscription::Demand(id) => {
@@ -88,7 +90,7 @@ impl Resolver {
                     .get_offers_before(demand.insertion_ts.unwrap())
                     .await?
                     .into_iter()
-                    .filter(|offer| matches(offer, &demand))
+                    .filter(|offer| matches(offer, &demand, &self.store.dynamic_properties))
                     .for_each(|offer| self.emit_proposal(offer, demand.clone()));
             }
         }
@@ -109,7 +111,7 @@ impl Resolver {
     }
 }
 
-fn matches(offer: &Offer, demand: &Demand) -> bool {
+fn matches(offer: &Offer, demand: &Demand, dynamic: &DynamicProperties) -> bool {
     if offer.node_id == demand.node_id {
         log::info!(
             "Rejecting Demand Offer pair from single identity. node_id: {}",
@@ -118,13 +120,11 @@ fn matches(offer: &Offer, demand: &Demand) -> bool {
         return false;
     }
 
-    match match_demand_offer(
-        &demand.properties,
-        &demand.constraints,
-        &offer.properties,
-        &offer.constraints,
-    ) {
+    match match_local_demand(offer, demand, dynamic) {
         Ok(Match::Yes) => true,
+        // Offer depends on properties, that can be resolved only by Provider.
+        // Provider will validate initial Proposal against their current values.
+        Ok(Match::Dynamic { demand_dynamic, .. }) if demand_dynamic.is_empty() => true,
         Err(e) => {
             log::warn!("Matching [{:?}] vs [{:?}] error: {}", offer, demand, e);
             false
@@ -133,13 +133,74 @@ fn matches(offer: &Offer, demand: &Demand) -> bool {
     }
 }
 
+/// Demands are always local, so they are extended with current values
+/// of our dynamic properties.
+fn match_local_demand(
+    offer: &Offer,
+    demand: &Demand,
+    dynamic: &DynamicProperties,
+) -> Result<Match, MatchError> {
+    let demand = ya_market_resolver::Demand::from(&demand.properties, &demand.constraints)?
+        .with_dynamic_properties(dynamic);
+    let offer = ya_market_resolver::Offer::from(&offer.properties, &offer.constraints)?;
+    match_demand_offer_with(&demand, &offer)
+}
+
+/// Checks ad-hoc constraints against Offer properties. Offers, which can't
+/// be evaluated, are skipped.
+pub(crate) fn scan_matches(constraints: &Constraints, offer: &Offer) -> bool {
+    ya_market_resolver::Offer::from(&offer.properties, &offer.constraints)
+        .and_then(|offer| constraints.matches(&offer))
+        .map_err(|e| log::debug!("Scanning Offer [{}] error: {}", offer.id, e))
+        .unwrap_or(false)
+}
+
+/// Explains matching of our local Demand, evaluated the same way as in `matches`.
+pub(crate) fn explain_local_demand(
+    offer: &Offer,
+    demand: &Demand,
+    dynamic: &DynamicProperties,
+) -> Result<MatchExplanation, MatchError> {
+    let demand = ya_market_resolver::Demand::from(&demand.properties, &demand.constraints)?
+        .with_dynamic_properties(dynamic);
+    let offer = ya_market_resolver::Offer::from(&offer.properties, &offer.constraints)?;
+    let explanation = explain_match(&demand, &offer)?;
+
+    let into_model = |side: ya_market_resolver::ConstraintsExplanation| ConstraintsExplanation {
+        result: side.result,
+        reduced: side.reduced,
+        failed: side.failed,
+        undefined: side.undefined,
+    };
+    Ok(MatchExplanation {
+        demand: into_model(explanation.demand),
+        offer: into_model(explanation.offer),
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use ya_market_resolver::dynamic::DynamicProperties;
+
     use crate::matcher::resolver::matches;
     use crate::testing::mock_offer::{sample_demand, sample_offer};
 
     #[test]
     fn matches_empty() {
-        assert!(matches(&sample_offer(), &sample_demand()))
+        assert!(matches(
+            &sample_offer(),
+            &sample_demand(),
+            &DynamicProperties::default()
+        ))
+    }
+
+    #[test]
+    fn matches_deferred_to_dynamic_offer_properties() {
+        let mut offer = sample_offer();
+        offer.properties = r#"{"golem.node.free.mem.gib": null}"#.to_string();
+        let mut demand = sample_demand();
+        demand.constraints = "(golem.node.free.mem.gib>=8)".to_string();
+
+        assert!(matches(&offer, &demand, &DynamicProperties::default()))
     }
 }
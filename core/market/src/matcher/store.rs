@@ -4,6 +4,7 @@ use std::sync::Arc;
 
 use ya_client::model::market::{Demand as ClientDemand, NewDemand, NewOffer, Offer as ClientOffer};
 use ya_client::model::NodeId;
+use ya_market_resolver::dynamic::DynamicProperties;
 use ya_service_api_web::middleware::Identity;
 
 use crate::config::Config;
@@ -18,12 +19,18 @@ use crate::matcher::error::{
 #[derive(Clone)]
 pub struct SubscriptionStore {
     pub(crate) db: DbMixedExecutor,
+    /// Properties of local subscriptions evaluated at matching time.
+    pub(crate) dynamic_properties: DynamicProperties,
     config: Arc<Config>,
 }
 
 impl SubscriptionStore {
     pub fn new(db: DbMixedExecutor, config: Arc<Config>) -> Self {
-        SubscriptionStore { db, config }
+        SubscriptionStore {
+            db,
+            dynamic_properties: DynamicProperties::with_builtin(),
+            config,
+        }
     }
 
     /// returns newly created offer with insertion_ts
@@ -140,6 +147,15 @@ impl SubscriptionStore {
             .map_err(QueryOffersError::from)
     }
 
+    /// Returns all active Offers known to this node, both ours and from other nodes.
+    pub async fn get_all_offers(&self) -> Result<Vec<Offer>, QueryOffersError> {
+        self.db
+            .as_dao::<OfferDao>()
+            .get_offers(None, None, None, Utc::now().naive_utc())
+            .await
+            .map_err(QueryOffersError::from)
+    }
+
     /// Returns Offers SubscriptionId from vector, that don't exist in our database.
     pub async fn filter_out_known_offer_ids(
         &self,
@@ -6,7 +6,8 @@ use std::time::{Duration, Instant};
 
 use ya_client::model::market::{proposal::Proposal as ClientProposal, reason::Reason, NewProposal};
 use ya_client::model::NodeId;
-use ya_market_resolver::{match_demand_offer, Match};
+use ya_market_resolver::dynamic::DynamicProperties;
+use ya_market_resolver::{match_demand_offer_with, Demand, Match, MatchError, Offer};
 use ya_service_api_web::middleware::Identity;
 
 use crate::config::Config;
@@ -17,8 +18,8 @@ use crate::db::{
         TakeEventsError,
     },
     model::{
-        Agreement, AgreementEvent, AgreementId, AgreementState, AppSessionId, MarketEvent, Owner,
-        Proposal, ProposalId, ProposalState, SubscriptionId,
+        Agreement, AgreementEvent, AgreementId, AgreementState, AppSessionId, Issuer, MarketEvent,
+        Owner, Proposal, ProposalId, ProposalState, SubscriptionId,
     },
     DbMixedExecutor,
 };
@@ -127,7 +128,11 @@ impl CommonBroker {
         let new_proposal =
             prev_proposal.from_client(proposal, &prev_proposal.body.expiration_ts)?;
 
-        validate_match(&new_proposal, &prev_proposal)?;
+        validate_match(
+            &new_proposal,
+            &prev_proposal,
+            &self.store.dynamic_properties,
+        )?;
 
         self.db
             .as_dao::<ProposalDao>()
@@ -550,7 +555,7 @@ impl CommonBroker {
 
         self.validate_proposal(&prev_proposal, &caller_id, caller_role)
             .await?;
-        validate_match(&proposal, &prev_proposal)?;
+        validate_match(&proposal, &prev_proposal, &self.store.dynamic_properties)?;
 
         self.db
             .as_dao::<ProposalDao>()
@@ -774,35 +779,66 @@ impl CommonBroker {
 pub fn validate_match(
     new_proposal: &Proposal,
     prev_proposal: &Proposal,
+    dynamic: &DynamicProperties,
 ) -> Result<(), MatchValidationError> {
-    match match_demand_offer(
+    let matching_failed = |e: MatchError| MatchValidationError::MatchingFailed {
+        new: new_proposal.body.id.clone(),
+        prev: prev_proposal.body.id.clone(),
+        error: e.to_string(),
+    };
+
+    // Only we know current values of dynamic properties of our Proposals.
+    // Other side will validate its own dynamic properties, when receiving Proposal.
+    let mut new = Demand::from(
         &new_proposal.body.properties,
         &new_proposal.body.constraints,
+    )
+    .map_err(matching_failed)?;
+    if new_proposal.body.issuer == Issuer::Us {
+        new = new.with_dynamic_properties(dynamic);
+    }
+    let mut prev = Offer::from(
         &prev_proposal.body.properties,
         &prev_proposal.body.constraints,
     )
-    .map_err(|e| MatchValidationError::MatchingFailed {
+    .map_err(matching_failed)?;
+    if prev_proposal.body.issuer == Issuer::Us {
+        prev = prev.with_dynamic_properties(dynamic);
+    }
+
+    let (offer_mismatch, demand_mismatch) =
+        match match_demand_offer_with(&new, &prev).map_err(matching_failed)? {
+            Match::Yes => return Ok(()),
+            Match::Dynamic {
+                offer_dynamic,
+                demand_dynamic,
+            } => {
+                let ours_resolved = (prev_proposal.body.issuer == Issuer::Them
+                    || offer_dynamic.is_empty())
+                    && (new_proposal.body.issuer == Issuer::Them || demand_dynamic.is_empty());
+                if ours_resolved {
+                    return Ok(());
+                }
+                (offer_dynamic, demand_dynamic)
+            }
+            Match::No {
+                demand_mismatch,
+                offer_mismatch,
+            }
+            | Match::Undefined {
+                demand_mismatch,
+                offer_mismatch,
+            } => (offer_mismatch, demand_mismatch),
+        };
+
+    Err(MatchValidationError::NotMatching {
         new: new_proposal.body.id.clone(),
         prev: prev_proposal.body.id.clone(),
-        error: e.to_string(),
-    })? {
-        Match::Yes => Ok(()),
-        Match::No {
-            demand_mismatch,
-            offer_mismatch,
-        }
-        | Match::Undefined {
-            demand_mismatch,
-            offer_mismatch,
-        } => Err(MatchValidationError::NotMatching {
-            new: new_proposal.body.id.clone(),
-            prev: prev_proposal.body.id.clone(),
-            mismatches: format!(
-                "Mismatched constraints - Offer: {:?}, Demand: {:?}",
-                offer_mismatch, demand_mismatch
-            ),
-        }),
-    }
+        mismatches: format!(
+            "Mismatched constraints - Offer: {:?}, Demand: {:?}",
+            offer_mismatch, demand_mismatch
+        ),
+    })
 }
 
 pub fn validate_transition(
@@ -22,6 +22,7 @@ pub(crate) mod requestor;
 
 const DEFAULT_EVENT_TIMEOUT: f32 = 5.0; // seconds
 const DEFAULT_QUERY_TIMEOUT: f32 = 5.0;
+const DEFAULT_SCAN_LIMIT: usize = 100;
 
 pub fn path_config() -> PathConfig {
     PathConfig::default().error_handler(|err, _req| {
@@ -53,12 +54,23 @@ pub struct PathSubscription {
     pub subscription_id: SubscriptionId,
 }
 
+#[derive(Deserialize)]
+pub struct PathDynamicProperty {
+    pub name: String,
+}
+
 #[derive(Deserialize)]
 pub struct PathSubscriptionProposal {
     pub subscription_id: SubscriptionId,
     pub proposal_id: ProposalId,
 }
 
+#[derive(Deserialize)]
+pub struct PathSubscriptionOffer {
+    pub subscription_id: SubscriptionId,
+    pub offer_id: SubscriptionId,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryAgreementList {
@@ -106,6 +118,18 @@ pub struct QueryTimeoutMaxEvents {
     pub max_events: Option<i32>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct QueryScanOffers {
+    /// constraints expression, which Offer properties must match
+    pub constraints: String,
+    /// number of matching Offers to skip
+    #[serde(default)]
+    pub offset: usize,
+    /// maximum count of Offers to return
+    #[serde(default = "default_scan_limit")]
+    pub limit: usize,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct QueryAgreementEvents {
     /// number of seconds to wait
@@ -130,6 +154,11 @@ pub(crate) fn default_query_timeout() -> f32 {
     DEFAULT_QUERY_TIMEOUT
 }
 
+#[inline(always)]
+pub(crate) fn default_scan_limit() -> usize {
+    DEFAULT_SCAN_LIMIT
+}
+
 #[inline(always)]
 pub(crate) fn default_event_timeout() -> f32 {
     DEFAULT_EVENT_TIMEOUT
@@ -4,10 +4,11 @@ use chrono::{TimeZone, Utc};
 use std::sync::Arc;
 
 use ya_client::model::market::Reason;
+use ya_client::model::ErrorMessage;
 use ya_service_api_web::middleware::Identity;
 use ya_std_utils::LogErr;
 
-use super::PathAgreement;
+use super::{PathAgreement, PathDynamicProperty};
 use crate::db::model::Owner;
 use crate::market::MarketService;
 use crate::negotiation::error::AgreementError;
@@ -19,6 +20,7 @@ pub fn register_endpoints(scope: Scope) -> Scope {
         .service(collect_agreement_events)
         .service(get_agreement)
         .service(terminate_agreement)
+        .service(set_dynamic_property)
 }
 
 #[actix_web::get("/agreements")]
@@ -107,3 +109,24 @@ async fn terminate_agreement(
         .log_err()
         .map(|_| HttpResponse::Ok().finish())
 }
+
+/// Sets current value of dynamic property declared in local Offers or Demands.
+/// `null` body makes the property undefined.
+#[actix_web::put("/dynamic-properties/{name}")]
+async fn set_dynamic_property(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathDynamicProperty>,
+    body: Json<Option<serde_json::Value>>,
+    _id: Identity,
+) -> impl Responder {
+    let name = path.into_inner().name;
+    log::debug!("Setting dynamic property [{}] to: {:?}", name, body);
+    match market
+        .matcher
+        .dynamic_properties()
+        .set_value(&name, body.into_inner())
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::BadRequest().json(ErrorMessage::new(e)),
+    }
+}
@@ -1,6 +1,7 @@
 use actix_web::{HttpResponse, ResponseError};
 
 use ya_client::model::ErrorMessage;
+use ya_market_resolver::MatchError;
 
 use crate::db::dao::{AgreementDaoError, SaveProposalError};
 use crate::db::model::AgreementState;
@@ -11,7 +12,7 @@ use crate::{
     market::MarketError,
     matcher::error::{
         DemandError, MatcherError, ModifyOfferError, QueryDemandsError, QueryOfferError,
-        QueryOffersError, ResolverError, SaveOfferError,
+        QueryOffersError, ResolverError, SaveOfferError, ScanOffersError,
     },
     negotiation::error::{
         AgreementError, GetProposalError, NegotiationError, ProposalError, QueryEventsError,
@@ -46,6 +47,13 @@ impl ResponseError for MatcherError {
             MatcherError::QueryOffer(e) => e.error_response(),
             MatcherError::SaveOffer(e) => e.error_response(),
             MatcherError::ModifyOffer(e) => e.error_response(),
+            MatcherError::Resolve(MatchError::InternalError(_)) => {
+                HttpResponse::InternalServerError().json(ErrorMessage::new(self.to_string()))
+            }
+            // Malformed properties or constraints expression given by the caller.
+            MatcherError::Resolve(_) => {
+                HttpResponse::BadRequest().json(ErrorMessage::new(self.to_string()))
+            }
         }
     }
 }
@@ -75,6 +83,17 @@ impl ResponseError for QueryDemandsError {
     }
 }
 
+impl ResponseError for ScanOffersError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ScanOffersError::InvalidConstraints(_) => {
+                HttpResponse::BadRequest().json(ErrorMessage::new(self.to_string()))
+            }
+            ScanOffersError::QueryOffers(e) => e.error_response(),
+        }
+    }
+}
+
 impl ResponseError for QueryOffersError {
     fn error_response(&self) -> HttpResponse {
         HttpResponse::InternalServerError().json(ErrorMessage::new(self.to_string()))
@@ -13,8 +13,8 @@ use crate::db::model::Owner;
 use crate::market::MarketService;
 
 use super::{
-    PathAgreement, PathSubscription, PathSubscriptionProposal, ProposalId, QueryTimeout,
-    QueryTimeoutMaxEvents,
+    PathAgreement, PathSubscription, PathSubscriptionOffer, PathSubscriptionProposal, ProposalId,
+    QueryScanOffers, QueryTimeout, QueryTimeoutMaxEvents,
 };
 use crate::negotiation::ApprovalStatus;
 use crate::rest_api::QueryAppSessionId;
@@ -25,6 +25,8 @@ pub fn register_endpoints(scope: Scope) -> Scope {
         .service(get_demands)
         .service(unsubscribe)
         .service(collect)
+        .service(explain_match)
+        .service(scan_offers)
         .service(counter_proposal)
         .service(get_proposal)
         .service(reject_proposal)
@@ -88,6 +90,43 @@ async fn collect(
         .map(|events| HttpResponse::Ok().json(events))
 }
 
+#[actix_web::get("/demands/{subscription_id}/offers/{offer_id}/explain")]
+async fn explain_match(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathSubscriptionOffer>,
+    id: Identity,
+) -> impl Responder {
+    let PathSubscriptionOffer {
+        subscription_id,
+        offer_id,
+    } = path.into_inner();
+    market
+        .matcher
+        .explain_match(&subscription_id, &offer_id, Some(id.identity))
+        .await
+        .log_err()
+        .map(|explanation| HttpResponse::Ok().json(explanation))
+}
+
+#[actix_web::get("/scan")]
+async fn scan_offers(
+    market: Data<Arc<MarketService>>,
+    query: Query<QueryScanOffers>,
+    _id: Identity,
+) -> impl Responder {
+    let QueryScanOffers {
+        constraints,
+        offset,
+        limit,
+    } = query.into_inner();
+    market
+        .matcher
+        .scan_offers(&constraints, offset, limit)
+        .await
+        .log_err()
+        .map(|offers| HttpResponse::Ok().json(offers))
+}
+
 #[actix_web::post("/demands/{subscription_id}/proposals/{proposal_id}")]
 async fn counter_proposal(
     market: Data<Arc<MarketService>>,
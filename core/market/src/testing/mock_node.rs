@@ -200,6 +200,29 @@ impl MarketsNetwork {
             .await
     }
 
+    /// Replaces Market Service of the node with new instance using the same
+    /// databases and identities, as if the daemon was restarted.
+    pub async fn restart_market_instance(mut self, name: &str) -> Self {
+        let idx = self
+            .nodes
+            .iter()
+            .position(|node| node.name == name)
+            .unwrap_or_else(|| panic!("Node {} doesn't exist", name));
+        let node = self.nodes.remove(idx);
+
+        let db = self.create_database(name);
+        let market = Arc::new(
+            MarketService::new(
+                &db,
+                node.mock_identity.clone() as Arc<dyn IdentityApi>,
+                self.config.clone(),
+            )
+            .unwrap(),
+        );
+        self.add_node(name, node.mock_identity, MockNodeKind::Market(market))
+            .await
+    }
+
     pub async fn add_matcher_instance(self, name: &str) -> Self {
         let db = self.init_database(name);
 
@@ -459,15 +482,13 @@ impl MarketsNetwork {
         let disk_db = DbExecutor::from_data_dir(&db_path, "yagna")
             .map_err(|e| anyhow!("Failed to create db [{:?}]. Error: {}", db_path, e))
             .unwrap();
-        let ram_db = DbExecutor::in_memory(&db_name)
-            .map_err(|e| {
-                anyhow!(
-                    "Failed to create in memory db [{:?}]. Error: {}",
-                    db_name,
-                    e
-                )
-            })
-            .unwrap();
+        let ram_db = if self.config.db.persistent_state {
+            DbExecutor::from_data_dir(&db_path, "yagna-state")
+        } else {
+            DbExecutor::in_memory(&db_name)
+        }
+        .map_err(|e| anyhow!("Failed to create state db [{:?}]. Error: {}", db_name, e))
+        .unwrap();
 
         DbMixedExecutor::new(disk_db, ram_db)
     }
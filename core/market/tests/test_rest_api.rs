@@ -115,6 +115,65 @@ async fn test_rest_get_demands() {
     assert_eq!(vec![demand_local.into_client_demand().unwrap()], result);
 }
 
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_rest_scan_offers() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await;
+
+    let market_local = network.get_market("Node-1");
+    let identity_local = network.get_default_id("Node-1");
+    let mut subscription_ids = vec![];
+    for mem in &[4, 8, 16] {
+        let offer = NewOffer::new(json!({ "golem.inf.mem.gib": mem }), "()".to_string());
+        subscription_ids.push(
+            market_local
+                .subscribe_offer(&offer, &identity_local)
+                .await
+                .unwrap(),
+        );
+    }
+
+    let app = network.get_rest_app("Node-1").await;
+
+    let req = actix_web::test::TestRequest::get()
+        .uri("/market-api/v1/scan?constraints=(golem.inf.mem.gib%3E%3D8)")
+        .to_request();
+    let resp = actix_web::test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let result: Vec<Offer> = read_response_json(resp).await;
+    let mut result_ids = result
+        .into_iter()
+        .map(|offer| offer.offer_id)
+        .collect::<Vec<_>>();
+    result_ids.sort();
+    let mut expected_ids = vec![
+        subscription_ids[1].to_string(),
+        subscription_ids[2].to_string(),
+    ];
+    expected_ids.sort();
+    assert_eq!(result_ids, expected_ids);
+
+    let req = actix_web::test::TestRequest::get()
+        .uri("/market-api/v1/scan?constraints=(golem.inf.mem.gib%3E%3D8)&offset=1&limit=5")
+        .to_request();
+    let resp = actix_web::test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let result: Vec<Offer> = read_response_json(resp).await;
+    assert_eq!(result.len(), 1);
+
+    let req = actix_web::test::TestRequest::get()
+        .uri("/market-api/v1/scan?constraints=(golem.inf.mem.gib%3E%3D")
+        .to_request();
+    let resp = actix_web::test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
 #[cfg_attr(not(feature = "test-suite"), ignore)]
 #[serial_test::serial]
 async fn test_rest_invalid_subscription_id_should_return_400() {
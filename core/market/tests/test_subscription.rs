@@ -1,5 +1,8 @@
+use std::sync::Arc;
+
 use ya_market::assert_err_eq;
 use ya_market::testing::client::{sample_demand, sample_offer};
+use ya_market::testing::mock_node::create_market_config_for_test;
 use ya_market::testing::mock_offer::flatten_json;
 use ya_market::testing::{DemandError, QueryOfferError};
 use ya_market::testing::{MarketServiceExt, MarketsNetwork};
@@ -88,3 +91,49 @@ async fn test_subscribe_demand() {
         market1.get_demand(&subscription_id).await
     );
 }
+
+/// Test restarts Market Service with persistent state enabled and checks,
+/// that subscriptions made before restart are still available and can be unsubscribed.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_restore_subscriptions_after_restart() {
+    let mut config = create_market_config_for_test();
+    config.db.persistent_state = true;
+
+    let network = MarketsNetwork::new(None)
+        .await
+        .with_config(Arc::new(config))
+        .add_market_instance("Node-1")
+        .await;
+
+    let market1 = network.get_market("Node-1");
+    let identity1 = network.get_default_id("Node-1");
+
+    let offer_id = market1
+        .subscribe_offer(&sample_offer(), &identity1)
+        .await
+        .unwrap();
+    let demand_id = market1
+        .subscribe_demand(&sample_demand(), &identity1)
+        .await
+        .unwrap();
+
+    let network = network.restart_market_instance("Node-1").await;
+    let market1 = network.get_market("Node-1");
+
+    let offers = market1.get_offers(Some(identity1.clone())).await.unwrap();
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0].offer_id, offer_id.to_string());
+    let demands = market1.get_demands(Some(identity1.clone())).await.unwrap();
+    assert_eq!(demands.len(), 1);
+    assert_eq!(demands[0].demand_id, demand_id.to_string());
+
+    market1
+        .unsubscribe_offer(&offer_id, &identity1)
+        .await
+        .unwrap();
+    market1
+        .unsubscribe_demand(&demand_id, &identity1)
+        .await
+        .unwrap();
+}
@@ -395,6 +395,38 @@ impl RpcMessage for ValidateAllocation {
     type Error = GenericError;
 }
 
+// ************************** DEPOSIT **************************
+
+/// Reserves `amount` of funds for the allocation. Sending it again
+/// for the same allocation replaces previously reserved amount.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MakeDeposit {
+    pub address: String,
+    pub platform: String,
+    pub allocation_id: String,
+    pub amount: BigDecimal,
+}
+
+impl RpcMessage for MakeDeposit {
+    const ID: &'static str = "MakeDeposit";
+    type Item = Ack;
+    type Error = GenericError;
+}
+
+/// Frees funds reserved for the allocation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReleaseDeposit {
+    pub address: String,
+    pub platform: String,
+    pub allocation_id: String,
+}
+
+impl RpcMessage for ReleaseDeposit {
+    const ID: &'static str = "ReleaseDeposit";
+    type Item = Ack;
+    type Error = GenericError;
+}
+
 // ************************** ENTER **************************
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -578,6 +610,19 @@ pub enum DriverStatusError {
     NetworkNotFound(String),
 }
 
+// ************************* PING *************************
+
+/// Liveness check sent periodically by the payment service to registered drivers.
+/// Drivers not responding are unregistered, until they register again.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Ping {}
+
+impl RpcMessage for Ping {
+    const ID: &'static str = "Ping";
+    type Item = ();
+    type Error = GenericError;
+}
+
 // ************************* SHUT DOWN *************************
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -3,7 +3,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use ya_client_model::market::{agreement::State, Role};
-pub use ya_client_model::market::{Agreement, AgreementListEntry};
+pub use ya_client_model::market::{Agreement, AgreementListEntry, Offer};
 use ya_service_bus::RpcMessage;
 
 /// Public Market bus address.
@@ -58,6 +58,76 @@ impl RpcMessage for ListAgreements {
     type Error = RpcMessageError;
 }
 
+/// Sets current value of dynamic property of the local node.
+/// Dynamic properties are declared in Offers and Demands as value-less (`null`)
+/// properties and are resolved at the time of matching. `None` value makes
+/// the property undefined.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetDynamicProperty {
+    pub name: String,
+    pub value: Option<serde_json::Value>,
+}
+
+impl RpcMessage for SetDynamicProperty {
+    const ID: &'static str = "SetDynamicProperty";
+    type Item = ();
+    type Error = RpcMessageError;
+}
+
+/// Explains why local Demand matches or doesn't match the Offer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainMatch {
+    pub demand_id: String,
+    pub offer_id: String,
+}
+
+impl RpcMessage for ExplainMatch {
+    const ID: &'static str = "ExplainMatch";
+    type Item = MatchExplanation;
+    type Error = RpcMessageError;
+}
+
+/// Returns active Offers known to the local node, which properties match
+/// given constraints. Doesn't create any Proposals.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanOffers {
+    pub constraints: String,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl RpcMessage for ScanOffers {
+    const ID: &'static str = "ScanOffers";
+    type Item = Vec<Offer>;
+    type Error = RpcMessageError;
+}
+
+/// Constraints of one side resolved against properties of the other side.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConstraintsExplanation {
+    /// `None` if constraints couldn't be resolved to boolean value.
+    pub result: Option<bool>,
+    /// Constraints reduced with known properties.
+    pub reduced: String,
+    /// Properties referenced by comparisons, which didn't hold.
+    pub failed: Vec<String>,
+    /// Properties referenced by comparisons, which couldn't be resolved.
+    pub undefined: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchExplanation {
+    /// Demand constraints resolved against Offer properties.
+    pub demand: ConstraintsExplanation,
+    /// Offer constraints resolved against Demand properties.
+    pub offer: ConstraintsExplanation,
+}
+
 /// Error message for market service bus API.
 #[derive(thiserror::Error, Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
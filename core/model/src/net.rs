@@ -17,6 +17,7 @@ pub const DIAGNOSTIC: &str = "/public/diagnostic/net";
 ///
 ///
 pub mod local {
+    use chrono::{DateTime, Utc};
     use std::net::SocketAddr;
     use std::time::Duration;
 
@@ -317,6 +318,272 @@ pub mod local {
         type Item = Vec<NodeId>;
         type Error = StatusError;
     }
+
+    /// Action taken for inbound calls to public services matching firewall rule.
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        Serialize,
+        Deserialize,
+        Eq,
+        PartialEq,
+        Hash,
+        Default,
+        strum_macros::Display,
+        strum_macros::EnumString,
+    )]
+    #[serde(rename_all = "camelCase")]
+    #[strum(serialize_all = "lowercase")]
+    pub enum FirewallAction {
+        #[default]
+        Allow,
+        Deny,
+    }
+
+    /// Maximal number of calls from single Node within `period_secs`.
+    /// Calls above the limit are denied.
+    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+    #[serde(rename_all = "camelCase")]
+    pub struct RateLimit {
+        pub calls: u32,
+        pub period_secs: u64,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FirewallRule {
+        pub action: FirewallAction,
+        /// Rule applies to all Nodes, if not set.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub node_id: Option<NodeId>,
+        /// Prefix of public service address, for example `/public/market`.
+        #[serde(default = "default_firewall_service")]
+        pub service: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub rate_limit: Option<RateLimit>,
+    }
+
+    fn default_firewall_service() -> String {
+        crate::net::PUBLIC_PREFIX.to_string()
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FirewallRuleInfo {
+        /// Rules are numbered from 1 in order of evaluation.
+        pub index: usize,
+        #[serde(flatten)]
+        pub rule: FirewallRule,
+        pub allowed: u64,
+        pub denied: u64,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FirewallStatus {
+        pub default_action: FirewallAction,
+        pub default_allowed: u64,
+        pub default_denied: u64,
+        pub rules: Vec<FirewallRuleInfo>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ListFirewallRules {}
+
+    impl RpcMessage for ListFirewallRules {
+        const ID: &'static str = "ListFirewallRules";
+        type Item = FirewallStatus;
+        type Error = GenericNetError;
+    }
+
+    /// Inserts rule at `position` (numbered from 1) or appends it to the end.
+    /// Returns index of the added rule.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AddFirewallRule {
+        pub rule: FirewallRule,
+        pub position: Option<usize>,
+    }
+
+    impl RpcMessage for AddFirewallRule {
+        const ID: &'static str = "AddFirewallRule";
+        type Item = usize;
+        type Error = GenericNetError;
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct RemoveFirewallRule {
+        pub index: usize,
+    }
+
+    impl RpcMessage for RemoveFirewallRule {
+        const ID: &'static str = "RemoveFirewallRule";
+        type Item = FirewallRule;
+        type Error = GenericNetError;
+    }
+
+    /// Sets action for calls not matching any rule.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SetFirewallDefault {
+        pub action: FirewallAction,
+    }
+
+    impl RpcMessage for SetFirewallDefault {
+        const ID: &'static str = "SetFirewallDefault";
+        type Item = ();
+        type Error = GenericNetError;
+    }
+
+    /// Loads rules from firewall config file again. Counters are reset.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ReloadFirewall {}
+
+    impl RpcMessage for ReloadFirewall {
+        const ID: &'static str = "ReloadFirewall";
+        type Item = FirewallStatus;
+        type Error = GenericNetError;
+    }
+
+    /// Bytes and messages of GSB traffic forwarded between local services and the network.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TrafficCounters {
+        pub tx_bytes: u64,
+        pub tx_messages: u64,
+        pub rx_bytes: u64,
+        pub rx_messages: u64,
+        pub errors: u64,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct NodeTraffic {
+        pub node_id: NodeId,
+        #[serde(flatten)]
+        pub counters: TrafficCounters,
+    }
+
+    /// Traffic of services grouped by the first segment of service address
+    /// (e.g. `/market`) or of broadcast topic (e.g. `bcast:new-neighbour`).
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ServiceTraffic {
+        pub service: String,
+        #[serde(flatten)]
+        pub counters: TrafficCounters,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TrafficStats {
+        pub total: TrafficCounters,
+        pub nodes: Vec<NodeTraffic>,
+        pub services: Vec<ServiceTraffic>,
+    }
+
+    /// Returns traffic counted since net service start.
+    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Traffic {}
+
+    impl RpcMessage for Traffic {
+        const ID: &'static str = "Traffic";
+        type Item = TrafficStats;
+        type Error = StatusError;
+    }
+
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        Serialize,
+        Deserialize,
+        Eq,
+        PartialEq,
+        Hash,
+        Default,
+        strum_macros::Display,
+        strum_macros::EnumString,
+    )]
+    #[serde(rename_all = "camelCase")]
+    #[strum(serialize_all = "lowercase")]
+    pub enum PeerMode {
+        /// Any session is accepted, relayed if direct connection isn't possible.
+        #[default]
+        Relay,
+        /// Direct session is preferred. Relayed sessions are re-established,
+        /// until a few attempts fail.
+        P2p,
+    }
+
+    /// Entry of the address book of known peers.
+    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Peer {
+        pub node_id: NodeId,
+        /// Static peers are connected at startup and reconnected, when session is lost.
+        #[serde(default, rename = "static")]
+        pub is_static: bool,
+        #[serde(default)]
+        pub mode: PeerMode,
+        /// Endpoints of the last direct session.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub endpoints: Vec<SocketAddr>,
+        /// Time of the last established session.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub last_connected: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PeerStatus {
+        #[serde(flatten)]
+        pub peer: Peer,
+        pub connected: bool,
+        pub is_p2p: bool,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ListPeers {}
+
+    impl RpcMessage for ListPeers {
+        const ID: &'static str = "ListPeers";
+        type Item = Vec<PeerStatus>;
+        type Error = GenericNetError;
+    }
+
+    /// Adds peer to the address book or updates its mode and static flag,
+    /// if it's already known.
+    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AddPeer {
+        pub peer: Peer,
+    }
+
+    impl RpcMessage for AddPeer {
+        const ID: &'static str = "AddPeer";
+        type Item = ();
+        type Error = GenericNetError;
+    }
+
+    /// Removes peer from the address book. Existing session isn't closed.
+    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+    #[serde(rename_all = "camelCase")]
+    pub struct RemovePeer {
+        pub node_id: NodeId,
+    }
+
+    impl RpcMessage for RemovePeer {
+        const ID: &'static str = "RemovePeer";
+        type Item = Peer;
+        type Error = GenericNetError;
+    }
 }
 
 /// For documentation check local::GsbPing
@@ -330,6 +597,22 @@ impl RpcMessage for GsbRemotePing {
     type Error = GenericNetError;
 }
 
+/// Capabilities of net service, exchanged between Nodes before using them.
+/// Nodes, which don't handle this message, support none of them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct GsbCapabilities {
+    /// Payload compression algorithms, which Node can decode (e.g. `zstd`).
+    #[serde(default)]
+    pub compression: Vec<String>,
+}
+
+impl RpcMessage for GsbCapabilities {
+    const ID: &'static str = "GsbCapabilities";
+    type Item = GsbCapabilities;
+    type Error = GenericNetError;
+}
+
 #[derive(thiserror::Error, Debug, Serialize, Deserialize)]
 #[error("{0}")]
 pub struct GenericNetError(pub String);
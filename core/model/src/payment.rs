@@ -25,6 +25,7 @@ pub mod local {
     use erc20_payment_lib::rpc_pool::{Web3ExternalSources, Web3FullNodeData};
     use std::collections::BTreeMap;
     use std::fmt::Display;
+    use std::str::FromStr;
     use std::time::Duration;
     use structopt::*;
     use strum::{EnumProperty, IntoEnumIterator, VariantNames};
@@ -235,6 +236,22 @@ pub mod local {
         type Error = GenericError;
     }
 
+    /// Sent by driver, when scheduled orders couldn't be sent or their transaction was dropped.
+    /// `reason` tells, if the driver retries them later.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct NotifyPaymentFailed {
+        pub driver: String,
+        pub platform: String,
+        pub order_ids: Vec<String>,
+        pub reason: String,
+    }
+
+    impl RpcMessage for NotifyPaymentFailed {
+        const ID: &'static str = "NotifyPaymentFailed";
+        type Item = ();
+        type Error = GenericError;
+    }
+
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct GetStatus {
         pub address: String,
@@ -266,7 +283,7 @@ pub mod local {
     pub struct GetRpcEndpoints {
         pub address: String,
         pub driver: DriverName,
-        pub network: Option<NetworkName>,
+        pub network: Option<String>,
         pub verify: bool,
         pub resolve: bool,
         pub no_wait: bool,
@@ -437,6 +454,196 @@ pub mod local {
         type Error = NoError;
     }
 
+    // ********************* SPENDING POLICY ********************************
+    /// Requestor-side limits applied when accepting invoices and debit notes.
+    /// Limits are applied to amounts scheduled for payment across all Allocations
+    /// of the identity. Daily and monthly periods are rolling 24 hours and 30 days.
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SpendingPolicy {
+        pub daily_limit: Option<BigDecimal>,
+        pub monthly_limit: Option<BigDecimal>,
+        pub provider_daily_limit: Option<BigDecimal>,
+        pub provider_monthly_limit: Option<BigDecimal>,
+        /// Maximum ratio of debit note amount to the cost computed from
+        /// Agreement's linear coefficients and reported usage.
+        pub max_debit_note_price_ratio: Option<f64>,
+        /// Maximum ratio of invoice amount to the cost computed from
+        /// Agreement's linear coefficients and last reported usage.
+        pub max_invoice_price_ratio: Option<f64>,
+        /// Reject invoices and debit notes breaking the policy instead of leaving them unaccepted.
+        #[serde(default)]
+        pub auto_reject: bool,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SpendingPolicyStatus {
+        pub policy: Option<SpendingPolicy>,
+        pub daily_spent: BigDecimal,
+        pub monthly_spent: BigDecimal,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct GetSpendingPolicy {
+        pub owner_id: NodeId,
+    }
+
+    impl RpcMessage for GetSpendingPolicy {
+        const ID: &'static str = "GetSpendingPolicy";
+        type Item = SpendingPolicyStatus;
+        type Error = GenericError;
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct SetSpendingPolicy {
+        pub owner_id: NodeId,
+        pub policy: SpendingPolicy,
+    }
+
+    impl RpcMessage for SetSpendingPolicy {
+        const ID: &'static str = "SetSpendingPolicy";
+        type Item = ();
+        type Error = GenericError;
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct ClearSpendingPolicy {
+        pub owner_id: NodeId,
+    }
+
+    impl RpcMessage for ClearSpendingPolicy {
+        const ID: &'static str = "ClearSpendingPolicy";
+        type Item = ();
+        type Error = GenericError;
+    }
+
+    // ********************* STATEMENT ********************************
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+    #[serde(rename_all = "lowercase")]
+    #[strum(serialize_all = "lowercase")]
+    pub enum StatementRole {
+        Requestor,
+        Provider,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Display)]
+    #[serde(rename_all = "lowercase")]
+    #[strum(serialize_all = "lowercase")]
+    pub enum StatementEntryKind {
+        Payment,
+        Invoice,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StatementFilter {
+        pub role: Option<StatementRole>,
+        pub platform: Option<String>,
+        pub after_timestamp: Option<DateTime<Utc>>,
+        pub before_timestamp: Option<DateTime<Utc>>,
+    }
+
+    /// Single row of payment statement. Payments are split into one entry
+    /// per paid Activity or Agreement, invoices have one entry each.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StatementEntry {
+        pub kind: StatementEntryKind,
+        pub id: String,
+        pub role: StatementRole,
+        pub timestamp: DateTime<Utc>,
+        pub agreement_id: Option<String>,
+        pub activity_id: Option<String>,
+        pub counterparty: NodeId,
+        pub platform: String,
+        pub tx_hash: Option<String>,
+        pub amount: BigDecimal,
+        pub status: Option<String>,
+        pub accepted_at: Option<DateTime<Utc>>,
+        pub settled_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct ExportStatement {
+        pub owner_id: NodeId,
+        pub filter: StatementFilter,
+    }
+
+    impl RpcMessage for ExportStatement {
+        const ID: &'static str = "ExportStatement";
+        type Item = Vec<StatementEntry>;
+        type Error = GenericError;
+    }
+
+    // ********************* RECONCILE ********************************
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Display)]
+    #[serde(rename_all = "camelCase")]
+    #[strum(serialize_all = "kebab-case")]
+    pub enum DiscrepancyKind {
+        /// Payment order was never confirmed by the driver.
+        UnconfirmedOrder,
+        /// Confirmed payment wasn't delivered to the Provider yet.
+        UnsentPayment,
+        /// Delivery of payment sync notifications to the peer was given up.
+        AbandonedSync,
+        /// Driver doesn't confirm the payment or confirms different details.
+        UnverifiedPayment,
+        /// Agreement is paid, but its invoice isn't settled.
+        UnsettledInvoice,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Discrepancy {
+        pub kind: DiscrepancyKind,
+        /// Id of the order, payment or invoice. Node id for abandoned sync.
+        pub id: String,
+        pub peer_id: Option<NodeId>,
+        pub platform: Option<String>,
+        pub amount: Option<BigDecimal>,
+        pub details: String,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ReconcileOptions {
+        /// Verify confirmations of payments made since the last reconciliation with drivers.
+        #[serde(default)]
+        pub verify: bool,
+        /// Verify all payments, not only the ones since the last reconciliation.
+        #[serde(default)]
+        pub verify_all: bool,
+        /// Resend sync notifications to peers with undelivered payments.
+        #[serde(default)]
+        pub resend: bool,
+        /// Limit reconciliation to documents exchanged with the peer.
+        pub peer_id: Option<NodeId>,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ReconcileReport {
+        pub discrepancies: Vec<Discrepancy>,
+        pub verified_payments: u32,
+        /// Only payments after this time were verified.
+        pub verified_since: Option<DateTime<Utc>>,
+        pub resent_to: Vec<NodeId>,
+        pub resend_failed: Vec<NodeId>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct Reconcile {
+        pub owner_id: NodeId,
+        pub options: ReconcileOptions,
+    }
+
+    impl RpcMessage for Reconcile {
+        const ID: &'static str = "Reconcile";
+        type Item = ReconcileReport;
+        type Error = GenericError;
+    }
+
     // ********************* STATUS ********************************
     #[derive(Clone, Debug, Serialize, Deserialize, thiserror::Error)]
     pub enum PaymentDriverStatusError {
@@ -565,12 +772,12 @@ pub mod local {
         /// Wallet address [default: <DEFAULT_IDENTITY>]
         #[structopt(long, env = "YA_ACCOUNT")]
         pub account: Option<NodeId>,
-        /// Payment driver
-        #[structopt(long, possible_values = DriverName::VARIANTS, default_value = DriverName::Erc20.into())]
-        pub driver: DriverName,
-        /// Payment network
-        #[structopt(long, possible_values = NetworkName::VARIANTS, default_value = NetworkName::Holesky.into())]
-        pub network: NetworkName,
+        /// Payment driver, `erc20` or external driver registered over GSB
+        #[structopt(long, default_value = DriverName::Erc20.into())]
+        pub driver: String,
+        /// Payment network, one of networks listed by `yagna payment driver list`
+        #[structopt(long, default_value = NetworkName::Holesky.into())]
+        pub network: String,
     }
 
     impl AccountCli {
@@ -579,15 +786,19 @@ pub mod local {
         }
 
         pub fn driver(&self) -> String {
-            self.driver.to_string()
+            self.driver.clone()
         }
 
         pub fn network(&self) -> String {
-            self.network.to_string()
+            self.network.clone()
         }
 
-        pub fn token(&self) -> String {
-            self.network.get_str("token").unwrap().to_string()
+        /// Token of built-in network. Tokens of networks added by drivers
+        /// are known only to the driver.
+        pub fn token(&self) -> Option<String> {
+            NetworkName::from_str(&self.network)
+                .ok()
+                .map(|network| get_token_from_network_name(&network).to_string())
         }
     }
 
@@ -601,7 +812,20 @@ pub mod local {
             assert_eq!(None, a.address());
             assert_eq!("erc20", a.driver());
             assert_eq!("holesky", a.network());
-            assert_eq!("tGLM", a.token());
+            assert_eq!(Some("tGLM".to_string()), a.token());
+        }
+
+        #[test]
+        fn test_cli_custom_network() {
+            let a = AccountCli::from_iter(&["", "--network", "staging"]);
+            assert_eq!("staging", a.network());
+            assert_eq!(None, a.token());
+        }
+
+        #[test]
+        fn test_cli_external_driver() {
+            let a = AccountCli::from_iter(&["", "--driver", "dummy"]);
+            assert_eq!("dummy", a.driver());
         }
     }
 }
@@ -686,6 +910,17 @@ pub mod public {
     pub struct RejectDebitNote {
         pub debit_note_id: String,
         pub rejection: Rejection,
+        pub issuer_id: NodeId,
+    }
+
+    impl RejectDebitNote {
+        pub fn new(debit_note_id: String, rejection: Rejection, issuer_id: NodeId) -> Self {
+            Self {
+                debit_note_id,
+                rejection,
+                issuer_id,
+            }
+        }
     }
 
     impl RpcMessage for RejectDebitNote {
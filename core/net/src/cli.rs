@@ -23,6 +23,12 @@ pub enum NetCommand {
     Sessions {},
     /// List virtual sockets
     Sockets {},
+    /// Show traffic forwarded to and from other Nodes
+    Traffic {
+        /// Group traffic by service instead of by Node
+        #[structopt(long)]
+        services: bool,
+    },
     /// Find node
     Find {
         /// Node information to query for
@@ -44,6 +50,96 @@ pub enum NetCommand {
     Disconnect { node_id: String },
     /// List current neighbors of this Node.
     ListNeighbors { size: u32 },
+    /// Manage rules for inbound calls to public services
+    Firewall(FirewallCommand),
+    /// Manage address book of known and static peers
+    Peers(PeersCommand),
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub enum PeersCommand {
+    /// List peers from the address book with their connection state
+    List {},
+    /// Add peer to the address book or update it
+    Add {
+        node_id: NodeId,
+        /// Connect at startup and reconnect, when session is lost
+        #[structopt(long = "static")]
+        is_static: bool,
+        /// Accept relayed sessions (`relay`) or require direct one (`p2p`)
+        #[structopt(long, default_value = "relay", possible_values = &["relay", "p2p"])]
+        mode: model::PeerMode,
+        /// Known endpoint of the peer, can be repeated
+        #[structopt(long = "endpoint")]
+        endpoints: Vec<std::net::SocketAddr>,
+    },
+    /// Remove peer from the address book
+    Remove { node_id: NodeId },
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub enum FirewallCommand {
+    /// List rules with number of allowed and denied calls
+    List {},
+    /// Allow calls matching the rule
+    Allow(FirewallRuleArgs),
+    /// Deny calls matching the rule
+    Deny(FirewallRuleArgs),
+    /// Remove rule
+    Remove {
+        /// Rule number as shown by `list`
+        index: usize,
+    },
+    /// Set action for calls not matching any rule
+    Default {
+        #[structopt(possible_values = &["allow", "deny"])]
+        action: model::FirewallAction,
+    },
+    /// Load rules from firewall config file again
+    Reload {},
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub struct FirewallRuleArgs {
+    /// Calling Node. Rule applies to all Nodes, if not set
+    #[structopt(long)]
+    node_id: Option<NodeId>,
+    /// Prefix of public service address
+    #[structopt(long, default_value = "/public")]
+    service: String,
+    /// Maximal number of calls from single Node per period, for example `100/1min`
+    #[structopt(long, parse(try_from_str = parse_rate_limit))]
+    rate: Option<model::RateLimit>,
+    /// Insert rule at given position instead of appending it
+    #[structopt(long)]
+    position: Option<usize>,
+}
+
+impl FirewallRuleArgs {
+    fn into_msg(self, action: model::FirewallAction) -> model::AddFirewallRule {
+        model::AddFirewallRule {
+            rule: model::FirewallRule {
+                action,
+                node_id: self.node_id,
+                service: self.service,
+                rate_limit: self.rate,
+            },
+            position: self.position,
+        }
+    }
+}
+
+fn parse_rate_limit(s: &str) -> anyhow::Result<model::RateLimit> {
+    let (calls, period) = s
+        .split_once('/')
+        .context("Expected rate limit in `calls/period` format")?;
+    Ok(model::RateLimit {
+        calls: calls.trim().parse()?,
+        period_secs: humantime::parse_duration(period.trim())?.as_secs(),
+    })
 }
 
 impl NetCommand {
@@ -148,6 +244,61 @@ impl NetCommand {
                 }
                 .into())
             }
+            NetCommand::Traffic { services } => {
+                let stats: model::TrafficStats = bus::service(model::BUS_ID)
+                    .send(model::Traffic {})
+                    .await
+                    .map_err(anyhow::Error::msg)??;
+
+                if is_json {
+                    return CommandOutput::object(stats);
+                }
+
+                let (key, rows) = match services {
+                    true => (
+                        "service",
+                        stats
+                            .services
+                            .into_iter()
+                            .map(|s| (s.service, s.counters))
+                            .collect::<Vec<_>>(),
+                    ),
+                    false => (
+                        "nodeId",
+                        stats
+                            .nodes
+                            .into_iter()
+                            .map(|n| (n.node_id.to_string(), n.counters))
+                            .collect(),
+                    ),
+                };
+
+                Ok(ResponseTable {
+                    columns: vec![
+                        key.into(),
+                        "out [MiB]".into(),
+                        "out msgs".into(),
+                        "in [MiB]".into(),
+                        "in msgs".into(),
+                        "errors".into(),
+                    ],
+                    values: rows
+                        .into_iter()
+                        .chain(std::iter::once(("total".to_string(), stats.total)))
+                        .map(|(name, c)| {
+                            serde_json::json! {[
+                                name,
+                                to_mib(c.tx_bytes as usize, is_json),
+                                c.tx_messages,
+                                to_mib(c.rx_bytes as usize, is_json),
+                                c.rx_messages,
+                                c.errors,
+                            ]}
+                        })
+                        .collect(),
+                }
+                .into())
+            }
             NetCommand::Find { node_id } => {
                 let node: model::FindNodeResponse = bus::service(model::BUS_ID)
                     .send(model::FindNode { node_id })
@@ -224,7 +375,177 @@ impl NetCommand {
                     .map_err(anyhow::Error::msg)??;
                 CommandOutput::object(serde_json::json!(list))
             }
+            NetCommand::Firewall(command) => command.run_command(ctx).await,
+            NetCommand::Peers(command) => command.run_command(ctx).await,
+        }
+    }
+}
+
+impl PeersCommand {
+    pub async fn run_command(self, ctx: &CliCtx) -> anyhow::Result<CommandOutput> {
+        let peers = match self {
+            PeersCommand::List {} => {
+                bus::service(model::BUS_ID)
+                    .send(model::ListPeers {})
+                    .await??
+            }
+            PeersCommand::Add {
+                node_id,
+                is_static,
+                mode,
+                endpoints,
+            } => {
+                let peer = model::Peer {
+                    node_id,
+                    is_static,
+                    mode,
+                    endpoints,
+                    last_connected: None,
+                };
+                bus::service(model::BUS_ID)
+                    .send(model::AddPeer { peer })
+                    .await??;
+                return CommandOutput::object(format!("Added peer {node_id}"));
+            }
+            PeersCommand::Remove { node_id } => {
+                bus::service(model::BUS_ID)
+                    .send(model::RemovePeer { node_id })
+                    .await??;
+                return CommandOutput::object(format!("Removed peer {node_id}"));
+            }
+        };
+
+        if ctx.json_output {
+            return CommandOutput::object(peers);
+        }
+
+        Ok(ResponseTable {
+            columns: vec![
+                "nodeId".into(),
+                "static".into(),
+                "mode".into(),
+                "connected".into(),
+                "endpoints".into(),
+                "last connected".into(),
+            ],
+            values: peers
+                .into_iter()
+                .map(|status| {
+                    let connected = match (status.connected, status.is_p2p) {
+                        (true, true) => "p2p",
+                        (true, false) => "relay",
+                        (false, _) => "no",
+                    };
+                    let endpoints = status
+                        .peer
+                        .endpoints
+                        .iter()
+                        .map(|e| e.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let last_connected = status
+                        .peer
+                        .last_connected
+                        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_default();
+                    serde_json::json! {[
+                        status.peer.node_id,
+                        status.peer.is_static,
+                        status.peer.mode.to_string(),
+                        connected,
+                        endpoints,
+                        last_connected,
+                    ]}
+                })
+                .collect(),
+        }
+        .into())
+    }
+}
+
+impl FirewallCommand {
+    pub async fn run_command(self, ctx: &CliCtx) -> anyhow::Result<CommandOutput> {
+        let status = match self {
+            FirewallCommand::List {} => {
+                bus::service(model::BUS_ID)
+                    .send(model::ListFirewallRules {})
+                    .await??
+            }
+            FirewallCommand::Allow(args) => {
+                let msg = args.into_msg(model::FirewallAction::Allow);
+                let index = bus::service(model::BUS_ID).send(msg).await??;
+                return CommandOutput::object(format!("Added firewall rule {index}"));
+            }
+            FirewallCommand::Deny(args) => {
+                let msg = args.into_msg(model::FirewallAction::Deny);
+                let index = bus::service(model::BUS_ID).send(msg).await??;
+                return CommandOutput::object(format!("Added firewall rule {index}"));
+            }
+            FirewallCommand::Remove { index } => {
+                bus::service(model::BUS_ID)
+                    .send(model::RemoveFirewallRule { index })
+                    .await??;
+                return CommandOutput::object(format!("Removed firewall rule {index}"));
+            }
+            FirewallCommand::Default { action } => {
+                bus::service(model::BUS_ID)
+                    .send(model::SetFirewallDefault { action })
+                    .await??;
+                return Ok(CommandOutput::NoOutput);
+            }
+            FirewallCommand::Reload {} => {
+                bus::service(model::BUS_ID)
+                    .send(model::ReloadFirewall {})
+                    .await??
+            }
+        };
+
+        if ctx.json_output {
+            return CommandOutput::object(status);
+        }
+
+        let mut values: Vec<serde_json::Value> = status
+            .rules
+            .into_iter()
+            .map(|info| {
+                let rate = info.rule.rate_limit.map(|limit| {
+                    let period = format_duration(Duration::from_secs(limit.period_secs));
+                    format!("{}/{}", limit.calls, period)
+                });
+                serde_json::json! {[
+                    info.index,
+                    info.rule.action.to_string(),
+                    info.rule.node_id.map(|id| id.to_string()).unwrap_or_else(|| "*".into()),
+                    info.rule.service,
+                    rate.unwrap_or_default(),
+                    info.allowed,
+                    info.denied,
+                ]}
+            })
+            .collect();
+        values.push(serde_json::json! {[
+            "default",
+            status.default_action.to_string(),
+            "*",
+            "*",
+            "",
+            status.default_allowed,
+            status.default_denied,
+        ]});
+
+        Ok(ResponseTable {
+            columns: vec![
+                "rule".into(),
+                "action".into(),
+                "nodeId".into(),
+                "service".into(),
+                "rate".into(),
+                "allowed".into(),
+                "denied".into(),
+            ],
+            values,
         }
+        .into())
     }
 }
 
@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
 use structopt::StructOpt;
 use strum::VariantNames;
@@ -27,6 +29,8 @@ pub enum NetType {
     Central,
     #[cfg_attr(not(feature = "central-net"), default)]
     Hybrid,
+    /// Direct connections to statically configured Nodes, without relay server.
+    Local,
 }
 
 #[derive(StructOpt, Clone)]
@@ -44,6 +48,29 @@ pub struct Config {
     pub session_expiration: Duration,
     #[structopt(env = "YA_NET_SESSION_REQUEST_TIMEOUT", parse(try_from_str = humantime::parse_duration), default_value = "3s")]
     pub session_request_timeout: Duration,
+    /// Compress GSB messages sent to Nodes, which announced support for it (`hybrid` net only).
+    #[structopt(
+        env = "YA_NET_COMPRESSION",
+        default_value = "true",
+        parse(try_from_str)
+    )]
+    pub compression: bool,
+    /// Messages smaller than this number of bytes are sent uncompressed.
+    #[structopt(env = "YA_NET_COMPRESSION_THRESHOLD", default_value = "1024")]
+    pub compression_threshold: usize,
+    /// Inbound calls firewall rules. Defaults to `net-firewall.json` in yagna data directory.
+    #[structopt(env = "YA_NET_FIREWALL_FILE")]
+    pub firewall_file: Option<PathBuf>,
+    /// Address book of known and static peers used by `hybrid` net.
+    /// Defaults to `net-peers.json` in yagna data directory.
+    #[structopt(env = "YA_NET_PEERS_FILE")]
+    pub peers_file: Option<PathBuf>,
+    /// Address on which `local` net accepts connections from other Nodes.
+    #[structopt(env = "YA_NET_LOCAL_BIND", default_value = "127.0.0.1:11501")]
+    pub local_bind: SocketAddr,
+    /// Comma separated `host:port` addresses of Nodes, to which `local` net connects.
+    #[structopt(env = "YA_NET_LOCAL_PEERS")]
+    pub local_peers: Option<String>,
 }
 
 impl Config {
@@ -52,4 +79,14 @@ impl Config {
         // or default values if ENV variables are not set.
         Config::from_iter_safe(&[""])
     }
+
+    pub fn local_peers(&self) -> Vec<String> {
+        self.local_peers
+            .iter()
+            .flat_map(|peers| peers.split(','))
+            .map(str::trim)
+            .filter(|peer| !peer.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
 }
@@ -0,0 +1,462 @@
+use anyhow::{anyhow, bail};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use ya_core_model::net::local::{
+    self as model, FirewallAction, FirewallRule, FirewallRuleInfo, FirewallStatus,
+};
+use ya_core_model::net::{GenericNetError, PUBLIC_PREFIX};
+use ya_core_model::NodeId;
+use ya_service_bus::typed as bus;
+
+pub const FIREWALL_FILE: &str = "net-firewall.json";
+
+/// Rate limit windows are pruned, when number of tracked Nodes exceeds this value.
+const MAX_TRACKED_NODES: usize = 1024;
+
+lazy_static::lazy_static! {
+    pub(crate) static ref FIREWALL: Mutex<Firewall> = Default::default();
+}
+
+/// Content of firewall config file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirewallConfig {
+    #[serde(default)]
+    pub default_action: FirewallAction,
+    #[serde(default)]
+    pub rules: Vec<FirewallRule>,
+}
+
+#[derive(Clone)]
+struct RuleState {
+    rule: FirewallRule,
+    allowed: u64,
+    denied: u64,
+    /// Start of current rate limit window and number of calls within it per Node.
+    windows: HashMap<NodeId, (Instant, u32)>,
+}
+
+impl RuleState {
+    fn new(rule: FirewallRule) -> Self {
+        RuleState {
+            rule,
+            allowed: 0,
+            denied: 0,
+            windows: HashMap::new(),
+        }
+    }
+
+    fn matches(&self, node_id: NodeId, address: &str) -> bool {
+        self.rule.node_id.map(|id| id == node_id).unwrap_or(true)
+            && service_matches(&self.rule.service, address)
+    }
+
+    fn within_rate_limit(&mut self, node_id: NodeId, now: Instant) -> bool {
+        let limit = match &self.rule.rate_limit {
+            Some(limit) => limit,
+            None => return true,
+        };
+        let period = Duration::from_secs(limit.period_secs);
+
+        if self.windows.len() >= MAX_TRACKED_NODES {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < period);
+        }
+
+        let (start, calls) = self.windows.entry(node_id).or_insert((now, 0));
+        if now.duration_since(*start) >= period {
+            *start = now;
+            *calls = 0;
+        }
+        if *calls >= limit.calls {
+            return false;
+        }
+        *calls += 1;
+        true
+    }
+}
+
+/// `prefix` matches whole segments of service address only.
+fn service_matches(prefix: &str, address: &str) -> bool {
+    match address.strip_prefix(prefix.trim_end_matches('/')) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+pub fn validate(rule: &FirewallRule) -> anyhow::Result<()> {
+    if !service_matches(PUBLIC_PREFIX, &rule.service) {
+        bail!(
+            "Service prefix should start with {}: {}",
+            PUBLIC_PREFIX,
+            rule.service
+        );
+    }
+    if let Some(limit) = &rule.rate_limit {
+        if rule.action == FirewallAction::Deny {
+            bail!("Rate limit can be set only for allowing rules");
+        }
+        if limit.calls == 0 || limit.period_secs == 0 {
+            bail!("Rate limit calls and period should be positive");
+        }
+    }
+    Ok(())
+}
+
+/// Rules limiting which Nodes can call which public services and how often.
+/// Rules are evaluated in order, the first matching rule decides. Calls matching
+/// allowing rule, but exceeding its rate limit are denied.
+#[derive(Clone, Default)]
+pub struct Firewall {
+    path: Option<PathBuf>,
+    default_action: FirewallAction,
+    default_allowed: u64,
+    default_denied: u64,
+    rules: Vec<RuleState>,
+}
+
+impl Firewall {
+    pub fn from_config(config: FirewallConfig) -> anyhow::Result<Self> {
+        config.rules.iter().try_for_each(validate)?;
+        Ok(Firewall {
+            default_action: config.default_action,
+            rules: config.rules.into_iter().map(RuleState::new).collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Loads rules from `path`. Changes made later are saved to the same file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let config = if path.exists() {
+            log::debug!("Loading net firewall rules from: {}", path.display());
+            serde_json::from_reader(io::BufReader::new(fs::File::open(path)?))
+                .map_err(|e| anyhow!("Invalid firewall file {}: {}", path.display(), e))?
+        } else {
+            FirewallConfig::default()
+        };
+        let mut firewall = Self::from_config(config)?;
+        firewall.path = Some(path.to_path_buf());
+        Ok(firewall)
+    }
+
+    pub fn reload(&mut self) -> anyhow::Result<()> {
+        if let Some(path) = self.path.clone() {
+            *self = Self::load(&path)?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            fs::write(path, serde_json::to_string_pretty(&self.config())?)?;
+        }
+        Ok(())
+    }
+
+    /// Applies `f` to the rules and saves them. Rules are left unchanged, when
+    /// `f` or saving fails.
+    pub fn modify<T>(
+        &mut self,
+        f: impl FnOnce(&mut Firewall) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let mut modified = self.clone();
+        let result = f(&mut modified)?;
+        modified
+            .save()
+            .map_err(|e| anyhow!("Failed to save firewall rules: {e}"))?;
+        *self = modified;
+        Ok(result)
+    }
+
+    pub fn config(&self) -> FirewallConfig {
+        FirewallConfig {
+            default_action: self.default_action,
+            rules: self.rules.iter().map(|state| state.rule.clone()).collect(),
+        }
+    }
+
+    /// Checks inbound call from `node_id` to public service `address`.
+    /// Returns reason, when call is denied.
+    pub fn check(&mut self, node_id: NodeId, address: &str, now: Instant) -> Result<(), String> {
+        let matching = self
+            .rules
+            .iter_mut()
+            .enumerate()
+            .find(|(_, state)| state.matches(node_id, address));
+
+        let (allowed, reason) = match matching {
+            Some((idx, state)) => {
+                let allowed = match state.rule.action {
+                    FirewallAction::Allow => state.within_rate_limit(node_id, now),
+                    FirewallAction::Deny => false,
+                };
+                let reason = match allowed {
+                    true => {
+                        state.allowed += 1;
+                        String::new()
+                    }
+                    false if state.rule.action == FirewallAction::Allow => {
+                        state.denied += 1;
+                        format!("exceeded rate limit of firewall rule {}", idx + 1)
+                    }
+                    false => {
+                        state.denied += 1;
+                        format!("denied by firewall rule {}", idx + 1)
+                    }
+                };
+                (allowed, reason)
+            }
+            None => {
+                let allowed = self.default_action == FirewallAction::Allow;
+                match allowed {
+                    true => self.default_allowed += 1,
+                    false => self.default_denied += 1,
+                }
+                (allowed, "denied by firewall default policy".to_string())
+            }
+        };
+
+        if allowed {
+            counter!("net.firewall.allowed", 1);
+            return Ok(());
+        }
+        counter!("net.firewall.denied", 1);
+        log::debug!("Call from [{node_id}] to {address} {reason}");
+        Err(format!("Call to {address} {reason}"))
+    }
+
+    pub fn status(&self) -> FirewallStatus {
+        FirewallStatus {
+            default_action: self.default_action,
+            default_allowed: self.default_allowed,
+            default_denied: self.default_denied,
+            rules: self
+                .rules
+                .iter()
+                .enumerate()
+                .map(|(idx, state)| FirewallRuleInfo {
+                    index: idx + 1,
+                    rule: state.rule.clone(),
+                    allowed: state.allowed,
+                    denied: state.denied,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn add(&mut self, rule: FirewallRule, position: Option<usize>) -> anyhow::Result<usize> {
+        validate(&rule)?;
+        let idx = match position {
+            Some(position) if position == 0 || position > self.rules.len() + 1 => {
+                bail!("Invalid rule position: {position}")
+            }
+            Some(position) => position - 1,
+            None => self.rules.len(),
+        };
+        self.rules.insert(idx, RuleState::new(rule));
+        Ok(idx + 1)
+    }
+
+    pub fn remove(&mut self, index: usize) -> anyhow::Result<FirewallRule> {
+        if index == 0 || index > self.rules.len() {
+            bail!("Firewall rule {index} doesn't exist");
+        }
+        Ok(self.rules.remove(index - 1).rule)
+    }
+
+    pub fn set_default(&mut self, action: FirewallAction) {
+        self.default_action = action;
+    }
+}
+
+/// Loads firewall rules. Rules aren't applied, until this function succeeds.
+pub(crate) fn init(path: &Path) -> anyhow::Result<()> {
+    let firewall = Firewall::load(path)?;
+    log::info!(
+        "Net firewall loaded {} rule(s) from {}, default action: {}",
+        firewall.rules.len(),
+        path.display(),
+        firewall.default_action
+    );
+    *FIREWALL.lock().unwrap() = firewall;
+    Ok(())
+}
+
+/// Checks inbound call against global firewall rules.
+pub(crate) fn check(node_id: NodeId, address: &str) -> Result<(), String> {
+    FIREWALL
+        .lock()
+        .unwrap()
+        .check(node_id, address, Instant::now())
+}
+
+/// Checks inbound broadcast from `node_id` against global firewall rules.
+pub(crate) fn check_broadcast(node_id: NodeId, topic: &str) -> Result<(), String> {
+    check(node_id, &broadcast_address(topic))
+}
+
+/// Address, to which broadcasts of given topic are matched by firewall rules.
+pub fn broadcast_address(topic: &str) -> String {
+    format!("{PUBLIC_PREFIX}/bcast/{topic}")
+}
+
+fn modify<T>(f: impl FnOnce(&mut Firewall) -> anyhow::Result<T>) -> Result<T, GenericNetError> {
+    FIREWALL
+        .lock()
+        .unwrap()
+        .modify(f)
+        .map_err(|e| GenericNetError(e.to_string()))
+}
+
+pub(crate) fn bind_service() {
+    let _ = bus::bind(model::BUS_ID, |_: model::ListFirewallRules| async move {
+        Ok(FIREWALL.lock().unwrap().status())
+    });
+    let _ = bus::bind(model::BUS_ID, |msg: model::AddFirewallRule| async move {
+        modify(|firewall| firewall.add(msg.rule, msg.position))
+    });
+    let _ = bus::bind(model::BUS_ID, |msg: model::RemoveFirewallRule| async move {
+        modify(|firewall| firewall.remove(msg.index))
+    });
+    let _ = bus::bind(model::BUS_ID, |msg: model::SetFirewallDefault| async move {
+        modify(|firewall| {
+            firewall.set_default(msg.action);
+            Ok(())
+        })
+    });
+    let _ = bus::bind(model::BUS_ID, |_: model::ReloadFirewall| async move {
+        let mut firewall = FIREWALL.lock().unwrap();
+        firewall
+            .reload()
+            .map_err(|e| GenericNetError(e.to_string()))?;
+        Ok::<_, GenericNetError>(firewall.status())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ya_core_model::net::local::RateLimit;
+
+    fn node(n: u8) -> NodeId {
+        NodeId::from(&[n; 20][..])
+    }
+
+    fn rule(action: FirewallAction, node_id: Option<NodeId>, service: &str) -> FirewallRule {
+        FirewallRule {
+            action,
+            node_id,
+            service: service.to_string(),
+            rate_limit: None,
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_decides() {
+        let mut firewall = Firewall::from_config(FirewallConfig {
+            default_action: FirewallAction::Deny,
+            rules: vec![
+                rule(FirewallAction::Deny, Some(node(1)), "/public/market"),
+                rule(FirewallAction::Allow, None, "/public/market"),
+            ],
+        })
+        .unwrap();
+        let now = Instant::now();
+
+        assert!(firewall
+            .check(node(1), "/public/market/provider/Subscribe", now)
+            .is_err());
+        assert!(firewall
+            .check(node(2), "/public/market/provider/Subscribe", now)
+            .is_ok());
+        assert!(firewall.check(node(2), "/public/marketplace", now).is_err());
+        assert!(firewall.check(node(2), "/public/payment", now).is_err());
+
+        let status = firewall.status();
+        assert_eq!((status.rules[0].allowed, status.rules[0].denied), (0, 1));
+        assert_eq!((status.rules[1].allowed, status.rules[1].denied), (1, 0));
+        assert_eq!((status.default_allowed, status.default_denied), (0, 2));
+    }
+
+    #[test]
+    fn test_rate_limit_per_node() {
+        let mut limited = rule(FirewallAction::Allow, None, "/public");
+        limited.rate_limit = Some(RateLimit {
+            calls: 2,
+            period_secs: 10,
+        });
+        let mut firewall = Firewall::default();
+        assert_eq!(firewall.add(limited, None).unwrap(), 1);
+
+        let now = Instant::now();
+        assert!(firewall.check(node(1), "/public/activity", now).is_ok());
+        assert!(firewall.check(node(1), "/public/activity", now).is_ok());
+        assert!(firewall.check(node(1), "/public/activity", now).is_err());
+        assert!(firewall.check(node(2), "/public/activity", now).is_ok());
+
+        let later = now + Duration::from_secs(10);
+        assert!(firewall.check(node(1), "/public/activity", later).is_ok());
+        assert_eq!(firewall.status().rules[0].denied, 1);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let mut firewall = Firewall::default();
+        assert!(firewall
+            .add(rule(FirewallAction::Deny, None, "/local/net"), None)
+            .is_err());
+
+        let mut limited = rule(FirewallAction::Deny, None, "/public");
+        limited.rate_limit = Some(RateLimit {
+            calls: 1,
+            period_secs: 1,
+        });
+        assert!(firewall.add(limited, None).is_err());
+        assert!(firewall
+            .add(rule(FirewallAction::Deny, None, "/public"), Some(2))
+            .is_err());
+        assert!(firewall.remove(1).is_err());
+    }
+
+    #[test]
+    fn test_broadcast_rules() {
+        let mut firewall = Firewall::from_config(FirewallConfig {
+            default_action: FirewallAction::Allow,
+            rules: vec![rule(FirewallAction::Deny, Some(node(1)), "/public/bcast")],
+        })
+        .unwrap();
+        let now = Instant::now();
+        let address = broadcast_address("market-protocol-mk1-offer");
+
+        assert!(firewall.check(node(1), &address, now).is_err());
+        assert!(firewall.check(node(2), &address, now).is_ok());
+    }
+
+    #[test]
+    fn test_modify_keeps_rules_when_save_fails() {
+        let mut firewall = Firewall::load(
+            &std::env::temp_dir()
+                .join("ya-net-firewall-missing-dir")
+                .join(FIREWALL_FILE),
+        )
+        .unwrap();
+
+        assert!(firewall
+            .modify(|firewall| firewall.add(rule(FirewallAction::Deny, None, "/public"), None))
+            .is_err());
+        assert!(firewall
+            .modify(|firewall| {
+                firewall.set_default(FirewallAction::Deny);
+                Ok(())
+            })
+            .is_err());
+        assert!(firewall.config().rules.is_empty());
+        assert_eq!(firewall.config().default_action, FirewallAction::Allow);
+    }
+}
@@ -1,10 +1,22 @@
+use metrics::{counter, value};
 use prost::Message;
+use std::io::Read;
 
 use ya_core_model::NodeId;
 use ya_sb_proto::codec::{GsbMessage, ProtocolError};
 use ya_sb_proto::CallReplyCode;
 use ya_service_bus::{Error, ResponseChunk};
 
+/// Compression algorithm announced to other Nodes in `GsbCapabilities`.
+pub(crate) const COMPRESSION_ZSTD: &str = "zstd";
+
+/// Set in length prefix of messages carrying zstd compressed packet.
+/// Such messages are sent only to Nodes, which announced support for compression.
+const COMPRESSED_FLAG: u32 = 0x8000_0000;
+const COMPRESSION_LEVEL: i32 = 3;
+/// Protects from decompressing messages of unbounded size.
+const MAX_DECOMPRESSED_LEN: u64 = 128 * 1024 * 1024;
+
 pub(crate) fn encode_message(msg: GsbMessage) -> Result<Vec<u8>, Error> {
     let packet = ya_sb_proto::Packet { packet: Some(msg) };
     let len: usize = packet.encoded_len();
@@ -18,21 +30,65 @@ pub(crate) fn encode_message(msg: GsbMessage) -> Result<Vec<u8>, Error> {
     Ok(dst)
 }
 
+/// Compresses message encoded with `encode_message`, if it's at least `threshold` bytes long.
+/// Message is left intact, when compression doesn't reduce its size.
+pub(crate) fn compress_message(encoded: Vec<u8>, threshold: usize) -> Vec<u8> {
+    if encoded.len() < 4 + threshold {
+        return encoded;
+    }
+
+    let compressed = match zstd::bulk::compress(&encoded[4..], COMPRESSION_LEVEL) {
+        Ok(compressed) => compressed,
+        Err(e) => {
+            log::debug!("Unable to compress message: {e}");
+            return encoded;
+        }
+    };
+    if compressed.len() + 4 >= encoded.len() || compressed.len() as u32 >= COMPRESSED_FLAG {
+        counter!("net.compression.skipped", 1);
+        return encoded;
+    }
+
+    let original = encoded.len() - 4;
+    counter!("net.compression.original.bytes", original as u64);
+    counter!("net.compression.compressed.bytes", compressed.len() as u64);
+    value!(
+        "net.compression.ratio",
+        (compressed.len() * 100 / original) as u64
+    );
+
+    let mut dst = Vec::with_capacity(4 + compressed.len());
+    dst.extend((COMPRESSED_FLAG | compressed.len() as u32).to_be_bytes());
+    dst.extend(compressed);
+    dst
+}
+
 pub(crate) fn decode_message(src: &[u8]) -> Result<Option<GsbMessage>, Error> {
-    let msg_length = if src.len() < 4 {
+    let prefix = if src.len() < 4 {
         return Ok(None);
     } else {
         let mut buf = [0u8; 4];
         buf.copy_from_slice(&src[0..4]);
-        u32::from_be_bytes(buf) as usize
+        u32::from_be_bytes(buf)
     };
+    let compressed = prefix & COMPRESSED_FLAG != 0;
+    let msg_length = (prefix & !COMPRESSED_FLAG) as usize;
 
     if src.len() < 4 + msg_length {
         return Ok(None);
     }
 
-    let packet = ya_sb_proto::Packet::decode(&src[4..4 + msg_length])
-        .map_err(|e| Error::EncodingProblem(e.to_string()))?;
+    let decompressed;
+    let packet = match compressed {
+        true => {
+            decompressed = decompress(&src[4..4 + msg_length], MAX_DECOMPRESSED_LEN)?;
+            decompressed.as_slice()
+        }
+        false => &src[4..4 + msg_length],
+    };
+
+    let packet =
+        ya_sb_proto::Packet::decode(packet).map_err(|e| Error::EncodingProblem(e.to_string()))?;
     match packet.packet {
         Some(msg) => Ok(Some(msg)),
         None => Err(Error::EncodingProblem(
@@ -41,6 +97,24 @@ pub(crate) fn decode_message(src: &[u8]) -> Result<Option<GsbMessage>, Error> {
     }
 }
 
+fn decompress(src: &[u8], max_len: u64) -> Result<Vec<u8>, Error> {
+    let decoder =
+        zstd::stream::read::Decoder::new(src).map_err(|e| Error::EncodingProblem(e.to_string()))?;
+    let mut dst = Vec::new();
+    decoder
+        .take(max_len + 1)
+        .read_to_end(&mut dst)
+        .map_err(|e| Error::EncodingProblem(format!("Invalid compressed message: {e}")))?;
+    if dst.len() as u64 > max_len {
+        return Err(Error::EncodingProblem(format!(
+            "Decompressed message exceeds {max_len} B"
+        )));
+    }
+
+    counter!("net.compression.decompressed.bytes", dst.len() as u64);
+    Ok(dst)
+}
+
 pub(crate) fn decode_reply(data: Vec<u8>) -> Result<Vec<u8>, Error> {
     use std::convert::TryInto;
 
@@ -141,7 +215,9 @@ pub(crate) fn reply_eos(request_id: impl ToString) -> GsbMessage {
 mod tests {
     use std::iter::FromIterator;
 
-    use crate::hybrid::codec::{decode_message, encode_message};
+    use crate::hybrid::codec::{
+        compress_message, decode_message, decompress, encode_message, COMPRESSED_FLAG,
+    };
 
     #[test]
     fn encode_message_compat() {
@@ -165,4 +241,35 @@ mod tests {
         assert_eq!(encoded_orig, encoded);
         assert_eq!(decode_message(encoded.as_slice()).unwrap().unwrap(), msg);
     }
+
+    #[test]
+    fn compress_message_above_threshold() {
+        use ya_sb_proto::codec::GsbMessage;
+
+        let msg = GsbMessage::CallRequest(ya_sb_proto::CallRequest {
+            caller: "0x0000000000000000000000000000000000000001".to_string(),
+            address: "/net/0x0000000000000000000000000000000000000002/market".to_string(),
+            request_id: "1".to_string(),
+            data: "{\"properties\": {}}".repeat(200).into_bytes(),
+            no_reply: false,
+        });
+        let encoded = encode_message(msg.clone()).unwrap();
+
+        let compressed = compress_message(encoded.clone(), 1024);
+        assert!(compressed.len() < encoded.len());
+        assert_eq!(decode_message(compressed.as_slice()).unwrap().unwrap(), msg);
+
+        assert_eq!(compress_message(encoded.clone(), encoded.len()), encoded);
+    }
+
+    #[test]
+    fn reject_oversized_decompressed_message() {
+        let compressed = zstd::bulk::compress(&[0u8; 4096], 3).unwrap();
+        assert_eq!(decompress(&compressed, 4096).unwrap().len(), 4096);
+        assert!(decompress(&compressed, 4095).is_err());
+
+        let mut msg = (COMPRESSED_FLAG | 4).to_be_bytes().to_vec();
+        msg.extend_from_slice(b"junk");
+        assert!(decode_message(msg.as_slice()).is_err());
+    }
 }
@@ -1,10 +1,13 @@
 mod api;
 pub(crate) mod cli;
-mod codec;
+pub(crate) mod codec;
 mod crypto;
+pub(crate) mod peers;
 mod rest_api;
 mod service;
 
 pub use api::*;
 pub use rest_api::web_scope;
 pub use service::{send_bcast_new_neighbour, start_network, Net};
+
+pub(crate) use service::{parse_from_to_addr, parse_net_to_addr};
@@ -0,0 +1,465 @@
+use anyhow::anyhow;
+use chrono::Utc;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use ya_core_model::net::local::{self as model, Peer, PeerMode, PeerStatus};
+use ya_core_model::net::GenericNetError;
+use ya_core_model::NodeId;
+use ya_relay_client::Client;
+use ya_service_bus::typed as bus;
+
+pub const PEERS_FILE: &str = "net-peers.json";
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+/// Relayed session with peer in `p2p` mode is kept after this number of failed
+/// attempts to replace it with direct one.
+const MAX_P2P_ATTEMPTS: u32 = 3;
+
+lazy_static::lazy_static! {
+    static ref ADDRESS_BOOK: Mutex<AddressBook> = Default::default();
+}
+
+/// Content of address book file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddressBookConfig {
+    #[serde(default)]
+    peers: Vec<Peer>,
+}
+
+/// Known peers with endpoints and times of their last established sessions.
+#[derive(Clone, Default)]
+pub struct AddressBook {
+    path: Option<PathBuf>,
+    peers: Vec<Peer>,
+}
+
+impl AddressBook {
+    /// Loads peers from `path`. Changes made later are saved to the same file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let config: AddressBookConfig = if path.exists() {
+            log::debug!("Loading net address book from: {}", path.display());
+            serde_json::from_reader(io::BufReader::new(fs::File::open(path)?))
+                .map_err(|e| anyhow!("Invalid address book file {}: {}", path.display(), e))?
+        } else {
+            AddressBookConfig::default()
+        };
+        Ok(AddressBook {
+            path: Some(path.to_path_buf()),
+            peers: config.peers,
+        })
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            let config = AddressBookConfig {
+                peers: self.peers.clone(),
+            };
+            fs::write(path, serde_json::to_string_pretty(&config)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn peers(&self) -> Vec<Peer> {
+        self.peers.clone()
+    }
+
+    pub fn static_peers(&self) -> Vec<Peer> {
+        self.peers.iter().filter(|p| p.is_static).cloned().collect()
+    }
+
+    /// Applies `f` to a copy of the address book and saves it. Address book
+    /// is left unchanged, when `f` fails or saving fails.
+    pub fn modify<T>(
+        &mut self,
+        f: impl FnOnce(&mut AddressBook) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let mut modified = self.clone();
+        let result = f(&mut modified)?;
+        modified
+            .save()
+            .map_err(|e| anyhow!("Failed to save net address book: {e}"))?;
+        *self = modified;
+        Ok(result)
+    }
+
+    /// Adds new peer. For known peer only mode and static flag are updated,
+    /// endpoints are replaced only if new ones were given.
+    pub fn add(&mut self, peer: Peer) {
+        match self.peers.iter_mut().find(|p| p.node_id == peer.node_id) {
+            Some(known) => {
+                known.is_static = peer.is_static;
+                known.mode = peer.mode;
+                if !peer.endpoints.is_empty() {
+                    known.endpoints = peer.endpoints;
+                }
+            }
+            None => self.peers.push(peer),
+        }
+    }
+
+    pub fn remove(&mut self, node_id: NodeId) -> anyhow::Result<Peer> {
+        let idx = self
+            .peers
+            .iter()
+            .position(|p| p.node_id == node_id)
+            .ok_or_else(|| anyhow!("Peer {node_id} isn't in the address book"))?;
+        Ok(self.peers.remove(idx))
+    }
+
+    /// Remembers time of established session with known peer.
+    /// Returns false, if peer isn't in the address book.
+    pub fn connected(&mut self, node_id: NodeId) -> bool {
+        match self.peers.iter_mut().find(|p| p.node_id == node_id) {
+            Some(peer) => {
+                peer.last_connected = Some(Utc::now());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remembers endpoints of direct session with known peer.
+    /// Returns false, if peer isn't in the address book or endpoints didn't change.
+    pub fn set_endpoints(&mut self, node_id: NodeId, endpoints: Vec<SocketAddr>) -> bool {
+        match self.peers.iter_mut().find(|p| p.node_id == node_id) {
+            Some(peer) if peer.endpoints != endpoints => {
+                peer.endpoints = endpoints;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Reconnection attempts of single static peer.
+#[derive(Clone, Copy, Debug)]
+struct Backoff {
+    failures: u32,
+    next_attempt: Instant,
+}
+
+impl Backoff {
+    fn new(now: Instant) -> Self {
+        Backoff {
+            failures: 0,
+            next_attempt: now,
+        }
+    }
+
+    /// Delay doubles with every failed attempt, up to `MAX_BACKOFF`.
+    fn failed(&mut self, now: Instant) {
+        let delay = MIN_BACKOFF
+            .checked_mul(2u32.saturating_pow(self.failures))
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF);
+        self.failures = self.failures.saturating_add(1);
+        self.next_attempt = now + delay;
+    }
+}
+
+/// Loads address book. Static peers aren't connected, until this function succeeds.
+pub(crate) fn init(path: &Path) -> anyhow::Result<()> {
+    let book = AddressBook::load(path)?;
+    log::info!(
+        "Net address book loaded {} peer(s) ({} static) from {}",
+        book.peers.len(),
+        book.static_peers().len(),
+        path.display()
+    );
+    *ADDRESS_BOOK.lock().unwrap() = book;
+    Ok(())
+}
+
+/// Updates address book with new sessions and endpoints of direct sessions
+/// with known peers.
+fn update_sessions(
+    established: impl IntoIterator<Item = NodeId>,
+    endpoints: HashMap<NodeId, Vec<SocketAddr>>,
+) {
+    let mut book = ADDRESS_BOOK.lock().unwrap();
+    let mut updated = book.clone();
+    let mut changed = false;
+    for node_id in established {
+        changed |= updated.connected(node_id);
+    }
+    for (node_id, endpoints) in endpoints {
+        changed |= updated.set_endpoints(node_id, endpoints);
+    }
+    if !changed {
+        return;
+    }
+    match updated.save() {
+        Ok(()) => *book = updated,
+        Err(e) => log::warn!("Failed to save net address book: {e}"),
+    }
+}
+
+/// Remote addresses of direct sessions by Node id.
+async fn direct_endpoints(client: &Client) -> HashMap<NodeId, Vec<SocketAddr>> {
+    let mut endpoints: HashMap<NodeId, Vec<SocketAddr>> = HashMap::new();
+    for session in client.sessions().await {
+        if let Some(node_id) = client.remote_id(&session.remote).await {
+            if client.is_p2p(node_id).await {
+                endpoints.entry(node_id).or_default().push(session.remote);
+            }
+        }
+    }
+    endpoints
+}
+
+fn modify<T>(f: impl FnOnce(&mut AddressBook) -> anyhow::Result<T>) -> Result<T, GenericNetError> {
+    ADDRESS_BOOK
+        .lock()
+        .unwrap()
+        .modify(f)
+        .map_err(|e| GenericNetError(e.to_string()))
+}
+
+pub(crate) fn bind_service(client: Client) {
+    let _ = bus::bind(model::BUS_ID, move |_: model::ListPeers| {
+        let client = client.clone();
+        async move {
+            let peers = ADDRESS_BOOK.lock().unwrap().peers();
+            let connected = client.connected_nodes().await;
+
+            let mut statuses = Vec::with_capacity(peers.len());
+            for peer in peers {
+                let is_connected = connected.iter().any(|(id, _)| *id == peer.node_id);
+                let is_p2p = is_connected && client.is_p2p(peer.node_id).await;
+                statuses.push(PeerStatus {
+                    peer,
+                    connected: is_connected,
+                    is_p2p,
+                });
+            }
+            Ok::<_, GenericNetError>(statuses)
+        }
+    });
+    let _ = bus::bind(model::BUS_ID, |msg: model::AddPeer| async move {
+        modify(|book| {
+            book.add(msg.peer);
+            Ok(())
+        })
+    });
+    let _ = bus::bind(model::BUS_ID, |msg: model::RemovePeer| async move {
+        modify(|book| book.remove(msg.node_id))
+    });
+}
+
+/// Records sessions established with known peers and endpoints of direct
+/// sessions. Connects static peers from
+/// the address book and reconnects them with backoff, when session is lost.
+/// Peers in `p2p` mode connected through relay are disconnected and connected
+/// again, until `MAX_P2P_ATTEMPTS` attempts fail.
+pub(crate) async fn keep_static_peers(client: Client) {
+    let mut backoffs: HashMap<NodeId, Backoff> = HashMap::new();
+    let mut last_connected: HashSet<NodeId> = HashSet::new();
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let connected: HashSet<NodeId> = client
+            .connected_nodes()
+            .await
+            .into_iter()
+            .map(|(node_id, _)| node_id)
+            .collect();
+        let endpoints = direct_endpoints(&client).await;
+        update_sessions(connected.difference(&last_connected).copied(), endpoints);
+        last_connected = connected;
+
+        let peers = ADDRESS_BOOK.lock().unwrap().static_peers();
+        backoffs.retain(|node_id, _| peers.iter().any(|p| p.node_id == *node_id));
+
+        let now = Instant::now();
+        let mut due = Vec::new();
+
+        for peer in peers {
+            let is_connected = last_connected.contains(&peer.node_id);
+            let satisfied =
+                is_connected && (peer.mode != PeerMode::P2p || client.is_p2p(peer.node_id).await);
+            if satisfied {
+                backoffs.remove(&peer.node_id);
+                continue;
+            }
+
+            let backoff = backoffs
+                .entry(peer.node_id)
+                .or_insert_with(|| Backoff::new(now));
+            if is_connected && backoff.failures >= MAX_P2P_ATTEMPTS {
+                continue;
+            }
+            if backoff.next_attempt <= now {
+                due.push((peer, is_connected));
+            }
+        }
+
+        let results = join_all(due.into_iter().map(|(peer, is_connected)| {
+            let client = client.clone();
+            async move {
+                let result = reconnect(client, &peer, is_connected).await;
+                (peer.node_id, peer.mode, result)
+            }
+        }))
+        .await;
+
+        let now = Instant::now();
+        for (node_id, mode, result) in results {
+            match result {
+                Ok(()) => {
+                    backoffs.remove(&node_id);
+                }
+                Err(e) => {
+                    let backoff = backoffs.entry(node_id).or_insert_with(|| Backoff::new(now));
+                    backoff.failed(now);
+                    log::debug!(
+                        "Connecting static peer [{node_id}] failed ({} attempt(s)): {e}",
+                        backoff.failures
+                    );
+                    if mode == PeerMode::P2p && backoff.failures == MAX_P2P_ATTEMPTS {
+                        log::info!(
+                            "Unable to establish p2p session with static peer [{node_id}]. Keeping relayed session"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn reconnect(client: Client, peer: &Peer, is_connected: bool) -> anyhow::Result<()> {
+    if is_connected {
+        log::info!(
+            "Static peer [{}] is connected through relay. Reconnecting to establish p2p session",
+            peer.node_id
+        );
+        client.disconnect(peer.node_id).await?;
+    }
+
+    let msg = model::Connect {
+        node: peer.node_id,
+        keep: true,
+        reliable_channel: true,
+        transfer_channel: false,
+    };
+    super::cli::connect(client.clone(), msg).await?;
+
+    if peer.mode == PeerMode::P2p && !client.is_p2p(peer.node_id).await {
+        anyhow::bail!("only relayed session could be established");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(n: u8) -> NodeId {
+        NodeId::from(&[n; 20][..])
+    }
+
+    fn peer(n: u8, is_static: bool) -> Peer {
+        Peer {
+            node_id: node(n),
+            is_static,
+            mode: PeerMode::Relay,
+            endpoints: Vec::new(),
+            last_connected: None,
+        }
+    }
+
+    #[test]
+    fn test_add_known_peer_keeps_endpoints() {
+        let endpoint: SocketAddr = "1.2.3.4:7464".parse().unwrap();
+        let mut book = AddressBook::default();
+        book.add(peer(1, false));
+        book.add(peer(2, true));
+        assert!(book.connected(node(1)));
+        assert!(!book.connected(node(3)));
+        assert!(book.set_endpoints(node(1), vec![endpoint]));
+        assert!(!book.set_endpoints(node(1), vec![endpoint]));
+        assert!(!book.set_endpoints(node(3), vec![endpoint]));
+
+        book.add(Peer {
+            mode: PeerMode::P2p,
+            ..peer(1, true)
+        });
+
+        let peers = book.peers();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].mode, PeerMode::P2p);
+        assert_eq!(peers[0].endpoints, vec![endpoint]);
+        assert!(peers[0].last_connected.is_some());
+        assert_eq!(book.static_peers().len(), 2);
+
+        assert_eq!(book.remove(node(2)).unwrap().node_id, node(2));
+        assert!(book.remove(node(2)).is_err());
+    }
+
+    #[test]
+    fn test_load_save_round_trip() {
+        let path = std::env::temp_dir().join(format!("ya-net-{}-{PEERS_FILE}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut book = AddressBook::load(&path).unwrap();
+        assert!(book.peers().is_empty());
+        book.add(peer(1, true));
+        book.add(Peer {
+            mode: PeerMode::P2p,
+            ..peer(2, false)
+        });
+        book.connected(node(2));
+        book.set_endpoints(node(2), vec!["1.2.3.4:7464".parse().unwrap()]);
+        book.save().unwrap();
+
+        let loaded = AddressBook::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.peers(), book.peers());
+        assert_eq!(loaded.static_peers(), vec![peer(1, true)]);
+    }
+
+    #[test]
+    fn test_modify_keeps_peers_when_save_fails() {
+        let path = std::env::temp_dir()
+            .join(format!("ya-net-{}-missing", std::process::id()))
+            .join(PEERS_FILE);
+        let mut book = AddressBook::load(&path).unwrap();
+        book.peers.push(peer(1, true));
+
+        let result = book.modify(|book| {
+            book.add(peer(2, true));
+            book.remove(node(1))
+        });
+        assert!(result.is_err());
+        assert_eq!(book.peers(), vec![peer(1, true)]);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let now = Instant::now();
+        let mut backoff = Backoff::new(now);
+        assert_eq!(backoff.next_attempt, now);
+
+        let delays: Vec<_> = (0..10)
+            .map(|_| {
+                backoff.failed(now);
+                backoff.next_attempt - now
+            })
+            .collect();
+        assert_eq!(delays[0], MIN_BACKOFF);
+        assert_eq!(delays[1], MIN_BACKOFF * 2);
+        assert_eq!(delays[3], MIN_BACKOFF * 8);
+        assert_eq!(delays[9], MAX_BACKOFF);
+    }
+}
@@ -1,5 +1,7 @@
 use actix_web::{HttpResponse, Responder, Scope};
+use serde::Serialize;
 use ya_client_model::net::{Status, NET_API_V2_NET_PATH};
+use ya_core_model::net::local::TrafficStats;
 use ya_service_bus::{typed, RpcEndpoint};
 
 use crate::error::{NetError, Result};
@@ -8,6 +10,15 @@ pub fn web_scope() -> Scope {
     actix_web::web::scope(NET_API_V2_NET_PATH).service(get_info)
 }
 
+/// Status extended with traffic forwarded per Node and per service.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusWithTraffic {
+    #[serde(flatten)]
+    status: Status,
+    traffic: TrafficStats,
+}
+
 #[actix_web::get("/status")]
 async fn get_info() -> Result<impl Responder> {
     let s = typed::service(ya_core_model::net::local::BUS_ID)
@@ -21,5 +32,8 @@ async fn get_info() -> Result<impl Responder> {
         public_ip: s.public_address.map(|addr| addr.to_string()),
         sessions: s.sessions,
     };
-    Ok(HttpResponse::Ok().json(status))
+    Ok(HttpResponse::Ok().json(StatusWithTraffic {
+        status,
+        traffic: crate::traffic::stats(),
+    }))
 }
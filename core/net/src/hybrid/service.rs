@@ -8,6 +8,7 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
 use std::sync::Arc;
 use std::task::Poll;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context as AnyhowContext};
 use futures::channel::{mpsc, oneshot};
@@ -22,7 +23,7 @@ use ya_core_model::identity::event::IdentityEvent;
 use ya_core_model::net::local::{
     BindBroadcastError, BroadcastMessage, NewNeighbour, SendBroadcastMessage, SendBroadcastStub,
 };
-use ya_core_model::net::GenericNetError;
+use ya_core_model::net::{GenericNetError, GsbCapabilities, RemoteEndpoint};
 use ya_core_model::{identity, net, NodeId};
 use ya_relay_client::channels::{ForwardReceiver, ForwardSender, PrefixedStream};
 use ya_relay_client::crypto::CryptoProvider;
@@ -32,6 +33,7 @@ use ya_sb_proto::codec::GsbMessage;
 use ya_sb_proto::CallReplyCode;
 use ya_sb_util::RevPrefixes;
 use ya_service_bus::timeout::IntoTimeoutFuture;
+use ya_service_bus::typed::ServiceBinder;
 use ya_service_bus::untyped::{Fn4HandlerExt, Fn4StreamHandlerExt};
 use ya_service_bus::{
     serialization, typed, untyped as local_bus, Error, ResponseChunk, RpcEndpoint, RpcMessage,
@@ -40,13 +42,17 @@ use ya_utils_networking::resolver;
 
 use crate::bcast::BCastService;
 use crate::config::Config;
+use crate::firewall;
 use crate::hybrid::codec;
 use crate::hybrid::codec::encode_message;
 use crate::hybrid::crypto::IdentityCryptoProvider;
 use crate::service::NET_TYPE;
+use crate::traffic::{self, Direction};
 use crate::{broadcast, NetType};
 
 const DEFAULT_NET_RELAY_HOST: &str = "127.0.0.1:7464";
+const CAPABILITIES_TIMEOUT: Duration = Duration::from_secs(10);
+const CAPABILITIES_TTL: Duration = Duration::from_secs(600);
 
 type BusSender = mpsc::Sender<ResponseChunk>;
 type BusReceiver = mpsc::Receiver<ResponseChunk>;
@@ -118,10 +124,15 @@ pub async fn start_network(
     log::info!("Starting network (hybrid) with identity: {default_id}");
 
     let broadcast_size = config.broadcast_size;
+    let compression_threshold = match config.compression {
+        true => Some(config.compression_threshold),
+        false => None,
+    };
     let crypto = IdentityCryptoProvider::new(default_id);
     let client = build_client(config, crypto.clone()).await?;
 
     super::cli::bind_service(client.clone());
+    super::peers::bind_service(client.clone());
 
     let receiver = client.clone().forward_receiver().await.unwrap();
     let mut services: HashSet<_> = Default::default();
@@ -130,7 +141,8 @@ pub async fn start_network(
         services.insert(net::net_service(id));
         services.insert(net::net_transfer_service(id));
     });
-    let state = State::new(ids, services);
+    let state = State::new(ids, services, compression_threshold);
+    bind_capabilities(state.clone());
 
     // outbound traffic
     let net_handler = || {
@@ -213,6 +225,7 @@ pub async fn start_network(
     );
 
     tokio::task::spawn_local(forward_handler(client.clone(), receiver, state.clone()));
+    tokio::task::spawn_local(super::peers::keep_static_peers(client.clone()));
 
     bind_broadcast_handlers(client.clone(), broadcast_size);
     bind_identity_event_handler(client.clone(), crypto).await;
@@ -519,6 +532,7 @@ fn forward_bus_to_net(
     transport: TransportType,
 ) -> BusReceiver {
     let address = address.to_string();
+    let service = traffic::service(&address);
     let state = state.clone();
     let request_id = gen_id().to_string();
 
@@ -534,9 +548,10 @@ fn forward_bus_to_net(
         msg.to_vec(),
         false,
     ) {
-        Ok(vec) => vec,
+        Ok(vec) => state.compress(caller_id, remote_id, vec),
         Err(err) => {
             log::debug!("Forward bus->net ({caller_id} -> {remote_id}), address: {address}: invalid request: {err}");
+            traffic::record_error(Some(remote_id), &service);
             handler_reply_bad_request(request_id, format!("Net: invalid request: {err}"), tx);
             return rx;
         }
@@ -559,14 +574,20 @@ fn forward_bus_to_net(
             msg.len()
         );
 
+        let len = msg.len();
         match state.forward_sink(client, remote_id, transport).await {
-            Ok(mut sink) => {
-                let _ = sink.send(msg.into()).await.map_err(|_| {
+            Ok(mut sink) => match sink.send(msg.into()).await {
+                Ok(_) => traffic::record(Some(remote_id), &service, Direction::Outbound, len),
+                Err(_) => {
+                    traffic::record_error(Some(remote_id), &service);
+                    state.forget_capabilities(remote_id);
                     let err = "Net: error sending message: session closed".to_string();
                     handler_reply_service_err(request_id, err, tx);
-                });
-            }
+                }
+            },
             Err(error) => {
+                traffic::record_error(Some(remote_id), &service);
+                state.forget_capabilities(remote_id);
                 let err = format!("Net: error forwarding message: {}", error);
                 handler_reply_service_err(request_id, err, tx);
             }
@@ -586,6 +607,7 @@ fn push_bus_to_net(
     transport: TransportType,
 ) {
     let address = address.to_string();
+    let service = traffic::service(&address);
     let state = state.clone();
     let request_id = gen_id().to_string();
 
@@ -600,9 +622,10 @@ fn push_bus_to_net(
         msg.to_vec(),
         true,
     ) {
-        Ok(vec) => vec,
+        Ok(vec) => state.compress(caller_id, remote_id, vec),
         Err(err) => {
             log::debug!("Push bus->net ({caller_id} -> {remote_id}), address: {address}: invalid request: {err}");
+            traffic::record_error(Some(remote_id), &service);
             return;
         }
     };
@@ -613,16 +636,22 @@ fn push_bus_to_net(
             msg.len()
         );
 
+        let len = msg.len();
         match state
             .forward_sink(client.clone(), remote_id, transport)
             .await
         {
-            Ok(mut sink) => {
-                let _ = sink.send(msg.into()).await.map_err(|_| {
+            Ok(mut sink) => match sink.send(msg.into()).await {
+                Ok(_) => traffic::record(Some(remote_id), &service, Direction::Outbound, len),
+                Err(_) => {
+                    traffic::record_error(Some(remote_id), &service);
+                    state.forget_capabilities(remote_id);
                     log::debug!("Net: error sending message: session closed");
-                });
-            }
+                }
+            },
             Err(error) => {
+                traffic::record_error(Some(remote_id), &service);
+                state.forget_capabilities(remote_id);
                 log::debug!("Net: error forwarding message: {}", error);
             }
         };
@@ -644,6 +673,7 @@ fn broadcast_handler(
         let stub: SendBroadcastStub = serialization::from_slice(&message)
             .map_err(|e| Error::GsbFailure(format!("Invalid broadcast message: {e}")))?;
 
+        let service = traffic::broadcast(&stub.topic);
         let request = GsbMessage::BroadcastRequest(ya_sb_proto::BroadcastRequest {
             //data: serialization::to_vec(&message)?,
             data: message,
@@ -652,11 +682,16 @@ fn broadcast_handler(
         });
 
         let payload = encode_message(request).map_err(|e| Error::EncodingProblem(e.to_string()))?;
+        let len = payload.len();
 
         client
             .broadcast(payload, broadcast_size)
             .await
-            .map_err(|e| Error::GsbFailure(format!("Broadcast failed: {e}")))?;
+            .map_err(|e| {
+                traffic::record_error(None, &service);
+                Error::GsbFailure(format!("Broadcast failed: {e}"))
+            })?;
+        traffic::record(None, &service, Direction::Outbound, len);
 
         Ok(serialization::to_vec(&Ok::<(), ()>(())).unwrap())
     }
@@ -833,13 +868,27 @@ fn handle_push(
 
     log::debug!("Handle push request {request_id} to {address} from {remote_id}");
 
-    let fut = match state.get_public_service(address.as_str()) {
+    let public_address = state.get_public_service(address.as_str());
+    let service = traffic::inbound_service(public_address.as_deref());
+    traffic::record(
+        Some(remote_id),
+        &service,
+        Direction::Inbound,
+        request.data.len(),
+    );
+
+    let fut = match public_address {
         Some(address) => {
+            if let Err(reason) = firewall::check(remote_id, &address) {
+                traffic::record_error(Some(remote_id), &service);
+                return Err(Error::GsbBadRequest(reason).into());
+            }
             log::trace!("Handle push request: calling: {address}");
             local_bus::push(&address, &request.caller, &request.data)
         }
         None => {
             log::trace!("Handle push request failed: unknown address: {address}");
+            traffic::record_error(Some(remote_id), &service);
             let err = Error::GsbBadRequest(format!("Unknown address: {address}"));
             return Err(err.into());
         }
@@ -877,16 +926,37 @@ fn handle_request(
 
     log::debug!("Handle request {request_id} to {address} from {remote_id}");
 
+    // Replies are compressed only if we've already learned, that remote Node supports it.
+    // Caller id isn't verified, so it can't decide about the session.
+    let compress = state.compression_supported(remote_id, Instant::now());
+
+    let public_address = state.get_public_service(address.as_str());
+    let service = traffic::inbound_service(public_address.as_deref());
+    traffic::record(
+        Some(remote_id),
+        &service,
+        Direction::Inbound,
+        request.data.len(),
+    );
+
     let eos = Rc::new(AtomicBool::new(false));
     let eos_map = eos.clone();
 
-    let stream = match state.get_public_service(address.as_str()) {
-        Some(address) => {
-            log::trace!("Handle request: calling: {address}");
-            local_bus::call_stream(&address, &request.caller, &request.data).left_stream()
-        }
+    let stream = match public_address {
+        Some(address) => match firewall::check(remote_id, &address) {
+            Ok(()) => {
+                log::trace!("Handle request: calling: {address}");
+                local_bus::call_stream(&address, &request.caller, &request.data).left_stream()
+            }
+            Err(reason) => {
+                traffic::record_error(Some(remote_id), &service);
+                let err = Error::GsbBadRequest(reason);
+                futures::stream::once(futures::future::err(err)).right_stream()
+            }
+        },
         None => {
             log::trace!("Handle request failed: unknown address: {address}");
+            traffic::record_error(Some(remote_id), &service);
             let err = Error::GsbBadRequest(format!("Unknown address: {address}"));
             futures::stream::once(futures::future::err(err)).right_stream()
         }
@@ -918,6 +988,10 @@ fn handle_request(
     .filter_map(move |reply| {
         let filtered = match codec::encode_message(reply) {
             Ok(vec) => {
+                let vec = match compress {
+                    Some(threshold) => codec::compress_message(vec, threshold),
+                    None => vec,
+                };
                 log::debug!(
                     "Handle request {request_id_filter}: reply chunk ({} B)",
                     vec.len()
@@ -939,7 +1013,12 @@ fn handle_request(
 
             //stream.forward(sink).await?;
             while let Some(item) = stream.next().await {
-                sink.send(item?.into()).await.ok();
+                let item = item?;
+                let len = item.len();
+                match sink.send(item.into()).await {
+                    Ok(_) => traffic::record(Some(remote_id), &service, Direction::Outbound, len),
+                    Err(_) => traffic::record_error(Some(remote_id), &service),
+                }
                 log::debug!("Handled request: {request_id_sent} from: {caller_id}");
             }
 
@@ -985,10 +1064,19 @@ fn handle_reply(
         None => anyhow::bail!("invalid reply request id: {}", reply.request_id),
     };
 
+    let service = traffic::service(&request.address);
+    traffic::record(
+        Some(remote_id),
+        &service,
+        Direction::Inbound,
+        reply.data.len(),
+    );
+
     let request_id = reply.request_id.clone();
     let data = if reply.code == CallReplyCode::CallReplyOk as i32 {
         reply.data
     } else {
+        traffic::record_error(Some(remote_id), &service);
         codec::encode_reply(reply).context("Unable to encode reply {request_id}")?
     };
 
@@ -1027,10 +1115,17 @@ fn handle_broadcast(
     tokio::task::spawn_local(async move {
         let data = request.data;
         let topic = request.topic;
+        let endpoints = BCAST.resolve(&topic).await;
 
-        for endpoint in BCAST
-            .resolve(&topic)
-            .await
+        let service = traffic::inbound_broadcast(&topic, !endpoints.is_empty());
+        traffic::record(Some(remote_id), &service, Direction::Inbound, data.len());
+        if let Err(reason) = firewall::check_broadcast(remote_id, &topic) {
+            traffic::record_error(Some(remote_id), &service);
+            log::debug!("Broadcast from [{remote_id}] dropped: {reason}");
+            return;
+        }
+
+        for endpoint in endpoints
             .into_iter()
             .map(|endpoint| endpoint.as_ref().to_string())
         {
@@ -1060,19 +1155,125 @@ struct StateInner {
     routes: HashMap<NetSinkKey, NetSender>,
     ids: HashSet<NodeId>,
     services: HashSet<String>,
+    /// Messages smaller than threshold are not compressed. Compression is disabled, if not set.
+    compression_threshold: Option<usize>,
+    capabilities: HashMap<NodeId, Capabilities>,
+}
+
+/// Result of capabilities exchange with remote Node. Exchange is repeated after
+/// `CAPABILITIES_TTL` or when session is closed, because Node might have been
+/// restarted with other version meanwhile.
+#[derive(Clone, Copy, Debug)]
+enum Capabilities {
+    Pending,
+    Compression(Instant),
+    /// Node doesn't support compression or didn't answer.
+    Uncompressed(Instant),
+}
+
+impl Capabilities {
+    fn expired(&self, now: Instant) -> bool {
+        match self {
+            Capabilities::Pending => false,
+            Capabilities::Compression(since) | Capabilities::Uncompressed(since) => {
+                now.saturating_duration_since(*since) >= CAPABILITIES_TTL
+            }
+        }
+    }
 }
 
 impl State {
-    fn new(ids: impl IntoIterator<Item = NodeId>, services: HashSet<String>) -> Self {
+    fn new(
+        ids: impl IntoIterator<Item = NodeId>,
+        services: HashSet<String>,
+        compression_threshold: Option<usize>,
+    ) -> Self {
         Self {
             inner: Rc::new(RefCell::new(StateInner {
                 ids: ids.into_iter().collect(),
                 services,
+                compression_threshold,
                 ..Default::default()
             })),
         }
     }
 
+    /// Returns compression threshold, if `remote_id` is known to decode compressed messages.
+    fn compression_supported(&self, remote_id: NodeId, now: Instant) -> Option<usize> {
+        let inner = self.inner.borrow();
+        match inner.capabilities.get(&remote_id) {
+            Some(c @ Capabilities::Compression(_)) if !c.expired(now) => {
+                inner.compression_threshold
+            }
+            _ => None,
+        }
+    }
+
+    /// Stores result of capabilities exchange. Node, which didn't answer,
+    /// doesn't receive compressed messages.
+    fn set_capabilities(&self, remote_id: NodeId, remote: Option<GsbCapabilities>, now: Instant) {
+        let compression = remote
+            .map(|remote| {
+                remote
+                    .compression
+                    .iter()
+                    .any(|c| c == codec::COMPRESSION_ZSTD)
+            })
+            .unwrap_or(false);
+        let capabilities = match compression {
+            true => Capabilities::Compression(now),
+            false => Capabilities::Uncompressed(now),
+        };
+        log::debug!("Capabilities of Node [{remote_id}]: {capabilities:?}");
+
+        let mut inner = self.inner.borrow_mut();
+        inner.capabilities.insert(remote_id, capabilities);
+    }
+
+    /// Capabilities are exchanged again before next message to the Node. Called when
+    /// session with the Node is closed.
+    fn forget_capabilities(&self, remote_id: NodeId) {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(Capabilities::Compression(_) | Capabilities::Uncompressed(_)) =
+            inner.capabilities.get(&remote_id)
+        {
+            inner.capabilities.remove(&remote_id);
+        }
+    }
+
+    /// Compresses message sent to `remote_id`, if it supports compression.
+    /// Until capabilities of remote Node are known, messages are sent uncompressed
+    /// and capabilities exchange is started.
+    fn compress(&self, caller_id: NodeId, remote_id: NodeId, msg: Vec<u8>) -> Vec<u8> {
+        let now = Instant::now();
+        if let Some(threshold) = self.compression_supported(remote_id, now) {
+            return codec::compress_message(msg, threshold);
+        }
+
+        let exchange = {
+            let mut inner = self.inner.borrow_mut();
+            let expired = match inner.capabilities.get(&remote_id) {
+                None => true,
+                Some(capabilities) => capabilities.expired(now),
+            };
+            if inner.compression_threshold.is_some() && expired {
+                inner.capabilities.insert(remote_id, Capabilities::Pending);
+                true
+            } else {
+                false
+            }
+        };
+        if exchange {
+            tokio::task::spawn_local(exchange_capabilities(
+                self.clone(),
+                caller_id,
+                remote_id,
+                CAPABILITIES_TIMEOUT,
+            ));
+        }
+        msg
+    }
+
     async fn forward_sink(
         &self,
         client: Client,
@@ -1103,18 +1304,73 @@ impl State {
     }
 
     fn remove_sink(&self, key: &NetSinkKey) {
-        let mut inner = self.inner.borrow_mut();
-        inner.routes.remove(key);
+        self.inner.borrow_mut().routes.remove(key);
+        self.forget_capabilities(key.0);
     }
 }
 
+/// Asks remote Node for its capabilities. Nodes not handling `GsbCapabilities`
+/// reply with an error and keep receiving uncompressed messages.
+async fn exchange_capabilities(
+    state: State,
+    caller_id: NodeId,
+    remote_id: NodeId,
+    timeout: Duration,
+) {
+    let result = net::from(caller_id)
+        .to(remote_id)
+        .service(net::DIAGNOSTIC)
+        .send(local_capabilities(true))
+        .timeout(Some(timeout))
+        .await;
+
+    let remote = match result {
+        Ok(Ok(Ok(remote))) => Some(remote),
+        Ok(Ok(Err(e))) => {
+            log::debug!("Capabilities exchange with Node [{remote_id}] failed: {e}");
+            None
+        }
+        Ok(Err(e)) => {
+            log::debug!("Capabilities exchange with Node [{remote_id}] failed: {e}");
+            None
+        }
+        Err(_) => {
+            log::debug!("Capabilities exchange with Node [{remote_id}] timed out");
+            None
+        }
+    };
+    state.set_capabilities(remote_id, remote, Instant::now());
+}
+
+fn local_capabilities(compression: bool) -> GsbCapabilities {
+    GsbCapabilities {
+        compression: match compression {
+            true => vec![codec::COMPRESSION_ZSTD.to_string()],
+            false => Vec::new(),
+        },
+    }
+}
+
+fn bind_capabilities(state: State) {
+    ServiceBinder::new(net::DIAGNOSTIC, &(), state).bind_with_processor(
+        move |_, state: State, caller: String, msg: GsbCapabilities| async move {
+            // Node asks with its own capabilities, e.g. after restart, so they
+            // don't have to be exchanged in the other direction.
+            if let Ok(caller_id) = NodeId::from_str(&caller) {
+                state.set_capabilities(caller_id, Some(msg), Instant::now());
+            }
+            let compression = state.inner.borrow().compression_threshold.is_some();
+            Ok(local_capabilities(compression))
+        },
+    );
+}
+
 #[derive(Clone)]
 struct Request<S: Clone> {
     #[allow(unused)]
     caller_id: NodeId,
     #[allow(unused)]
     remote_id: NodeId,
-    #[allow(unused)]
     address: String,
     tx: S,
 }
@@ -1141,7 +1397,7 @@ fn handler_reply_err(
     });
 }
 
-fn parse_net_to_addr(addr: &str) -> anyhow::Result<(NodeId, String)> {
+pub(crate) fn parse_net_to_addr(addr: &str) -> anyhow::Result<(NodeId, String)> {
     const ADDR_CONST: usize = 6;
 
     let mut it = addr.split('/').fuse().skip(1).peekable();
@@ -1159,7 +1415,7 @@ fn parse_net_to_addr(addr: &str) -> anyhow::Result<(NodeId, String)> {
     Ok((to_id, format!("{}{}", prefix, addr)))
 }
 
-fn parse_from_to_addr(addr: &str) -> anyhow::Result<(NodeId, NodeId, String)> {
+pub(crate) fn parse_from_to_addr(addr: &str) -> anyhow::Result<(NodeId, NodeId, String)> {
     const ADDR_CONST: usize = 10;
 
     let mut it = addr.split('/').fuse().skip(1).peekable();
@@ -1225,6 +1481,102 @@ mod tests {
     use super::*;
     use test_case::test_case;
 
+    fn node(n: u8) -> NodeId {
+        NodeId::from(&[n; 20][..])
+    }
+
+    fn compression_state() -> State {
+        State::new(vec![node(1)], HashSet::new(), Some(1024))
+    }
+
+    fn capabilities(state: &State, remote_id: NodeId) -> Option<Capabilities> {
+        state.inner.borrow().capabilities.get(&remote_id).copied()
+    }
+
+    #[test]
+    fn test_capabilities_expire_and_are_forgotten() {
+        let state = compression_state();
+        let now = Instant::now();
+
+        state.set_capabilities(node(2), Some(local_capabilities(true)), now);
+        assert_eq!(state.compression_supported(node(2), now), Some(1024));
+        assert_eq!(
+            state.compression_supported(node(2), now + CAPABILITIES_TTL),
+            None
+        );
+
+        state.set_capabilities(node(3), Some(local_capabilities(false)), now);
+        assert_eq!(state.compression_supported(node(3), now), None);
+        state.set_capabilities(node(4), None, now);
+        assert_eq!(state.compression_supported(node(4), now), None);
+
+        state.forget_capabilities(node(2));
+        assert!(capabilities(&state, node(2)).is_none());
+        assert_eq!(state.compression_supported(node(2), now), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_exchange_capabilities() {
+        let state = compression_state();
+        bind_capabilities(state.clone());
+
+        let remote = typed::service(net::DIAGNOSTIC)
+            .send(local_capabilities(true))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(remote, local_capabilities(true));
+        state.set_capabilities(node(2), Some(remote), Instant::now());
+        assert!(matches!(
+            capabilities(&state, node(2)),
+            Some(Capabilities::Compression(_))
+        ));
+
+        // Node asks for our capabilities after restart. Its capabilities are
+        // kept, so asking it back doesn't start another exchange.
+        typed::service(net::DIAGNOSTIC)
+            .send_as(node(2), local_capabilities(false))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            capabilities(&state, node(2)),
+            Some(Capabilities::Uncompressed(_))
+        ));
+        let msg = state.compress(node(1), node(2), vec![0; 4096]);
+        assert_eq!(msg, vec![0; 4096]);
+        assert!(matches!(
+            capabilities(&state, node(2)),
+            Some(Capabilities::Uncompressed(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_exchange_capabilities_fallback() {
+        let state = compression_state();
+        let failing = format!("/from/{}/to/{}/diagnostic/net", node(1), node(2));
+        let _ = typed::bind(&failing, |_: GsbCapabilities| async move {
+            Err::<GsbCapabilities, _>(GenericNetError("unknown message".to_string()))
+        });
+        let silent = format!("/from/{}/to/{}/diagnostic/net", node(1), node(3));
+        let _ = typed::bind(&silent, |_: GsbCapabilities| {
+            futures::future::pending::<Result<GsbCapabilities, GenericNetError>>()
+        });
+
+        let timeout = Duration::from_millis(100);
+        exchange_capabilities(state.clone(), node(1), node(2), timeout).await;
+        exchange_capabilities(state.clone(), node(1), node(3), timeout).await;
+        for remote_id in [node(2), node(3)] {
+            assert!(matches!(
+                capabilities(&state, remote_id),
+                Some(Capabilities::Uncompressed(_))
+            ));
+        }
+
+        let msg = vec![0u8; 4096];
+        assert_eq!(state.compress(node(1), node(2), msg.clone()), msg);
+    }
+
     #[test_case(
         "/net/0x95369fc6fd02afeca110b9c32a21fb8ad899ee0a/vpn/VpnControl",
         NodeId::from_str("0x95369fc6fd02afeca110b9c32a21fb8ad899ee0a").unwrap();
@@ -8,8 +8,11 @@ pub use service::{bind_broadcast_with_caller, broadcast, Net};
 mod bcast;
 pub mod central;
 pub mod hybrid;
+pub mod local;
 mod service;
 
 mod cli;
 mod config;
 mod error;
+mod firewall;
+mod traffic;
@@ -0,0 +1,183 @@
+use futures::future::join_all;
+use std::time::{Duration, Instant};
+
+use ya_core_model::net as ya_net;
+use ya_core_model::net::local::{FindNodeResponse, GsbPingResponse, StatusError};
+use ya_core_model::net::{
+    local as model, GenericNetError, GsbRemotePing, RemoteEndpoint, DIAGNOSTIC,
+};
+use ya_core_model::NodeId;
+use ya_service_bus::timeout::IntoTimeoutFuture;
+use ya_service_bus::typed::ServiceBinder;
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+use crate::local::service::State;
+
+const SESSION_TYPE: &str = "local";
+
+pub(crate) fn bind_service(base_state: State) {
+    let state = base_state.clone();
+    let _ = bus::bind(model::BUS_ID, move |ping: model::GsbPing| {
+        cli_ping(state.clone(), ping.nodes)
+    });
+
+    let state = base_state.clone();
+    let _ = bus::bind(model::BUS_ID, move |msg: model::Connect| {
+        let result = find_node(&state, &msg.node).ok_or_else(|| {
+            GenericNetError(format!(
+                "Node {} isn't connected. Local net connects only to Nodes from YA_NET_LOCAL_PEERS",
+                msg.node
+            ))
+        });
+        futures::future::ready(result)
+    });
+
+    let state = base_state.clone();
+    let _ = bus::bind(model::BUS_ID, move |msg: model::Disconnect| {
+        let result = match state.find_peer(&msg.node) {
+            Some((remote, _)) => {
+                state.remove_peer(&remote);
+                Ok(())
+            }
+            None => Err(GenericNetError(format!(
+                "Node {} isn't connected",
+                msg.node
+            ))),
+        };
+        futures::future::ready(result)
+    });
+
+    let state = base_state.clone();
+    let _ = bus::bind(model::BUS_ID, move |_: model::Shutdown| {
+        state.remove_peers();
+        futures::future::ok(())
+    });
+
+    ServiceBinder::new(DIAGNOSTIC, &(), ())
+        .bind(move |_, _caller: String, _msg: GsbRemotePing| async move { Ok(GsbRemotePing {}) });
+
+    let state = base_state.clone();
+    let _ = bus::bind(model::BUS_ID, move |_: model::Status| {
+        futures::future::ok(model::StatusResponse {
+            node_id: state.default_id(),
+            listen_address: state.listen_address(),
+            public_address: None,
+            sessions: state.peers().len(),
+            metrics: empty_metrics(),
+        })
+    });
+
+    let state = base_state.clone();
+    let _ = bus::bind(model::BUS_ID, move |_: model::Sessions| {
+        let now = Instant::now();
+        let sessions = state
+            .peers()
+            .into_iter()
+            .map(
+                |(remote, (node_ids, created, last_seen))| model::SessionResponse {
+                    node_id: node_ids.first().copied(),
+                    id: remote.to_string(),
+                    session_type: SESSION_TYPE.to_string(),
+                    remote_address: remote,
+                    seen: now - last_seen,
+                    duration: now - created,
+                    ping: Duration::default(),
+                    metrics: empty_metrics(),
+                },
+            )
+            .collect();
+        futures::future::ok(sessions)
+    });
+
+    let _ = bus::bind(model::BUS_ID, move |_: model::Sockets| {
+        futures::future::ok(Vec::new())
+    });
+
+    let state = base_state.clone();
+    let _ = bus::bind(model::BUS_ID, move |find: model::FindNode| {
+        let result = find
+            .node_id
+            .parse::<NodeId>()
+            .map_err(|e| StatusError::RuntimeException(e.to_string()))
+            .and_then(|node_id| {
+                find_node(&state, &node_id).ok_or_else(|| {
+                    StatusError::RuntimeException(format!("Node {} isn't connected", node_id))
+                })
+            });
+        futures::future::ready(result)
+    });
+
+    let state = base_state;
+    let _ = bus::bind(model::BUS_ID, move |list: model::ListNeighbours| {
+        let neighbours = state
+            .peers()
+            .into_values()
+            .flat_map(|(node_ids, _, _)| node_ids)
+            .take(list.size as usize)
+            .collect();
+        futures::future::ok(neighbours)
+    });
+}
+
+fn find_node(state: &State, node_id: &NodeId) -> Option<FindNodeResponse> {
+    let (remote, identities) = state.find_peer(node_id)?;
+    Some(FindNodeResponse {
+        identities,
+        endpoints: vec![remote],
+        seen: chrono::Utc::now().timestamp() as u64,
+        slot: 0,
+        encryption: Vec::new(),
+    })
+}
+
+fn empty_metrics() -> model::StatusMetrics {
+    model::StatusMetrics {
+        tx_total: 0,
+        tx_current: 0.,
+        tx_avg: 0.,
+        rx_total: 0,
+        rx_current: 0.,
+        rx_avg: 0.,
+    }
+}
+
+async fn cli_ping(state: State, nodes: Vec<NodeId>) -> Result<Vec<GsbPingResponse>, StatusError> {
+    let nodes = match nodes.is_empty() {
+        true => state
+            .peers()
+            .into_values()
+            .filter_map(|(node_ids, _, _)| node_ids.first().copied())
+            .collect(),
+        false => nodes,
+    };
+
+    let our_node_id = state.default_id();
+    let ping_timeout = Duration::from_secs(10);
+
+    // All transports use the same connection, so only Tcp ping is measured.
+    Ok(join_all(nodes.into_iter().map(|node_id| async move {
+        let before = Instant::now();
+        let result = ya_net::from(our_node_id)
+            .to(node_id)
+            .service(ya_net::DIAGNOSTIC)
+            .send(GsbRemotePing {})
+            .timeout(Some(ping_timeout))
+            .await;
+
+        let tcp_ping = match result {
+            Ok(Ok(Ok(_))) => Some(before.elapsed()),
+            _ => {
+                log::warn!("Failed to ping node: {node_id}");
+                None
+            }
+        };
+        GsbPingResponse {
+            node_id,
+            node_alias: None,
+            tcp_ping,
+            udp_ping: None,
+            is_p2p: true,
+        }
+    }))
+    .await)
+}
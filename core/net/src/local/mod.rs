@@ -0,0 +1,4 @@
+pub(crate) mod cli;
+mod service;
+
+pub use service::{start_network, Net};
@@ -0,0 +1,1321 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use anyhow::Context as AnyhowContext;
+use ethsign::Signature;
+use futures::channel::{mpsc, oneshot};
+use futures::prelude::*;
+use sha3::{Digest, Sha3_256};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::Framed;
+
+use ya_core_model::net::local::{SendBroadcastMessage, SendBroadcastStub};
+use ya_core_model::{identity, net, NodeId};
+use ya_sb_proto::codec::{GsbMessage, GsbMessageCodec};
+use ya_sb_proto::{CallReplyCode, CallReplyType};
+use ya_sb_util::RevPrefixes;
+use ya_service_bus::timeout::IntoTimeoutFuture;
+use ya_service_bus::untyped::{Fn4HandlerExt, Fn4StreamHandlerExt};
+use ya_service_bus::{
+    serialization, typed, untyped as local_bus, Error, ResponseChunk, RpcEndpoint, RpcMessage,
+};
+
+use crate::bcast::BCastService;
+use crate::config::Config;
+use crate::firewall;
+use crate::hybrid::codec;
+use crate::hybrid::{parse_from_to_addr, parse_net_to_addr};
+use crate::traffic::{self, Direction};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Connected Nodes send random challenge to each other. Identity is registered
+/// on the connection only with signature of the challenge made with its key.
+const CHALLENGE_ADDRESS: &str = "/local/net/challenge";
+const REGISTER_ADDRESS: &str = "/local/net/register";
+/// Challenges are hashed with this prefix before signing, so signatures can't be
+/// reused for anything else.
+const CHALLENGE_PREFIX: &[u8] = b"ya-net local challenge";
+
+/// Connected Nodes are pinged every `PING_INTERVAL` and disconnected,
+/// when nothing was received from them for `IDLE_TIMEOUT`.
+const PING_ADDRESS: &str = "/local/net/ping";
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Limits of messages queued for sending to a Node and of reply chunks queued
+/// for a caller, so slow Nodes and callers can't grow the queues without bounds.
+/// Calls, broadcasts and replies above the limits are dropped, while replies
+/// to calls from the Node wait for free space.
+const PEER_QUEUE_SIZE: usize = 1024;
+const REPLY_QUEUE_SIZE: usize = 64;
+
+type ReplySender = mpsc::Sender<Result<ResponseChunk, Error>>;
+type ReplyReceiver = mpsc::Receiver<Result<ResponseChunk, Error>>;
+type Replies = stream::LocalBoxStream<'static, Result<ResponseChunk, Error>>;
+type PeerSender = mpsc::Sender<GsbMessage>;
+
+lazy_static::lazy_static! {
+    pub(crate) static ref BCAST: BCastService = Default::default();
+}
+
+pub struct Net;
+
+impl Net {
+    pub async fn gsb<Context>(_: Context, config: Config) -> anyhow::Result<()> {
+        let (default_id, ids) = crate::service::identities().await?;
+        log::info!(
+            "Local NET - Using default identity as network id: {:?}",
+            default_id
+        );
+
+        start_network(Arc::new(config), default_id, ids).await
+    }
+
+    pub async fn shutdown() -> anyhow::Result<()> {
+        if typed::service(net::local::BUS_ID)
+            .send(net::local::Shutdown {})
+            .timeout(Some(Duration::from_secs(5)))
+            .await
+            .is_err()
+        {
+            log::info!("NET shutdown due to timeout.");
+        }
+        Ok(())
+    }
+}
+
+/// Starts accepting connections from other Nodes and connects to configured peers.
+/// Calls to `/net/<node_id>` are routed over connection, on which
+/// the Node registered its identities.
+pub async fn start_network(
+    config: Arc<Config>,
+    default_id: NodeId,
+    ids: Vec<NodeId>,
+) -> anyhow::Result<()> {
+    log::info!("Starting network (local) with identity: {default_id}");
+
+    let listener = TcpListener::bind(config.local_bind)
+        .await
+        .with_context(|| format!("Unable to listen on {}", config.local_bind))?;
+    let listen_address = listener.local_addr()?;
+    log::info!("Local NET listening on: tcp://{listen_address}");
+
+    let state = State::new(default_id, ids.clone(), Some(listen_address));
+    super::cli::bind_service(state.clone());
+
+    let net_handler = || {
+        move |addr: &str| -> anyhow::Result<(NodeId, NodeId, String)> {
+            let (to, addr) = parse_net_to_addr(addr)?;
+            Ok((default_id, to, addr))
+        }
+    };
+
+    bind_local_bus(net::BUS_ID, state.clone(), net_handler());
+    bind_local_bus(net::BUS_ID_UDP, state.clone(), net_handler());
+    bind_local_bus(net::BUS_ID_TRANSFER, state.clone(), net_handler());
+
+    let from_handler = || {
+        let ids = ids.clone();
+        move |addr: &str| -> anyhow::Result<(NodeId, NodeId, String)> {
+            let (from, to, addr) = parse_from_to_addr(addr)?;
+            if !ids.contains(&from) {
+                anyhow::bail!("Trying to send message from unknown identity: {}", from);
+            }
+            Ok((from, to, addr))
+        }
+    };
+
+    bind_local_bus("/from", state.clone(), from_handler());
+    bind_local_bus("/udp/from", state.clone(), from_handler());
+    bind_local_bus("/transfer/from", state.clone(), from_handler());
+
+    bind_broadcast_handlers(state.clone());
+
+    tokio::task::spawn_local(accept_connections(listener, state.clone()));
+    tokio::task::spawn_local(ping_peers(state.clone()));
+
+    for peer in config.local_peers() {
+        tokio::task::spawn_local(connect_peer(peer, state.clone()));
+    }
+
+    Ok(())
+}
+
+async fn accept_connections(listener: TcpListener, state: State) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, remote)) => {
+                log::debug!("Local NET: accepted connection from {remote}");
+                tokio::task::spawn_local(run_connection(stream, remote, state.clone()));
+            }
+            Err(e) => log::warn!("Local NET: unable to accept connection: {e}"),
+        }
+    }
+}
+
+/// Keeps connection to statically configured peer, reconnecting when it's lost.
+async fn connect_peer(peer: String, state: State) {
+    loop {
+        match TcpStream::connect(peer.as_str()).await {
+            Ok(stream) => match stream.peer_addr() {
+                Ok(remote) => {
+                    log::info!("Local NET: connected to peer {peer} ({remote})");
+                    run_connection(stream, remote, state.clone()).await;
+                    log::info!("Local NET: disconnected from peer {peer}");
+                }
+                Err(e) => log::debug!("Local NET: peer {peer} address unknown: {e}"),
+            },
+            Err(e) => log::debug!("Local NET: unable to connect to peer {peer}: {e}"),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Pings connected Nodes and closes connections, on which nothing was received
+/// for `IDLE_TIMEOUT`.
+async fn ping_peers(state: State) {
+    let mut interval = tokio::time::interval(PING_INTERVAL);
+    loop {
+        interval.tick().await;
+        for remote in state.remove_idle_peers(IDLE_TIMEOUT) {
+            log::info!(
+                "Local NET: connection with {remote} closed, no messages for {IDLE_TIMEOUT:?}"
+            );
+        }
+
+        let ping = GsbMessage::CallRequest(ya_sb_proto::CallRequest {
+            caller: state.default_id().to_string(),
+            address: PING_ADDRESS.to_string(),
+            request_id: String::new(),
+            data: Vec::new(),
+            no_reply: true,
+        });
+        for mut tx in state.peer_senders() {
+            let _ = tx.try_send(ping.clone());
+        }
+    }
+}
+
+/// Challenges connected Node to prove its identities and handles messages it sends
+/// until connection is closed.
+async fn run_connection(stream: TcpStream, remote: SocketAddr, state: State) {
+    let (mut sink, stream) = Framed::new(stream, GsbMessageCodec::default()).split();
+    let (mut tx, mut rx) = mpsc::channel(PEER_QUEUE_SIZE);
+    let (close_tx, close_rx) = oneshot::channel::<()>();
+
+    let challenge = state.add_peer(remote, tx.clone(), close_tx);
+    let _ = tx.try_send(GsbMessage::CallRequest(ya_sb_proto::CallRequest {
+        caller: state.default_id().to_string(),
+        address: CHALLENGE_ADDRESS.to_string(),
+        request_id: String::new(),
+        data: challenge.to_vec(),
+        no_reply: true,
+    }));
+
+    let writer = tokio::task::spawn_local(async move {
+        while let Some(msg) = rx.next().await {
+            if let Err(e) = sink.send(msg).await {
+                log::debug!("Local NET: error sending message to {remote}: {e}");
+                break;
+            }
+        }
+    });
+
+    let mut stream = stream.take_until(close_rx);
+    while let Some(result) = stream.next().await {
+        match result {
+            Ok(msg) => {
+                state.seen(&remote);
+                if let Err(e) = handle_message(msg, remote, &state) {
+                    log::debug!("Local NET: ingress message from {remote} error: {e}");
+                }
+            }
+            Err(e) => {
+                log::debug!("Local NET: connection with {remote} error: {e}");
+                break;
+            }
+        }
+    }
+
+    state.remove_peer(&remote);
+    // Writer may wait for Node, which doesn't read anymore.
+    writer.abort();
+}
+
+fn handle_message(msg: GsbMessage, remote: SocketAddr, state: &State) -> anyhow::Result<()> {
+    match msg {
+        GsbMessage::CallRequest(request) if request.address == CHALLENGE_ADDRESS => {
+            handle_challenge(request, remote, state)
+        }
+        GsbMessage::CallRequest(request) if request.address == REGISTER_ADDRESS => {
+            let node_id = NodeId::from_str(&request.caller)?;
+            state.register(&remote, node_id, &request.data)
+        }
+        // Every message marks the Node as seen, pings don't need handling.
+        GsbMessage::CallRequest(request) if request.address == PING_ADDRESS => Ok(()),
+        GsbMessage::CallRequest(request) => handle_request(request, remote, state),
+        GsbMessage::CallReply(reply) => handle_reply(reply, remote, state),
+        GsbMessage::BroadcastRequest(request) => handle_broadcast(request, remote, state),
+        _ => anyhow::bail!("unexpected message type"),
+    }
+}
+
+/// Answers challenge of connected Node with signatures of our identities.
+fn handle_challenge(
+    request: ya_sb_proto::CallRequest,
+    remote: SocketAddr,
+    state: &State,
+) -> anyhow::Result<()> {
+    let default_id = NodeId::from_str(&request.caller)?;
+    state.set_peer_default_id(&remote, default_id)?;
+    let mut tx = state
+        .peer_sender(&remote)
+        .ok_or_else(|| anyhow::anyhow!("connection with {remote} closed"))?;
+
+    let digest = challenge_digest(&request.data);
+    let ids = state.ids();
+    tokio::task::spawn_local(async move {
+        for node_id in ids {
+            let signature = match sign(node_id, digest.clone()).await {
+                Ok(signature) => signature,
+                Err(e) => {
+                    log::debug!("Local NET: unable to register [{node_id}] on {remote}: {e}");
+                    continue;
+                }
+            };
+            let request = GsbMessage::CallRequest(ya_sb_proto::CallRequest {
+                caller: node_id.to_string(),
+                address: REGISTER_ADDRESS.to_string(),
+                request_id: String::new(),
+                data: signature,
+                no_reply: true,
+            });
+            if tx.send(request).await.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn sign(node_id: NodeId, payload: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    Ok(typed::service(identity::BUS_ID)
+        .send(identity::Sign { node_id, payload })
+        .await
+        .map_err(anyhow::Error::msg)??)
+}
+
+fn challenge_digest(challenge: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha3_256::new();
+    hasher.input(CHALLENGE_PREFIX);
+    hasher.input(challenge);
+    hasher.result().to_vec()
+}
+
+/// Checks, that `signature` of the challenge was made with key of `node_id`.
+fn verify_challenge(challenge: &[u8], node_id: NodeId, signature: &[u8]) -> bool {
+    if signature.len() != 65 {
+        return false;
+    }
+    let mut r = [0u8; 32];
+    let mut s = [0u8; 32];
+    r.copy_from_slice(&signature[1..33]);
+    s.copy_from_slice(&signature[33..65]);
+    let signature = Signature {
+        v: signature[0],
+        r,
+        s,
+    };
+
+    match signature.recover(&challenge_digest(challenge)) {
+        Ok(key) => key.address() == &node_id.into_array(),
+        Err(_) => false,
+    }
+}
+
+/// Forward calls from the network to the local bus
+fn handle_request(
+    request: ya_sb_proto::CallRequest,
+    remote: SocketAddr,
+    state: &State,
+) -> anyhow::Result<()> {
+    let (caller_id, remote_id) = state.peer_identity(&remote, &request.caller)?;
+    let request_id = request.request_id;
+
+    log::debug!(
+        "Handle request {request_id} to {} from {caller_id}",
+        request.address
+    );
+
+    let public_address = state.get_public_service(&request.address);
+    let service = traffic::inbound_service(public_address.as_deref());
+    traffic::record(
+        Some(remote_id),
+        &service,
+        Direction::Inbound,
+        request.data.len(),
+    );
+
+    let address = match public_address {
+        Some(address) => firewall::check(remote_id, &address)
+            .map(|_| address)
+            .map_err(Error::GsbBadRequest),
+        None => Err(Error::GsbBadRequest(format!(
+            "Unknown address: {}",
+            request.address
+        ))),
+    };
+    if address.is_err() {
+        traffic::record_error(Some(remote_id), &service);
+    }
+
+    if request.no_reply {
+        let address = address?;
+        let push = local_bus::push(&address, &request.caller, &request.data);
+        tokio::task::spawn_local(async move {
+            let _ = push.await;
+        });
+        return Ok(());
+    }
+
+    let mut tx = state
+        .peer_sender(&remote)
+        .ok_or_else(|| anyhow::anyhow!("connection with {remote} closed"))?;
+    let stream = match address {
+        Ok(address) => {
+            local_bus::call_stream(&address, &request.caller, &request.data).left_stream()
+        }
+        Err(e) => stream::once(future::err(e)).right_stream(),
+    };
+
+    tokio::task::spawn_local(async move {
+        let mut stream = Box::pin(stream);
+        let mut full = false;
+        while let Some(result) = stream.next().await {
+            let reply = match result {
+                Ok(chunk) => {
+                    full = chunk.is_full();
+                    let len = match &chunk {
+                        ResponseChunk::Full(data) | ResponseChunk::Part(data) => data.len(),
+                    };
+                    traffic::record(Some(remote_id), &service, Direction::Outbound, len);
+                    codec::reply_ok(&request_id, chunk)
+                }
+                Err(e) => {
+                    full = true;
+                    codec::reply_err(&request_id, e)
+                }
+            };
+            if tx.send(reply).await.is_err() {
+                traffic::record_error(Some(remote_id), &service);
+                break;
+            }
+            if full {
+                break;
+            }
+        }
+        if !full {
+            let _ = tx.send(codec::reply_eos(&request_id)).await;
+        }
+    });
+
+    Ok(())
+}
+
+/// Forward replies from the network to the local bus
+fn handle_reply(
+    reply: ya_sb_proto::CallReply,
+    remote: SocketAddr,
+    state: &State,
+) -> anyhow::Result<()> {
+    let full = reply.reply_type == CallReplyType::Full as i32;
+    let Request {
+        node_id,
+        service,
+        mut tx,
+        ..
+    } = state
+        .request(&reply.request_id, &remote, full)
+        .ok_or_else(|| anyhow::anyhow!("invalid reply request id: {}", reply.request_id))?;
+
+    traffic::record(
+        Some(node_id),
+        &service,
+        Direction::Inbound,
+        reply.data.len(),
+    );
+    if reply.code != CallReplyCode::CallReplyOk as i32 {
+        traffic::record_error(Some(node_id), &service);
+    }
+
+    let result = if reply.code == CallReplyCode::CallReplyOk as i32 {
+        Ok(match full {
+            true => ResponseChunk::Full(reply.data),
+            false => ResponseChunk::Part(reply.data),
+        })
+    } else if reply.code == CallReplyCode::CallReplyBadRequest as i32 {
+        Err(Error::GsbBadRequest(
+            String::from_utf8_lossy(&reply.data).to_string(),
+        ))
+    } else {
+        Err(Error::GsbFailure(
+            String::from_utf8_lossy(&reply.data).to_string(),
+        ))
+    };
+
+    // Reply stream with a dropped chunk would be incomplete, so the call is ended.
+    if let Err(e) = tx.try_send(result) {
+        traffic::record_error(Some(node_id), &service);
+        state.remove_request(&reply.request_id);
+        let reason = match e.is_full() {
+            true => "caller queue full",
+            false => "channel closed",
+        };
+        log::debug!("Failed to forward reply {}: {reason}", reply.request_id);
+    }
+    Ok(())
+}
+
+/// Forward broadcasts from the network to the local bus
+fn handle_broadcast(
+    request: ya_sb_proto::BroadcastRequest,
+    remote: SocketAddr,
+    state: &State,
+) -> anyhow::Result<()> {
+    let (caller_id, remote_id) = state.peer_identity(&remote, &request.caller)?;
+    let caller = caller_id.to_string();
+
+    log::trace!(
+        "Received broadcast to topic {} from [{}].",
+        &request.topic,
+        &caller
+    );
+
+    tokio::task::spawn_local(async move {
+        let endpoints = BCAST.resolve(&request.topic).await;
+        let service = traffic::inbound_broadcast(&request.topic, !endpoints.is_empty());
+        traffic::record(
+            Some(remote_id),
+            &service,
+            Direction::Inbound,
+            request.data.len(),
+        );
+        if let Err(reason) = firewall::check_broadcast(remote_id, &request.topic) {
+            traffic::record_error(Some(remote_id), &service);
+            log::debug!("Broadcast from [{remote_id}] dropped: {reason}");
+            return;
+        }
+
+        let bcast_service_id = <SendBroadcastMessage<()> as RpcMessage>::ID;
+        for endpoint in endpoints {
+            let addr = format!("{}/{}", endpoint, bcast_service_id);
+            if let Err(e) = local_bus::send(&addr, &caller, &request.data).await {
+                log::debug!("Forwarding broadcast from [{caller}] to local endpoint error: {e}");
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn bind_broadcast_handlers(state: State) {
+    let _ = typed::bind(
+        net::local::BUS_ID,
+        move |subscribe: net::local::Subscribe| {
+            let topic = subscribe.topic().to_owned();
+
+            async move {
+                log::debug!("NET: Subscribe to broadcast topic {}", topic);
+                let (is_new, id) = BCAST.add(subscribe).await;
+                if is_new {
+                    log::debug!("NET: Created new topic: {}", topic);
+                }
+                Ok(id)
+            }
+        },
+    );
+
+    let bcast_service_id = <SendBroadcastMessage<()> as RpcMessage>::ID;
+    let _ = local_bus::subscribe(
+        &format!("{}/{}", net::local::BUS_ID, bcast_service_id),
+        move |caller: &str, _addr: &str, msg: &[u8]| {
+            let result = broadcast_to_peers(&state, caller, msg);
+            if let Err(e) = &result {
+                log::debug!("Unable to broadcast message: {e}")
+            }
+            future::ready(result)
+        },
+        (),
+    );
+}
+
+/// Forward broadcast messages from the local bus to all connected Nodes
+fn broadcast_to_peers(state: &State, caller: &str, msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let stub: SendBroadcastStub = serialization::from_slice(msg)
+        .map_err(|e| Error::GsbFailure(format!("Invalid broadcast message: {e}")))?;
+
+    let service = traffic::broadcast(&stub.topic);
+    let request = GsbMessage::BroadcastRequest(ya_sb_proto::BroadcastRequest {
+        caller: caller.to_string(),
+        topic: stub.topic,
+        data: msg.to_vec(),
+    });
+    for mut tx in state.peer_senders() {
+        match tx.try_send(request.clone()) {
+            Ok(_) => traffic::record(None, &service, Direction::Outbound, msg.len()),
+            Err(_) => traffic::record_error(None, &service),
+        }
+    }
+
+    Ok(serialization::to_vec(&Ok::<(), ()>(())).unwrap())
+}
+
+/// Forward calls from the local bus to the network
+fn bind_local_bus<F>(address: &'static str, state: State, resolver: F)
+where
+    F: Fn(&str) -> anyhow::Result<(NodeId, NodeId, String)> + 'static,
+{
+    let resolver = Rc::new(resolver);
+    let resolver_ = resolver.clone();
+    let state_ = state.clone();
+
+    let rpc = move |_caller: &str, addr: &str, msg: &[u8], no_reply: bool| {
+        let rx = call(&state_, &*resolver_, addr, msg, no_reply);
+        async move {
+            match rx? {
+                None => Ok(Vec::new()),
+                Some(mut rx) => match rx.next().await.ok_or(Error::Cancelled)?? {
+                    ResponseChunk::Full(data) => Ok(data),
+                    ResponseChunk::Part(_) => {
+                        Err(Error::GsbFailure("partial response".to_string()))
+                    }
+                },
+            }
+        }
+    };
+
+    let stream = move |_caller: &str, addr: &str, msg: &[u8], no_reply: bool| match call(
+        &state, &*resolver, addr, msg, no_reply,
+    ) {
+        Ok(Some(rx)) => rx,
+        Ok(None) => stream::empty().boxed_local(),
+        Err(e) => stream::once(future::err(e)).boxed_local(),
+    };
+
+    log::debug!("local bus: subscribing to {}", address);
+    local_bus::subscribe(address, rpc.into_handler(), stream.into_stream_handler());
+}
+
+fn call<F>(
+    state: &State,
+    resolver: &F,
+    addr: &str,
+    msg: &[u8],
+    no_reply: bool,
+) -> Result<Option<Replies>, Error>
+where
+    F: Fn(&str) -> anyhow::Result<(NodeId, NodeId, String)>,
+{
+    let (caller_id, remote_id, address) =
+        resolver(addr).map_err(|e| Error::GsbFailure(format!("invalid address: {e}")))?;
+    let address = strip_transport_prefix(&address).to_string();
+
+    log::trace!("local bus: call (egress): {address} ({caller_id} -> {remote_id})");
+
+    if state.is_local(&remote_id) {
+        return Ok(call_local(state, caller_id, &address, msg, no_reply).map(|rx| rx.boxed_local()));
+    }
+
+    let service = traffic::service(&address);
+    let (remote, mut tx) = match state.route(&remote_id) {
+        Some(route) => route,
+        None => {
+            traffic::record_error(Some(remote_id), &service);
+            let err = "Node not connected".to_string();
+            return Err(Error::RemoteError(address, err));
+        }
+    };
+    let request_id = state.next_request_id();
+    let rx = match no_reply {
+        true => None,
+        false => {
+            let (reply_tx, reply_rx) = mpsc::channel(REPLY_QUEUE_SIZE);
+            let request = Request {
+                remote,
+                node_id: remote_id,
+                service: service.clone(),
+                tx: reply_tx,
+            };
+            state.add_request(request_id.clone(), request);
+            Some(PendingReplies {
+                rx: reply_rx,
+                request_id: request_id.clone(),
+                state: state.clone(),
+            })
+        }
+    };
+
+    let request = GsbMessage::CallRequest(ya_sb_proto::CallRequest {
+        caller: caller_id.to_string(),
+        address: address.clone(),
+        request_id: request_id.clone(),
+        data: msg.to_vec(),
+        no_reply,
+    });
+    // Pending request is removed together with `rx`.
+    if let Err(e) = tx.try_send(request) {
+        traffic::record_error(Some(remote_id), &service);
+        let reason = match e.is_full() {
+            true => "connection queue full",
+            false => "connection closed",
+        };
+        return Err(Error::RemoteError(address, reason.to_string()));
+    }
+    traffic::record(Some(remote_id), &service, Direction::Outbound, msg.len());
+    Ok(rx.map(|rx| rx.boxed_local()))
+}
+
+/// Replies to a call sent to connected Node. Call is removed from pending
+/// requests, when the caller drops it, e.g. after timeout.
+struct PendingReplies {
+    rx: ReplyReceiver,
+    request_id: String,
+    state: State,
+}
+
+impl Stream for PendingReplies {
+    type Item = Result<ResponseChunk, Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.rx.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for PendingReplies {
+    fn drop(&mut self) {
+        self.state.remove_request(&self.request_id);
+    }
+}
+
+/// Calls to our own identities don't leave the local bus
+fn call_local(
+    state: &State,
+    caller_id: NodeId,
+    address: &str,
+    msg: &[u8],
+    no_reply: bool,
+) -> Option<ReplyReceiver> {
+    let caller = caller_id.to_string();
+    let (mut tx, rx) = mpsc::channel(REPLY_QUEUE_SIZE);
+    let address = match state.get_public_service(address) {
+        Some(address) => address,
+        None => {
+            let err = Error::GsbBadRequest(format!("Unknown address: {address}"));
+            let _ = tx.try_send(Err(err));
+            return if no_reply { None } else { Some(rx) };
+        }
+    };
+
+    if no_reply {
+        let push = local_bus::push(&address, &caller, msg);
+        tokio::task::spawn_local(async move {
+            let _ = push.await;
+        });
+        return None;
+    }
+
+    let call = local_bus::call_stream(&address, &caller, msg);
+    tokio::task::spawn_local(async move {
+        let _ = call.map(Ok).forward(tx).await;
+    });
+    Some(rx)
+}
+
+/// Local net uses a single connection with each Node, so unreliable
+/// and transfer calls are sent the same way as reliable ones.
+fn strip_transport_prefix(addr: &str) -> &str {
+    let addr = addr.strip_prefix("/udp").unwrap_or(addr);
+    addr.strip_prefix("/transfer").unwrap_or(addr)
+}
+
+/// Connected Node.
+pub(crate) struct Peer {
+    /// Identities proven by the Node. Its default identity goes first.
+    pub node_ids: Vec<NodeId>,
+    /// Default identity announced by the Node with its challenge.
+    default_id: Option<NodeId>,
+    challenge: [u8; 32],
+    pub created: Instant,
+    pub last_seen: Instant,
+    tx: PeerSender,
+    _close: oneshot::Sender<()>,
+}
+
+/// Call waiting for reply from connected Node.
+#[derive(Clone)]
+struct Request {
+    remote: SocketAddr,
+    node_id: NodeId,
+    service: String,
+    tx: ReplySender,
+}
+
+#[derive(Clone)]
+pub(crate) struct State {
+    inner: Rc<RefCell<StateInner>>,
+}
+
+struct StateInner {
+    default_id: NodeId,
+    listen_address: Option<SocketAddr>,
+    ids: HashSet<NodeId>,
+    services: HashSet<String>,
+    peers: HashMap<SocketAddr, Peer>,
+    routes: HashMap<NodeId, SocketAddr>,
+    requests: HashMap<String, Request>,
+    last_request_id: u64,
+}
+
+impl State {
+    fn new(
+        default_id: NodeId,
+        ids: impl IntoIterator<Item = NodeId>,
+        listen_address: Option<SocketAddr>,
+    ) -> Self {
+        let ids: HashSet<_> = ids.into_iter().collect();
+        let services = ids.iter().map(net::net_service).collect();
+        Self {
+            inner: Rc::new(RefCell::new(StateInner {
+                default_id,
+                listen_address,
+                ids,
+                services,
+                peers: Default::default(),
+                routes: Default::default(),
+                requests: Default::default(),
+                last_request_id: 0,
+            })),
+        }
+    }
+
+    pub fn default_id(&self) -> NodeId {
+        self.inner.borrow().default_id
+    }
+
+    pub fn listen_address(&self) -> Option<SocketAddr> {
+        self.inner.borrow().listen_address
+    }
+
+    fn ids(&self) -> Vec<NodeId> {
+        self.inner.borrow().ids.iter().copied().collect()
+    }
+
+    fn is_local(&self, node_id: &NodeId) -> bool {
+        self.inner.borrow().ids.contains(node_id)
+    }
+
+    fn get_public_service(&self, addr: &str) -> Option<String> {
+        let inner = self.inner.borrow();
+        RevPrefixes(addr)
+            .find_map(|s| inner.services.get(s))
+            .map(|s| addr.replacen(s, net::PUBLIC_PREFIX, 1))
+    }
+
+    /// Returns challenge, which the Node has to sign to register its identities.
+    fn add_peer(&self, remote: SocketAddr, tx: PeerSender, close: oneshot::Sender<()>) -> [u8; 32] {
+        let now = Instant::now();
+        let challenge = rand::random();
+        let peer = Peer {
+            node_ids: Vec::new(),
+            default_id: None,
+            challenge,
+            created: now,
+            last_seen: now,
+            tx,
+            _close: close,
+        };
+        self.inner.borrow_mut().peers.insert(remote, peer);
+        challenge
+    }
+
+    /// Node announces its default identity once, together with its challenge.
+    fn set_peer_default_id(&self, remote: &SocketAddr, node_id: NodeId) -> anyhow::Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        let peer = inner
+            .peers
+            .get_mut(remote)
+            .with_context(|| format!("connection with {remote} closed"))?;
+        if peer.default_id.is_some() {
+            anyhow::bail!("challenge from {remote} already answered");
+        }
+        peer.default_id = Some(node_id);
+        Ok(())
+    }
+
+    fn seen(&self, remote: &SocketAddr) {
+        if let Some(peer) = self.inner.borrow_mut().peers.get_mut(remote) {
+            peer.last_seen = Instant::now();
+        }
+    }
+
+    /// Registers Node's identity proven with signature of the connection challenge.
+    /// Calls to the identity are routed over this connection, unless it's already
+    /// routed over another live connection.
+    fn register(
+        &self,
+        remote: &SocketAddr,
+        node_id: NodeId,
+        signature: &[u8],
+    ) -> anyhow::Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        let peer = inner
+            .peers
+            .get_mut(remote)
+            .with_context(|| format!("connection with {remote} closed"))?;
+        if !verify_challenge(&peer.challenge, node_id, signature) {
+            anyhow::bail!("invalid signature of [{node_id}] from {remote}");
+        }
+        if !peer.node_ids.contains(&node_id) {
+            match peer.default_id == Some(node_id) {
+                true => peer.node_ids.insert(0, node_id),
+                false => peer.node_ids.push(node_id),
+            }
+        }
+
+        match inner.routes.get(&node_id) {
+            Some(route) if route != remote && inner.peers.contains_key(route) => {
+                log::debug!("Local NET: Node [{node_id}] already connected from {route}");
+            }
+            _ => {
+                inner.routes.insert(node_id, *remote);
+                log::info!("Local NET: Node [{node_id}] connected from {remote}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Closes connection and fails calls waiting for replies on it.
+    pub fn remove_peer(&self, remote: &SocketAddr) -> Option<Peer> {
+        let mut inner = self.inner.borrow_mut();
+        let peer = inner.peers.remove(remote)?;
+
+        for node_id in peer.node_ids.iter() {
+            if inner.routes.get(node_id) != Some(remote) {
+                continue;
+            }
+            // Nodes listing each other as peers have two connections.
+            match inner
+                .peers
+                .iter()
+                .find(|(_, other)| other.node_ids.contains(node_id))
+                .map(|(addr, _)| *addr)
+            {
+                Some(addr) => inner.routes.insert(*node_id, addr),
+                None => inner.routes.remove(node_id),
+            };
+        }
+
+        let closed = inner
+            .requests
+            .iter()
+            .filter(|(_, request)| request.remote == *remote)
+            .map(|(request_id, _)| request_id.clone())
+            .collect::<Vec<_>>();
+        for request_id in closed {
+            if let Some(mut request) = inner.requests.remove(&request_id) {
+                let err = Error::RemoteError(remote.to_string(), "connection closed".to_string());
+                let _ = request.tx.try_send(Err(err));
+            }
+        }
+
+        Some(peer)
+    }
+
+    pub fn remove_peers(&self) {
+        let peers = self.peers();
+        for remote in peers.keys() {
+            self.remove_peer(remote);
+        }
+    }
+
+    /// Closes connections, on which nothing was received for `timeout`.
+    fn remove_idle_peers(&self, timeout: Duration) -> Vec<SocketAddr> {
+        let idle = self
+            .inner
+            .borrow()
+            .peers
+            .iter()
+            .filter(|(_, peer)| peer.last_seen.elapsed() > timeout)
+            .map(|(remote, _)| *remote)
+            .collect::<Vec<_>>();
+        for remote in idle.iter() {
+            self.remove_peer(remote);
+        }
+        idle
+    }
+
+    /// Returns connected Nodes with their connection times.
+    pub fn peers(&self) -> HashMap<SocketAddr, (Vec<NodeId>, Instant, Instant)> {
+        self.inner
+            .borrow()
+            .peers
+            .iter()
+            .map(|(addr, peer)| (*addr, (peer.node_ids.clone(), peer.created, peer.last_seen)))
+            .collect()
+    }
+
+    pub fn find_peer(&self, node_id: &NodeId) -> Option<(SocketAddr, Vec<NodeId>)> {
+        let inner = self.inner.borrow();
+        let remote = inner.routes.get(node_id)?;
+        let peer = inner.peers.get(remote)?;
+        Some((*remote, peer.node_ids.clone()))
+    }
+
+    /// Identity of the caller, which must be registered on given connection,
+    /// and default identity of the Node, which identifies the connection.
+    fn peer_identity(&self, remote: &SocketAddr, caller: &str) -> anyhow::Result<(NodeId, NodeId)> {
+        let caller_id = NodeId::from_str(caller)?;
+        let inner = self.inner.borrow();
+        let peer = match inner.peers.get(remote) {
+            Some(peer) if peer.node_ids.contains(&caller_id) => peer,
+            _ => anyhow::bail!("Invalid caller id: {caller} from {remote}"),
+        };
+        match peer.default_id {
+            Some(default_id) if peer.node_ids.contains(&default_id) => Ok((caller_id, default_id)),
+            _ => anyhow::bail!("Default identity of {remote} isn't registered"),
+        }
+    }
+
+    fn peer_sender(&self, remote: &SocketAddr) -> Option<PeerSender> {
+        self.inner.borrow().peers.get(remote).map(|p| p.tx.clone())
+    }
+
+    fn peer_senders(&self) -> Vec<PeerSender> {
+        let inner = self.inner.borrow();
+        inner.peers.values().map(|peer| peer.tx.clone()).collect()
+    }
+
+    fn route(&self, node_id: &NodeId) -> Option<(SocketAddr, PeerSender)> {
+        let inner = self.inner.borrow();
+        let remote = inner.routes.get(node_id)?;
+        let peer = inner.peers.get(remote)?;
+        Some((*remote, peer.tx.clone()))
+    }
+
+    fn next_request_id(&self) -> String {
+        let mut inner = self.inner.borrow_mut();
+        inner.last_request_id += 1;
+        inner.last_request_id.to_string()
+    }
+
+    fn add_request(&self, request_id: String, request: Request) {
+        let mut inner = self.inner.borrow_mut();
+        inner.requests.insert(request_id, request);
+    }
+
+    fn remove_request(&self, request_id: &str) {
+        self.inner.borrow_mut().requests.remove(request_id);
+    }
+
+    /// Call waiting for reply from given connection. Removed from pending
+    /// requests after the full reply.
+    fn request(&self, request_id: &str, remote: &SocketAddr, full: bool) -> Option<Request> {
+        let mut inner = self.inner.borrow_mut();
+        match inner.requests.get(request_id) {
+            Some(request) if request.remote == *remote => (),
+            _ => return None,
+        }
+        match full {
+            true => inner.requests.remove(request_id),
+            false => inner.requests.get(request_id).cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethsign::SecretKey;
+
+    fn node(n: u8) -> NodeId {
+        NodeId::from(&[n; 20][..])
+    }
+
+    fn key(n: u8) -> SecretKey {
+        SecretKey::from_raw(&[n; 32]).unwrap()
+    }
+
+    fn key_id(key: &SecretKey) -> NodeId {
+        NodeId::from(&key.public().address()[..])
+    }
+
+    fn sign_challenge(key: &SecretKey, challenge: &[u8]) -> Vec<u8> {
+        let signature = key.sign(&challenge_digest(challenge)).unwrap();
+        let mut v = vec![signature.v];
+        v.extend_from_slice(&signature.r);
+        v.extend_from_slice(&signature.s);
+        v
+    }
+
+    fn connect(state: &State, remote: SocketAddr, key: &SecretKey) -> mpsc::Receiver<GsbMessage> {
+        let (tx, rx) = mpsc::channel(PEER_QUEUE_SIZE);
+        let (close_tx, _) = oneshot::channel();
+        let challenge = state.add_peer(remote, tx, close_tx);
+        state.set_peer_default_id(&remote, key_id(key)).unwrap();
+        state
+            .register(&remote, key_id(key), &sign_challenge(key, &challenge))
+            .unwrap();
+        rx
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Local NET: condition not met in time");
+    }
+
+    #[test]
+    fn test_strip_transport_prefix() {
+        let addr = "/net/0x95369fc6fd02afeca110b9c32a21fb8ad899ee0a/vpn/VpnControl";
+        assert_eq!(strip_transport_prefix(addr), addr);
+        assert_eq!(strip_transport_prefix(&format!("/udp{addr}")), addr);
+        assert_eq!(strip_transport_prefix(&format!("/transfer{addr}")), addr);
+    }
+
+    #[test]
+    fn test_routes_follow_remaining_connection() {
+        let state = State::new(node(1), vec![node(1)], None);
+        let first: SocketAddr = "127.0.0.1:20001".parse().unwrap();
+        let second: SocketAddr = "127.0.0.1:20002".parse().unwrap();
+
+        let remote_id = key_id(&key(2));
+        let _first_rx = connect(&state, first, &key(2));
+        let _second_rx = connect(&state, second, &key(2));
+        // Identity isn't re-routed from live connection.
+        assert_eq!(state.route(&remote_id).unwrap().0, first);
+        assert_eq!(
+            state
+                .peer_identity(&second, &remote_id.to_string())
+                .unwrap(),
+            (remote_id, remote_id)
+        );
+        assert!(state.peer_identity(&first, &node(3).to_string()).is_err());
+
+        let (reply_tx, mut reply_rx) = mpsc::channel(REPLY_QUEUE_SIZE);
+        let request = Request {
+            remote: first,
+            node_id: remote_id,
+            service: "/market".to_string(),
+            tx: reply_tx,
+        };
+        state.add_request("1".to_string(), request);
+        assert!(state.request("1", &second, true).is_none());
+
+        state.remove_peer(&first);
+        assert_eq!(state.route(&remote_id).unwrap().0, second);
+        assert!(matches!(reply_rx.try_next(), Ok(Some(Err(_)))));
+
+        state.remove_peer(&second);
+        assert!(state.route(&remote_id).is_none());
+        assert_eq!(
+            state.get_public_service(&format!("{}/market", net::net_service(node(1)))),
+            Some("/public/market".to_string())
+        );
+    }
+
+    #[test]
+    fn test_register_requires_signed_challenge() {
+        let state = State::new(node(1), vec![node(1)], None);
+        let remote: SocketAddr = "127.0.0.1:20001".parse().unwrap();
+        let (tx, _rx) = mpsc::channel(PEER_QUEUE_SIZE);
+        let (close_tx, _) = oneshot::channel();
+        let challenge = state.add_peer(remote, tx, close_tx);
+        let remote_id = key_id(&key(2));
+        state.set_peer_default_id(&remote, remote_id).unwrap();
+        assert!(state.set_peer_default_id(&remote, remote_id).is_err());
+
+        // Signed with other key or of other challenge.
+        let signature = sign_challenge(&key(3), &challenge);
+        assert!(state.register(&remote, remote_id, &signature).is_err());
+        let signature = sign_challenge(&key(2), &[0; 32]);
+        assert!(state.register(&remote, remote_id, &signature).is_err());
+        assert!(state.register(&remote, remote_id, &[]).is_err());
+        assert!(state.route(&remote_id).is_none());
+
+        // Calls are accepted only after default identity is registered.
+        let other_id = key_id(&key(3));
+        let signature = sign_challenge(&key(3), &challenge);
+        state.register(&remote, other_id, &signature).unwrap();
+        assert!(state.peer_identity(&remote, &other_id.to_string()).is_err());
+
+        let signature = sign_challenge(&key(2), &challenge);
+        state.register(&remote, remote_id, &signature).unwrap();
+        assert_eq!(
+            state.peer_identity(&remote, &other_id.to_string()).unwrap(),
+            (other_id, remote_id)
+        );
+        assert_eq!(state.peers()[&remote].0, vec![remote_id, other_id]);
+    }
+
+    #[test]
+    fn test_dropped_calls_and_idle_peers_removed() {
+        let state = State::new(node(1), vec![node(1)], None);
+        let remote: SocketAddr = "127.0.0.1:20001".parse().unwrap();
+        let remote_id = key_id(&key(2));
+        let mut peer_rx = connect(&state, remote, &key(2));
+
+        let resolver = |addr: &str| -> anyhow::Result<(NodeId, NodeId, String)> {
+            let (to, addr) = parse_net_to_addr(addr)?;
+            Ok((node(1), to, addr))
+        };
+        let address = format!("{}/market", net::net_service(remote_id));
+        let pending = |state: &State| state.inner.borrow().requests.len();
+
+        // Caller timed out.
+        let replies = call(&state, &resolver, &address, b"ping", false)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            peer_rx.try_next(),
+            Ok(Some(GsbMessage::CallRequest(_)))
+        ));
+        assert_eq!(pending(&state), 1);
+        drop(replies);
+        assert_eq!(pending(&state), 0);
+
+        let mut replies = call(&state, &resolver, &address, b"ping", false)
+            .unwrap()
+            .unwrap();
+        assert!(state.remove_idle_peers(IDLE_TIMEOUT).is_empty());
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(
+            state.remove_idle_peers(Duration::from_millis(5)),
+            vec![remote]
+        );
+        assert!(state.route(&remote_id).is_none());
+        assert_eq!(pending(&state), 0);
+        assert!(matches!(
+            replies.next().now_or_never(),
+            Some(Some(Err(Error::RemoteError(..))))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_call_between_nodes_over_tcp() {
+        let keys = Rc::new(vec![key(1), key(2), key(3)]);
+        let _ = typed::bind(identity::BUS_ID, move |msg: identity::Sign| {
+            let result = keys
+                .iter()
+                .find(|key| key_id(key) == msg.node_id)
+                .map(|key| {
+                    let signature = key.sign(&msg.payload).unwrap();
+                    let mut v = vec![signature.v];
+                    v.extend_from_slice(&signature.r);
+                    v.extend_from_slice(&signature.s);
+                    v
+                })
+                .ok_or_else(|| identity::Error::NodeNotFound(Box::new(msg.node_id)));
+            future::ready(result)
+        });
+        let _ = local_bus::subscribe(
+            "/public/local-net-test",
+            |caller: &str, _addr: &str, _msg: &[u8]| {
+                future::ok::<_, Error>(caller.as_bytes().to_vec())
+            },
+            (),
+        );
+
+        let (a_id, b_id) = (key_id(&key(1)), key_id(&key(2)));
+        let a = State::new(a_id, vec![a_id], None);
+        let b = State::new(b_id, vec![b_id], None);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen_address = listener.local_addr().unwrap();
+        tokio::task::spawn_local(accept_connections(listener, b.clone()));
+        let stream = TcpStream::connect(listen_address).await.unwrap();
+        let a_local = stream.local_addr().unwrap();
+        tokio::task::spawn_local(run_connection(stream, listen_address, a.clone()));
+
+        wait_for(|| a.route(&b_id).is_some() && b.route(&a_id).is_some()).await;
+
+        let resolver = |addr: &str| -> anyhow::Result<(NodeId, NodeId, String)> {
+            let (to, addr) = parse_net_to_addr(addr)?;
+            Ok((a_id, to, addr))
+        };
+        let address = format!("{}/local-net-test", net::net_service(b_id));
+        let mut rx = call(&a, &resolver, &address, b"ping", false)
+            .unwrap()
+            .unwrap();
+        match rx.next().await.unwrap().unwrap() {
+            ResponseChunk::Full(data) => assert_eq!(data, a_id.to_string().into_bytes()),
+            ResponseChunk::Part(_) => panic!("unexpected partial response"),
+        }
+
+        // Node can't take over identity of another Node without its key.
+        let rogue = TcpStream::connect(listen_address).await.unwrap();
+        let rogue_local = rogue.local_addr().unwrap();
+        let mut rogue = Framed::new(rogue, GsbMessageCodec::default());
+        let challenge = match rogue.next().await.unwrap().unwrap() {
+            GsbMessage::CallRequest(request) if request.address == CHALLENGE_ADDRESS => {
+                request.data
+            }
+            _ => panic!("expected challenge"),
+        };
+        let rogue_id = key_id(&key(3));
+        let messages = vec![
+            GsbMessage::RegisterRequest(ya_sb_proto::RegisterRequest {
+                service_id: net::net_service(a_id),
+            }),
+            GsbMessage::CallRequest(ya_sb_proto::CallRequest {
+                caller: a_id.to_string(),
+                address: REGISTER_ADDRESS.to_string(),
+                request_id: String::new(),
+                data: sign_challenge(&key(3), &challenge),
+                no_reply: true,
+            }),
+            GsbMessage::CallRequest(ya_sb_proto::CallRequest {
+                caller: rogue_id.to_string(),
+                address: CHALLENGE_ADDRESS.to_string(),
+                request_id: String::new(),
+                data: vec![0; 32],
+                no_reply: true,
+            }),
+            GsbMessage::CallRequest(ya_sb_proto::CallRequest {
+                caller: rogue_id.to_string(),
+                address: REGISTER_ADDRESS.to_string(),
+                request_id: String::new(),
+                data: sign_challenge(&key(3), &challenge),
+                no_reply: true,
+            }),
+        ];
+        for msg in messages {
+            rogue.send(msg).await.unwrap();
+        }
+
+        let registered = |remote: &SocketAddr| {
+            b.peers()
+                .get(remote)
+                .map(|(node_ids, _, _)| node_ids.clone())
+                .unwrap_or_default()
+        };
+        wait_for(|| registered(&rogue_local).contains(&rogue_id)).await;
+        assert_eq!(registered(&rogue_local), vec![rogue_id]);
+        assert_eq!(b.route(&a_id).unwrap().0, a_local);
+        assert_eq!(b.route(&rogue_id).unwrap().0, rogue_local);
+    }
+}
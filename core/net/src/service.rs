@@ -2,10 +2,14 @@ use std::sync::{Arc, RwLock};
 
 use ya_core_model::net::local::{BindBroadcastError, BroadcastMessage, SendBroadcastMessage};
 use ya_core_model::{identity, NodeId};
+use ya_service_api::CliCtx;
 use ya_service_api_interfaces::{Provider, Service};
 use ya_service_bus::{Error, RpcEndpoint, RpcMessage};
 
 use crate::config::{Config, NetType};
+use crate::firewall::{self, FIREWALL_FILE};
+use crate::hybrid::peers::{self, PEERS_FILE};
+use crate::traffic;
 
 pub(crate) async fn identities() -> anyhow::Result<(NodeId, Vec<NodeId>)> {
     let ids: Vec<identity::IdentityInfo> = ya_service_bus::typed::service(identity::BUS_ID)
@@ -28,7 +32,7 @@ pub(crate) async fn identities() -> anyhow::Result<(NodeId, Vec<NodeId>)> {
     Ok((default_id, ids))
 }
 
-/// Both Hybrid and Central Net implementation. Only one of them is initialized.
+/// Hybrid, Central and Local Net implementation. Only one of them is initialized.
 /// TODO: Remove after transitioning to Hybrid Net.
 pub struct Net;
 
@@ -41,19 +45,35 @@ impl Service for Net {
 }
 
 impl Net {
-    pub async fn gsb<Context>(ctx: Context) -> anyhow::Result<()> {
+    pub async fn gsb<Context: Provider<Self, CliCtx>>(ctx: &Context) -> anyhow::Result<()> {
         let config = Config::from_env()?;
 
         {
             (*NET_TYPE.write().unwrap()) = config.net_type;
         }
 
+        let firewall_file = match &config.firewall_file {
+            Some(path) => path.clone(),
+            None => ctx.component().data_dir.join(FIREWALL_FILE),
+        };
+        firewall::init(&firewall_file)?;
+        firewall::bind_service();
+        traffic::bind_service();
+
         match &config.net_type {
             NetType::Central => {
                 crate::central::cli::bind_service();
                 crate::central::Net::gsb(ctx, config).await
             }
-            NetType::Hybrid => crate::hybrid::Net::gsb(ctx, config).await,
+            NetType::Hybrid => {
+                let peers_file = match &config.peers_file {
+                    Some(path) => path.clone(),
+                    None => ctx.component().data_dir.join(PEERS_FILE),
+                };
+                peers::init(&peers_file)?;
+                crate::hybrid::Net::gsb(ctx, config).await
+            }
+            NetType::Local => crate::local::Net::gsb(ctx, config).await,
         }
     }
 
@@ -61,7 +81,7 @@ impl Net {
         let net_type = { *NET_TYPE.read().unwrap() };
         match net_type {
             NetType::Central => crate::central::web_scope(),
-            NetType::Hybrid => crate::hybrid::web_scope(),
+            NetType::Hybrid | NetType::Local => crate::hybrid::web_scope(),
         }
     }
 
@@ -75,12 +95,13 @@ impl Net {
         match &config.net_type {
             NetType::Central => Ok(()),
             NetType::Hybrid => crate::hybrid::Net::shutdown().await,
+            NetType::Local => crate::local::Net::shutdown().await,
         }
     }
 }
 
 /// Chooses one of implementations of `broadcast` function
-/// for Hybrid Net or for Central Net.
+/// for Hybrid (also used by Local) Net or for Central Net.
 pub async fn broadcast<M, S>(
     caller: S,
     message: M,
@@ -98,12 +119,12 @@ where
     let net_type = { *NET_TYPE.read().unwrap() };
     match net_type {
         NetType::Central => crate::central::broadcast(caller, message).await,
-        NetType::Hybrid => crate::hybrid::broadcast(caller, message).await,
+        NetType::Hybrid | NetType::Local => crate::hybrid::broadcast(caller, message).await,
     }
 }
 
 /// Chooses one of implementations of `bind_broadcast_with_caller` function
-/// for Hybrid Net or for Central Net.
+/// for Hybrid (also used by Local) Net or for Central Net.
 pub async fn bind_broadcast_with_caller<M, T, F>(
     broadcast_address: &str,
     handler: F,
@@ -123,7 +144,7 @@ where
         NetType::Central => {
             crate::central::bind_broadcast_with_caller(broadcast_address, handler).await
         }
-        NetType::Hybrid => {
+        NetType::Hybrid | NetType::Local => {
             crate::hybrid::bind_broadcast_with_caller(broadcast_address, handler).await
         }
     }
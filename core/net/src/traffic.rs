@@ -0,0 +1,281 @@
+use metrics::counter;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use ya_core_model::net::local::{
+    self as model, NodeTraffic, ServiceTraffic, TrafficCounters, TrafficStats,
+};
+use ya_core_model::NodeId;
+use ya_service_bus::typed as bus;
+
+/// Service, to which traffic of unknown services and broadcast topics is accounted.
+pub(crate) const OTHER_SERVICE: &str = "/other";
+
+/// Limits of counters kept in memory. Services above the limit are accounted
+/// to [`OTHER_SERVICE`]. Above the limit of Nodes, Node with the least traffic is forgotten.
+const MAX_TRACKED_SERVICES: usize = 256;
+const MAX_TRACKED_NODES: usize = 1024;
+
+lazy_static::lazy_static! {
+    static ref TRAFFIC: Mutex<Traffic> = Default::default();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Outbound,
+    Inbound,
+}
+
+/// GSB traffic forwarded by net service, counted per remote Node and per service.
+#[derive(Default)]
+struct Traffic {
+    total: TrafficCounters,
+    nodes: HashMap<NodeId, TrafficCounters>,
+    services: HashMap<String, TrafficCounters>,
+}
+
+impl Traffic {
+    fn record(&mut self, node_id: Option<NodeId>, service: &str, direction: Direction, bytes: u64) {
+        let update = |counters: &mut TrafficCounters| match direction {
+            Direction::Outbound => {
+                counters.tx_bytes += bytes;
+                counters.tx_messages += 1;
+            }
+            Direction::Inbound => {
+                counters.rx_bytes += bytes;
+                counters.rx_messages += 1;
+            }
+        };
+        self.counters(node_id, service).into_iter().for_each(update);
+    }
+
+    fn record_error(&mut self, node_id: Option<NodeId>, service: &str) {
+        self.counters(node_id, service)
+            .into_iter()
+            .for_each(|counters| counters.errors += 1);
+    }
+
+    fn counters(&mut self, node_id: Option<NodeId>, service: &str) -> Vec<&mut TrafficCounters> {
+        if let Some(node_id) = node_id {
+            if !self.nodes.contains_key(&node_id) && self.nodes.len() >= MAX_TRACKED_NODES {
+                self.forget_least_used_node();
+            }
+        }
+        let service = self.accounted(service);
+
+        let mut counters = vec![&mut self.total];
+        if let Some(node_id) = node_id {
+            counters.push(self.nodes.entry(node_id).or_default());
+        }
+        counters.push(self.services.entry(service.to_string()).or_default());
+        counters
+    }
+
+    /// Name, under which traffic of the service is counted.
+    fn accounted<'a>(&self, service: &'a str) -> &'a str {
+        match self.services.contains_key(service) || self.services.len() < MAX_TRACKED_SERVICES {
+            true => service,
+            false => OTHER_SERVICE,
+        }
+    }
+
+    fn forget_least_used_node(&mut self) {
+        let least_used = self
+            .nodes
+            .iter()
+            .min_by_key(|(_, c)| c.tx_bytes + c.rx_bytes)
+            .map(|(node_id, _)| *node_id);
+        if let Some(node_id) = least_used {
+            self.nodes.remove(&node_id);
+        }
+    }
+
+    /// Nodes and services using most bandwidth come first.
+    fn stats(&self) -> TrafficStats {
+        let bandwidth = |c: &TrafficCounters| std::cmp::Reverse(c.tx_bytes + c.rx_bytes);
+
+        let mut nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|(node_id, counters)| NodeTraffic {
+                node_id: *node_id,
+                counters: counters.clone(),
+            })
+            .collect();
+        nodes.sort_by_key(|node| bandwidth(&node.counters));
+
+        let mut services: Vec<_> = self
+            .services
+            .iter()
+            .map(|(service, counters)| ServiceTraffic {
+                service: service.clone(),
+                counters: counters.clone(),
+            })
+            .collect();
+        services.sort_by_key(|service| bandwidth(&service.counters));
+
+        TrafficStats {
+            total: self.total.clone(),
+            nodes,
+            services,
+        }
+    }
+}
+
+/// Service, to which traffic to or from GSB address is accounted. Addresses are grouped
+/// by their first segment, so `/udp/net/<node_id>/vpn/...` is accounted to `/vpn`
+/// and `/public/market/...` to `/market`.
+pub(crate) fn service(address: &str) -> String {
+    let mut segments = address.split('/').filter(|s| !s.is_empty());
+    let mut segment = segments.next();
+    if matches!(segment, Some("udp") | Some("transfer")) {
+        segment = segments.next();
+    }
+    match segment {
+        Some("net") => {
+            segments.next();
+            segment = segments.next();
+        }
+        Some("public") => segment = segments.next(),
+        _ => (),
+    }
+    format!("/{}", segment.unwrap_or_default())
+}
+
+/// Service, to which inbound call to GSB address is accounted. Calls to addresses,
+/// which aren't our public services, are accounted to [`OTHER_SERVICE`].
+pub(crate) fn inbound_service(public_address: Option<&str>) -> String {
+    match public_address {
+        Some(address) => service(address),
+        None => OTHER_SERVICE.to_string(),
+    }
+}
+
+/// Service, to which broadcasts of given topic are accounted.
+pub(crate) fn broadcast(topic: &str) -> String {
+    format!("bcast:{topic}")
+}
+
+/// Service, to which inbound broadcasts are accounted. Broadcasts of topics
+/// without local subscribers are accounted to [`OTHER_SERVICE`].
+pub(crate) fn inbound_broadcast(topic: &str, subscribed: bool) -> String {
+    match subscribed {
+        true => broadcast(topic),
+        false => OTHER_SERVICE.to_string(),
+    }
+}
+
+pub(crate) fn record(node_id: Option<NodeId>, service: &str, direction: Direction, bytes: usize) {
+    let bytes = bytes as u64;
+    let service = {
+        let mut traffic = TRAFFIC.lock().unwrap();
+        traffic.record(node_id, service, direction, bytes);
+        traffic.accounted(service).to_string()
+    };
+    match direction {
+        Direction::Outbound => {
+            counter!("net.traffic.out.bytes", bytes, "service" => service.clone());
+            counter!("net.traffic.out.messages", 1, "service" => service);
+        }
+        Direction::Inbound => {
+            counter!("net.traffic.in.bytes", bytes, "service" => service.clone());
+            counter!("net.traffic.in.messages", 1, "service" => service);
+        }
+    }
+}
+
+pub(crate) fn record_error(node_id: Option<NodeId>, service: &str) {
+    let service = {
+        let mut traffic = TRAFFIC.lock().unwrap();
+        traffic.record_error(node_id, service);
+        traffic.accounted(service).to_string()
+    };
+    counter!("net.traffic.errors", 1, "service" => service);
+}
+
+pub(crate) fn stats() -> TrafficStats {
+    TRAFFIC.lock().unwrap().stats()
+}
+
+pub(crate) fn bind_service() {
+    let _ = bus::bind(
+        model::BUS_ID,
+        |_: model::Traffic| async move { Ok(stats()) },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_from_address() {
+        let node_id = "0x95369fc6fd02afeca110b9c32a21fb8ad899ee0a";
+        assert_eq!(
+            service(&format!("/net/{node_id}/market/protocol")),
+            "/market"
+        );
+        assert_eq!(service(&format!("/udp/net/{node_id}/vpn/x")), "/vpn");
+        assert_eq!(service(&format!("/transfer/net/{node_id}/gftp/x")), "/gftp");
+        assert_eq!(service("/public/activity/exec"), "/activity");
+        assert_eq!(service(&format!("/net/{node_id}")), "/");
+    }
+
+    #[test]
+    fn test_counters_per_node_and_service() {
+        let first = NodeId::from(&[1u8; 20][..]);
+        let second = NodeId::from(&[2u8; 20][..]);
+        let mut traffic = Traffic::default();
+        traffic.record(Some(first), "/market", Direction::Outbound, 100);
+        traffic.record(Some(first), "/gftp", Direction::Inbound, 1000);
+        traffic.record(Some(second), "/market", Direction::Inbound, 10);
+        traffic.record(None, "bcast:topic", Direction::Outbound, 5);
+        traffic.record_error(Some(second), "/market");
+
+        let stats = traffic.stats();
+        assert_eq!(
+            stats.total,
+            TrafficCounters {
+                tx_bytes: 105,
+                tx_messages: 2,
+                rx_bytes: 1010,
+                rx_messages: 2,
+                errors: 1,
+            }
+        );
+        assert_eq!(stats.nodes[0].node_id, first);
+        assert_eq!(stats.nodes[1].counters.errors, 1);
+        let services: Vec<_> = stats.services.iter().map(|s| s.service.as_str()).collect();
+        assert_eq!(services, vec!["/gftp", "/market", "bcast:topic"]);
+        assert_eq!(stats.services[1].counters.rx_bytes, 10);
+    }
+
+    #[test]
+    fn test_counters_limits() {
+        let mut traffic = Traffic::default();
+        for i in 0..MAX_TRACKED_SERVICES {
+            traffic.record(None, &format!("/service-{i}"), Direction::Inbound, 1);
+        }
+        traffic.record(None, "/service-0", Direction::Inbound, 1);
+        traffic.record(None, "/unknown", Direction::Inbound, 1);
+        let stats = traffic.stats();
+        assert_eq!(stats.services.len(), MAX_TRACKED_SERVICES + 1);
+        assert!(stats.services.iter().any(|s| s.service == OTHER_SERVICE));
+        assert!(!stats.services.iter().any(|s| s.service == "/unknown"));
+
+        let busy = NodeId::from(&[1u8; 20][..]);
+        traffic.record(Some(busy), "/market", Direction::Inbound, 1000);
+        for i in 0..MAX_TRACKED_NODES as u32 {
+            let mut id = [0u8; 20];
+            id[..4].copy_from_slice(&(i + 2).to_be_bytes());
+            traffic.record(
+                Some(NodeId::from(&id[..])),
+                "/market",
+                Direction::Inbound,
+                1,
+            );
+        }
+        assert_eq!(traffic.nodes.len(), MAX_TRACKED_NODES);
+        assert!(traffic.nodes.contains_key(&busy));
+    }
+}
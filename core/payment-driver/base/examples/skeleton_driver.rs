@@ -0,0 +1,175 @@
+/*
+    Skeleton of an external payment driver.
+
+    External drivers are separate processes started by yagna as extensions.
+    They connect to yagna's service bus (`GSB_URL` is inherited from yagna),
+    bind the driver service and register in the payment service with `RegisterDriver`.
+    Payment service pings registered drivers and unregisters the ones, which disconnect.
+
+    To run it with yagna, copy the binary to the extensions directory as `yagna-skeleton-driver`
+    and register it: `yagna extension register skeleton-driver`.
+*/
+
+use futures::channel::oneshot;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ya_client_model::payment::DriverStatusProperty;
+use ya_payment_driver::bus;
+use ya_payment_driver::driver::{
+    async_trait, BigDecimal, IdentityError, IdentityEvent, Network, PaymentDriver,
+};
+use ya_payment_driver::model::*;
+
+const DRIVER_NAME: &str = "skeleton";
+const NETWORK_NAME: &str = "skeleton";
+const TOKEN_NAME: &str = "GLM";
+const PLATFORM_NAME: &str = "skeleton-skeleton-glm";
+
+struct SkeletonDriver {
+    shutdown: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl SkeletonDriver {
+    fn not_implemented(&self, operation: &str) -> GenericError {
+        GenericError::new(format!(
+            "{} is not implemented by {} driver",
+            operation, DRIVER_NAME
+        ))
+    }
+}
+
+#[async_trait(?Send)]
+impl PaymentDriver for SkeletonDriver {
+    async fn account_event(
+        &self,
+        _caller: String,
+        msg: IdentityEvent,
+    ) -> Result<(), IdentityError> {
+        log::info!("account event: {:?}", msg);
+        Ok(())
+    }
+
+    async fn get_rpc_endpoints(
+        &self,
+        _caller: String,
+        _msg: GetRpcEndpoints,
+    ) -> Result<GetRpcEndpointsResult, GenericError> {
+        Err(self.not_implemented("GetRpcEndpoints"))
+    }
+
+    async fn get_account_balance(
+        &self,
+        _caller: String,
+        _msg: GetAccountBalance,
+    ) -> Result<BigDecimal, GenericError> {
+        Ok(BigDecimal::from(0))
+    }
+
+    async fn get_account_gas_balance(
+        &self,
+        _caller: String,
+        _msg: GetAccountGasBalance,
+    ) -> Result<Option<GasDetails>, GenericError> {
+        Ok(None)
+    }
+
+    async fn enter(&self, _caller: String, _msg: Enter) -> Result<String, GenericError> {
+        Err(self.not_implemented("Enter"))
+    }
+
+    async fn exit(&self, _caller: String, _msg: Exit) -> Result<String, GenericError> {
+        Err(self.not_implemented("Exit"))
+    }
+
+    fn get_name(&self) -> String {
+        DRIVER_NAME.to_string()
+    }
+
+    fn get_default_network(&self) -> String {
+        NETWORK_NAME.to_string()
+    }
+
+    fn get_networks(&self) -> HashMap<String, Network> {
+        let tokens = HashMap::from([(TOKEN_NAME.to_string(), PLATFORM_NAME.to_string())]);
+        let network = Network {
+            default_token: TOKEN_NAME.to_string(),
+            tokens,
+        };
+        HashMap::from([(NETWORK_NAME.to_string(), network)])
+    }
+
+    fn recv_init_required(&self) -> bool {
+        false
+    }
+
+    async fn init(&self, _caller: String, msg: Init) -> Result<Ack, GenericError> {
+        log::info!("init: {:?}", msg);
+        bus::register_account(self, &msg.address(), NETWORK_NAME, TOKEN_NAME, msg.mode()).await?;
+        Ok(Ack {})
+    }
+
+    async fn fund(&self, _caller: String, _msg: Fund) -> Result<String, GenericError> {
+        Err(self.not_implemented("Fund"))
+    }
+
+    async fn transfer(&self, _caller: String, _msg: Transfer) -> Result<String, GenericError> {
+        Err(self.not_implemented("Transfer"))
+    }
+
+    async fn schedule_payment(
+        &self,
+        _caller: String,
+        _msg: SchedulePayment,
+    ) -> Result<String, GenericError> {
+        Err(self.not_implemented("SchedulePayment"))
+    }
+
+    async fn verify_payment(
+        &self,
+        _caller: String,
+        _msg: VerifyPayment,
+    ) -> Result<PaymentDetails, GenericError> {
+        Err(self.not_implemented("VerifyPayment"))
+    }
+
+    async fn validate_allocation(
+        &self,
+        _caller: String,
+        _msg: ValidateAllocation,
+    ) -> Result<bool, GenericError> {
+        Ok(false)
+    }
+
+    async fn status(
+        &self,
+        _caller: String,
+        _msg: DriverStatus,
+    ) -> Result<Vec<DriverStatusProperty>, DriverStatusError> {
+        Ok(vec![])
+    }
+
+    async fn shut_down(&self, _caller: String, _msg: ShutDown) -> Result<(), GenericError> {
+        log::info!("Shutting down {} driver", DRIVER_NAME);
+        if let Some(shutdown) = self.shutdown.lock().unwrap().take() {
+            let _ = shutdown.send(());
+        }
+        Ok(())
+    }
+}
+
+#[actix_rt::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let (shutdown, shutdown_rx) = oneshot::channel();
+    let driver = SkeletonDriver {
+        shutdown: Mutex::new(Some(shutdown)),
+    };
+    bus::bind_service(Arc::new(driver)).await?;
+    log::info!("{} driver registered in payment service", DRIVER_NAME);
+
+    // Exiting with code 0 on yagna shutdown, so the extension isn't restarted.
+    futures::future::select(shutdown_rx, Box::pin(actix_rt::signal::ctrl_c())).await;
+    Ok(())
+}
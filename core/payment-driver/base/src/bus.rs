@@ -74,6 +74,12 @@ pub async fn bind_service<Driver: PaymentDriver + 'static>(
         .bind_with_processor(
             move |_, dr, c, m| async move { dr.validate_allocation( c, m).await }
         )
+        .bind_with_processor(
+            move |_, dr, c, m| async move { dr.make_deposit( c, m).await }
+        )
+        .bind_with_processor(
+            move |_, dr, c, m| async move { dr.release_deposit( c, m).await }
+        )
         .bind_with_processor(
             move |_, dr, c, m| async move { dr.sign_payment( c, m).await }
         )
@@ -83,6 +89,9 @@ pub async fn bind_service<Driver: PaymentDriver + 'static>(
         .bind_with_processor(
             move |_, dr, c, m| async move { dr.status( c, m).await }
         )
+        .bind_with_processor(
+            move |_, dr, c, m| async move { dr.ping( c, m).await }
+        )
         .bind_with_processor(
             move |_, dr, c, m| async move { dr.shut_down( c, m).await }
         );
@@ -194,6 +203,26 @@ pub async fn notify_payment(
     Ok(())
 }
 
+pub async fn notify_payment_failed(
+    driver_name: &str,
+    platform: &str,
+    order_ids: Vec<String>,
+    reason: String,
+) -> Result<(), GenericError> {
+    let msg = payment_srv::NotifyPaymentFailed {
+        driver: driver_name.to_string(),
+        platform: platform.to_string(),
+        order_ids,
+        reason,
+    };
+    service(payment_srv::BUS_ID)
+        .send(msg)
+        .await
+        .map_err(GenericError::new)?
+        .map_err(GenericError::new)?;
+    Ok(())
+}
+
 pub async fn status_changed(properties: Vec<DriverStatusProperty>) -> Result<(), GenericError> {
     let msg = PaymentDriverStatusChange { properties };
     service(payment_srv::BUS_ID)
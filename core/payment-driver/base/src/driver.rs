@@ -89,6 +89,22 @@ pub trait PaymentDriver {
         msg: ValidateAllocation,
     ) -> Result<bool, GenericError>;
 
+    /// Reserves funds for an allocation created with `makeDeposit` flag.
+    async fn make_deposit(&self, _caller: String, _msg: MakeDeposit) -> Result<Ack, GenericError> {
+        Err(GenericError::new(format!(
+            "Deposits are not supported by {} driver",
+            self.get_name()
+        )))
+    }
+
+    async fn release_deposit(
+        &self,
+        _caller: String,
+        _msg: ReleaseDeposit,
+    ) -> Result<Ack, GenericError> {
+        Ok(Ack {})
+    }
+
     async fn sign_payment(
         &self,
 
@@ -130,5 +146,10 @@ pub trait PaymentDriver {
         _msg: DriverStatus,
     ) -> Result<Vec<DriverStatusProperty>, DriverStatusError>;
 
+    /// Answers liveness checks of the payment service.
+    async fn ping(&self, _caller: String, _msg: Ping) -> Result<(), GenericError> {
+        Ok(())
+    }
+
     async fn shut_down(&self, caller: String, msg: ShutDown) -> Result<(), GenericError>;
 }
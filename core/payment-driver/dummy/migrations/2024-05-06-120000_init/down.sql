@@ -0,0 +1,5 @@
+DROP TABLE `transaction`;
+
+DROP TABLE deposit;
+
+DROP TABLE account;
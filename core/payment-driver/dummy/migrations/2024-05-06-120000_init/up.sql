@@ -0,0 +1,28 @@
+CREATE TABLE account(
+    address VARCHAR(50) NOT NULL PRIMARY KEY,
+    balance TEXT NOT NULL
+);
+
+CREATE TABLE deposit(
+    address VARCHAR(50) NOT NULL,
+    allocation_id VARCHAR(50) NOT NULL,
+    amount TEXT NOT NULL,
+    PRIMARY KEY(address, allocation_id)
+);
+
+CREATE TABLE `transaction`(
+    tx_hash VARCHAR(66) NOT NULL PRIMARY KEY,
+    tx_type INTEGER NOT NULL,
+    sender VARCHAR(50) NOT NULL,
+    recipient VARCHAR(50) NOT NULL,
+    nonce INTEGER NOT NULL,
+    amount TEXT NOT NULL,
+    order_id VARCHAR(50) NULL,
+    status INTEGER NOT NULL,
+    time_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    time_confirmed DATETIME NULL,
+    notified BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX transaction_sender_idx ON `transaction` (sender);
+CREATE INDEX transaction_status_idx ON `transaction` (status, notified);
@@ -0,0 +1,120 @@
+/*
+    Dummy driver configuration.
+
+    Failure injection and confirmation delays are set with `yagna service run` options,
+    which fall back to environment variables:
+
+    --dummy-driver-failures                     DUMMY_DRIVER_FAILURES
+    --dummy-driver-confirmation-delay-ms        DUMMY_DRIVER_CONFIRMATION_DELAY_MS
+    --dummy-driver-slow-confirmation-delay-secs DUMMY_DRIVER_SLOW_CONFIRMATION_DELAY_SECS
+
+    Balances are read from environment only:
+
+    DUMMY_DRIVER_INITIAL_BALANCE                balance of an address seen for the first time
+    DUMMY_DRIVER_FUND_AMOUNT                    amount added by `yagna payment fund`
+*/
+
+// External crates
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// Every transfer, payment and deposit fails as if the sender had no funds.
+    InsufficientFunds,
+    /// Transactions are never confirmed. Debited funds are returned to the sender.
+    DroppedTx,
+    /// Transactions are confirmed after the slow confirmation delay.
+    SlowConfirmation,
+}
+
+impl FromStr for Failure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "insufficient-funds" => Ok(Failure::InsufficientFunds),
+            "dropped-tx" => Ok(Failure::DroppedTx),
+            "slow-confirmation" => Ok(Failure::SlowConfirmation),
+            _ => Err(format!("Unknown dummy driver failure: {}", s)),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref INITIAL_BALANCE: BigDecimal = std::env::var("DUMMY_DRIVER_INITIAL_BALANCE")
+        .ok()
+        .and_then(|x| BigDecimal::from_str(&x).ok())
+        .unwrap_or_else(|| BigDecimal::from_str("1000000000000000000000000").unwrap());
+    pub static ref FUND_AMOUNT: BigDecimal = std::env::var("DUMMY_DRIVER_FUND_AMOUNT")
+        .ok()
+        .and_then(|x| BigDecimal::from_str(&x).ok())
+        .unwrap_or_else(|| BigDecimal::from(1000));
+}
+
+#[derive(StructOpt, Clone, Debug)]
+pub struct DummyDriverConfig {
+    /// Comma separated failures injected by the dummy driver:
+    /// insufficient-funds, dropped-tx or slow-confirmation
+    #[structopt(
+        long = "dummy-driver-failures",
+        env = "DUMMY_DRIVER_FAILURES",
+        use_delimiter = true
+    )]
+    pub failures: Vec<Failure>,
+
+    /// Delay of dummy transaction confirmation in milliseconds
+    #[structopt(
+        long = "dummy-driver-confirmation-delay-ms",
+        env = "DUMMY_DRIVER_CONFIRMATION_DELAY_MS",
+        default_value = "100"
+    )]
+    pub confirmation_delay_ms: u64,
+
+    /// Delay of dummy transaction confirmation in seconds used with slow-confirmation failure
+    #[structopt(
+        long = "dummy-driver-slow-confirmation-delay-secs",
+        env = "DUMMY_DRIVER_SLOW_CONFIRMATION_DELAY_SECS",
+        default_value = "300"
+    )]
+    pub slow_confirmation_delay_secs: u64,
+}
+
+impl DummyDriverConfig {
+    pub fn fails(&self, failure: Failure) -> bool {
+        self.failures.contains(&failure)
+    }
+
+    pub fn confirmation_delay(&self) -> Duration {
+        match self.fails(Failure::SlowConfirmation) {
+            true => Duration::from_secs(self.slow_confirmation_delay_secs),
+            false => Duration::from_millis(self.confirmation_delay_ms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_args() {
+        let config = DummyDriverConfig::from_iter_safe(&[
+            "dummy",
+            "--dummy-driver-failures",
+            "dropped-tx,slow-confirmation",
+            "--dummy-driver-slow-confirmation-delay-secs",
+            "5",
+        ])
+        .unwrap();
+        assert!(config.fails(Failure::DroppedTx));
+        assert!(!config.fails(Failure::InsufficientFunds));
+        assert_eq!(config.confirmation_delay(), Duration::from_secs(5));
+        assert!(
+            DummyDriverConfig::from_iter_safe(&["dummy", "--dummy-driver-failures", "oops"])
+                .is_err()
+        );
+    }
+}
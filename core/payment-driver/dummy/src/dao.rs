@@ -0,0 +1,465 @@
+/*
+    Data access object for dummy accounts, deposits and transactions.
+
+    Balances are stored as decimal strings. Addresses seen for the first time
+    start with `INITIAL_BALANCE`.
+*/
+
+// External crates
+use bigdecimal::{BigDecimal, Zero};
+use chrono::Utc;
+use diesel::{self, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use sha3::{Digest, Sha3_256};
+use std::str::FromStr;
+
+// Workspace uses
+use ya_payment_driver::dao::{DbError, DbResult};
+use ya_persistence::executor::{
+    do_with_transaction, readonly_transaction, AsDao, ConnType, PoolType,
+};
+
+// Local uses
+use crate::config::INITIAL_BALANCE;
+use crate::db::models::{
+    AccountEntity, DepositEntity, TransactionEntity, TxType, TX_STATUS_CONFIRMED,
+    TX_STATUS_DROPPED, TX_STATUS_PENDING,
+};
+use crate::db::schema::{account, deposit, transaction};
+
+/// Fake transaction hash. The same transfer sent with the same nonce always gets the same hash.
+pub fn tx_hash(sender: &str, recipient: &str, amount: &BigDecimal, nonce: i32) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(format!("{}:{}:{}:{}", sender, recipient, amount, nonce).as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+fn parse_amount(amount: &str) -> DbResult<BigDecimal> {
+    BigDecimal::from_str(amount)
+        .map_err(|e| DbError::InvalidData(format!("Invalid amount {}: {}", amount, e)))
+}
+
+fn get_balance(conn: &ConnType, address: &str) -> DbResult<BigDecimal> {
+    let balance: Option<String> = account::table
+        .find(address)
+        .select(account::balance)
+        .first(conn)
+        .optional()?;
+    match balance {
+        Some(balance) => parse_amount(&balance),
+        None => Ok(INITIAL_BALANCE.clone()),
+    }
+}
+
+fn set_balance(conn: &ConnType, address: &str, balance: BigDecimal) -> DbResult<()> {
+    diesel::replace_into(account::table)
+        .values(AccountEntity {
+            address: address.to_string(),
+            balance: balance.to_string(),
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+fn get_deposited(conn: &ConnType, address: &str, except: Option<&str>) -> DbResult<BigDecimal> {
+    let deposits: Vec<DepositEntity> = deposit::table
+        .filter(deposit::address.eq(address))
+        .load(conn)?;
+    let mut total = BigDecimal::zero();
+    for deposit in deposits {
+        if Some(deposit.allocation_id.as_str()) != except {
+            total += parse_amount(&deposit.amount)?;
+        }
+    }
+    Ok(total)
+}
+
+pub struct DummyDao<'c> {
+    pool: &'c PoolType,
+}
+
+impl<'c> AsDao<'c> for DummyDao<'c> {
+    fn as_dao(pool: &'c PoolType) -> Self {
+        Self { pool }
+    }
+}
+
+impl<'c> DummyDao<'c> {
+    /// Balance of the address, including funds reserved by deposits.
+    pub async fn get_balance(&self, address: String) -> DbResult<BigDecimal> {
+        readonly_transaction(self.pool, "dummy_dao_get_balance", move |conn| {
+            get_balance(conn, &address)
+        })
+        .await
+    }
+
+    /// Funds reserved by deposits of the address.
+    pub async fn get_deposited(&self, address: String) -> DbResult<BigDecimal> {
+        readonly_transaction(self.pool, "dummy_dao_get_deposited", move |conn| {
+            get_deposited(conn, &address, None)
+        })
+        .await
+    }
+
+    pub async fn fund(&self, address: String, amount: BigDecimal) -> DbResult<BigDecimal> {
+        do_with_transaction(self.pool, "dummy_dao_fund", move |conn| {
+            let balance = get_balance(conn, &address)? + amount;
+            set_balance(conn, &address, balance.clone())?;
+            Ok(balance)
+        })
+        .await
+    }
+
+    /// Reserves funds for the allocation, replacing previous deposit.
+    /// Returns `false` if there are not enough funds not reserved by other deposits.
+    pub async fn make_deposit(
+        &self,
+        address: String,
+        allocation_id: String,
+        amount: BigDecimal,
+    ) -> DbResult<bool> {
+        do_with_transaction(self.pool, "dummy_dao_make_deposit", move |conn| {
+            let reserved = get_deposited(conn, &address, Some(&allocation_id))?;
+            if reserved + &amount > get_balance(conn, &address)? {
+                return Ok(false);
+            }
+            diesel::replace_into(deposit::table)
+                .values(DepositEntity {
+                    address,
+                    allocation_id,
+                    amount: amount.to_string(),
+                })
+                .execute(conn)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    pub async fn release_deposit(&self, address: String, allocation_id: String) -> DbResult<()> {
+        do_with_transaction(self.pool, "dummy_dao_release_deposit", move |conn| {
+            diesel::delete(
+                deposit::table
+                    .filter(deposit::address.eq(address))
+                    .filter(deposit::allocation_id.eq(allocation_id)),
+            )
+            .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_transaction(&self, tx_hash: String) -> DbResult<Option<TransactionEntity>> {
+        readonly_transaction(self.pool, "dummy_dao_get_transaction", move |conn| {
+            let tx = transaction::table.find(tx_hash).first(conn).optional()?;
+            Ok(tx)
+        })
+        .await
+    }
+
+    /// Transactions waiting for confirmation, oldest first.
+    pub async fn get_pending_transactions(&self) -> DbResult<Vec<TransactionEntity>> {
+        readonly_transaction(
+            self.pool,
+            "dummy_dao_get_pending_transactions",
+            move |conn| {
+                let txs = transaction::table
+                    .filter(transaction::status.eq(TX_STATUS_PENDING))
+                    .order_by(transaction::time_created.asc())
+                    .load(conn)?;
+                Ok(txs)
+            },
+        )
+        .await
+    }
+
+    /// Confirmed or dropped payments, which weren't reported to the payment service yet.
+    pub async fn get_unnotified_transactions(&self) -> DbResult<Vec<TransactionEntity>> {
+        readonly_transaction(
+            self.pool,
+            "dummy_dao_get_unnotified_transactions",
+            move |conn| {
+                let txs = transaction::table
+                    .filter(transaction::status.ne(TX_STATUS_PENDING))
+                    .filter(transaction::notified.eq(false))
+                    .order_by(transaction::time_created.asc())
+                    .load(conn)?;
+                Ok(txs)
+            },
+        )
+        .await
+    }
+
+    pub async fn set_notified(&self, tx_hash: String) -> DbResult<()> {
+        do_with_transaction(self.pool, "dummy_dao_set_notified", move |conn| {
+            diesel::update(transaction::table.find(tx_hash))
+                .set(transaction::notified.eq(true))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Creates pending transaction and debits the sender if required by transaction type.
+    /// Returns `None` if the sender doesn't have enough funds.
+    pub async fn insert_transaction(
+        &self,
+        tx_type: TxType,
+        sender: String,
+        recipient: String,
+        amount: BigDecimal,
+        order_id: Option<String>,
+    ) -> DbResult<Option<TransactionEntity>> {
+        do_with_transaction(self.pool, "dummy_dao_insert_transaction", move |conn| {
+            if tx_type.debits_sender() {
+                let balance = get_balance(conn, &sender)?;
+                if balance < amount {
+                    return Ok(None);
+                }
+                set_balance(conn, &sender, balance - &amount)?;
+            }
+
+            let sent: i64 = transaction::table
+                .filter(transaction::sender.eq(&sender))
+                .count()
+                .get_result(conn)?;
+            let nonce = sent as i32;
+            let tx = TransactionEntity {
+                tx_hash: tx_hash(&sender, &recipient, &amount, nonce),
+                tx_type: tx_type as i32,
+                sender,
+                recipient,
+                nonce,
+                amount: amount.to_string(),
+                order_id,
+                status: TX_STATUS_PENDING,
+                time_created: Utc::now().naive_utc(),
+                time_confirmed: None,
+                notified: tx_type != TxType::Payment,
+            };
+            diesel::insert_into(transaction::table)
+                .values(tx.clone())
+                .execute(conn)?;
+            Ok(Some(tx))
+        })
+        .await
+    }
+
+    /// Marks pending transaction as confirmed and credits the recipient if required.
+    pub async fn confirm_transaction(&self, tx_hash: String) -> DbResult<TransactionEntity> {
+        self.finish_transaction(tx_hash, TX_STATUS_CONFIRMED).await
+    }
+
+    /// Marks pending transaction as dropped and returns debited funds to the sender.
+    pub async fn drop_transaction(&self, tx_hash: String) -> DbResult<TransactionEntity> {
+        self.finish_transaction(tx_hash, TX_STATUS_DROPPED).await
+    }
+
+    async fn finish_transaction(
+        &self,
+        tx_hash: String,
+        status: i32,
+    ) -> DbResult<TransactionEntity> {
+        do_with_transaction(self.pool, "dummy_dao_finish_transaction", move |conn| {
+            let mut tx: TransactionEntity = transaction::table.find(&tx_hash).first(conn)?;
+            if tx.status != TX_STATUS_PENDING {
+                return Err(DbError::InvalidData(format!(
+                    "Transaction {} is not pending",
+                    tx_hash
+                )));
+            }
+            let tx_type = TxType::from_i32(tx.tx_type)
+                .ok_or_else(|| DbError::InvalidData(format!("Unknown tx type: {}", tx.tx_type)))?;
+            let amount = parse_amount(&tx.amount)?;
+
+            if status == TX_STATUS_CONFIRMED && tx_type.credits_recipient() {
+                let balance = get_balance(conn, &tx.recipient)? + amount;
+                set_balance(conn, &tx.recipient, balance)?;
+            } else if status == TX_STATUS_DROPPED && tx_type.debits_sender() {
+                let balance = get_balance(conn, &tx.sender)? + amount;
+                set_balance(conn, &tx.sender, balance)?;
+            }
+
+            tx.status = status;
+            tx.time_confirmed = match status {
+                TX_STATUS_CONFIRMED => Some(Utc::now().naive_utc()),
+                _ => None,
+            };
+            diesel::update(transaction::table.find(&tx_hash))
+                .set((
+                    transaction::status.eq(tx.status),
+                    transaction::time_confirmed.eq(tx.time_confirmed),
+                ))
+                .execute(conn)?;
+            Ok(tx)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ya_persistence::executor::DbExecutor;
+
+    fn dao_db(name: &str) -> DbExecutor {
+        let db = DbExecutor::in_memory(name).unwrap();
+        db.apply_migration(crate::db::migrations::run_with_output)
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_tx_hash() {
+        let amount = BigDecimal::from(10);
+        let hash = tx_hash("0xa", "0xb", &amount, 0);
+        assert_eq!(hash.len(), 66);
+        assert_eq!(hash, tx_hash("0xa", "0xb", &amount, 0));
+        assert_ne!(hash, tx_hash("0xa", "0xb", &amount, 1));
+        assert_ne!(hash, tx_hash("0xb", "0xa", &amount, 0));
+    }
+
+    #[actix_rt::test]
+    async fn test_balance_and_fund() {
+        let db = dao_db("dummy-dao-balance");
+        let dao = db.as_dao::<DummyDao>();
+        let address = "0xa".to_string();
+
+        assert_eq!(
+            dao.get_balance(address.clone()).await.unwrap(),
+            *INITIAL_BALANCE
+        );
+        let funded = dao
+            .fund(address.clone(), BigDecimal::from(10))
+            .await
+            .unwrap();
+        assert_eq!(funded, INITIAL_BALANCE.clone() + BigDecimal::from(10));
+        assert_eq!(dao.get_balance(address).await.unwrap(), funded);
+    }
+
+    #[actix_rt::test]
+    async fn test_deposits_reserve_balance() {
+        let db = dao_db("dummy-dao-deposit");
+        let dao = db.as_dao::<DummyDao>();
+        let address = "0xa".to_string();
+        let half = INITIAL_BALANCE.clone() / BigDecimal::from(2);
+
+        assert!(dao
+            .make_deposit(address.clone(), "alloc-1".into(), half.clone())
+            .await
+            .unwrap());
+        // Replacing deposit of the same allocation doesn't count the previous amount.
+        assert!(dao
+            .make_deposit(address.clone(), "alloc-1".into(), half.clone())
+            .await
+            .unwrap());
+        assert!(dao
+            .make_deposit(address.clone(), "alloc-2".into(), half.clone())
+            .await
+            .unwrap());
+        assert!(!dao
+            .make_deposit(address.clone(), "alloc-3".into(), BigDecimal::from(1))
+            .await
+            .unwrap());
+        assert_eq!(
+            dao.get_deposited(address.clone()).await.unwrap(),
+            INITIAL_BALANCE.clone()
+        );
+
+        dao.release_deposit(address.clone(), "alloc-1".into())
+            .await
+            .unwrap();
+        assert_eq!(dao.get_deposited(address).await.unwrap(), half);
+    }
+
+    #[actix_rt::test]
+    async fn test_confirm_transaction() {
+        let db = dao_db("dummy-dao-confirm");
+        let dao = db.as_dao::<DummyDao>();
+        let amount = BigDecimal::from(10);
+
+        let tx = dao
+            .insert_transaction(
+                TxType::Payment,
+                "0xa".into(),
+                "0xb".into(),
+                amount.clone(),
+                Some("order".into()),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tx.status, TX_STATUS_PENDING);
+        assert!(!tx.notified);
+        assert_eq!(
+            dao.get_balance("0xa".into()).await.unwrap(),
+            INITIAL_BALANCE.clone() - &amount
+        );
+        // Recipient is credited only after confirmation.
+        assert_eq!(
+            dao.get_balance("0xb".into()).await.unwrap(),
+            *INITIAL_BALANCE
+        );
+        assert_eq!(
+            dao.get_pending_transactions().await.unwrap(),
+            vec![tx.clone()]
+        );
+
+        let confirmed = dao.confirm_transaction(tx.tx_hash.clone()).await.unwrap();
+        assert_eq!(confirmed.status, TX_STATUS_CONFIRMED);
+        assert!(confirmed.time_confirmed.is_some());
+        assert_eq!(
+            dao.get_balance("0xb".into()).await.unwrap(),
+            INITIAL_BALANCE.clone() + &amount
+        );
+        assert!(dao.get_pending_transactions().await.unwrap().is_empty());
+        assert!(dao.confirm_transaction(tx.tx_hash.clone()).await.is_err());
+
+        assert_eq!(
+            dao.get_unnotified_transactions().await.unwrap(),
+            vec![confirmed]
+        );
+        dao.set_notified(tx.tx_hash).await.unwrap();
+        assert!(dao.get_unnotified_transactions().await.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_drop_transaction() {
+        let db = dao_db("dummy-dao-drop");
+        let dao = db.as_dao::<DummyDao>();
+        let amount = BigDecimal::from(10);
+
+        // Not enough funds.
+        assert!(dao
+            .insert_transaction(
+                TxType::Transfer,
+                "0xa".into(),
+                "0xb".into(),
+                INITIAL_BALANCE.clone() + &amount,
+                None,
+            )
+            .await
+            .unwrap()
+            .is_none());
+
+        let tx = dao
+            .insert_transaction(TxType::Transfer, "0xa".into(), "0xb".into(), amount, None)
+            .await
+            .unwrap()
+            .unwrap();
+        // Only payments are reported to the payment service.
+        assert!(tx.notified);
+
+        let dropped = dao.drop_transaction(tx.tx_hash.clone()).await.unwrap();
+        assert_eq!(dropped.status, TX_STATUS_DROPPED);
+        assert!(dropped.time_confirmed.is_none());
+        assert_eq!(
+            dao.get_balance("0xa".into()).await.unwrap(),
+            *INITIAL_BALANCE
+        );
+        assert_eq!(
+            dao.get_balance("0xb".into()).await.unwrap(),
+            *INITIAL_BALANCE
+        );
+        assert!(dao.get_pending_transactions().await.unwrap().is_empty());
+        assert!(dao.get_unnotified_transactions().await.unwrap().is_empty());
+    }
+}
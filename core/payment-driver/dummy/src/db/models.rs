@@ -0,0 +1,82 @@
+/*
+    Raw database models.
+*/
+
+// External crates
+use chrono::NaiveDateTime;
+
+// Local uses
+use crate::db::schema::*;
+
+pub const TX_STATUS_PENDING: i32 = 1;
+pub const TX_STATUS_CONFIRMED: i32 = 2;
+pub const TX_STATUS_DROPPED: i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxType {
+    /// Payment scheduled by the payment service. Confirmation is sent with `NotifyPayment`,
+    /// dropped payment is reported with `NotifyPaymentFailed`.
+    Payment = 0,
+    Transfer = 1,
+    /// Funds entering dummy network. Only the recipient is credited.
+    Enter = 2,
+    /// Funds leaving dummy network. Only the sender is debited.
+    Exit = 3,
+}
+
+impl TxType {
+    pub fn from_i32(tx_type: i32) -> Option<Self> {
+        match tx_type {
+            0 => Some(TxType::Payment),
+            1 => Some(TxType::Transfer),
+            2 => Some(TxType::Enter),
+            3 => Some(TxType::Exit),
+            _ => None,
+        }
+    }
+
+    /// Sender's balance is debited when the transaction is sent.
+    pub fn debits_sender(&self) -> bool {
+        !matches!(self, TxType::Enter)
+    }
+
+    /// Recipient's balance is credited when the transaction is confirmed.
+    pub fn credits_recipient(&self) -> bool {
+        !matches!(self, TxType::Exit)
+    }
+}
+
+#[derive(Queryable, Clone, Debug, Identifiable, Insertable, PartialEq, Eq)]
+#[primary_key(address)]
+#[table_name = "account"]
+pub struct AccountEntity {
+    pub address: String,
+    pub balance: String,
+}
+
+#[derive(Queryable, Clone, Debug, Insertable, PartialEq, Eq)]
+#[table_name = "deposit"]
+pub struct DepositEntity {
+    pub address: String,
+    pub allocation_id: String,
+    pub amount: String,
+}
+
+#[derive(Queryable, Clone, Debug, Identifiable, Insertable, PartialEq, Eq)]
+#[primary_key(tx_hash)]
+#[table_name = "transaction"]
+pub struct TransactionEntity {
+    pub tx_hash: String,
+    pub tx_type: i32,
+    pub sender: String,
+    pub recipient: String,
+    pub nonce: i32,
+    pub amount: String,
+    pub order_id: Option<String>,
+    pub status: i32,
+    pub time_created: NaiveDateTime,
+    pub time_confirmed: Option<NaiveDateTime>,
+    /// Payment service was notified about confirmed or dropped payment.
+    /// Always set for transactions other than payments.
+    pub notified: bool,
+}
@@ -0,0 +1,32 @@
+table! {
+    account (address) {
+        address -> Text,
+        balance -> Text,
+    }
+}
+
+table! {
+    deposit (address, allocation_id) {
+        address -> Text,
+        allocation_id -> Text,
+        amount -> Text,
+    }
+}
+
+table! {
+    transaction (tx_hash) {
+        tx_hash -> Text,
+        tx_type -> Integer,
+        sender -> Text,
+        recipient -> Text,
+        nonce -> Integer,
+        amount -> Text,
+        order_id -> Nullable<Text>,
+        status -> Integer,
+        time_created -> Timestamp,
+        time_confirmed -> Nullable<Timestamp>,
+        notified -> Bool,
+    }
+}
+
+allow_tables_to_appear_in_same_query!(account, deposit, transaction,);
@@ -1,5 +1,17 @@
+#[macro_use]
+extern crate diesel;
+
+mod config;
+mod dao;
+mod db;
 mod service;
 
+use std::path::PathBuf;
+
+use ya_persistence::executor::DbExecutor;
+
+pub use config::{DummyDriverConfig, Failure};
+
 pub const DRIVER_NAME: &str = "dummy";
 pub const NETWORK_NAME: &str = "dummy";
 pub const TOKEN_NAME: &str = "GLM";
@@ -8,9 +20,13 @@ pub const PLATFORM_NAME: &str = "dummy-glm";
 pub struct PaymentDriverService;
 
 impl PaymentDriverService {
-    pub async fn gsb<Context>(_context: &Context) -> anyhow::Result<()> {
-        self::service::bind_service();
+    pub async fn gsb(path: PathBuf, config: DummyDriverConfig) -> anyhow::Result<()> {
+        let db = DbExecutor::from_data_dir(&path, "dummy-driver")?;
+        db.apply_migration(self::db::migrations::run_with_output)?;
+        let ctx = self::service::DriverContext::new(config);
+        self::service::bind_service(&db, ctx.clone());
         self::service::register_in_payment_service().await?;
+        tokio::task::spawn_local(self::service::confirmation_job(db, ctx));
         Ok(())
     }
 }
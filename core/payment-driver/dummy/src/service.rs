@@ -1,27 +1,75 @@
+use crate::config::{DummyDriverConfig, Failure, FUND_AMOUNT};
+use crate::dao::DummyDao;
+use crate::db::models::{TransactionEntity, TxType, TX_STATUS_CONFIRMED, TX_STATUS_DROPPED};
 use crate::{DRIVER_NAME, NETWORK_NAME, PLATFORM_NAME, TOKEN_NAME};
-use bigdecimal::BigDecimal;
-use chrono::Utc;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{TimeZone, Utc};
 use maplit::hashmap;
-use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
 use uuid::Uuid;
 use ya_client_model::payment::{DriverDetails, Network};
 use ya_core_model::driver::*;
 use ya_core_model::payment::local as payment_srv;
+use ya_persistence::executor::DbExecutor;
 use ya_service_bus::typed::service;
 use ya_service_bus::{typed as bus, RpcEndpoint};
 
-pub fn bind_service() {
+/// Payment confirmation is self-contained, so it can be verified by
+/// a dummy driver running on another node.
+#[derive(Serialize, Deserialize)]
+struct DummyConfirmation {
+    #[serde(default)]
+    tx_hash: Option<String>,
+    #[serde(flatten)]
+    details: PaymentDetails,
+}
+
+/// Upper bound of confirmation job sleep. Notifications, which couldn't be
+/// delivered to the payment service, are retried that often.
+const MAX_JOB_INTERVAL: Duration = Duration::from_secs(10);
+
+/// State shared by bus handlers and the confirmation job.
+#[derive(Clone)]
+pub struct DriverContext {
+    config: Arc<DummyDriverConfig>,
+    /// Wakes the confirmation job up, when a transaction is sent.
+    tx_sent: Arc<Notify>,
+}
+
+impl DriverContext {
+    pub fn new(config: DummyDriverConfig) -> Self {
+        DriverContext {
+            config: Arc::new(config),
+            tx_sent: Default::default(),
+        }
+    }
+}
+
+fn insufficient_funds(address: &str) -> GenericError {
+    GenericError::new(format!("Insufficient funds on {}", address))
+}
+
+pub fn bind_service(db: &DbExecutor, ctx: DriverContext) {
     log::debug!("Binding payment driver service to service bus");
 
-    bus::ServiceBinder::new(&driver_bus_id(DRIVER_NAME), &(), ())
+    bus::ServiceBinder::new(&driver_bus_id(DRIVER_NAME), db, ctx)
         .bind(init)
         .bind(get_account_balance)
-        .bind(schedule_payment)
+        .bind_with_processor(schedule_payment)
         .bind(verify_payment)
-        .bind(validate_allocation)
+        .bind_with_processor(validate_allocation)
+        .bind_with_processor(make_deposit)
+        .bind(release_deposit)
         .bind(fund)
+        .bind_with_processor(transfer)
+        .bind_with_processor(enter)
+        .bind_with_processor(exit)
         .bind(sign_payment)
         .bind(verify_signature)
+        .bind(ping)
         .bind(shut_down);
 
     log::debug!("Successfully bound payment driver service to service bus");
@@ -51,7 +99,157 @@ pub async fn register_in_payment_service() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn init(_db: (), _caller: String, msg: Init) -> Result<Ack, GenericError> {
+/// Creates pending transaction. It is confirmed by the confirmation job after configured delay.
+async fn send_transaction(
+    db: &DbExecutor,
+    ctx: &DriverContext,
+    tx_type: TxType,
+    sender: String,
+    recipient: String,
+    amount: BigDecimal,
+    order_id: Option<String>,
+) -> Result<TransactionEntity, GenericError> {
+    if tx_type.debits_sender() && ctx.config.fails(Failure::InsufficientFunds) {
+        return Err(insufficient_funds(&sender));
+    }
+    let tx = db
+        .as_dao::<DummyDao>()
+        .insert_transaction(tx_type, sender.clone(), recipient, amount, order_id)
+        .await
+        .map_err(GenericError::new)?
+        .ok_or_else(|| insufficient_funds(&sender))?;
+    ctx.tx_sent.notify_one();
+    Ok(tx)
+}
+
+/// Confirms transactions and reports payments to the payment service. Pending
+/// transactions are kept in the database, so the ones left by previous run are
+/// resumed. Runs separately from bus handlers, because calling payment service
+/// while handling a call from payment service would result in a deadlock.
+pub async fn confirmation_job(db: DbExecutor, ctx: DriverContext) {
+    loop {
+        let wait = match process_transactions(&db, &ctx.config).await {
+            Ok(Some(wait)) => wait.min(MAX_JOB_INTERVAL),
+            Ok(None) => MAX_JOB_INTERVAL,
+            Err(e) => {
+                log::error!("Failed to process dummy transactions: {}", e);
+                MAX_JOB_INTERVAL
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = ctx.tx_sent.notified() => {}
+        }
+    }
+}
+
+/// Confirms or drops transactions pending longer than confirmation delay and
+/// notifies payment service about finished payments.
+/// Returns time left until the next pending transaction is due.
+async fn process_transactions(
+    db: &DbExecutor,
+    config: &DummyDriverConfig,
+) -> Result<Option<Duration>, GenericError> {
+    let dao = db.as_dao::<DummyDao>();
+    let delay =
+        chrono::Duration::from_std(config.confirmation_delay()).map_err(GenericError::new)?;
+    let now = Utc::now().naive_utc();
+
+    let mut next_due = None;
+    for tx in dao
+        .get_pending_transactions()
+        .await
+        .map_err(GenericError::new)?
+    {
+        let due = tx.time_created + delay;
+        if due > now {
+            next_due = Some(due);
+            break;
+        }
+        if config.fails(Failure::DroppedTx) {
+            dao.drop_transaction(tx.tx_hash.clone())
+                .await
+                .map_err(GenericError::new)?;
+            log::warn!("Dropped transaction {}", tx.tx_hash);
+        } else {
+            dao.confirm_transaction(tx.tx_hash.clone())
+                .await
+                .map_err(GenericError::new)?;
+            log::info!("Confirmed transaction {}", tx.tx_hash);
+        }
+    }
+
+    for tx in dao
+        .get_unnotified_transactions()
+        .await
+        .map_err(GenericError::new)?
+    {
+        let tx_hash = tx.tx_hash.clone();
+        match notify_payment_service(tx).await {
+            Ok(()) => dao.set_notified(tx_hash).await.map_err(GenericError::new)?,
+            Err(e) => log::warn!(
+                "Failed to notify payment service about transaction {}: {}. Will retry.",
+                tx_hash,
+                e
+            ),
+        }
+    }
+
+    Ok(next_due.map(|due| (due - now).to_std().unwrap_or_default()))
+}
+
+/// Sends `NotifyPayment` for confirmed payment or `NotifyPaymentFailed` for dropped one.
+async fn notify_payment_service(tx: TransactionEntity) -> Result<(), GenericError> {
+    let order_ids: Vec<String> = tx.order_id.into_iter().collect();
+    if tx.status == TX_STATUS_DROPPED {
+        return ya_payment_driver::bus::notify_payment_failed(
+            DRIVER_NAME,
+            PLATFORM_NAME,
+            order_ids,
+            format!(
+                "Transaction {} was dropped. Dummy driver doesn't resend dropped transactions.",
+                tx.tx_hash
+            ),
+        )
+        .await;
+    }
+    if tx.status != TX_STATUS_CONFIRMED {
+        return Err(GenericError::new(format!(
+            "Transaction {} is pending",
+            tx.tx_hash
+        )));
+    }
+
+    let amount: BigDecimal = tx.amount.parse().map_err(GenericError::new)?;
+    let confirmed_at = tx.time_confirmed.map(|date| Utc.from_utc_datetime(&date));
+    let confirmation = DummyConfirmation {
+        tx_hash: Some(tx.tx_hash),
+        details: PaymentDetails {
+            recipient: tx.recipient.clone(),
+            sender: tx.sender.clone(),
+            amount: amount.clone(),
+            date: confirmed_at,
+        },
+    };
+    let confirmation = serde_json::to_vec(&confirmation).map_err(GenericError::new)?;
+    let msg = payment_srv::NotifyPayment {
+        driver: DRIVER_NAME.to_string(),
+        platform: PLATFORM_NAME.to_string(),
+        amount,
+        sender: tx.sender,
+        recipient: tx.recipient,
+        order_ids,
+        confirmation: PaymentConfirmation { confirmation },
+    };
+    bus::service(payment_srv::BUS_ID)
+        .send(msg)
+        .await
+        .map_err(GenericError::new)?
+        .map_err(GenericError::new)?;
+    Ok(())
+}
+
+async fn init(_db: DbExecutor, _caller: String, msg: Init) -> Result<Ack, GenericError> {
     log::info!("init: {:?}", msg);
 
     let address = msg.address();
@@ -73,86 +271,229 @@ async fn init(_db: (), _caller: String, msg: Init) -> Result<Ack, GenericError>
 }
 
 async fn get_account_balance(
-    _db: (),
+    db: DbExecutor,
     _caller: String,
     msg: GetAccountBalance,
 ) -> Result<BigDecimal, GenericError> {
     log::info!("get account balance: {:?}", msg);
 
-    BigDecimal::from_str("1000000000000000000000000").map_err(GenericError::new)
+    let dao = db.as_dao::<DummyDao>();
+    let balance = dao
+        .get_balance(msg.address())
+        .await
+        .map_err(GenericError::new)?;
+    let deposited = dao
+        .get_deposited(msg.address())
+        .await
+        .map_err(GenericError::new)?;
+    Ok((balance - deposited).max(BigDecimal::zero()))
 }
 
 async fn schedule_payment(
-    _db: (),
+    db: DbExecutor,
+    ctx: DriverContext,
     _caller: String,
     msg: SchedulePayment,
 ) -> Result<String, GenericError> {
     log::info!("schedule payment: {:?}", msg);
 
-    let details = PaymentDetails {
-        recipient: msg.recipient(),
-        sender: msg.sender(),
-        amount: msg.amount(),
-        date: Some(Utc::now()),
-    };
-    let confirmation = serde_json::to_string(&details)
-        .map_err(GenericError::new)?
-        .into_bytes();
     let order_id = Uuid::new_v4().to_string();
-    let msg = payment_srv::NotifyPayment {
-        driver: DRIVER_NAME.to_string(),
-        platform: PLATFORM_NAME.to_string(),
-        amount: details.amount,
-        sender: details.sender,
-        recipient: details.recipient,
-        order_ids: vec![order_id.clone()],
-        confirmation: PaymentConfirmation { confirmation },
-    };
-
-    // Spawned because calling payment service while handling a call from payment service
-    // would result in a deadlock. We need to wait a bit, so parent scope be able to answer
-    tokio::task::spawn_local(async move {
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        let _ = bus::service(payment_srv::BUS_ID)
-            .send(msg)
-            .await
-            .map_err(|e| log::error!("{}", e));
-    });
-
+    send_transaction(
+        &db,
+        &ctx,
+        TxType::Payment,
+        msg.sender(),
+        msg.recipient(),
+        msg.amount(),
+        Some(order_id.clone()),
+    )
+    .await?;
     Ok(order_id)
 }
 
 async fn verify_payment(
-    _db: (),
+    db: DbExecutor,
     _caller: String,
     msg: VerifyPayment,
 ) -> Result<PaymentDetails, GenericError> {
     log::info!("verify payment: {:?}", msg);
 
-    let confirmation = msg.confirmation();
-    let json_str = std::str::from_utf8(confirmation.confirmation.as_slice()).unwrap();
-    let details = serde_json::from_str(json_str).unwrap();
-    Ok(details)
+    let confirmation: DummyConfirmation =
+        serde_json::from_slice(msg.confirmation().confirmation.as_slice())
+            .map_err(|e| GenericError::new(format!("Invalid payment confirmation: {}", e)))?;
+
+    // Transactions sent by another node are unknown locally and trusted.
+    if let Some(tx_hash) = confirmation.tx_hash {
+        let tx = db
+            .as_dao::<DummyDao>()
+            .get_transaction(tx_hash.clone())
+            .await
+            .map_err(GenericError::new)?;
+        if matches!(tx, Some(tx) if tx.status == TX_STATUS_DROPPED) {
+            return Err(GenericError::new(format!(
+                "Transaction {} was dropped",
+                tx_hash
+            )));
+        }
+    }
+    Ok(confirmation.details)
 }
 
 async fn validate_allocation(
-    _db: (),
+    db: DbExecutor,
+    ctx: DriverContext,
     _caller: String,
-    _msg: ValidateAllocation,
+    msg: ValidateAllocation,
 ) -> Result<bool, GenericError> {
-    Ok(true)
+    if ctx.config.fails(Failure::InsufficientFunds) {
+        return Ok(false);
+    }
+    let balance = db
+        .as_dao::<DummyDao>()
+        .get_balance(msg.address.clone())
+        .await
+        .map_err(GenericError::new)?;
+    let allocated: BigDecimal = msg
+        .existing_allocations
+        .iter()
+        .map(|allocation| &allocation.remaining_amount)
+        .sum();
+    Ok(msg.amount + allocated <= balance)
+}
+
+async fn make_deposit(
+    db: DbExecutor,
+    ctx: DriverContext,
+    _caller: String,
+    msg: MakeDeposit,
+) -> Result<Ack, GenericError> {
+    log::info!("make deposit: {:?}", msg);
+
+    let deposited = !ctx.config.fails(Failure::InsufficientFunds)
+        && db
+            .as_dao::<DummyDao>()
+            .make_deposit(msg.address, msg.allocation_id, msg.amount)
+            .await
+            .map_err(GenericError::new)?;
+    match deposited {
+        true => Ok(Ack {}),
+        false => Err(GenericError::new("Insufficient funds to make deposit")),
+    }
+}
+
+async fn release_deposit(
+    db: DbExecutor,
+    _caller: String,
+    msg: ReleaseDeposit,
+) -> Result<Ack, GenericError> {
+    log::info!("release deposit: {:?}", msg);
+
+    db.as_dao::<DummyDao>()
+        .release_deposit(msg.address, msg.allocation_id)
+        .await
+        .map_err(GenericError::new)?;
+    Ok(Ack {})
+}
+
+async fn fund(db: DbExecutor, _caller: String, msg: Fund) -> Result<String, GenericError> {
+    log::info!("fund: {:?}", msg);
+
+    let balance = db
+        .as_dao::<DummyDao>()
+        .fund(msg.address(), FUND_AMOUNT.clone())
+        .await
+        .map_err(GenericError::new)?;
+    Ok(format!(
+        "Funded {} with {} {}. Current balance: {} {}",
+        msg.address(),
+        *FUND_AMOUNT,
+        TOKEN_NAME,
+        balance,
+        TOKEN_NAME
+    ))
+}
+
+async fn transfer(
+    db: DbExecutor,
+    ctx: DriverContext,
+    _caller: String,
+    msg: Transfer,
+) -> Result<String, GenericError> {
+    log::info!("transfer: {:?}", msg);
+
+    let tx = send_transaction(
+        &db,
+        &ctx,
+        TxType::Transfer,
+        msg.sender,
+        msg.to,
+        msg.amount,
+        None,
+    )
+    .await?;
+    Ok(tx.tx_hash)
 }
 
-async fn fund(_db: (), _caller: String, _msg: Fund) -> Result<String, GenericError> {
-    Ok("Dummy driver is always funded.".to_owned())
+async fn enter(
+    db: DbExecutor,
+    ctx: DriverContext,
+    _caller: String,
+    msg: Enter,
+) -> Result<String, GenericError> {
+    log::info!("enter: {:?}", msg);
+
+    let tx = send_transaction(
+        &db,
+        &ctx,
+        TxType::Enter,
+        msg.address.clone(),
+        msg.address,
+        msg.amount,
+        None,
+    )
+    .await?;
+    Ok(tx.tx_hash)
 }
 
-async fn sign_payment(_db: (), _caller: String, msg: SignPayment) -> Result<Vec<u8>, GenericError> {
+async fn exit(
+    db: DbExecutor,
+    ctx: DriverContext,
+    _caller: String,
+    msg: Exit,
+) -> Result<String, GenericError> {
+    log::info!("exit: {:?}", msg);
+
+    let sender = msg.sender();
+    let amount = match msg.amount() {
+        Some(amount) => amount,
+        None => {
+            let dao = db.as_dao::<DummyDao>();
+            let balance = dao
+                .get_balance(sender.clone())
+                .await
+                .map_err(GenericError::new)?;
+            let deposited = dao
+                .get_deposited(sender.clone())
+                .await
+                .map_err(GenericError::new)?;
+            (balance - deposited).max(BigDecimal::zero())
+        }
+    };
+    let recipient = msg.to().unwrap_or_else(|| sender.clone());
+    let tx = send_transaction(&db, &ctx, TxType::Exit, sender, recipient, amount, None).await?;
+    Ok(tx.tx_hash)
+}
+
+async fn sign_payment(
+    _db: DbExecutor,
+    _caller: String,
+    msg: SignPayment,
+) -> Result<Vec<u8>, GenericError> {
     Ok(ya_payment_driver::utils::payment_hash(&msg.0))
 }
 
 async fn verify_signature(
-    _db: (),
+    _db: DbExecutor,
     _caller: String,
     msg: VerifySignature,
 ) -> Result<bool, GenericError> {
@@ -160,7 +501,11 @@ async fn verify_signature(
     Ok(hash == msg.signature)
 }
 
-async fn shut_down(_db: (), _caller: String, msg: ShutDown) -> Result<(), GenericError> {
+async fn ping(_db: DbExecutor, _caller: String, _msg: Ping) -> Result<(), GenericError> {
+    Ok(())
+}
+
+async fn shut_down(_db: DbExecutor, _caller: String, msg: ShutDown) -> Result<(), GenericError> {
     if msg.timeout > std::time::Duration::from_secs(1) {
         tokio::time::sleep(msg.timeout - std::time::Duration::from_secs(1)).await;
     }
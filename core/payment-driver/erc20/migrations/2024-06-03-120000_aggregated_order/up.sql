@@ -0,0 +1,12 @@
+CREATE TABLE aggregated_order(
+    order_id VARCHAR(50) NOT NULL PRIMARY KEY,
+    sender VARCHAR(50) NOT NULL,
+    recipient VARCHAR(50) NOT NULL,
+    network VARCHAR(50) NOT NULL,
+    amount TEXT NOT NULL,
+    deadline DATETIME NOT NULL,
+    send_at DATETIME NOT NULL,
+    payment_id VARCHAR(50) NULL
+);
+
+CREATE INDEX aggregated_order_payment_id_idx ON aggregated_order (payment_id);
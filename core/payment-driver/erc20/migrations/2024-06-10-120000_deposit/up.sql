@@ -0,0 +1,8 @@
+CREATE TABLE deposit(
+    allocation_id VARCHAR(50) NOT NULL PRIMARY KEY,
+    address VARCHAR(50) NOT NULL,
+    network VARCHAR(50) NOT NULL,
+    amount TEXT NOT NULL
+);
+
+CREATE INDEX deposit_address_idx ON deposit (address, network);
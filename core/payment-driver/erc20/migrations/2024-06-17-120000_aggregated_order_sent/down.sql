@@ -0,0 +1,19 @@
+CREATE TABLE aggregated_order_tmp(
+    order_id VARCHAR(50) NOT NULL PRIMARY KEY,
+    sender VARCHAR(50) NOT NULL,
+    recipient VARCHAR(50) NOT NULL,
+    network VARCHAR(50) NOT NULL,
+    amount TEXT NOT NULL,
+    deadline DATETIME NOT NULL,
+    send_at DATETIME NOT NULL,
+    payment_id VARCHAR(50) NULL
+);
+
+INSERT INTO aggregated_order_tmp(order_id, sender, recipient, network, amount, deadline, send_at, payment_id)
+SELECT order_id, sender, recipient, network, amount, deadline, send_at, payment_id FROM aggregated_order;
+
+DROP TABLE aggregated_order;
+
+ALTER TABLE aggregated_order_tmp RENAME TO aggregated_order;
+
+CREATE INDEX aggregated_order_payment_id_idx ON aggregated_order (payment_id);
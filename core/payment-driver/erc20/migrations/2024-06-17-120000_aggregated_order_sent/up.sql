@@ -0,0 +1 @@
+ALTER TABLE aggregated_order ADD COLUMN sent BOOLEAN NOT NULL DEFAULT FALSE;
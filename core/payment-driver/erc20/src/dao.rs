@@ -2,11 +2,15 @@
     Database Access Object, all you need to interact with the database.
 */
 
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use diesel::{self, ExpressionMethods, QueryDsl, RunQueryDsl};
+use std::str::FromStr;
 use web3::types::U256;
 
 // Workspace uses
 use ya_payment_driver::{
-    dao::{payment::PaymentDao, transaction::TransactionDao, DbExecutor},
+    dao::{payment::PaymentDao, transaction::TransactionDao, DbError, DbExecutor, DbResult},
     db::models::{
         Network, PaymentEntity, TransactionEntity, TransactionStatus, PAYMENT_STATUS_FAILED,
         PAYMENT_STATUS_NOT_YET,
@@ -14,8 +18,12 @@ use ya_payment_driver::{
     model::{GenericError, SchedulePayment},
     utils,
 };
+use ya_persistence::executor::{do_with_transaction, readonly_transaction, AsDao, PoolType};
 
-use crate::network::platform_to_network_token;
+use crate::db::models::{AggregatedOrderEntity, DepositEntity};
+use crate::db::schema::aggregated_order::dsl;
+use crate::db::schema::deposit::dsl as deposit_dsl;
+use crate::network::Chains;
 
 pub struct Erc20Dao {
     db: DbExecutor,
@@ -60,11 +68,19 @@ impl Erc20Dao {
         &self,
         order_id: &str,
         msg: &SchedulePayment,
+        chains: &Chains,
     ) -> Result<(), GenericError> {
         let recipient = msg.recipient().to_owned();
         let glm_amount = utils::big_dec_to_u256(&msg.amount());
         let gas_amount = Default::default();
-        let (network, _token) = platform_to_network_token(msg.platform())?;
+        let chain = chains.platform(&msg.platform())?;
+        // Payments table keeps only built-in networks
+        let network = Network::from_str(&chain.network).map_err(|_| {
+            GenericError::new(format!(
+                "Payments on network {} can't be stored in driver database",
+                chain.network
+            ))
+        })?;
 
         let payment = PaymentEntity {
             amount: utils::u256_to_big_endian_hex(glm_amount),
@@ -351,3 +367,184 @@ impl Erc20Dao {
         }
     }
 }
+
+/// Orders waiting in the driver database for aggregation into a single transfer.
+pub struct AggregatedOrderDao<'c> {
+    pool: &'c PoolType,
+}
+
+impl<'c> AsDao<'c> for AggregatedOrderDao<'c> {
+    fn as_dao(pool: &'c PoolType) -> Self {
+        Self { pool }
+    }
+}
+
+impl<'c> AggregatedOrderDao<'c> {
+    pub async fn insert(&self, order: AggregatedOrderEntity) -> DbResult<()> {
+        do_with_transaction(self.pool, "aggregated_order_insert", move |conn| {
+            diesel::insert_into(dsl::aggregated_order)
+                .values(order)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Assigns payment ids to orders not included in any transfer yet. `aggregate` gets
+    /// these orders and returns order ids together with payment id of each transfer.
+    /// Orders are selected and updated in one transaction and only orders without payment
+    /// id are updated, so an order is never included in two transfers.
+    /// Returns orders, which were assigned.
+    pub async fn assign_unsent<F>(&self, aggregate: F) -> DbResult<Vec<AggregatedOrderEntity>>
+    where
+        F: FnOnce(Vec<AggregatedOrderEntity>) -> Vec<(Vec<String>, String)> + Send + 'static,
+    {
+        do_with_transaction(self.pool, "aggregated_order_assign_unsent", move |conn| {
+            let orders = dsl::aggregated_order
+                .filter(dsl::payment_id.is_null())
+                .order(dsl::deadline.asc())
+                .load(conn)?;
+
+            let mut payment_ids = Vec::new();
+            for (order_ids, payment_id) in aggregate(orders) {
+                diesel::update(
+                    dsl::aggregated_order
+                        .filter(dsl::order_id.eq_any(order_ids))
+                        .filter(dsl::payment_id.is_null()),
+                )
+                .set(dsl::payment_id.eq(&payment_id))
+                .execute(conn)?;
+                payment_ids.push(payment_id);
+            }
+
+            let assigned = dsl::aggregated_order
+                .filter(dsl::payment_id.eq_any(payment_ids))
+                .order((dsl::deadline.asc(), dsl::order_id.asc()))
+                .load(conn)?;
+            Ok(assigned)
+        })
+        .await
+    }
+
+    /// Marks orders of transfer passed to the payment library.
+    pub async fn mark_sent(&self, payment_id: String) -> DbResult<()> {
+        do_with_transaction(self.pool, "aggregated_order_mark_sent", move |conn| {
+            diesel::update(dsl::aggregated_order.filter(dsl::payment_id.eq(payment_id)))
+                .set(dsl::sent.eq(true))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Returns orders, which got payment id, but weren't sent (eg. driver was stopped
+    /// in the meantime), back to the queue. Returns their payment ids.
+    pub async fn unassign_unsent(&self, send_at: NaiveDateTime) -> DbResult<Vec<String>> {
+        do_with_transaction(self.pool, "aggregated_order_unassign_unsent", move |conn| {
+            let payment_ids: Vec<Option<String>> = dsl::aggregated_order
+                .filter(dsl::payment_id.is_not_null())
+                .filter(dsl::sent.eq(false))
+                .select(dsl::payment_id)
+                .distinct()
+                .load(conn)?;
+            diesel::update(
+                dsl::aggregated_order
+                    .filter(dsl::payment_id.is_not_null())
+                    .filter(dsl::sent.eq(false)),
+            )
+            .set((dsl::payment_id.eq(None::<String>), dsl::send_at.eq(send_at)))
+            .execute(conn)?;
+            Ok(payment_ids.into_iter().flatten().collect())
+        })
+        .await
+    }
+
+    /// Returns orders of transfer, which couldn't be sent, back to the queue.
+    pub async fn unassign_payment(
+        &self,
+        payment_id: String,
+        send_at: NaiveDateTime,
+    ) -> DbResult<()> {
+        do_with_transaction(
+            self.pool,
+            "aggregated_order_unassign_payment",
+            move |conn| {
+                diesel::update(dsl::aggregated_order.filter(dsl::payment_id.eq(payment_id)))
+                    .set((dsl::payment_id.eq(None::<String>), dsl::send_at.eq(send_at)))
+                    .execute(conn)?;
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    pub async fn get_order_ids(&self, payment_id: String) -> DbResult<Vec<String>> {
+        readonly_transaction(self.pool, "aggregated_order_get_order_ids", move |conn| {
+            let order_ids = dsl::aggregated_order
+                .filter(dsl::payment_id.eq(payment_id))
+                .select(dsl::order_id)
+                .order(dsl::order_id.asc())
+                .load(conn)?;
+            Ok(order_ids)
+        })
+        .await
+    }
+
+    pub async fn remove_payment(&self, payment_id: String) -> DbResult<()> {
+        do_with_transaction(self.pool, "aggregated_order_remove_payment", move |conn| {
+            diesel::delete(dsl::aggregated_order.filter(dsl::payment_id.eq(payment_id)))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Funds reserved in the driver for allocations.
+pub struct DepositDao<'c> {
+    pool: &'c PoolType,
+}
+
+impl<'c> AsDao<'c> for DepositDao<'c> {
+    fn as_dao(pool: &'c PoolType) -> Self {
+        Self { pool }
+    }
+}
+
+impl<'c> DepositDao<'c> {
+    /// Reserves deposit, if `balance` covers it together with other deposits
+    /// made from the same account. Deposit made again for the same allocation
+    /// replaces the previous one. Returns false, if funds are insufficient.
+    pub async fn reserve(&self, deposit: DepositEntity, balance: BigDecimal) -> DbResult<bool> {
+        do_with_transaction(self.pool, "deposit_reserve", move |conn| {
+            let amounts: Vec<String> = deposit_dsl::deposit
+                .filter(deposit_dsl::address.eq(&deposit.address))
+                .filter(deposit_dsl::network.eq(&deposit.network))
+                .filter(deposit_dsl::allocation_id.ne(&deposit.allocation_id))
+                .select(deposit_dsl::amount)
+                .load(conn)?;
+            let mut reserved = BigDecimal::from_str(&deposit.amount)
+                .map_err(|e| DbError::InvalidData(e.to_string()))?;
+            for amount in amounts {
+                reserved += BigDecimal::from_str(&amount)
+                    .map_err(|e| DbError::InvalidData(e.to_string()))?;
+            }
+            if reserved > balance {
+                return Ok(false);
+            }
+            diesel::replace_into(deposit_dsl::deposit)
+                .values(deposit)
+                .execute(conn)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    pub async fn release(&self, allocation_id: String) -> DbResult<()> {
+        do_with_transaction(self.pool, "deposit_release", move |conn| {
+            diesel::delete(deposit_dsl::deposit.find(allocation_id)).execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,11 @@
+/*
+    Raw database components. Schemas, models and migrations.
+*/
+
+pub mod migrations {
+    #[derive(diesel_migrations::EmbedMigrations)]
+    struct _Erc20;
+}
+
+pub mod models;
+pub mod schema;
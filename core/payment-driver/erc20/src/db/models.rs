@@ -0,0 +1,38 @@
+/*
+    Raw database models.
+*/
+
+// External crates
+use chrono::NaiveDateTime;
+
+// Local uses
+use crate::db::schema::*;
+
+/// Order scheduled for aggregation. `payment_id` is assigned, when the order
+/// is included in a transfer, and `sent` is set, when the transfer was passed
+/// to the payment library.
+#[derive(Queryable, Clone, Debug, Identifiable, Insertable, PartialEq, Eq)]
+#[primary_key(order_id)]
+#[table_name = "aggregated_order"]
+pub struct AggregatedOrderEntity {
+    pub order_id: String,
+    pub sender: String,
+    pub recipient: String,
+    pub network: String,
+    pub amount: String,
+    pub deadline: NaiveDateTime,
+    pub send_at: NaiveDateTime,
+    pub payment_id: Option<String>,
+    pub sent: bool,
+}
+
+/// Funds reserved for an allocation created with `makeDeposit` flag.
+#[derive(Queryable, Clone, Debug, Identifiable, Insertable, PartialEq, Eq)]
+#[primary_key(allocation_id)]
+#[table_name = "deposit"]
+pub struct DepositEntity {
+    pub allocation_id: String,
+    pub address: String,
+    pub network: String,
+    pub amount: String,
+}
@@ -0,0 +1,22 @@
+table! {
+    aggregated_order (order_id) {
+        order_id -> Text,
+        sender -> Text,
+        recipient -> Text,
+        network -> Text,
+        amount -> Text,
+        deadline -> Timestamp,
+        send_at -> Timestamp,
+        payment_id -> Nullable<Text>,
+        sent -> Bool,
+    }
+}
+
+table! {
+    deposit (allocation_id) {
+        allocation_id -> Text,
+        address -> Text,
+        network -> Text,
+        amount -> Text,
+    }
+}
@@ -29,31 +29,59 @@ use ya_payment_driver::driver::IdentityError;
 
 use ya_payment_driver::{
     bus,
+    dao::DbExecutor,
     driver::{async_trait, BigDecimal, IdentityEvent, Network as NetworkConfig, PaymentDriver},
     model::*,
 };
 
 // Local uses
+use crate::dao::DepositDao;
+use crate::db::models::DepositEntity;
 use crate::erc20::utils;
 use crate::erc20::utils::{big_dec_to_u256, u256_to_big_dec};
-use crate::network::platform_to_currency;
+use crate::network::Chains;
 use crate::signer::IdentitySigner;
-use crate::{driver::PaymentDetails, network, HOLESKY_NETWORK};
-use crate::{network::SUPPORTED_NETWORKS, DRIVER_NAME};
+use crate::DRIVER_NAME;
+use crate::{driver::PaymentDetails, HOLESKY_NETWORK};
 
+mod aggregator;
 mod cli;
 
+pub use aggregator::PaymentAggregator;
+use aggregator::{AggregatedTransfer, TransferKey};
+
+/// How often pending aggregated payments are checked for sending.
+const AGGREGATION_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
 pub struct Erc20Driver {
     payment_runtime: PaymentRuntime,
+    chains: Chains,
+    aggregator: PaymentAggregator,
+    db: DbExecutor,
 }
 
 impl Erc20Driver {
-    pub fn new(payment_runtime: PaymentRuntime, recv: Receiver<DriverEvent>) -> Arc<Self> {
-        let this = Arc::new(Self { payment_runtime });
+    pub fn new(
+        payment_runtime: PaymentRuntime,
+        recv: Receiver<DriverEvent>,
+        chains: Chains,
+        aggregator: PaymentAggregator,
+        db: DbExecutor,
+    ) -> Arc<Self> {
+        let this = Arc::new(Self {
+            payment_runtime,
+            chains,
+            aggregator,
+            db,
+        });
 
         let this_ = Arc::clone(&this);
         tokio::task::spawn_local(Self::payment_confirm_job(this_, recv));
 
+        // Also sends orders left in the database, when aggregation was disabled since
+        let this_ = Arc::clone(&this);
+        tokio::task::spawn_local(Self::payment_aggregation_job(this_));
+
         this
     }
 
@@ -118,6 +146,20 @@ impl Erc20Driver {
         amount: &BigDecimal,
         network: &str,
         deadline: Option<DateTime<Utc>>,
+    ) -> Result<String, GenericError> {
+        let payment_id = Uuid::new_v4().to_simple().to_string();
+        self.insert_transfer(sender, to, amount, network, deadline, payment_id)
+            .await
+    }
+
+    async fn insert_transfer(
+        &self,
+        sender: &str,
+        to: &str,
+        amount: &BigDecimal,
+        network: &str,
+        deadline: Option<DateTime<Utc>>,
+        payment_id: String,
     ) -> Result<String, GenericError> {
         self.is_account_active(sender).await?;
         let sender = H160::from_str(sender)
@@ -126,8 +168,6 @@ impl Erc20Driver {
             .map_err(|err| GenericError::new(format!("Error when parsing receiver {err:?}")))?;
         let amount = big_dec_to_u256(amount)?;
 
-        let payment_id = Uuid::new_v4().to_simple().to_string();
-
         self.payment_runtime
             .transfer_guess_account(TransferArgs {
                 network: network.to_string(),
@@ -146,6 +186,88 @@ impl Erc20Driver {
         Ok(payment_id)
     }
 
+    async fn send_aggregated(&self, transfers: Vec<AggregatedTransfer>) {
+        for transfer in transfers {
+            let payment_id = transfer.payment_id.clone();
+            log::debug!(
+                "Sending aggregated payment of {} to {} for {} orders",
+                transfer.amount,
+                transfer.key.recipient,
+                transfer.order_ids.len()
+            );
+            let result = self
+                .insert_transfer(
+                    &transfer.key.sender,
+                    &transfer.key.recipient,
+                    &transfer.amount,
+                    &transfer.key.network,
+                    Some(transfer.deadline),
+                    payment_id.clone(),
+                )
+                .await;
+            match result {
+                Ok(_) => {
+                    if let Err(e) = self.aggregator.sent(&transfer).await {
+                        log::error!(
+                            "Failed to mark aggregated payment [{payment_id}] as sent: {e}"
+                        );
+                    }
+                }
+                Err(err) => {
+                    log::error!(
+                        "Failed to send aggregated payment [{payment_id}] for orders {:?}: {err}",
+                        transfer.order_ids
+                    );
+                    if let Err(e) = self.aggregator.failed(&transfer).await {
+                        log::error!(
+                            "Failed to return orders of payment [{payment_id}] to the queue: {e}"
+                        );
+                    }
+                    let platform = self
+                        .chains
+                        .get(&transfer.key.network)
+                        .map(|chain| chain.platform.clone())
+                        .unwrap_or_default();
+                    if let Err(e) = bus::notify_payment_failed(
+                        &self.get_name(),
+                        &platform,
+                        transfer.order_ids.clone(),
+                        format!("{err}. Driver will retry."),
+                    )
+                    .await
+                    {
+                        log::warn!(
+                            "Failed to notify payment service about failed payment [{payment_id}]: {e}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    async fn payment_aggregation_job(this: Arc<Self>) {
+        if let Err(e) = this.aggregator.restore().await {
+            log::error!("Failed to restore unsent aggregated payments: {e}");
+        }
+
+        if !this.aggregator.is_enabled() {
+            match this.aggregator.take_all().await {
+                Ok(transfers) => this.send_aggregated(transfers).await,
+                Err(e) => log::error!("Failed to load aggregated payments: {e}"),
+            }
+            return;
+        }
+
+        let mut interval = tokio::time::interval(AGGREGATION_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match this.aggregator.take_ready(Utc::now()).await {
+                Ok(transfers) => this.send_aggregated(transfers).await,
+                Err(e) => log::error!("Failed to load aggregated payments: {e}"),
+            }
+        }
+    }
+
     async fn payment_confirm_job(this: Arc<Self>, mut events: Receiver<DriverEvent>) {
         while let Some(event) = events.recv().await {
             match &event.content {
@@ -348,11 +470,12 @@ impl Erc20Driver {
         bus::notify_payment(
             &self.get_name(),
             platform,
-            vec![payment_id.clone()],
+            self.aggregator.order_ids(payment_id).await?,
             &payment_details,
             transaction_hash,
         )
-        .await
+        .await?;
+        self.aggregator.confirmed(payment_id).await
     }
 }
 
@@ -485,7 +608,9 @@ impl PaymentDriver for Erc20Driver {
         let balance_int = BigInt::from_str(&format!("{balance}")).unwrap();
         let balance = BigDecimal::new(balance_int, 18);
 
-        let (currency_short_name, currency_long_name) = platform_to_currency(platform)?;
+        let chain = self.chains.platform(&platform)?;
+        let currency_short_name = chain.currency_short.clone();
+        let currency_long_name = chain.currency_long.clone();
 
         Ok(Some(GasDetails {
             currency_long_name,
@@ -503,7 +628,7 @@ impl PaymentDriver for Erc20Driver {
     }
 
     fn get_networks(&self) -> HashMap<String, NetworkConfig> {
-        SUPPORTED_NETWORKS.clone()
+        self.chains.networks()
     }
 
     fn recv_init_required(&self) -> bool {
@@ -518,7 +643,8 @@ impl PaymentDriver for Erc20Driver {
     async fn fund(&self, _caller: String, msg: Fund) -> Result<String, GenericError> {
         log::debug!("fund: {:?}", msg);
         let address = msg.address();
-        let network = network::network_like_to_network(msg.network());
+        let chain = self.chains.network_like(msg.network())?;
+        let network = &chain.network;
         let result = {
             let address = utils::str_to_addr(&address)?;
             log::info!(
@@ -530,7 +656,7 @@ impl PaymentDriver for Erc20Driver {
                 .payment_runtime
                 .setup
                 .chain_setup
-                .get(&(network as i64))
+                .get(&chain.chain_id)
                 .ok_or(GenericError::new(format!(
                     "Missing chain config for network {}",
                     network
@@ -837,13 +963,31 @@ impl PaymentDriver for Erc20Driver {
         )))?;
 
         let transfer_margin = Duration::minutes(2);
+        let deadline = msg.due_date() - transfer_margin;
+
+        if self.aggregator.is_enabled() {
+            self.is_account_active(&msg.sender()).await?;
+            H160::from_str(&msg.recipient())
+                .map_err(|err| GenericError::new(format!("Error when parsing receiver {err:?}")))?;
+
+            let order_id = Uuid::new_v4().to_simple().to_string();
+            let key = TransferKey {
+                sender: msg.sender(),
+                recipient: msg.recipient(),
+                network: network.to_string(),
+            };
+            self.aggregator
+                .add(key, &msg.amount(), deadline, order_id.clone())
+                .await?;
+            return Ok(order_id);
+        }
 
         self.do_transfer(
             &msg.sender(),
             &msg.recipient(),
             &msg.amount(),
             network,
-            Some(msg.due_date() - transfer_margin),
+            Some(deadline),
         )
         .await
     }
@@ -854,13 +998,14 @@ impl PaymentDriver for Erc20Driver {
         msg: VerifyPayment,
     ) -> Result<PaymentDetails, GenericError> {
         log::debug!("verify_payment: {:?}", msg);
-        let (network, _) = network::platform_to_network_token(msg.platform())?;
+        let chain = self.chains.platform(&msg.platform())?;
+        let network = &chain.network;
         let tx_hash = format!("0x{}", hex::encode(msg.confirmation().confirmation));
         log::info!("Verifying transaction: {} on network {}", tx_hash, network);
         let verify_res = self
             .payment_runtime
             .verify_transaction(
-                network as i64,
+                chain.chain_id,
                 H256::from_str(&tx_hash)
                     .map_err(|_| GenericError::new("Hash cannot be converted to string"))?,
                 H160::from_str(&msg.details.payer_addr)
@@ -922,6 +1067,55 @@ impl PaymentDriver for Erc20Driver {
         Ok(msg.amount <= account_balance - total_allocated_amount)
     }
 
+    async fn make_deposit(&self, caller: String, msg: MakeDeposit) -> Result<Ack, GenericError> {
+        log::debug!("make_deposit: {:?}", msg);
+        let network = msg
+            .platform
+            .split('-')
+            .nth(1)
+            .ok_or(GenericError::new(format!(
+                "Malformed platform string: {}",
+                msg.platform
+            )))?;
+        let account_balance = self
+            .get_account_balance(
+                caller,
+                GetAccountBalance::new(msg.address.clone(), msg.platform.clone()),
+            )
+            .await?;
+
+        let deposit = DepositEntity {
+            allocation_id: msg.allocation_id,
+            address: msg.address,
+            network: network.to_string(),
+            amount: msg.amount.to_string(),
+        };
+        let reserved = self
+            .db
+            .as_dao::<DepositDao>()
+            .reserve(deposit, account_balance)
+            .await
+            .map_err(GenericError::new)?;
+        if !reserved {
+            return Err(GenericError::new("Insufficient funds to make deposit"));
+        }
+        Ok(Ack {})
+    }
+
+    async fn release_deposit(
+        &self,
+        _caller: String,
+        msg: ReleaseDeposit,
+    ) -> Result<Ack, GenericError> {
+        log::debug!("release_deposit: {:?}", msg);
+        self.db
+            .as_dao::<DepositDao>()
+            .release(msg.allocation_id)
+            .await
+            .map_err(GenericError::new)?;
+        Ok(Ack {})
+    }
+
     async fn status(
         &self,
         _caller: String,
@@ -931,7 +1125,12 @@ impl PaymentDriver for Erc20Driver {
     }
 
     async fn shut_down(&self, _caller: String, _msg: ShutDown) -> Result<(), GenericError> {
-        // no-op, erc20_payment_lib driver doesn't expose clean shutdown interface yet
+        // Pending aggregated payments are kept in the database, but shouldn't wait for restart
+        match self.aggregator.take_all().await {
+            Ok(transfers) => self.send_aggregated(transfers).await,
+            Err(e) => log::error!("Failed to load aggregated payments: {e}"),
+        }
+        // erc20_payment_lib driver doesn't expose clean shutdown interface yet
         Ok(())
     }
 }
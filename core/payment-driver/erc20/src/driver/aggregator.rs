@@ -0,0 +1,430 @@
+/*
+    Aggregation of scheduled payments sent from the same account to the same recipient.
+
+    Orders wait in the driver database, so they survive driver restart. Orders
+    aggregated into a single transfer are assigned its payment id, which is used
+    to confirm all of them together. Orders are marked as sent, when the transfer
+    is passed to the payment library. Orders with payment id, which weren't sent,
+    are returned to the queue on startup.
+*/
+// Extrnal crates
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// Workspace uses
+use ya_payment_driver::dao::DbExecutor;
+use ya_payment_driver::driver::BigDecimal;
+use ya_payment_driver::model::GenericError;
+
+// Local uses
+use crate::dao::AggregatedOrderDao;
+use crate::db::models::AggregatedOrderEntity;
+
+/// Limits number of orders confirmed by a single transfer.
+const MAX_ORDERS_PER_TRANSFER: usize = 100;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct TransferKey {
+    pub sender: String,
+    pub recipient: String,
+    pub network: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct AggregatedTransfer {
+    pub key: TransferKey,
+    pub amount: BigDecimal,
+    pub deadline: DateTime<Utc>,
+    pub order_ids: Vec<String>,
+    pub payment_id: String,
+}
+
+pub struct PaymentAggregator {
+    window: Duration,
+    db: DbExecutor,
+}
+
+impl PaymentAggregator {
+    pub fn new(window: std::time::Duration, db: DbExecutor) -> Self {
+        PaymentAggregator {
+            window: Duration::from_std(window).unwrap_or_else(|_| Duration::zero()),
+            db,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.window > Duration::zero()
+    }
+
+    fn dao(&self) -> AggregatedOrderDao<'_> {
+        self.db.as_dao::<AggregatedOrderDao>()
+    }
+
+    /// Adds order to pending transfer. Transfer is sent after aggregation window
+    /// elapses, but not later than the earliest deadline of its orders.
+    pub async fn add(
+        &self,
+        key: TransferKey,
+        amount: &BigDecimal,
+        deadline: DateTime<Utc>,
+        order_id: String,
+    ) -> Result<(), GenericError> {
+        let send_at = (Utc::now() + self.window).min(deadline);
+        self.dao()
+            .insert(AggregatedOrderEntity {
+                order_id,
+                sender: key.sender,
+                recipient: key.recipient,
+                network: key.network,
+                amount: amount.to_string(),
+                deadline: deadline.naive_utc(),
+                send_at: send_at.naive_utc(),
+                payment_id: None,
+                sent: false,
+            })
+            .await
+            .map_err(GenericError::new)
+    }
+
+    /// Takes transfers, which should be sent now, and assigns payment ids to their orders.
+    pub async fn take_ready(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<AggregatedTransfer>, GenericError> {
+        self.take(Some(now)).await
+    }
+
+    pub async fn take_all(&self) -> Result<Vec<AggregatedTransfer>, GenericError> {
+        self.take(None).await
+    }
+
+    async fn take(
+        &self,
+        now: Option<DateTime<Utc>>,
+    ) -> Result<Vec<AggregatedTransfer>, GenericError> {
+        let orders = self
+            .dao()
+            .assign_unsent(move |orders| aggregate(orders, now))
+            .await
+            .map_err(GenericError::new)?;
+        build_transfers(orders)
+    }
+
+    /// Marks orders of transfer, which was passed to the payment library.
+    pub async fn sent(&self, transfer: &AggregatedTransfer) -> Result<(), GenericError> {
+        self.dao()
+            .mark_sent(transfer.payment_id.clone())
+            .await
+            .map_err(GenericError::new)
+    }
+
+    /// Returns orders of transfers interrupted by driver restart to the queue.
+    /// They are sent, when they are ready again.
+    pub async fn restore(&self) -> Result<(), GenericError> {
+        let payment_ids = self
+            .dao()
+            .unassign_unsent(Utc::now().naive_utc())
+            .await
+            .map_err(GenericError::new)?;
+        if !payment_ids.is_empty() {
+            log::info!("Orders of unsent aggregated payments {payment_ids:?} returned to queue");
+        }
+        Ok(())
+    }
+
+    /// Returns orders of transfer, which couldn't be sent, to the queue.
+    /// They are retried after the aggregation window.
+    pub async fn failed(&self, transfer: &AggregatedTransfer) -> Result<(), GenericError> {
+        let send_at = (Utc::now() + self.window).naive_utc();
+        self.dao()
+            .unassign_payment(transfer.payment_id.clone(), send_at)
+            .await
+            .map_err(GenericError::new)
+    }
+
+    /// Order ids confirmed by transfer with given payment id. Transfers sent
+    /// without aggregation use order id as payment id.
+    pub async fn order_ids(&self, payment_id: &str) -> Result<Vec<String>, GenericError> {
+        let order_ids = self
+            .dao()
+            .get_order_ids(payment_id.to_string())
+            .await
+            .map_err(GenericError::new)?;
+        Ok(match order_ids.is_empty() {
+            true => vec![payment_id.to_string()],
+            false => order_ids,
+        })
+    }
+
+    /// Forgets orders of transfer, which was confirmed to the payment service.
+    pub async fn confirmed(&self, payment_id: &str) -> Result<(), GenericError> {
+        self.dao()
+            .remove_payment(payment_id.to_string())
+            .await
+            .map_err(GenericError::new)
+    }
+}
+
+/// Groups orders by transfer key. Groups are taken, if any of their orders
+/// should be sent before `now`, or always, if `now` isn't given. Returns order ids
+/// with new payment id for each transfer.
+fn aggregate(
+    orders: Vec<AggregatedOrderEntity>,
+    now: Option<DateTime<Utc>>,
+) -> Vec<(Vec<String>, String)> {
+    let mut groups: HashMap<TransferKey, Vec<AggregatedOrderEntity>> = HashMap::new();
+    for order in orders {
+        let key = TransferKey {
+            sender: order.sender.clone(),
+            recipient: order.recipient.clone(),
+            network: order.network.clone(),
+        };
+        groups.entry(key).or_default().push(order);
+    }
+
+    let mut assignments = Vec::new();
+    for (_, mut orders) in groups {
+        let ready = match now {
+            Some(now) => {
+                orders.len() >= MAX_ORDERS_PER_TRANSFER
+                    || orders
+                        .iter()
+                        .any(|o| Utc.from_utc_datetime(&o.send_at) <= now)
+            }
+            None => true,
+        };
+        if !ready {
+            continue;
+        }
+
+        orders.sort_by_key(|o| (o.deadline, o.order_id.clone()));
+        for chunk in orders.chunks(MAX_ORDERS_PER_TRANSFER) {
+            assignments.push((
+                chunk.iter().map(|o| o.order_id.clone()).collect(),
+                Uuid::new_v4().to_simple().to_string(),
+            ));
+        }
+    }
+    assignments
+}
+
+/// Builds transfers from orders with assigned payment ids. Orders must be
+/// sorted by deadline, so the first order of a transfer has the earliest one.
+fn build_transfers(
+    orders: Vec<AggregatedOrderEntity>,
+) -> Result<Vec<AggregatedTransfer>, GenericError> {
+    let mut transfers: Vec<AggregatedTransfer> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for order in orders {
+        let payment_id = order
+            .payment_id
+            .clone()
+            .ok_or_else(|| GenericError::new("Aggregated order without payment id"))?;
+        let amount = BigDecimal::from_str(&order.amount).map_err(GenericError::new)?;
+        match indices.get(&payment_id) {
+            Some(&index) => {
+                let transfer = &mut transfers[index];
+                transfer.amount += amount;
+                transfer.order_ids.push(order.order_id);
+            }
+            None => {
+                indices.insert(payment_id.clone(), transfers.len());
+                transfers.push(AggregatedTransfer {
+                    key: TransferKey {
+                        sender: order.sender,
+                        recipient: order.recipient,
+                        network: order.network,
+                    },
+                    amount,
+                    deadline: Utc.from_utc_datetime(&order.deadline),
+                    order_ids: vec![order.order_id],
+                    payment_id,
+                });
+            }
+        }
+    }
+    Ok(transfers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(recipient: &str) -> TransferKey {
+        TransferKey {
+            sender: "0x01".to_string(),
+            recipient: recipient.to_string(),
+            network: "holesky".to_string(),
+        }
+    }
+
+    fn aggregator(name: &str) -> PaymentAggregator {
+        let db = DbExecutor::in_memory(name).unwrap();
+        db.apply_migration(crate::db::migrations::run_with_output)
+            .unwrap();
+        PaymentAggregator::new(std::time::Duration::from_secs(60), db)
+    }
+
+    #[actix_rt::test]
+    async fn test_take_ready_after_window_or_deadline() {
+        let aggregator = aggregator("aggregator-take-ready");
+        let now = Utc::now();
+        let late = now + Duration::hours(1);
+        let amount = BigDecimal::from(1);
+
+        aggregator
+            .add(key("0xa"), &amount, late, "a1".into())
+            .await
+            .unwrap();
+        aggregator
+            .add(key("0xa"), &amount, late, "a2".into())
+            .await
+            .unwrap();
+        // Deadline shortens aggregation window.
+        let soon = now + Duration::seconds(10);
+        aggregator
+            .add(key("0xb"), &amount, soon, "b1".into())
+            .await
+            .unwrap();
+
+        assert!(aggregator.take_ready(now).await.unwrap().is_empty());
+
+        let ready = aggregator.take_ready(soon).await.unwrap();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].key, key("0xb"));
+        assert_eq!(ready[0].order_ids, vec!["b1".to_string()]);
+        assert_eq!(ready[0].deadline.timestamp(), soon.timestamp());
+
+        let ready = aggregator
+            .take_ready(now + Duration::minutes(2))
+            .await
+            .unwrap();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].amount, BigDecimal::from(2));
+        assert_eq!(ready[0].order_ids, vec!["a1".to_string(), "a2".to_string()]);
+
+        // Taken orders aren't taken again.
+        assert!(aggregator.take_all().await.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_take_all_caps_orders_per_transfer() {
+        let aggregator = aggregator("aggregator-take-all");
+        let deadline = Utc::now() + Duration::hours(1);
+        let amount = BigDecimal::from(1);
+        for i in 0..MAX_ORDERS_PER_TRANSFER + 1 {
+            aggregator
+                .add(key("0xa"), &amount, deadline, format!("a{i:03}"))
+                .await
+                .unwrap();
+        }
+
+        let mut transfers = aggregator.take_all().await.unwrap();
+        transfers.sort_by_key(|t| t.order_ids.len());
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].order_ids.len(), 1);
+        assert_eq!(transfers[1].order_ids.len(), MAX_ORDERS_PER_TRANSFER);
+        assert_ne!(transfers[0].payment_id, transfers[1].payment_id);
+    }
+
+    #[actix_rt::test]
+    async fn test_order_ids_bookkeeping() {
+        let aggregator = aggregator("aggregator-order-ids");
+        let deadline = Utc::now() + Duration::hours(1);
+        let amount = BigDecimal::from(1);
+        aggregator
+            .add(key("0xa"), &amount, deadline, "a1".into())
+            .await
+            .unwrap();
+        aggregator
+            .add(key("0xa"), &amount, deadline, "a2".into())
+            .await
+            .unwrap();
+
+        let transfer = aggregator.take_all().await.unwrap().remove(0);
+        let payment_id = transfer.payment_id.clone();
+        assert_eq!(
+            aggregator.order_ids(&payment_id).await.unwrap(),
+            vec!["a1".to_string(), "a2".to_string()]
+        );
+        // Transfer without aggregation confirms the order with its payment id.
+        assert_eq!(
+            aggregator.order_ids("single").await.unwrap(),
+            vec!["single".to_string()]
+        );
+
+        // Orders of failed transfer are retried with new payment id.
+        aggregator.failed(&transfer).await.unwrap();
+        assert_eq!(
+            aggregator.order_ids(&payment_id).await.unwrap(),
+            vec![payment_id.clone()]
+        );
+        let retried = aggregator.take_all().await.unwrap().remove(0);
+        assert_ne!(retried.payment_id, payment_id);
+        assert_eq!(retried.order_ids.len(), 2);
+
+        aggregator.confirmed(&retried.payment_id).await.unwrap();
+        assert!(aggregator.take_all().await.unwrap().is_empty());
+        assert_eq!(
+            aggregator.order_ids(&retried.payment_id).await.unwrap(),
+            vec![retried.payment_id.clone()]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_concurrent_takes_dont_share_orders() {
+        let aggregator = aggregator("aggregator-concurrent");
+        let deadline = Utc::now();
+        let amount = BigDecimal::from(1);
+        for i in 0..10 {
+            aggregator
+                .add(key("0xa"), &amount, deadline, format!("a{i}"))
+                .await
+                .unwrap();
+        }
+
+        let (ready, all) = futures::join!(aggregator.take_ready(Utc::now()), aggregator.take_all());
+        let mut order_ids: Vec<String> = ready
+            .unwrap()
+            .into_iter()
+            .chain(all.unwrap())
+            .flat_map(|t| t.order_ids)
+            .collect();
+        order_ids.sort();
+        order_ids.dedup();
+        assert_eq!(order_ids.len(), 10);
+        assert!(aggregator.take_all().await.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_restore_returns_unsent_orders() {
+        let aggregator = aggregator("aggregator-restore");
+        let deadline = Utc::now() + Duration::hours(1);
+        let amount = BigDecimal::from(1);
+        aggregator
+            .add(key("0xa"), &amount, deadline, "a1".into())
+            .await
+            .unwrap();
+        aggregator
+            .add(key("0xb"), &amount, deadline, "b1".into())
+            .await
+            .unwrap();
+
+        // Driver stopped after sending only one of the transfers.
+        let transfers = aggregator.take_all().await.unwrap();
+        assert_eq!(transfers.len(), 2);
+        let sent = &transfers[0];
+        aggregator.sent(sent).await.unwrap();
+
+        aggregator.restore().await.unwrap();
+        let retried = aggregator.take_all().await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_ne!(retried[0].key, sent.key);
+        assert_eq!(
+            aggregator.order_ids(&sent.payment_id).await.unwrap(),
+            sent.order_ids
+        );
+    }
+}
@@ -47,9 +47,12 @@ pub use service::Erc20Service as PaymentDriverService;
 
 // Private
 #[macro_use]
+extern crate diesel;
+#[macro_use]
 extern crate log;
 
 mod dao;
+mod db;
 mod driver;
 pub mod erc20;
 mod network;
@@ -1,130 +1,288 @@
+use anyhow::Context;
+use erc20_payment_lib::config;
 use maplit::hashmap;
-use std::collections::HashMap;
-use std::str::FromStr;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 // Workspace uses
-use ya_payment_driver::{db::models::Network as DbNetwork, driver::Network, model::GenericError};
+use ya_payment_driver::{driver::Network, model::GenericError};
 
 // Local uses
 use crate::{
-    GOERLI_CURRENCY_LONG, GOERLI_CURRENCY_SHORT, GOERLI_NETWORK, GOERLI_PLATFORM, GOERLI_TOKEN,
+    DRIVER_NAME, GOERLI_CURRENCY_LONG, GOERLI_CURRENCY_SHORT, GOERLI_PLATFORM,
     HOLESKY_CURRENCY_LONG, HOLESKY_CURRENCY_SHORT, HOLESKY_NETWORK, HOLESKY_PLATFORM,
-    HOLESKY_TOKEN, MAINNET_CURRENCY_LONG, MAINNET_CURRENCY_SHORT, MAINNET_NETWORK,
-    MAINNET_PLATFORM, MAINNET_TOKEN, MUMBAI_CURRENCY_LONG, MUMBAI_CURRENCY_SHORT, MUMBAI_NETWORK,
-    MUMBAI_PLATFORM, MUMBAI_TOKEN, POLYGON_MAINNET_CURRENCY_LONG, POLYGON_MAINNET_CURRENCY_SHORT,
-    POLYGON_MAINNET_NETWORK, POLYGON_MAINNET_PLATFORM, POLYGON_MAINNET_TOKEN,
-    RINKEBY_CURRENCY_LONG, RINKEBY_CURRENCY_SHORT, RINKEBY_NETWORK, RINKEBY_PLATFORM,
-    RINKEBY_TOKEN,
+    MAINNET_CURRENCY_LONG, MAINNET_CURRENCY_SHORT, MAINNET_PLATFORM, MUMBAI_CURRENCY_LONG,
+    MUMBAI_CURRENCY_SHORT, MUMBAI_PLATFORM, POLYGON_MAINNET_CURRENCY_LONG,
+    POLYGON_MAINNET_CURRENCY_SHORT, POLYGON_MAINNET_PLATFORM, RINKEBY_CURRENCY_LONG,
+    RINKEBY_CURRENCY_SHORT, RINKEBY_PLATFORM,
 };
 
-lazy_static::lazy_static! {
-    pub static ref SUPPORTED_NETWORKS: HashMap<String, Network> = hashmap! {
-        RINKEBY_NETWORK.to_string() => Network {
-            default_token: RINKEBY_TOKEN.to_string(),
-            tokens: hashmap! {
-                RINKEBY_TOKEN.to_string() => RINKEBY_PLATFORM.to_string()
-            }
-        },
-        GOERLI_NETWORK.to_string() => Network {
-            default_token: GOERLI_TOKEN.to_string(),
-            tokens: hashmap! {
-                GOERLI_TOKEN.to_string() => GOERLI_PLATFORM.to_string()
-            }
-        },
-        HOLESKY_NETWORK.to_string() => Network {
-            default_token: HOLESKY_TOKEN.to_string(),
-            tokens: hashmap! {
-                HOLESKY_TOKEN.to_string() => HOLESKY_PLATFORM.to_string()
-            }
-        },
-        MAINNET_NETWORK.to_string() => Network {
-            default_token: MAINNET_TOKEN.to_string(),
-            tokens: hashmap! {
-                MAINNET_TOKEN.to_string() => MAINNET_PLATFORM.to_string()
+/// Env variable with path to chain registry file, overriding the default location.
+pub const CHAIN_REGISTRY_ENV: &str = "ERC20_CHAIN_REGISTRY";
+const CHAIN_REGISTRY_FILES: [&str; 2] = ["erc20-chains.toml", "erc20-chains.json"];
+
+/// Chain registry defines additional (or overrides built-in) chains in the same
+/// format as `[chain.<network>]` sections of `config-payments.toml`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ChainRegistry {
+    #[serde(default)]
+    pub chain: BTreeMap<String, config::Chain>,
+}
+
+impl ChainRegistry {
+    /// Finds registry file pointed by env variable or placed in driver data directory.
+    pub fn find(data_dir: &Path) -> Option<PathBuf> {
+        if let Ok(path) = std::env::var(CHAIN_REGISTRY_ENV) {
+            return Some(PathBuf::from(path));
+        }
+        CHAIN_REGISTRY_FILES
+            .iter()
+            .map(|name| data_dir.join(name))
+            .find(|path| path.exists())
+    }
+
+    pub async fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Can't read chain registry {}", path.display()))?;
+        Self::parse(path, &content)
+    }
+
+    fn parse(path: &Path, content: &str) -> anyhow::Result<Self> {
+        let registry: ChainRegistry = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(content)?,
+            _ => toml::from_str(content)?,
+        };
+        for (network, chain) in &registry.chain {
+            if network.contains('-') {
+                anyhow::bail!("Network name can't contain '-': {}", network);
             }
-        },
-        MUMBAI_NETWORK.to_string() => Network {
-            default_token: MUMBAI_TOKEN.to_string(),
-            tokens: hashmap! {
-                MUMBAI_TOKEN.to_string() => MUMBAI_PLATFORM.to_string()
+            if chain.token.symbol.contains('-') {
+                anyhow::bail!("Token symbol can't contain '-': {}", chain.token.symbol);
             }
-        },
-        POLYGON_MAINNET_NETWORK.to_string() => Network {
-            default_token: POLYGON_MAINNET_TOKEN.to_string(),
-            tokens: hashmap! {
-                POLYGON_MAINNET_TOKEN.to_string() => POLYGON_MAINNET_PLATFORM.to_string()
+        }
+        Ok(registry)
+    }
+
+    /// Adds registry chains to the config. Chains with the same network name are replaced.
+    pub fn merge_into(self, config: &mut config::Config) -> anyhow::Result<()> {
+        for (network, chain) in self.chain {
+            if let Some((other, _)) = config
+                .chain
+                .iter()
+                .find(|(other, c)| c.chain_id == chain.chain_id && **other != network)
+            {
+                anyhow::bail!(
+                    "Chain id {} of network {} is already used by network {}",
+                    chain.chain_id,
+                    network,
+                    other
+                );
             }
+            log::info!(
+                "Registered chain {} (id: {}, token: {}) from chain registry",
+                network,
+                chain.chain_id,
+                chain.token.symbol
+            );
+            config.chain.insert(network, chain);
         }
-    };
-    pub static ref RINKEBY_DB_NETWORK: DbNetwork = DbNetwork::from_str(RINKEBY_NETWORK).unwrap();
-    pub static ref GOERLI_DB_NETWORK: DbNetwork = DbNetwork::from_str(GOERLI_NETWORK).unwrap();
-    pub static ref HOLESKY_DB_NETWORK: DbNetwork = DbNetwork::from_str(HOLESKY_NETWORK).unwrap();
-    pub static ref MAINNET_DB_NETWORK: DbNetwork = DbNetwork::from_str(MAINNET_NETWORK).unwrap();
-    pub static ref MUMBAI_DB_NETWORK: DbNetwork = DbNetwork::from_str(MUMBAI_NETWORK).unwrap();
-    pub static ref POLYGON_MAINNET_DB_NETWORK: DbNetwork = DbNetwork::from_str(POLYGON_MAINNET_NETWORK).unwrap();
+        Ok(())
+    }
 }
 
-pub fn platform_to_network_token(platform: String) -> Result<(DbNetwork, String), GenericError> {
-    match platform.as_str() {
-        RINKEBY_PLATFORM => Ok((*RINKEBY_DB_NETWORK, RINKEBY_TOKEN.to_owned())),
-        GOERLI_PLATFORM => Ok((*GOERLI_DB_NETWORK, GOERLI_TOKEN.to_owned())),
-        HOLESKY_PLATFORM => Ok((*HOLESKY_DB_NETWORK, HOLESKY_TOKEN.to_owned())),
-        MAINNET_PLATFORM => Ok((*MAINNET_DB_NETWORK, MAINNET_TOKEN.to_owned())),
-        MUMBAI_PLATFORM => Ok((*MUMBAI_DB_NETWORK, MUMBAI_TOKEN.to_owned())),
-        POLYGON_MAINNET_PLATFORM => Ok((
-            *POLYGON_MAINNET_DB_NETWORK,
-            POLYGON_MAINNET_TOKEN.to_owned(),
-        )),
-        other => Err(GenericError::new(format!(
-            "Unable to find network for platform: {}",
-            other
-        ))),
-    }
+/// Chain supported by the driver, as defined in payment config and chain registry.
+#[derive(Clone, Debug)]
+pub struct ChainInfo {
+    pub chain_id: i64,
+    pub network: String,
+    pub token: String,
+    pub platform: String,
+    pub currency_short: String,
+    pub currency_long: String,
+}
+
+/// Networks supported by the driver, indexed by network name.
+#[derive(Clone, Debug, Default)]
+pub struct Chains {
+    chains: BTreeMap<String, ChainInfo>,
 }
 
-pub fn platform_to_currency(platform: String) -> Result<(String, String), GenericError> {
-    match platform.as_str() {
-        RINKEBY_PLATFORM => Ok((
-            RINKEBY_CURRENCY_SHORT.to_owned(),
-            RINKEBY_CURRENCY_LONG.to_owned(),
-        )),
-        GOERLI_PLATFORM => Ok((
-            GOERLI_CURRENCY_SHORT.to_owned(),
-            GOERLI_CURRENCY_LONG.to_owned(),
-        )),
-        HOLESKY_PLATFORM => Ok((
-            HOLESKY_CURRENCY_SHORT.to_owned(),
-            HOLESKY_CURRENCY_LONG.to_owned(),
-        )),
-        MAINNET_PLATFORM => Ok((
-            MAINNET_CURRENCY_SHORT.to_owned(),
-            MAINNET_CURRENCY_LONG.to_owned(),
-        )),
-        MUMBAI_PLATFORM => Ok((
-            MUMBAI_CURRENCY_SHORT.to_owned(),
-            MUMBAI_CURRENCY_LONG.to_owned(),
-        )),
-        POLYGON_MAINNET_PLATFORM => Ok((
-            POLYGON_MAINNET_CURRENCY_SHORT.to_owned(),
-            POLYGON_MAINNET_CURRENCY_LONG.to_owned(),
-        )),
-        other => Err(GenericError::new(format!(
-            "Unable to find network currency for platform: {}",
-            other
-        ))),
+impl Chains {
+    pub fn from_config(config: &config::Config) -> Self {
+        let chains = config
+            .chain
+            .iter()
+            .map(|(network, chain)| {
+                let token = chain.token.symbol.clone();
+                let platform = format!("{}-{}-{}", DRIVER_NAME, network, token.to_lowercase());
+                let (currency_short, currency_long) = builtin_currency(&platform)
+                    .unwrap_or_else(|| (chain.currency_symbol.clone(), chain.chain_name.clone()));
+                let info = ChainInfo {
+                    chain_id: chain.chain_id,
+                    network: network.clone(),
+                    token,
+                    platform,
+                    currency_short,
+                    currency_long,
+                };
+                (network.clone(), info)
+            })
+            .collect();
+        Chains { chains }
+    }
+
+    pub fn networks(&self) -> HashMap<String, Network> {
+        self.chains
+            .values()
+            .map(|chain| {
+                let network = Network {
+                    default_token: chain.token.clone(),
+                    tokens: hashmap! {
+                        chain.token.clone() => chain.platform.clone()
+                    },
+                };
+                (chain.network.clone(), network)
+            })
+            .collect()
+    }
+
+    pub fn get(&self, network: &str) -> Result<&ChainInfo, GenericError> {
+        self.chains
+            .get(network)
+            .ok_or_else(|| GenericError::new(format!("Unsupported network: {}", network)))
+    }
+
+    /// Resolves optional network name from user input, falling back to default network.
+    pub fn network_like(&self, network_like: Option<String>) -> Result<&ChainInfo, GenericError> {
+        self.get(network_like.as_deref().unwrap_or(HOLESKY_NETWORK))
+    }
+
+    pub fn platform(&self, platform: &str) -> Result<&ChainInfo, GenericError> {
+        self.chains
+            .values()
+            .find(|chain| chain.platform == platform)
+            .ok_or_else(|| {
+                GenericError::new(format!("Unable to find network for platform: {}", platform))
+            })
     }
 }
 
-pub fn get_network_token(network: DbNetwork, token: Option<String>) -> String {
-    // Fetch network config, safe as long as all DbNetwork entries are in SUPPORTED_NETWORKS
-    let network_config = (*SUPPORTED_NETWORKS).get(&(network.to_string())).unwrap();
-    // TODO: Check if token in network.tokens
-    token.unwrap_or_else(|| network_config.default_token.clone())
+fn builtin_currency(platform: &str) -> Option<(String, String)> {
+    let (short, long) = match platform {
+        RINKEBY_PLATFORM => (RINKEBY_CURRENCY_SHORT, RINKEBY_CURRENCY_LONG),
+        GOERLI_PLATFORM => (GOERLI_CURRENCY_SHORT, GOERLI_CURRENCY_LONG),
+        HOLESKY_PLATFORM => (HOLESKY_CURRENCY_SHORT, HOLESKY_CURRENCY_LONG),
+        MAINNET_PLATFORM => (MAINNET_CURRENCY_SHORT, MAINNET_CURRENCY_LONG),
+        MUMBAI_PLATFORM => (MUMBAI_CURRENCY_SHORT, MUMBAI_CURRENCY_LONG),
+        POLYGON_MAINNET_PLATFORM => (
+            POLYGON_MAINNET_CURRENCY_SHORT,
+            POLYGON_MAINNET_CURRENCY_LONG,
+        ),
+        _ => return None,
+    };
+    Some((short.to_string(), long.to_string()))
 }
 
-pub fn network_like_to_network(network_like: Option<String>) -> DbNetwork {
-    match network_like {
-        Some(n) => DbNetwork::from_str(&n).unwrap_or(*HOLESKY_DB_NETWORK),
-        None => *HOLESKY_DB_NETWORK,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAGING: &str = r#"
+[chain.staging]
+chain-name = "Staging"
+chain-id = 987789
+currency-symbol = "sETH"
+priority-fee = 1.0
+max-fee-per-gas = 10.0
+transaction-timeout = 100
+token = { address = "0x8888888815bf4DB87e57B609A50f938311EEd068", symbol = "tGLM" }
+multi-contract = { address = "0xAaAAAaA00E1841A63342db7188abA84BDeE236c7", max-at-once = 10 }
+confirmation-blocks = 1
+
+[[chain.staging.rpc-endpoints]]
+names = "staging-node"
+endpoints = "http://127.0.0.1:8545"
+priority = 0
+max-timeout-ms = 5000
+verify-interval-secs = 300
+allowed-head-behind-secs = 60
+"#;
+
+    fn default_config() -> config::Config {
+        config::Config::load_from_str(include_str!("../config-payments.toml")).unwrap()
+    }
+
+    fn registry(content: &str) -> ChainRegistry {
+        ChainRegistry::parse(Path::new("erc20-chains.toml"), content).unwrap()
+    }
+
+    #[test]
+    fn test_parse_registry() {
+        let registry = registry(STAGING);
+        assert_eq!(registry.chain.len(), 1);
+        assert_eq!(registry.chain["staging"].chain_id, 987789);
+
+        let invalid = STAGING.replace("chain.staging", "chain.my-staging");
+        assert!(ChainRegistry::parse(Path::new("erc20-chains.toml"), &invalid).is_err());
+
+        let invalid = STAGING.replace("symbol = \"tGLM\"", "symbol = \"t-GLM\"");
+        assert!(ChainRegistry::parse(Path::new("erc20-chains.toml"), &invalid).is_err());
+
+        assert!(ChainRegistry::parse(Path::new("erc20-chains.json"), STAGING).is_err());
+        let registry = ChainRegistry::parse(Path::new("erc20-chains.json"), r#"{"chain":{}}"#);
+        assert!(registry.unwrap().chain.is_empty());
+    }
+
+    #[test]
+    fn test_merge_into() {
+        let mut config = default_config();
+        let builtin = config.chain.len();
+        registry(STAGING).merge_into(&mut config).unwrap();
+        assert_eq!(config.chain.len(), builtin + 1);
+        assert_eq!(config.chain["staging"].chain_name, "Staging");
+
+        // Chain with the same name replaces configured one.
+        let replaced = STAGING.replace("chain-name = \"Staging\"", "chain-name = \"Staging 2\"");
+        registry(&replaced).merge_into(&mut config).unwrap();
+        assert_eq!(config.chain.len(), builtin + 1);
+        assert_eq!(config.chain["staging"].chain_name, "Staging 2");
+
+        // Chain id can't be taken over by a network with different name.
+        let conflict = STAGING
+            .replace("chain.staging", "chain.other")
+            .replace("987789", "17000");
+        assert!(registry(&conflict).merge_into(&mut config).is_err());
+        assert!(!config.chain.contains_key("other"));
+    }
+
+    #[test]
+    fn test_chains_lookup() {
+        let mut config = default_config();
+        registry(STAGING).merge_into(&mut config).unwrap();
+        let chains = Chains::from_config(&config);
+
+        let staging = chains.platform("erc20-staging-tglm").unwrap();
+        assert_eq!(staging.network, "staging");
+        assert_eq!(staging.chain_id, 987789);
+        assert_eq!(staging.currency_short, "sETH");
+        assert_eq!(staging.currency_long, "Staging");
+        assert_eq!(
+            chains.get("staging").unwrap().platform,
+            "erc20-staging-tglm"
+        );
+
+        let holesky = chains.network_like(None).unwrap();
+        assert_eq!(holesky.platform, HOLESKY_PLATFORM);
+        assert_eq!(holesky.currency_long, HOLESKY_CURRENCY_LONG);
+
+        let networks = chains.networks();
+        assert_eq!(networks["staging"].default_token, "tGLM");
+        assert_eq!(networks["staging"].tokens["tGLM"], "erc20-staging-tglm");
+
+        assert!(chains.get("unknown").is_err());
+        assert!(chains.network_like(Some("unknown".to_string())).is_err());
+        assert!(chains.platform("erc20-unknown-tglm").is_err());
     }
 }
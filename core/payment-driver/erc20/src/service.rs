@@ -3,6 +3,7 @@
 */
 
 use std::sync::Arc;
+use std::time::Duration;
 use std::{env, path::PathBuf, str::FromStr};
 // External crates
 use erc20_payment_lib::config;
@@ -12,9 +13,12 @@ use ethereum_types::H160;
 
 // Workspace uses
 use ya_payment_driver::bus;
+use ya_persistence::executor::DbExecutor;
 
 // Local uses
-use crate::{driver::Erc20Driver, signer::IdentitySigner};
+use crate::driver::{Erc20Driver, PaymentAggregator};
+use crate::network::{ChainRegistry, Chains};
+use crate::signer::IdentitySigner;
 
 pub struct Erc20Service;
 
@@ -70,6 +74,14 @@ impl Erc20Service {
                 );
             }
 
+            // Chain registry allows adding private chains without replacing whole config
+            if let Some(registry_path) = ChainRegistry::find(&path) {
+                log::info!("Loading chain registry from {}", registry_path.display());
+                ChainRegistry::load(&registry_path)
+                    .await?
+                    .merge_into(&mut config)?;
+            }
+
             let sendout_interval_env = "ERC20_SENDOUT_INTERVAL_SECS";
             if let Ok(sendout_interval) = env::var(sendout_interval_env) {
                 match sendout_interval.parse::<u64>() {
@@ -81,6 +93,28 @@ impl Erc20Service {
                 }
             }
 
+            let aggregation_env = "ERC20_PAYMENT_AGGREGATION_SECS";
+            let mut aggregation_window = Duration::ZERO;
+            if let Ok(aggregation_secs) = env::var(aggregation_env) {
+                match aggregation_secs.parse::<u64>() {
+                    Ok(aggregation_secs) => {
+                        log::info!("erc20 payments aggregation window set to {aggregation_secs}s");
+                        aggregation_window = Duration::from_secs(aggregation_secs);
+                    }
+                    Err(e) => log::warn!("Value {aggregation_secs} for {aggregation_env} is not a valid integer: {e}"),
+                }
+            }
+
+            let multi_transfer_env = "ERC20_USE_MULTI_TRANSFER";
+            let use_multi_transfer = match env::var(multi_transfer_env).map(|s| s.parse::<bool>()) {
+                Ok(Ok(use_multi_transfer)) => use_multi_transfer,
+                Ok(Err(e)) => {
+                    log::warn!("Value for {multi_transfer_env} is not a valid bool: {e}");
+                    true
+                }
+                Err(_) => true,
+            };
+
             for (network, chain) in &mut config.chain {
                 let prefix = network.to_ascii_uppercase();
                 let symbol = chain.token.symbol.to_ascii_uppercase();
@@ -181,8 +215,14 @@ impl Erc20Service {
                         }
                     };
                 }
+                if !use_multi_transfer && chain.multi_contract.is_some() {
+                    log::info!("{network} multi payment contract disabled");
+                    chain.multi_contract = None;
+                }
             }
 
+            let chains = Chains::from_config(&config);
+
             log::debug!("Starting payment engine: {:#?}", config);
             let signer = IdentitySigner;
 
@@ -208,7 +248,11 @@ impl Erc20Service {
             //    .await?;
 
             log::debug!("Bind erc20 driver");
-            let driver = Erc20Driver::new(pr, recv);
+            let db = DbExecutor::from_data_dir(&path, "erc20-driver")?;
+            db.apply_migration(crate::db::migrations::run_with_output)?;
+            let aggregator = PaymentAggregator::new(aggregation_window, db.clone());
+
+            let driver = Erc20Driver::new(pr, recv, chains, aggregator, db);
             driver.load_active_accounts().await;
             bus::bind_service(driver).await?;
 
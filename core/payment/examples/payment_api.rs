@@ -84,10 +84,15 @@ struct Args {
     agreement_id: String,
     #[structopt(long)]
     app_session_id: Option<String>,
+    #[structopt(flatten)]
+    dummy_driver: dummy::DummyDriverConfig,
 }
 
-pub async fn start_dummy_driver() -> anyhow::Result<()> {
-    dummy::PaymentDriverService::gsb(&()).await?;
+pub async fn start_dummy_driver(
+    path: PathBuf,
+    config: dummy::DummyDriverConfig,
+) -> anyhow::Result<()> {
+    dummy::PaymentDriverService::gsb(path, config).await?;
     Ok(())
 }
 
@@ -235,7 +240,7 @@ async fn main() -> anyhow::Result<()> {
 
     let driver_name = match args.driver {
         Driver::Dummy => {
-            start_dummy_driver().await?;
+            start_dummy_driver("./".into(), args.dummy_driver.clone()).await?;
             dummy::DRIVER_NAME
         }
         Driver::Erc20 => {
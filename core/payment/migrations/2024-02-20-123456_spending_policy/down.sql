@@ -0,0 +1,3 @@
+DROP INDEX pay_spending_timestamp_idx;
+DROP TABLE pay_spending;
+DROP TABLE pay_spending_policy;
@@ -0,0 +1,21 @@
+CREATE TABLE pay_spending_policy(
+    owner_id VARCHAR(50) NOT NULL PRIMARY KEY,
+    daily_limit VARCHAR(32) NULL,
+    monthly_limit VARCHAR(32) NULL,
+    provider_daily_limit VARCHAR(32) NULL,
+    provider_monthly_limit VARCHAR(32) NULL,
+    max_debit_note_price_ratio REAL NULL,
+    max_invoice_price_ratio REAL NULL,
+    auto_reject BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE pay_spending(
+    owner_id VARCHAR(50) NOT NULL,
+    document_id VARCHAR(50) NOT NULL,
+    peer_id VARCHAR(50) NOT NULL,
+    amount VARCHAR(32) NOT NULL,
+    timestamp DATETIME NOT NULL DEFAULT(STRFTIME('%Y-%m-%d %H:%M:%f', 'NOW')),
+    PRIMARY KEY(owner_id, document_id)
+);
+
+CREATE INDEX pay_spending_timestamp_idx ON pay_spending (owner_id, timestamp);
@@ -0,0 +1,2 @@
+DROP INDEX pay_allocation_event_owner_idx;
+DROP TABLE pay_allocation_event;
@@ -0,0 +1,11 @@
+CREATE TABLE pay_allocation_event(
+    allocation_id VARCHAR(50) NOT NULL,
+    owner_id VARCHAR(50) NOT NULL,
+    event_type VARCHAR(50) NOT NULL,
+    reason VARCHAR(50) NULL,
+    timestamp DATETIME NOT NULL DEFAULT(STRFTIME('%Y-%m-%d %H:%M:%f', 'NOW')),
+    PRIMARY KEY(allocation_id, event_type),
+    FOREIGN KEY(allocation_id) REFERENCES pay_allocation (id)
+);
+
+CREATE INDEX pay_allocation_event_owner_idx ON pay_allocation_event (owner_id, timestamp);
@@ -0,0 +1,4 @@
+CREATE TABLE pay_reconcile_checkpoint(
+    owner_id VARCHAR(50) NOT NULL PRIMARY KEY,
+    verified_until DATETIME NOT NULL
+);
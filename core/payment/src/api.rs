@@ -6,20 +6,22 @@ use ya_service_api_web::scope::ExtendableScope;
 
 mod accounts;
 pub mod allocations;
-mod debit_notes;
+pub(crate) mod debit_notes;
 mod invoices;
 mod payments;
+mod policy;
 
 mod guard;
 
 pub fn api_scope(scope: Scope) -> Scope {
     scope
-        .app_data(web::Data::new(guard::AgreementLock::arc()))
+        .app_data(web::Data::new(guard::AgreementLock::shared()))
         .extend(accounts::register_endpoints)
         .extend(allocations::register_endpoints)
         .extend(debit_notes::register_endpoints)
         .extend(invoices::register_endpoints)
         .extend(payments::register_endpoints)
+        .extend(policy::register_endpoints)
 }
 
 pub fn web_scope(db: &DbExecutor) -> Scope {
@@ -1,12 +1,11 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::Display;
 use std::str::FromStr;
-use std::time::Duration;
 // External crates
 use actix_web::web::{delete, get, post, put, Data, Json, Path, Query};
 use actix_web::{HttpResponse, Scope};
 use bigdecimal::BigDecimal;
-use chrono::{DateTime, Utc};
 use serde_json::value::Value::Null;
 use ya_client_model::NodeId;
 
@@ -14,8 +13,9 @@ use ya_client_model::NodeId;
 use ya_agreement_utils::{ClauseOperator, ConstraintKey, Constraints};
 use ya_client_model::payment::allocation::{PaymentPlatform, PaymentPlatformEnum};
 use ya_client_model::payment::*;
+use ya_core_model::driver::{driver_bus_id, MakeDeposit, ReleaseDeposit};
 use ya_core_model::payment::local::{
-    get_token_from_network_name, DriverName, NetworkName, ValidateAllocation,
+    get_token_from_network_name, DriverName, GetDrivers, NetworkName, ValidateAllocation,
     ValidateAllocationError, BUS_ID as LOCAL_SERVICE,
 };
 use ya_core_model::payment::RpcMessageError;
@@ -27,7 +27,8 @@ use ya_service_bus::{typed as bus, RpcEndpoint};
 use crate::accounts::{init_account, Account};
 use crate::dao::*;
 use crate::error::{DbError, Error};
-use crate::utils::response;
+use crate::models::allocation_event::AllocationReleaseReason;
+use crate::utils::{listen_for_events, network_registered, response};
 
 const DEFAULT_TESTNET_NETWORK: NetworkName = NetworkName::Holesky;
 const DEFAULT_MAINNET_NETWORK: NetworkName = NetworkName::Polygon;
@@ -45,14 +46,33 @@ mod token_name {
     }
 
     impl TokenName {
-        pub fn default(_driver: &DriverName, network: &NetworkName) -> TokenName {
+        pub fn default(
+            driver: &str,
+            network: &str,
+            drivers: &HashMap<String, DriverDetails>,
+        ) -> TokenName {
+            match NetworkName::from_str(network) {
+                Ok(network) => Self::builtin(&network),
+                // Networks defined by driver's chain registry
+                Err(_) => Self(
+                    drivers
+                        .get(driver)
+                        .and_then(|details| details.networks.get(network))
+                        .map(|network| network.default_token.to_lowercase())
+                        .unwrap_or_default(),
+                ),
+            }
+        }
+
+        pub fn builtin(network: &NetworkName) -> TokenName {
             Self(get_token_from_network_name(network).to_lowercase())
         }
 
         pub fn from_token_string(
-            driver: &DriverName,
-            network: &NetworkName,
+            driver: &str,
+            network: &str,
             token: &str,
+            drivers: &HashMap<String, DriverDetails>,
         ) -> Result<Self, String> {
             if token == "GLM" || token == "tGLM" {
                 return Err(format!(
@@ -60,7 +80,7 @@ mod token_name {
                     token
                 ));
             }
-            let token_expected = Self::default(driver, network).to_string();
+            let token_expected = Self::default(driver, network, drivers).to_string();
             if token != token_expected {
                 return Err(format!(
                     "Token {} does not match expected token {} for driver {} and network {}. \
@@ -79,17 +99,17 @@ mod platform_triple {
     use anyhow::{anyhow, bail};
 
     pub struct PaymentPlatformTriple {
-        driver: DriverName,
-        network: NetworkName,
+        driver: String,
+        network: String,
         token: TokenName,
     }
 
     impl PaymentPlatformTriple {
-        pub fn driver(&self) -> &DriverName {
+        pub fn driver(&self) -> &str {
             &self.driver
         }
 
-        pub fn network(&self) -> &NetworkName {
+        pub fn network(&self) -> &str {
             &self.network
         }
 
@@ -99,22 +119,23 @@ mod platform_triple {
 
         pub fn default_testnet() -> Self {
             PaymentPlatformTriple {
-                driver: DEFAULT_PAYMENT_DRIVER,
-                network: DEFAULT_TESTNET_NETWORK,
-                token: TokenName::default(&DEFAULT_PAYMENT_DRIVER, &DEFAULT_TESTNET_NETWORK),
+                driver: DEFAULT_PAYMENT_DRIVER.to_string(),
+                network: DEFAULT_TESTNET_NETWORK.to_string(),
+                token: TokenName::builtin(&DEFAULT_TESTNET_NETWORK),
             }
         }
 
         pub fn default_mainnet() -> Self {
             PaymentPlatformTriple {
-                driver: DEFAULT_PAYMENT_DRIVER,
-                network: DEFAULT_MAINNET_NETWORK,
-                token: TokenName::default(&DEFAULT_PAYMENT_DRIVER, &DEFAULT_MAINNET_NETWORK),
+                driver: DEFAULT_PAYMENT_DRIVER.to_string(),
+                network: DEFAULT_MAINNET_NETWORK.to_string(),
+                token: TokenName::builtin(&DEFAULT_MAINNET_NETWORK),
             }
         }
 
         pub fn from_payment_platform_input(
             p: &PaymentPlatform,
+            drivers: &HashMap<String, DriverDetails>,
         ) -> anyhow::Result<PaymentPlatformTriple> {
             let platform = if p.driver.is_none() && p.network.is_none() && p.token.is_none() {
                 let default_platform = Self::default_testnet();
@@ -162,7 +183,7 @@ mod platform_triple {
                         DEFAULT_TESTNET_NETWORK.into()
                     }
                 });
-                let network = validate_network(network_str)
+                let network = validate_network(network_str, drivers)
                     .map_err(|err| anyhow!("Validate network failed (1): {err}"))?;
 
                 let driver_str = p.driver.as_deref().unwrap_or_else(|| {
@@ -172,11 +193,11 @@ mod platform_triple {
                     );
                     DEFAULT_PAYMENT_DRIVER.into()
                 });
-                let driver = validate_driver(&network, driver_str)
+                let driver = validate_driver(driver_str, drivers)
                     .map_err(|err| anyhow!("Validate driver failed (1): {err}"))?;
 
                 if let Some(token) = p.token.as_ref() {
-                    let token = TokenName::from_token_string(&driver, &network, token)
+                    let token = TokenName::from_token_string(&driver, &network, token, drivers)
                         .map_err(|err| anyhow!("Validate token failed (1): {err}"))?;
                     log::debug!("Selected network {}-{}-{}", driver, network, token);
                     Self {
@@ -185,7 +206,7 @@ mod platform_triple {
                         token,
                     }
                 } else {
-                    let default_token = TokenName::default(&driver, &network);
+                    let default_token = TokenName::default(&driver, &network, drivers);
 
                     log::debug!(
                         "Selected network with default token {}-{}-{}",
@@ -205,6 +226,7 @@ mod platform_triple {
 
         pub fn from_payment_platform_str(
             payment_platform_str: &str,
+            drivers: &HashMap<String, DriverDetails>,
         ) -> anyhow::Result<PaymentPlatformTriple> {
             // payment_platform is of the form driver-network-token
             // eg. erc20-rinkeby-tglm
@@ -219,13 +241,13 @@ mod platform_triple {
                     )
                 })?;
 
-            let network = validate_network(network_str)
+            let network = validate_network(network_str, drivers)
                 .map_err(|err| anyhow!("Validate network failed (2): {err}"))?;
 
-            let driver = validate_driver(&network, driver_str)
+            let driver = validate_driver(driver_str, drivers)
                 .map_err(|err| anyhow!("Validate driver failed (2): {err}"))?;
 
-            let token = TokenName::from_token_string(&driver, &network, token_str)
+            let token = TokenName::from_token_string(&driver, &network, token_str, drivers)
                 .map_err(|err| anyhow!("Validate token failed (2): {err}"))?;
 
             Ok(Self {
@@ -242,18 +264,27 @@ mod platform_triple {
         }
     }
 
-    fn validate_network(network: &str) -> Result<NetworkName, String> {
+    fn validate_network(
+        network: &str,
+        drivers: &HashMap<String, DriverDetails>,
+    ) -> Result<String, String> {
         match NetworkName::from_str(network) {
             Ok(NetworkName::Rinkeby) => Err("Rinkeby is no longer supported".to_string()),
-            Ok(network_name) => Ok(network_name),
+            Ok(network_name) => Ok(network_name.to_string()),
+            // Custom networks registered by drivers
+            Err(_) if network_registered(network, drivers) => Ok(network.to_string()),
             Err(_) => Err(format!("Invalid network name: {network}")),
         }
     }
 
-    fn validate_driver(network: &NetworkName, driver: &str) -> Result<DriverName, String> {
-        match DriverName::from_str(driver) {
-            Err(_) => Err(format!("Invalid driver name {}", driver)),
-            Ok(driver_name) => Ok(driver_name),
+    /// Built-in and external drivers are accepted, if they are registered.
+    fn validate_driver(
+        driver: &str,
+        drivers: &HashMap<String, DriverDetails>,
+    ) -> Result<String, String> {
+        match drivers.contains_key(driver) {
+            true => Ok(driver.to_string()),
+            false => Err(format!("Payment driver {} isn't registered", driver)),
         }
     }
 }
@@ -270,6 +301,7 @@ pub fn register_endpoints(scope: Scope) -> Scope {
             "/allocations/{allocation_id}",
             delete().to(release_allocation),
         )
+        .route("/allocationEvents", get().to(get_allocation_events))
         .route("/demandDecorations", get().to(get_demand_decorations))
 }
 
@@ -283,16 +315,22 @@ async fn create_allocation(
         response::bad_request(&err_msg)
     };
 
-    // TODO: Handle deposits & timeouts
     let allocation = body.into_inner();
     let node_id = id.identity;
 
+    let drivers = match bus::service(LOCAL_SERVICE).send(GetDrivers {}).await {
+        Ok(Ok(drivers)) => drivers,
+        Ok(Err(e)) => return response::server_error(&e),
+        Err(e) => return response::server_error(&e),
+    };
+
     let payment_triple = match &allocation.payment_platform {
         Some(PaymentPlatformEnum::PaymentPlatformName(name)) => {
-            let payment_platform = match PaymentPlatformTriple::from_payment_platform_str(name) {
-                Ok(p) => p,
-                Err(err) => return bad_req_and_log(format!("{}", err)),
-            };
+            let payment_platform =
+                match PaymentPlatformTriple::from_payment_platform_str(name, &drivers) {
+                    Ok(p) => p,
+                    Err(err) => return bad_req_and_log(format!("{}", err)),
+                };
             log::debug!(
                 "Successfully parsed API payment platform name: {}",
                 payment_platform
@@ -300,7 +338,7 @@ async fn create_allocation(
             payment_platform
         }
         Some(PaymentPlatformEnum::PaymentPlatform(payment_platform)) => {
-            match PaymentPlatformTriple::from_payment_platform_input(payment_platform) {
+            match PaymentPlatformTriple::from_payment_platform_input(payment_platform, &drivers) {
                 Ok(platform_str) => platform_str,
                 Err(err) => return bad_req_and_log(format!("{}", err)),
             }
@@ -365,16 +403,18 @@ async fn create_allocation(
     {
         Ok(allocation_id) => match dao.get(allocation_id, node_id).await {
             Ok(AllocationStatus::Active(allocation)) => {
-                let allocation_id = allocation.allocation_id.clone();
-
-                release_allocation_after(
-                    db.clone(),
-                    allocation_id,
-                    allocation.timeout,
-                    Some(node_id),
-                )
-                .await;
-
+                if allocation.make_deposit {
+                    if let Err(e) = make_deposit(&allocation).await {
+                        forced_release_allocation(
+                            db,
+                            allocation.allocation_id,
+                            Some(node_id),
+                            AllocationReleaseReason::DepositFailed,
+                        )
+                        .await;
+                        return bad_req_and_log(format!("Failed to make deposit: {e}"));
+                    }
+                }
                 response::created(allocation)
             }
             Ok(AllocationStatus::NotFound) => response::server_error(&"Database error"),
@@ -498,7 +538,26 @@ async fn amend_allocation(
         Err(e) => return response::server_error(&e),
     }
 
-    match dao.replace(amended_allocation, node_id).await {
+    let deposit_changed = amended_allocation.make_deposit
+        && amended_allocation.total_amount != current_allocation.total_amount;
+    if deposit_changed {
+        if let Err(e) = make_deposit(&amended_allocation).await {
+            return response::bad_request(&format!("Failed to make deposit: {e}"));
+        }
+    }
+
+    let replaced = dao.replace(amended_allocation, node_id).await;
+    if deposit_changed && !matches!(replaced, Ok(true)) {
+        // Deposit must match the allocation, which wasn't changed
+        if let Err(e) = make_deposit(&current_allocation).await {
+            log::error!(
+                "Restoring deposit of allocation {} failed: {}",
+                allocation_id,
+                e
+            );
+        }
+    }
+    match replaced {
         Ok(true) => {}
         Ok(false) => {
             return response::server_error(
@@ -520,8 +579,18 @@ async fn release_allocation(
     let node_id = Some(id.identity);
     let dao = db.as_dao::<AllocationDao>();
 
-    match dao.release(allocation_id.clone(), node_id).await {
-        Ok(AllocationReleaseStatus::Released) => response::ok(Null),
+    match dao
+        .release(
+            allocation_id.clone(),
+            node_id,
+            AllocationReleaseReason::Requested,
+        )
+        .await
+    {
+        Ok(AllocationReleaseStatus::Released(allocation)) => {
+            release_deposit(&allocation).await;
+            response::ok(Null)
+        }
         Ok(AllocationReleaseStatus::NotFound) => response::not_found(),
         Ok(AllocationReleaseStatus::Gone) => response::gone(&format!(
             "Allocation {} has been already released",
@@ -531,6 +600,28 @@ async fn release_allocation(
     }
 }
 
+async fn get_allocation_events(
+    db: Data<DbExecutor>,
+    query: Query<params::EventParams>,
+    id: Identity,
+) -> HttpResponse {
+    let node_id = id.identity;
+    let timeout_secs = query.timeout.unwrap_or(params::DEFAULT_EVENT_TIMEOUT);
+    let after_timestamp = query.after_timestamp.map(|d| d.naive_utc());
+    let max_events = query.max_events;
+
+    let dao: AllocationEventDao = db.as_dao();
+    let getter = || async {
+        dao.get_for_node_id(node_id, after_timestamp, max_events)
+            .await
+    };
+
+    match listen_for_events(getter, timeout_secs).await {
+        Ok(events) => response::ok(events),
+        Err(e) => response::server_error(&e),
+    }
+}
+
 async fn get_demand_decorations(
     db: Data<DbExecutor>,
     path: Query<params::AllocationIds>,
@@ -585,45 +676,20 @@ async fn get_demand_decorations(
     })
 }
 
-pub async fn release_allocation_after(
-    db: Data<DbExecutor>,
-    allocation_id: String,
-    allocation_timeout: Option<DateTime<Utc>>,
-    node_id: Option<NodeId>,
-) {
-    tokio::task::spawn(async move {
-        if let Some(timeout) = allocation_timeout {
-            //FIXME when upgrading to tokio 1.0 or greater. In tokio 0.2 timer panics when maximum duration of delay is exceeded.
-            let max_duration: i64 = 1 << 35;
-
-            loop {
-                let time_diff = timeout.timestamp_millis() - Utc::now().timestamp_millis();
-
-                if time_diff.is_negative() {
-                    break;
-                }
-
-                let timeout = time_diff.min(max_duration) as u64;
-                tokio::time::sleep(Duration::from_millis(timeout)).await;
-            }
-
-            forced_release_allocation(db, allocation_id, node_id).await;
-        }
-    });
-}
-
 pub async fn forced_release_allocation(
     db: Data<DbExecutor>,
     allocation_id: String,
     node_id: Option<NodeId>,
+    reason: AllocationReleaseReason,
 ) {
     match db
         .as_dao::<AllocationDao>()
-        .release(allocation_id.clone(), node_id)
+        .release(allocation_id.clone(), node_id, reason)
         .await
     {
-        Ok(AllocationReleaseStatus::Released) => {
-            log::info!("Allocation {} released.", allocation_id);
+        Ok(AllocationReleaseStatus::Released(allocation)) => {
+            log::info!("Allocation {} released ({}).", allocation_id, reason);
+            release_deposit(&allocation).await;
         }
         Err(e) => {
             log::warn!(
@@ -635,3 +701,126 @@ pub async fn forced_release_allocation(
         _ => (),
     }
 }
+
+fn platform_driver(platform: &str) -> &str {
+    platform.split('-').next().unwrap_or_default()
+}
+
+/// Reserves allocation funds in the driver.
+async fn make_deposit(allocation: &Allocation) -> anyhow::Result<()> {
+    let msg = MakeDeposit {
+        address: allocation.address.clone(),
+        platform: allocation.payment_platform.clone(),
+        allocation_id: allocation.allocation_id.clone(),
+        amount: allocation.total_amount.clone(),
+    };
+    bus::service(driver_bus_id(platform_driver(&allocation.payment_platform)))
+        .send(msg)
+        .await??;
+    Ok(())
+}
+
+async fn release_deposit(allocation: &Allocation) {
+    if !allocation.make_deposit {
+        return;
+    }
+    let msg = ReleaseDeposit {
+        address: allocation.address.clone(),
+        platform: allocation.payment_platform.clone(),
+        allocation_id: allocation.allocation_id.clone(),
+    };
+    let driver = platform_driver(&allocation.payment_platform);
+    match bus::service(driver_bus_id(driver)).send(msg).await {
+        Ok(Ok(_)) => log::debug!(
+            "Deposit of allocation {} released.",
+            allocation.allocation_id
+        ),
+        Ok(Err(e)) => log::warn!(
+            "Releasing deposit of allocation {} failed: {}",
+            allocation.allocation_id,
+            e
+        ),
+        Err(e) => log::warn!(
+            "Releasing deposit of allocation {} failed: {}",
+            allocation.allocation_id,
+            e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn new_allocation() -> NewAllocation {
+        NewAllocation {
+            address: None,
+            payment_platform: None,
+            total_amount: BigDecimal::from(10),
+            timeout: None,
+            make_deposit: false,
+        }
+    }
+
+    fn update(total_amount: Option<u32>, timeout: Option<i64>) -> AllocationUpdate {
+        AllocationUpdate {
+            total_amount: total_amount.map(BigDecimal::from),
+            timeout: timeout.map(|secs| Utc::now() + Duration::seconds(secs)),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_amend_allocation() {
+        let db = DbExecutor::in_memory("api-amend-allocation").unwrap();
+        db.apply_migration(crate::migrations::run_with_output)
+            .unwrap();
+        let owner = NodeId::from(&[1; 20][..]);
+        let dao = db.as_dao::<AllocationDao>();
+        let allocation_id = dao
+            .create(
+                new_allocation(),
+                owner,
+                "dummy-glm".to_string(),
+                "0x01".to_string(),
+            )
+            .await
+            .unwrap();
+        let allocation = match dao.get(allocation_id.clone(), owner).await.unwrap() {
+            AllocationStatus::Active(allocation) => allocation,
+            _ => panic!("Allocation not created"),
+        };
+        let spent = Allocation {
+            spent_amount: BigDecimal::from(4),
+            remaining_amount: BigDecimal::from(6),
+            ..allocation
+        };
+
+        assert!(amend_allocation_fields(spent.clone(), update(Some(3), None)).is_err());
+        assert!(amend_allocation_fields(spent.clone(), update(None, Some(-60))).is_err());
+
+        let amended = amend_allocation_fields(spent.clone(), update(Some(20), Some(60))).unwrap();
+        assert_eq!(amended.total_amount, BigDecimal::from(20));
+        assert_eq!(amended.remaining_amount, BigDecimal::from(16));
+        assert!(amended.timeout.is_some());
+        // Fields not given in the update are kept
+        let amended = amend_allocation_fields(amended, update(None, None)).unwrap();
+        assert_eq!(amended.total_amount, BigDecimal::from(20));
+        assert!(amended.timeout.is_some());
+
+        assert!(dao.replace(amended.clone(), owner).await.unwrap());
+        match dao.get(allocation_id.clone(), owner).await.unwrap() {
+            AllocationStatus::Active(allocation) => {
+                assert_eq!(allocation.total_amount, BigDecimal::from(20));
+                assert_eq!(allocation.remaining_amount, BigDecimal::from(16));
+            }
+            _ => panic!("Amended allocation not found"),
+        }
+
+        // Released allocation can't be amended
+        dao.release(allocation_id, None, AllocationReleaseReason::Requested)
+            .await
+            .unwrap();
+        assert!(!dao.replace(amended, owner).await.unwrap());
+    }
+}
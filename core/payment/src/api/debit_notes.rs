@@ -9,9 +9,11 @@ use std::time::Instant;
 // Workspace uses
 use metrics::{counter, timing};
 use ya_client_model::payment::*;
+use ya_client_model::NodeId;
 use ya_core_model::payment::local::{SchedulePayment, BUS_ID as LOCAL_SERVICE};
 use ya_core_model::payment::public::{
-    AcceptDebitNote, AcceptRejectError, SendDebitNote, SendError, BUS_ID as PUBLIC_SERVICE,
+    AcceptDebitNote, AcceptRejectError, RejectDebitNote, SendDebitNote, SendError,
+    BUS_ID as PUBLIC_SERVICE,
 };
 use ya_core_model::payment::RpcMessageError;
 use ya_net::RemoteEndpoint;
@@ -26,6 +28,7 @@ use super::guard::AgreementLock;
 use crate::dao::*;
 use crate::error::{DbError, Error};
 use crate::payment_sync::SYNC_NOTIFS_NOTIFY;
+use crate::policy::{self, PolicyError};
 use crate::utils::provider::get_agreement_for_activity;
 use crate::utils::*;
 
@@ -294,12 +297,48 @@ async fn accept_debit_note(
     query: Query<params::Timeout>,
     body: Json<Acceptance>,
     id: Identity,
+) -> HttpResponse {
+    let debit_note_id = path.debit_note_id.clone();
+    let timeout = query.timeout.unwrap_or(params::DEFAULT_ACK_TIMEOUT);
+    accept_received_debit_note(
+        &db,
+        &agreement_lock,
+        debit_note_id,
+        body.into_inner(),
+        id.identity,
+        timeout,
+    )
+    .await
+}
+
+/// Accepts debit note received by the Requestor, when it isn't accepted using REST API.
+pub(crate) async fn auto_accept_debit_note(
+    db: &DbExecutor,
+    debit_note_id: String,
+    acceptance: Acceptance,
+    node_id: NodeId,
+) -> HttpResponse {
+    accept_received_debit_note(
+        db,
+        &AgreementLock::shared(),
+        debit_note_id,
+        acceptance,
+        node_id,
+        params::DEFAULT_ACK_TIMEOUT,
+    )
+    .await
+}
+
+async fn accept_received_debit_note(
+    db: &DbExecutor,
+    agreement_lock: &Arc<AgreementLock>,
+    debit_note_id: String,
+    acceptance: Acceptance,
+    node_id: NodeId,
+    timeout: f64,
 ) -> HttpResponse {
     let start = Instant::now();
 
-    let debit_note_id = path.debit_note_id.clone();
-    let node_id = id.identity;
-    let acceptance = body.into_inner();
     let allocation_id = acceptance.allocation_id.clone();
 
     log::debug!("Requested accept DebitNote [{}]", debit_note_id);
@@ -316,7 +355,7 @@ async fn accept_debit_note(
     };
 
     // Required to serialize complex DB access patterns related to debit note / invoice acceptances.
-    let _agreement_lock = agreement_lock.lock(debit_note.agreement_id.clone());
+    let _agreement_lock = agreement_lock.lock(debit_note.agreement_id.clone()).await;
 
     if debit_note.total_amount_due != acceptance.total_amount_accepted {
         return response::bad_request(&"Invalid amount accepted");
@@ -424,12 +463,68 @@ async fn accept_debit_note(
         return response::bad_request(&msg);
     }
 
-    let timeout = query.timeout.unwrap_or(params::DEFAULT_ACK_TIMEOUT);
+    // Debit notes violating spending policy are held, unless the policy rejects them automatically.
+    log::trace!(
+        "Checking spending policy for Debit Note [{}]",
+        debit_note_id
+    );
+    let policy = match db.as_dao::<SpendingPolicyDao>().get(node_id).await {
+        Ok(policy) => policy,
+        Err(e) => return response::server_error(&e),
+    };
+    // Limits are checked and spent amount is recorded with acceptance in DB under the same lock.
+    // It isn't held while notifying the issuer, so it doesn't delay other acceptances.
+    let spending_lock = match policy {
+        Some(_) => Some(agreement_lock.lock_spending(node_id).await),
+        None => None,
+    };
+    let spending =
+        match policy::check_debit_note(db, node_id, policy.as_ref(), &debit_note, &amount_to_pay)
+            .await
+        {
+            Ok(spending) => spending,
+            Err(PolicyError::Violation(violation)) => {
+                counter!("payment.debit_notes.requestor.policy-violations", 1);
+                drop(spending_lock);
+                let auto_reject = policy.map(|policy| policy.auto_reject).unwrap_or(false);
+                if !auto_reject {
+                    log::warn!(
+                        "DebitNote [{}] not accepted due to spending policy: {}",
+                        debit_note_id,
+                        violation
+                    );
+                    return response::bad_request(&PolicyError::Violation(violation));
+                }
+
+                log::warn!(
+                    "Rejecting DebitNote [{}] due to spending policy: {}",
+                    debit_note_id,
+                    violation
+                );
+                let rejection = Rejection {
+                    rejection_reason: RejectionReason::IncorrectAmount,
+                    total_amount_accepted: Default::default(),
+                    message: Some(format!("Spending policy violated: {}", violation)),
+                };
+                let response =
+                    reject_locked_debit_note(db, debit_note_id, rejection, node_id, timeout).await;
+                if !response.status().is_success() {
+                    return response;
+                }
+                return response::bad_request(&format!(
+                    "Debit note rejected. {}",
+                    PolicyError::Violation(violation)
+                ));
+            }
+            Err(e) => return response::server_error(&e),
+        };
+
     let result = async move {
         let issuer_id = debit_note.issuer_id;
         let accept_msg = AcceptDebitNote::new(debit_note_id.clone(), acceptance, issuer_id);
         let schedule_msg =
             SchedulePayment::from_debit_note(debit_note, allocation_id, amount_to_pay);
+        let accepted_id = debit_note_id.clone();
         match async move {
             // Schedule payment (will be none for amount=0, which is OK)
             if let Some(msg) = schedule_msg {
@@ -439,7 +534,9 @@ async fn accept_debit_note(
 
             // Mark the debit note as accepted in DB
             log::trace!("Accepting DebitNote [{}] in DB", debit_note_id);
-            dao.accept(debit_note_id.clone(), node_id).await?;
+            dao.accept(debit_note_id.clone(), node_id, Some(spending))
+                .await?;
+            drop(spending_lock);
             log::trace!("DebitNote accepted successfully for [{}]", debit_note_id);
 
             log::debug!(
@@ -471,7 +568,7 @@ async fn accept_debit_note(
             Ok(Ok(_)) => {
                 log::info!(
                     "DebitNote [{}] for Activity [{}] accepted.",
-                    path.debit_note_id,
+                    accepted_id,
                     activity_id
                 );
                 counter!("payment.debit_notes.requestor.accepted", 1);
@@ -496,9 +593,149 @@ async fn accept_debit_note(
 
 async fn reject_debit_note(
     db: Data<DbExecutor>,
+    agreement_lock: Data<Arc<AgreementLock>>,
     path: Path<params::DebitNoteId>,
     query: Query<params::Timeout>,
     body: Json<Rejection>,
+    id: Identity,
 ) -> HttpResponse {
-    response::not_implemented() // TODO
+    let debit_note_id = path.debit_note_id.clone();
+    let timeout = query.timeout.unwrap_or(params::DEFAULT_ACK_TIMEOUT);
+    reject_received_debit_note(
+        &db,
+        &agreement_lock,
+        debit_note_id,
+        body.into_inner(),
+        id.identity,
+        timeout,
+    )
+    .await
+}
+
+/// Rejects debit note received by the Requestor.
+async fn reject_received_debit_note(
+    db: &DbExecutor,
+    agreement_lock: &Arc<AgreementLock>,
+    debit_note_id: String,
+    rejection: Rejection,
+    node_id: NodeId,
+    timeout: f64,
+) -> HttpResponse {
+    let agreement_id = match db
+        .as_dao::<DebitNoteDao>()
+        .get(debit_note_id.clone(), node_id)
+        .await
+    {
+        Ok(Some(debit_note)) => debit_note.agreement_id,
+        Ok(None) => return response::not_found(),
+        Err(e) => return response::server_error(&e),
+    };
+
+    // Required to serialize complex DB access patterns related to debit note / invoice acceptances.
+    let _agreement_lock = agreement_lock.lock(agreement_id).await;
+    reject_locked_debit_note(db, debit_note_id, rejection, node_id, timeout).await
+}
+
+/// Rejects debit note received by the Requestor, which failed automatic validation.
+pub(crate) async fn auto_reject_debit_note(
+    db: &DbExecutor,
+    debit_note_id: String,
+    rejection: Rejection,
+    node_id: NodeId,
+) -> HttpResponse {
+    reject_received_debit_note(
+        db,
+        &AgreementLock::shared(),
+        debit_note_id,
+        rejection,
+        node_id,
+        params::DEFAULT_ACK_TIMEOUT,
+    )
+    .await
+}
+
+/// Rejects debit note, which Agreement is already locked by the caller.
+async fn reject_locked_debit_note(
+    db: &DbExecutor,
+    debit_note_id: String,
+    rejection: Rejection,
+    node_id: NodeId,
+    timeout: f64,
+) -> HttpResponse {
+    let start = Instant::now();
+
+    log::debug!("Requested reject DebitNote [{}]", debit_note_id);
+    counter!("payment.debit_notes.requestor.rejected.call", 1);
+
+    let dao: DebitNoteDao = db.as_dao();
+
+    log::trace!("Querying DB for Debit Note [{}]", debit_note_id);
+    let debit_note: DebitNote = match dao.get(debit_note_id.clone(), node_id).await {
+        Ok(Some(debit_note)) => debit_note,
+        Ok(None) => return response::not_found(),
+        Err(e) => return response::server_error(&e),
+    };
+
+    match debit_note.status {
+        DocumentStatus::Received => (),
+        DocumentStatus::Rejected => return response::ok(Null),
+        DocumentStatus::Failed => (),
+        DocumentStatus::Accepted => return response::bad_request(&"Debit note accepted"),
+        DocumentStatus::Settled => return response::bad_request(&"Debit note settled"),
+        DocumentStatus::Cancelled => return response::bad_request(&"Debit note cancelled"),
+        DocumentStatus::Issued => return response::server_error(&"Illegal status: issued"),
+    }
+
+    let issuer_id = debit_note.issuer_id;
+    let reject_msg = RejectDebitNote::new(debit_note_id.clone(), rejection.clone(), issuer_id);
+    let rejected_id = debit_note_id.clone();
+    let result = match async move {
+        log::trace!("Rejecting DebitNote [{}] in DB", debit_note_id);
+        dao.reject(debit_note_id.clone(), node_id, rejection)
+            .await?;
+        log::trace!("DebitNote rejected successfully for [{}]", debit_note_id);
+
+        log::debug!(
+            "Sending RejectDebitNote [{}] to [{}]",
+            debit_note_id,
+            issuer_id
+        );
+        // Debit note rejections aren't synchronized later, so undelivered
+        // rejection is only logged. Provider will not get the payment anyway.
+        match ya_net::from(node_id)
+            .to(issuer_id)
+            .service(PUBLIC_SERVICE)
+            .call(reject_msg)
+            .await
+        {
+            Ok(response) => {
+                log::debug!("RejectDebitNote delivered");
+                response?;
+            }
+            Err(e) => log::warn!("RejectDebitNote [{}] not delivered: {}", debit_note_id, e),
+        }
+
+        Ok(())
+    }
+    .timeout(Some(timeout))
+    .await
+    {
+        Ok(Ok(_)) => {
+            counter!("payment.debit_notes.requestor.rejected", 1);
+            log::info!("DebitNote [{}] rejected.", rejected_id);
+            response::ok(Null)
+        }
+        Ok(Err(Error::Rpc(RpcMessageError::AcceptReject(AcceptRejectError::BadRequest(e))))) => {
+            response::bad_request(&e)
+        }
+        Ok(Err(e)) => response::server_error(&e),
+        Err(_) => response::timeout(&"Timeout rejecting Debit Note on remote Node."),
+    };
+
+    timing!(
+        "payment.debit_notes.requestor.rejected.time",
+        start,
+        Instant::now()
+    );
+    result
 }
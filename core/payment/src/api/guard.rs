@@ -3,6 +3,11 @@ use std::sync::Arc;
 
 use std::sync::Mutex as StdMutex;
 use tokio::sync::Mutex as TokioMutex;
+use ya_client_model::NodeId;
+
+lazy_static::lazy_static! {
+    static ref SHARED_AGREEMENT_LOCK: Arc<AgreementLock> = AgreementLock::arc();
+}
 
 /// Registry of locks for agreements
 pub(super) struct AgreementLock {
@@ -15,6 +20,11 @@ impl AgreementLock {
         Arc::new(Self::default())
     }
 
+    /// Instance shared by REST API workers and automatic debit note acceptance.
+    pub fn shared() -> Arc<Self> {
+        Arc::clone(&SHARED_AGREEMENT_LOCK)
+    }
+
     /// Take a lock for a given agreement.
     ///
     /// The entry in the internal registry will be automatically cleaned up.
@@ -35,6 +45,15 @@ impl AgreementLock {
     }
 }
 
+impl AgreementLock {
+    /// Take a lock for spending of a given identity.
+    ///
+    /// Serializes checks of spending limits with recording amounts of accepted documents.
+    pub async fn lock_spending(self: &Arc<Self>, owner_id: NodeId) -> AgreementLockGuard {
+        self.lock(format!("spending/{owner_id}")).await
+    }
+}
+
 impl Default for AgreementLock {
     fn default() -> Self {
         AgreementLock {
@@ -45,8 +64,8 @@ impl Default for AgreementLock {
 
 /// Lock guard ensuring unique operation on an agreement.
 ///
-/// For use in REST API only. Motivated by a need to synchronize debit note and
-/// invoice acceptances.
+/// For use in REST API and automatic debit note acceptance. Motivated by a need
+/// to synchronize debit note and invoice acceptances.
 pub(super) struct AgreementLockGuard {
     guard: Option<tokio::sync::OwnedMutexGuard<()>>,
     lock_map: Arc<AgreementLock>,
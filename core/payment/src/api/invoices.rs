@@ -9,6 +9,7 @@ use std::time::Instant;
 // Workspace uses
 use metrics::{counter, timing};
 use ya_client_model::payment::*;
+use ya_client_model::NodeId;
 use ya_core_model::payment::local::{SchedulePayment, BUS_ID as LOCAL_SERVICE};
 use ya_core_model::payment::public::{
     AcceptInvoice, AcceptRejectError, CancelError, CancelInvoice, RejectInvoiceV2, SendError,
@@ -27,6 +28,7 @@ use super::guard::AgreementLock;
 use crate::dao::*;
 use crate::error::{DbError, Error};
 use crate::payment_sync::SYNC_NOTIFS_NOTIFY;
+use crate::policy::{self, PolicyError};
 use crate::utils::provider::get_agreement_id;
 use crate::utils::*;
 
@@ -224,12 +226,18 @@ async fn send_invoice(
         Err(e) => return response::server_error(&e),
     };
 
-    if invoice.status != DocumentStatus::Issued {
-        return response::ok(Null); // Invoice has been already sent
+    // Received invoices can be sent again to remind Requestor about them.
+    // Requestor ignores invoices it already has.
+    if !matches!(
+        invoice.status,
+        DocumentStatus::Issued | DocumentStatus::Received
+    ) {
+        return response::ok(Null); // Invoice has been already accepted or rejected
     }
     let timeout = query.timeout.unwrap_or(params::DEFAULT_ACK_TIMEOUT);
     let agreement_id = invoice.agreement_id.clone();
     let recipient_id = invoice.recipient_id;
+    let first_send = invoice.status == DocumentStatus::Issued;
 
     let result = async move {
         match async move {
@@ -245,7 +253,10 @@ async fn send_invoice(
                 .service(PUBLIC_SERVICE)
                 .call(SendInvoice(invoice))
                 .await??;
-            dao.mark_received(invoice_id, node_id).await?;
+            // Don't overwrite acceptance, which could arrive in meantime.
+            if first_send {
+                dao.mark_received(invoice_id, node_id).await?;
+            }
             Ok(())
         }
         .timeout(Some(timeout))
@@ -380,7 +391,7 @@ async fn accept_invoice(
     };
 
     // Required to serialize complex DB access patterns related to debit note / invoice acceptances.
-    let _agreement_lock = agreement_lock.lock(invoice.agreement_id.clone());
+    let _agreement_lock = agreement_lock.lock(invoice.agreement_id.clone()).await;
 
     if invoice.amount != acceptance.total_amount_accepted {
         return response::bad_request(&"Invalid amount accepted");
@@ -458,6 +469,69 @@ async fn accept_invoice(
     }
 
     let timeout = query.timeout.unwrap_or(params::DEFAULT_ACK_TIMEOUT);
+
+    log::trace!("Checking spending policy for Invoice [{}]", invoice_id);
+    let policy = match db.as_dao::<SpendingPolicyDao>().get(node_id).await {
+        Ok(policy) => policy,
+        Err(e) => return response::server_error(&e),
+    };
+    // Limits are checked and spent amount is recorded with acceptance in DB under the same lock.
+    // It isn't held while notifying the issuer, so it doesn't delay other acceptances.
+    let spending_lock = match policy {
+        Some(_) => Some(agreement_lock.lock_spending(node_id).await),
+        None => None,
+    };
+    let spending = match policy::check_invoice(
+        &db,
+        node_id,
+        policy.as_ref(),
+        &invoice,
+        &amount_to_pay,
+    )
+    .await
+    {
+        Ok(spending) => spending,
+        Err(PolicyError::Violation(violation)) => {
+            counter!("payment.invoices.requestor.policy-violations", 1);
+            drop(spending_lock);
+            let auto_reject = policy.map(|policy| policy.auto_reject).unwrap_or(false);
+            if !auto_reject {
+                log::warn!(
+                    "Invoice [{}] not accepted due to spending policy: {}",
+                    invoice_id,
+                    violation
+                );
+                return response::bad_request(&PolicyError::Violation(violation));
+            }
+
+            log::warn!(
+                "Rejecting Invoice [{}] due to spending policy: {}",
+                invoice_id,
+                violation
+            );
+            let rejection = Rejection {
+                rejection_reason: RejectionReason::IncorrectAmount,
+                total_amount_accepted: Default::default(),
+                message: Some(format!("Spending policy violated: {}", violation)),
+            };
+            return match reject_received_invoice(&db, node_id, invoice, rejection)
+                .timeout(Some(timeout))
+                .await
+            {
+                Ok(Ok(_)) => {
+                    counter!("payment.invoices.requestor.rejected", 1);
+                    response::bad_request(&format!(
+                        "Invoice rejected. {}",
+                        PolicyError::Violation(violation)
+                    ))
+                }
+                Ok(Err(e)) => response::server_error(&e),
+                Err(_) => response::timeout(&"Timeout rejecting Invoice on remote Node."),
+            };
+        }
+        Err(e) => return response::server_error(&e),
+    };
+
     let result = async move {
         let issuer_id = invoice.issuer_id;
         let accept_msg = AcceptInvoice::new(invoice_id.clone(), acceptance, issuer_id);
@@ -471,7 +545,9 @@ async fn accept_invoice(
 
             // Mark the invoice as accepted in DB
             log::trace!("Accepting Invoice [{}] in DB", invoice_id);
-            dao.accept(invoice_id.clone(), node_id).await?;
+            dao.accept(invoice_id.clone(), node_id, Some(spending))
+                .await?;
+            drop(spending_lock);
             log::trace!("Invoice accepted successfully for [{}]", invoice_id);
 
             log::debug!("Sending AcceptInvoice [{}] to [{}]", invoice_id, issuer_id);
@@ -540,7 +616,6 @@ async fn reject_invoice(
     counter!("payment.invoices.requestor.rejected.call", 1);
 
     let dao: InvoiceDao = db.as_dao();
-    let sync_dao: SyncNotifsDao = db.as_dao();
 
     log::trace!("Querying DB for Invoice [{}]", invoice_id);
     let invoice = match dao.get(invoice_id.clone(), node_id).await {
@@ -561,38 +636,9 @@ async fn reject_invoice(
 
     let timeout = query.timeout.unwrap_or(params::DEFAULT_ACK_TIMEOUT);
     let result = async move {
-        let issuer_id = invoice.issuer_id;
-        let reject_msg = RejectInvoiceV2::new(invoice_id.clone(), rejection.clone(), issuer_id);
-        match async move {
-            log::trace!("Rejecting Invoice [{}] in DB", invoice_id);
-            dao.reject(invoice_id.clone(), node_id, rejection).await?;
-            log::trace!("Invoice rejected successfully for [{}]", invoice_id);
-
-            log::debug!(
-                "Sending RejectInvoiceV2 [{}] to [{}]",
-                invoice_id,
-                issuer_id
-            );
-            let send_result = ya_net::from(node_id)
-                .to(issuer_id)
-                .service(PUBLIC_SERVICE)
-                .call(reject_msg)
-                .await;
-
-            if let Ok(response) = send_result {
-                log::debug!("RejectInvoiceV2 delivered");
-                dao.mark_reject_sent(invoice_id.clone(), node_id).await?;
-                response?;
-            } else {
-                log::debug!("RejectInvoiceV2 not delivered");
-                sync_dao.upsert(issuer_id).await?;
-                SYNC_NOTIFS_NOTIFY.notify_one();
-            }
-
-            Ok(())
-        }
-        .timeout(Some(timeout))
-        .await
+        match reject_received_invoice(&db, node_id, invoice, rejection)
+            .timeout(Some(timeout))
+            .await
         {
             Ok(Ok(_)) => {
                 counter!("payment.invoices.requestor.rejected", 1);
@@ -615,3 +661,43 @@ async fn reject_invoice(
     );
     result
 }
+
+async fn reject_received_invoice(
+    db: &DbExecutor,
+    node_id: NodeId,
+    invoice: Invoice,
+    rejection: Rejection,
+) -> Result<(), Error> {
+    let dao: InvoiceDao = db.as_dao();
+    let sync_dao: SyncNotifsDao = db.as_dao();
+    let invoice_id = invoice.invoice_id;
+    let issuer_id = invoice.issuer_id;
+    let reject_msg = RejectInvoiceV2::new(invoice_id.clone(), rejection.clone(), issuer_id);
+
+    log::trace!("Rejecting Invoice [{}] in DB", invoice_id);
+    dao.reject(invoice_id.clone(), node_id, rejection).await?;
+    log::trace!("Invoice rejected successfully for [{}]", invoice_id);
+
+    log::debug!(
+        "Sending RejectInvoiceV2 [{}] to [{}]",
+        invoice_id,
+        issuer_id
+    );
+    let send_result = ya_net::from(node_id)
+        .to(issuer_id)
+        .service(PUBLIC_SERVICE)
+        .call(reject_msg)
+        .await;
+
+    if let Ok(response) = send_result {
+        log::debug!("RejectInvoiceV2 delivered");
+        dao.mark_reject_sent(invoice_id.clone(), node_id).await?;
+        response?;
+    } else {
+        log::debug!("RejectInvoiceV2 not delivered");
+        sync_dao.upsert(issuer_id).await?;
+        SYNC_NOTIFS_NOTIFY.notify_one();
+    }
+
+    Ok(())
+}
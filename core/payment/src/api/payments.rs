@@ -1,26 +1,30 @@
 // External crates
-use actix_web::web::{get, Data, Path, Query};
+use actix_web::web::{get, post, Data, Json, Path, Query};
 use actix_web::{HttpResponse, Scope};
-use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 use ya_service_bus::typed::service;
 
 // Workspace uses
 use ya_client_model::payment::*;
 use ya_core_model::payment::local::{
-    DriverName, NetworkName, PaymentDriverStatus, PaymentDriverStatusError,
-    BUS_ID as PAYMENT_BUS_ID,
+    PaymentDriverStatus, PaymentDriverStatusError, Reconcile, ReconcileOptions, StatementFilter,
+    StatementRole, BUS_ID as PAYMENT_BUS_ID,
 };
 use ya_persistence::executor::DbExecutor;
 use ya_service_api_web::middleware::Identity;
 
 // Local uses
 use crate::dao::*;
+use crate::statement::{self, StatementFormat};
 use crate::utils::*;
 
 pub fn register_endpoints(scope: Scope) -> Scope {
     scope
         .route("/payments", get().to(get_payments))
         .route("/payments/status", get().to(payment_status))
+        .route("/payments/export", get().to(export_payments))
+        .route("/payments/reconcile", post().to(reconcile_payments))
         .route("/payments/{payment_id}", get().to(get_payment))
 }
 
@@ -35,23 +39,19 @@ async fn get_payments(
         .timeout
         .unwrap_or(params::DEFAULT_EVENT_TIMEOUT);
     let after_timestamp = query.event_params.after_timestamp.map(|d| d.naive_utc());
-    let network = match query
-        .network
-        .as_ref()
-        .map(|n| NetworkName::from_str(n.as_str()))
-        .map_or(Ok(None), |v| v.map(Some))
-    {
-        Ok(network) => network,
-        Err(e) => return response::server_error(&e),
+    let network = match &query.network {
+        Some(network) => match validate_network(network).await {
+            Ok(network) => Some(network),
+            Err(e) => return response::bad_request(&e),
+        },
+        None => None,
     };
-    let driver = match query
-        .driver
-        .as_ref()
-        .map(|d| DriverName::from_str(d.as_str()))
-        .map_or(Ok(None), |v| v.map(Some))
-    {
-        Ok(driver) => driver,
-        Err(e) => return response::server_error(&e),
+    let driver = match &query.driver {
+        Some(driver) => match validate_driver(driver).await {
+            Ok(driver) => Some(driver),
+            Err(e) => return response::bad_request(&e),
+        },
+        None => None,
     };
     let max_events = query.event_params.max_events;
     let app_session_id = &query.event_params.app_session_id;
@@ -117,3 +117,50 @@ async fn payment_status(
 
     response::ok(status_props)
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportParams {
+    role: Option<StatementRole>,
+    platform: Option<String>,
+    after_timestamp: Option<DateTime<Utc>>,
+    before_timestamp: Option<DateTime<Utc>>,
+    format: Option<StatementFormat>,
+}
+
+async fn export_payments(
+    db: Data<DbExecutor>,
+    query: Query<ExportParams>,
+    id: Identity,
+) -> HttpResponse {
+    let query = query.into_inner();
+    let filter = StatementFilter {
+        role: query.role,
+        platform: query.platform,
+        after_timestamp: query.after_timestamp,
+        before_timestamp: query.before_timestamp,
+    };
+
+    let entries = match statement::build(&db, id.identity, &filter).await {
+        Ok(entries) => entries,
+        Err(e) => return response::server_error(&e),
+    };
+    match query.format.unwrap_or(StatementFormat::Json) {
+        StatementFormat::Json => response::ok(entries),
+        StatementFormat::Csv => HttpResponse::Ok()
+            .content_type("text/csv")
+            .body(statement::to_csv(&entries)),
+    }
+}
+
+async fn reconcile_payments(body: Json<ReconcileOptions>, id: Identity) -> HttpResponse {
+    let msg = Reconcile {
+        owner_id: id.identity,
+        options: body.into_inner(),
+    };
+    match service(PAYMENT_BUS_ID).call(msg).await {
+        Ok(Ok(report)) => response::ok(report),
+        Ok(Err(e)) => response::server_error(&e),
+        Err(e) => response::server_error(&e),
+    }
+}
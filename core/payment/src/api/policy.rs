@@ -0,0 +1,56 @@
+// External crates
+use actix_web::web::{delete, get, put, Json};
+use actix_web::{HttpResponse, Scope};
+use serde_json::value::Value::Null;
+
+// Workspace uses
+use ya_core_model::payment::local::{
+    ClearSpendingPolicy, GetSpendingPolicy, SetSpendingPolicy, SpendingPolicy,
+    BUS_ID as LOCAL_SERVICE,
+};
+use ya_service_api_web::middleware::Identity;
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+// Local uses
+use crate::utils::*;
+
+pub fn register_endpoints(scope: Scope) -> Scope {
+    scope
+        .route("/spendingPolicy", get().to(get_spending_policy))
+        .route("/spendingPolicy", put().to(set_spending_policy))
+        .route("/spendingPolicy", delete().to(clear_spending_policy))
+}
+
+async fn get_spending_policy(id: Identity) -> HttpResponse {
+    let msg = GetSpendingPolicy {
+        owner_id: id.identity,
+    };
+    match bus::service(LOCAL_SERVICE).send(msg).await {
+        Ok(Ok(status)) => response::ok(status),
+        Ok(Err(e)) => response::server_error(&e),
+        Err(e) => response::server_error(&e),
+    }
+}
+
+async fn set_spending_policy(body: Json<SpendingPolicy>, id: Identity) -> HttpResponse {
+    let msg = SetSpendingPolicy {
+        owner_id: id.identity,
+        policy: body.into_inner(),
+    };
+    match bus::service(LOCAL_SERVICE).send(msg).await {
+        Ok(Ok(())) => response::ok(Null),
+        Ok(Err(e)) => response::bad_request(&e),
+        Err(e) => response::server_error(&e),
+    }
+}
+
+async fn clear_spending_policy(id: Identity) -> HttpResponse {
+    let msg = ClearSpendingPolicy {
+        owner_id: id.identity,
+    };
+    match bus::service(LOCAL_SERVICE).send(msg).await {
+        Ok(Ok(())) => response::ok(Null),
+        Ok(Err(e)) => response::server_error(&e),
+        Err(e) => response::server_error(&e),
+    }
+}
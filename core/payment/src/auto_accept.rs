@@ -0,0 +1,223 @@
+/*
+    Requestor-side automatic debit note acceptance.
+
+    Expected cost is recomputed from usage counters reported in the debit note
+    and Agreement's linear pricing coefficients. Debit notes within tolerance
+    are accepted using the first matching allocation, the rest are rejected.
+    Debit notes, which can't be validated, are left for manual acceptance.
+*/
+// External crates
+use actix_web::HttpResponse;
+use bigdecimal::{BigDecimal, FromPrimitive};
+
+// Workspace uses
+use ya_client_model::market::Agreement;
+use ya_client_model::payment::{Acceptance, DebitNote, Rejection, RejectionReason};
+use ya_client_model::NodeId;
+use ya_persistence::executor::DbExecutor;
+
+// Local uses
+use crate::api::debit_notes::{auto_accept_debit_note, auto_reject_debit_note};
+use crate::dao::{ActivityDao, AllocationDao};
+use crate::error::DbResult;
+use crate::policy::{linear_cost, usage_counters};
+
+#[derive(Clone, Debug)]
+pub enum Validation {
+    Valid,
+    Invalid(Rejection),
+    /// Agreement doesn't use linear pricing model or usage wasn't reported.
+    Unknown,
+}
+
+/// Validates debit note amount against cost computed from Agreement's linear coefficients.
+/// Amount due may exceed the expected cost by `tolerance` (relative).
+pub fn validate(agreement: &Agreement, debit_note: &DebitNote, tolerance: f64) -> Validation {
+    let usage = match usage_counters(&debit_note.usage_counter_vector) {
+        Some(usage) => usage,
+        None => return Validation::Unknown,
+    };
+    let expected = match linear_cost(agreement, &usage) {
+        Some(expected) => expected,
+        None => return Validation::Unknown,
+    };
+
+    let max_amount = &expected * BigDecimal::from_f64(1.0 + tolerance).unwrap_or_default();
+    if debit_note.total_amount_due <= max_amount {
+        return Validation::Valid;
+    }
+
+    Validation::Invalid(Rejection {
+        rejection_reason: RejectionReason::IncorrectAmount,
+        message: Some(format!(
+            "Amount due {} exceeds cost {} computed from usage {:?} and Agreement's \
+            linear coefficients (tolerance: {})",
+            debit_note.total_amount_due, expected, usage, tolerance
+        )),
+        total_amount_accepted: expected,
+    })
+}
+
+/// Finds active allocation matching debit note's platform and payer address,
+/// which covers the amount not yet scheduled for payment.
+async fn find_allocation(
+    db: &DbExecutor,
+    node_id: NodeId,
+    debit_note: &DebitNote,
+) -> DbResult<Option<String>> {
+    let activity = match db
+        .as_dao::<ActivityDao>()
+        .get(debit_note.activity_id.clone(), node_id)
+        .await?
+    {
+        Some(activity) => activity,
+        None => return Ok(None),
+    };
+    let amount_to_pay = &debit_note.total_amount_due - &activity.total_amount_scheduled.0;
+
+    let allocations = db
+        .as_dao::<AllocationDao>()
+        .get_filtered(
+            Some(node_id),
+            None,
+            None,
+            Some(debit_note.payment_platform.clone()),
+            Some(debit_note.payer_addr.clone()),
+        )
+        .await?;
+    Ok(allocations
+        .into_iter()
+        .find(|allocation| allocation.remaining_amount >= amount_to_pay)
+        .map(|allocation| allocation.allocation_id))
+}
+
+/// Validates debit note received by the Requestor and accepts or rejects it.
+pub async fn process_received(
+    db: DbExecutor,
+    agreement: Agreement,
+    debit_note: DebitNote,
+    tolerance: f64,
+) {
+    let debit_note_id = debit_note.debit_note_id.clone();
+    let node_id = debit_note.recipient_id;
+
+    let response: HttpResponse = match validate(&agreement, &debit_note, tolerance) {
+        Validation::Unknown => {
+            log::info!(
+                "DebitNote [{}] can't be validated against Agreement [{}] pricing. \
+                Leaving it for manual acceptance.",
+                debit_note_id,
+                agreement.agreement_id
+            );
+            return;
+        }
+        Validation::Invalid(rejection) => {
+            log::info!(
+                "Rejecting DebitNote [{}]: {}",
+                debit_note_id,
+                rejection.message.as_deref().unwrap_or_default()
+            );
+            auto_reject_debit_note(&db, debit_note_id.clone(), rejection, node_id).await
+        }
+        Validation::Valid => {
+            let allocation_id = match find_allocation(&db, node_id, &debit_note).await {
+                Ok(Some(allocation_id)) => allocation_id,
+                Ok(None) => {
+                    log::info!(
+                        "No allocation covers DebitNote [{}]. Leaving it for manual acceptance.",
+                        debit_note_id
+                    );
+                    return;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Can't find allocation for DebitNote [{}]: {}",
+                        debit_note_id,
+                        e
+                    );
+                    return;
+                }
+            };
+            let acceptance = Acceptance {
+                total_amount_accepted: debit_note.total_amount_due.clone(),
+                allocation_id,
+            };
+            auto_accept_debit_note(&db, debit_note_id.clone(), acceptance, node_id).await
+        }
+    };
+
+    if response.status().is_success() {
+        log::debug!("DebitNote [{}] processed automatically.", debit_note_id);
+    } else {
+        log::warn!(
+            "Automatic processing of DebitNote [{}] failed with status {}.",
+            debit_note_id,
+            response.status()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::mock_agreement;
+    use chrono::Utc;
+    use serde_json::{json, Value};
+    use std::str::FromStr;
+    use ya_client_model::payment::DocumentStatus;
+
+    fn mock_debit_note(amount: &str, usage: Option<Value>) -> DebitNote {
+        DebitNote {
+            debit_note_id: "debit_note_id".to_string(),
+            issuer_id: Default::default(),
+            recipient_id: Default::default(),
+            payee_addr: "0x0".to_string(),
+            payer_addr: "0x0".to_string(),
+            payment_platform: "erc20-holesky-tglm".to_string(),
+            previous_debit_note_id: None,
+            timestamp: Utc::now(),
+            agreement_id: "agreement_id".to_string(),
+            activity_id: "activity_id".to_string(),
+            total_amount_due: BigDecimal::from_str(amount).unwrap(),
+            usage_counter_vector: usage,
+            payment_due_date: None,
+            status: DocumentStatus::Received,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let agreement = mock_agreement(json!({
+            "golem.com.pricing.model.linear.coeffs": [0.5, 0.25, 1.0]
+        }));
+        let usage = Some(json!([2.0, 4.0]));
+
+        assert!(matches!(
+            validate(&agreement, &mock_debit_note("3", usage.clone()), 0.01),
+            Validation::Valid
+        ));
+        assert!(matches!(
+            validate(&agreement, &mock_debit_note("3.02", usage.clone()), 0.01),
+            Validation::Valid
+        ));
+        assert!(matches!(
+            validate(&agreement, &mock_debit_note("3.1", usage.clone()), 0.01),
+            Validation::Invalid(Rejection {
+                rejection_reason: RejectionReason::IncorrectAmount,
+                ..
+            })
+        ));
+        assert!(matches!(
+            validate(&agreement, &mock_debit_note("3", None), 0.01),
+            Validation::Unknown
+        ));
+        assert!(matches!(
+            validate(
+                &mock_agreement(json!({})),
+                &mock_debit_note("3", usage),
+                0.01
+            ),
+            Validation::Unknown
+        ));
+    }
+}
@@ -4,10 +4,13 @@ mod rpc;
 use bigdecimal::BigDecimal;
 use chrono::{DateTime, Utc};
 use serde_json::to_value;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::UNIX_EPOCH;
 use structopt::*;
+use strum::VariantNames;
 use ya_client_model::payment::DriverStatusProperty;
+use ya_client_model::NodeId;
 use ya_core_model::payment::local::NetworkName;
 
 // Workspace uses
@@ -18,6 +21,8 @@ use ya_service_bus::{typed as bus, RpcEndpoint};
 // Local uses
 use crate::accounts::{init_account, Account};
 use crate::cli::rpc::{run_command_rpc, RpcCommandParams};
+use crate::statement::{self, StatementFormat};
+use crate::utils::validate_network;
 use crate::wallet;
 
 /// Payment driver management.
@@ -137,6 +142,59 @@ pub enum PaymentCli {
 
     /// Clear all existing allocations
     ReleaseAllocations,
+
+    /// Manage spending policy applied when accepting invoices and debit notes
+    Policy {
+        address: Option<String>,
+        #[structopt(subcommand)]
+        command: PolicyCommand,
+    },
+
+    /// Export statement of payments and invoices for accounting
+    Export {
+        address: Option<String>,
+        #[structopt(long, possible_values = &["requestor", "provider"])]
+        role: Option<pay::StatementRole>,
+        #[structopt(long, help = "Payment platform, e.g. erc20-polygon-glm")]
+        platform: Option<String>,
+        #[structopt(
+            long,
+            help = "Export entries from the given period of time",
+            conflicts_with = "since"
+        )]
+        last: Option<humantime::Duration>,
+        #[structopt(long, help = "Export entries after the given RFC 3339 timestamp")]
+        since: Option<DateTime<Utc>>,
+        #[structopt(long, help = "Export entries before the given RFC 3339 timestamp")]
+        until: Option<DateTime<Utc>>,
+        #[structopt(
+            long,
+            default_value = "csv",
+            possible_values = StatementFormat::VARIANTS,
+        )]
+        format: StatementFormat,
+        #[structopt(long, short, help = "Write statement to the file instead of stdout")]
+        output: Option<PathBuf>,
+    },
+
+    /// Find stuck or undelivered payments and unsettled invoices
+    Reconcile {
+        address: Option<String>,
+        #[structopt(long, help = "Limit to documents exchanged with the given node")]
+        peer: Option<NodeId>,
+        #[structopt(
+            long,
+            help = "Verify confirmations of payments made since the last reconciliation with drivers"
+        )]
+        verify: bool,
+        #[structopt(long, help = "Verify confirmations of all payments with drivers again")]
+        verify_all: bool,
+        #[structopt(
+            long,
+            help = "Resend sync notifications to peers with undelivered payments"
+        )]
+        resend: bool,
+    },
 }
 
 #[derive(StructOpt, Debug)]
@@ -147,17 +205,61 @@ pub enum InvoiceCommand {
     },
 }
 
+#[derive(StructOpt, Debug)]
+pub enum PolicyCommand {
+    /// Display spending policy and amounts spent recently
+    Show,
+    /// Set spending policy, replacing the previous one
+    Set {
+        #[structopt(long, help = "Maximum amount spent in the last 24 hours")]
+        daily_limit: Option<BigDecimal>,
+        #[structopt(long, help = "Maximum amount spent in the last 30 days")]
+        monthly_limit: Option<BigDecimal>,
+        #[structopt(
+            long,
+            help = "Maximum amount paid to a single Provider in the last 24 hours"
+        )]
+        provider_daily_limit: Option<BigDecimal>,
+        #[structopt(
+            long,
+            help = "Maximum amount paid to a single Provider in the last 30 days"
+        )]
+        provider_monthly_limit: Option<BigDecimal>,
+        #[structopt(
+            long,
+            help = "Maximum ratio of debit note amount to the cost computed from Agreement's linear coefficients"
+        )]
+        max_debit_note_price_ratio: Option<f64>,
+        #[structopt(
+            long,
+            help = "Maximum ratio of invoice amount to the cost computed from Agreement's linear coefficients"
+        )]
+        max_invoice_price_ratio: Option<f64>,
+        #[structopt(
+            long,
+            help = "Reject invoices and debit notes breaking the policy instead of holding them"
+        )]
+        auto_reject: bool,
+    },
+    /// Remove spending policy
+    Clear,
+}
+
 impl PaymentCli {
     pub async fn run_command(self, ctx: &CliCtx) -> anyhow::Result<CommandOutput> {
         match self {
             PaymentCli::Fund { account } => {
                 let address = resolve_address(account.address()).await?;
+                let network = validate_network(&account.network).await?;
 
-                let onboarding_supported = matches!(account.network, NetworkName::Polygon);
-                if !account.network.is_fundable() && !onboarding_supported {
+                // Networks added by driver's chain registry can't be funded automatically
+                let builtin = NetworkName::from_str(&network).ok();
+                let onboarding_supported = matches!(builtin, Some(NetworkName::Polygon));
+                let fundable = builtin.as_ref().map_or(false, NetworkName::is_fundable);
+                if !fundable && !onboarding_supported {
                     log::error!(
                         "Network {} does not support automatic funding. Consider using one of the following: {:?}",
-                        account.network,
+                        network,
                         NetworkName::all_fundable(),
                     );
 
@@ -165,11 +267,11 @@ impl PaymentCli {
                 } else if onboarding_supported {
                     let url = format!(
                         "https://glm.golem.network/#/onboarding/budget?yagnaAddress={}&network={}",
-                        address, account.network
+                        address, network
                     );
                     log::warn!(
                         "Funds for {} can be obtained via the onboarding portal, opening {} with the system browser. If the window doesn't open, you can do it manually.",
-                        account.network,
+                        network,
                         url
                     );
                     if let Err(e) = open::that_detached(&url) {
@@ -182,7 +284,7 @@ impl PaymentCli {
                 init_account(Account {
                     driver: account.driver(),
                     address: address.clone(),
-                    network: Some(account.network()),
+                    network: Some(network.clone()),
                     token: None, // Use default -- we don't yet support other tokens than GLM
                     send: true,
                     receive: false,
@@ -199,7 +301,7 @@ Typically operation should take less than 1 minute.
                 log::warn!("{}", warn_message);
 
                 CommandOutput::object(
-                    wallet::fund(address, account.driver(), Some(account.network()), None).await?,
+                    wallet::fund(address, account.driver(), Some(network), None).await?,
                 )
             }
             PaymentCli::Init {
@@ -210,7 +312,7 @@ Typically operation should take less than 1 minute.
                 let account = Account {
                     driver: account.driver(),
                     address: resolve_address(account.address()).await?,
-                    network: Some(account.network()),
+                    network: Some(validate_network(&account.network).await?),
                     token: None, // Use default -- we don't yet support other tokens than GLM
                     send: sender,
                     receive: receiver,
@@ -225,6 +327,7 @@ Typically operation should take less than 1 minute.
                 precise,
             } => {
                 let address = resolve_address(account.address()).await?;
+                let network = validate_network(&account.network).await?;
                 let timestamp = last
                     .map(|d| Utc::now() - chrono::Duration::seconds(d.as_secs() as i64))
                     .unwrap_or_else(|| DateTime::from(UNIX_EPOCH))
@@ -233,7 +336,7 @@ Typically operation should take less than 1 minute.
                     .call(pay::GetStatus {
                         address: address.clone(),
                         driver: account.driver(),
-                        network: Some(account.network()),
+                        network: Some(network.clone()),
                         token: None,
                         after_timestamp: timestamp,
                     })
@@ -262,7 +365,7 @@ Typically operation should take less than 1 minute.
                 let driver_status_props = bus::service(pay::BUS_ID)
                     .call(pay::PaymentDriverStatus {
                         driver: Some(account.driver()),
-                        network: Some(account.network()),
+                        network: Some(network),
                     })
                     .await??;
 
@@ -407,7 +510,7 @@ Typically operation should take less than 1 minute.
                     BigDecimal::from_str(&amount)?,
                     resolve_address(account.address()).await?,
                     account.driver(),
-                    Some(account.network()),
+                    Some(validate_network(&account.network).await?),
                     None,
                 )
                 .await?,
@@ -427,7 +530,7 @@ Typically operation should take less than 1 minute.
                         to_address,
                         amount,
                         account.driver(),
-                        Some(account.network()),
+                        Some(validate_network(&account.network).await?),
                         None,
                     )
                     .await?,
@@ -469,7 +572,7 @@ Typically operation should take less than 1 minute.
                         to_address,
                         amount,
                         account.driver(),
-                        Some(account.network()),
+                        Some(validate_network(&account.network).await?),
                         None,
                         gas_price,
                         max_gas_price,
@@ -489,7 +592,7 @@ Typically operation should take less than 1 minute.
                     let driver_status_props = bus::service(pay::BUS_ID)
                         .call(pay::PaymentDriverStatus {
                             driver: Some(account.driver()),
-                            network: Some(account.network()),
+                            network: Some(validate_network(&account.network).await?),
                         })
                         .await??;
 
@@ -585,6 +688,179 @@ Typically operation should take less than 1 minute.
                     .await;
                 Ok(CommandOutput::NoOutput)
             }
+            PaymentCli::Policy { address, command } => {
+                let owner_id = resolve_address(address).await?.parse()?;
+                match command {
+                    PolicyCommand::Show => {
+                        let status = bus::service(pay::BUS_ID)
+                            .call(pay::GetSpendingPolicy { owner_id })
+                            .await??;
+                        if ctx.json_output {
+                            return CommandOutput::object(status);
+                        }
+
+                        let policy = status.policy.unwrap_or_default();
+                        let format_limit = |limit: Option<BigDecimal>| {
+                            limit
+                                .map(|l| l.to_string())
+                                .unwrap_or_else(|| "-".to_string())
+                        };
+                        let format_ratio = |ratio: Option<f64>| {
+                            ratio
+                                .map(|r| r.to_string())
+                                .unwrap_or_else(|| "-".to_string())
+                        };
+                        Ok(ResponseTable {
+                            columns: vec!["limit".to_owned(), "value".to_owned()],
+                            values: vec![
+                                serde_json::json! {["daily", format_limit(policy.daily_limit)]},
+                                serde_json::json! {["monthly", format_limit(policy.monthly_limit)]},
+                                serde_json::json! {["provider daily", format_limit(policy.provider_daily_limit)]},
+                                serde_json::json! {["provider monthly", format_limit(policy.provider_monthly_limit)]},
+                                serde_json::json! {["debit note price ratio", format_ratio(policy.max_debit_note_price_ratio)]},
+                                serde_json::json! {["invoice price ratio", format_ratio(policy.max_invoice_price_ratio)]},
+                                serde_json::json! {["auto reject", if policy.auto_reject { "X" } else { "" }]},
+                            ],
+                        }
+                        .with_header(format!(
+                            "\nSpending policy for: {}\nSpent in the last 24 hours: {}, in the last 30 days: {}\n",
+                            owner_id, status.daily_spent, status.monthly_spent
+                        )))
+                    }
+                    PolicyCommand::Set {
+                        daily_limit,
+                        monthly_limit,
+                        provider_daily_limit,
+                        provider_monthly_limit,
+                        max_debit_note_price_ratio,
+                        max_invoice_price_ratio,
+                        auto_reject,
+                    } => {
+                        let policy = pay::SpendingPolicy {
+                            daily_limit,
+                            monthly_limit,
+                            provider_daily_limit,
+                            provider_monthly_limit,
+                            max_debit_note_price_ratio,
+                            max_invoice_price_ratio,
+                            auto_reject,
+                        };
+                        bus::service(pay::BUS_ID)
+                            .call(pay::SetSpendingPolicy { owner_id, policy })
+                            .await??;
+                        Ok(CommandOutput::NoOutput)
+                    }
+                    PolicyCommand::Clear => {
+                        bus::service(pay::BUS_ID)
+                            .call(pay::ClearSpendingPolicy { owner_id })
+                            .await??;
+                        Ok(CommandOutput::NoOutput)
+                    }
+                }
+            }
+            PaymentCli::Export {
+                address,
+                role,
+                platform,
+                last,
+                since,
+                until,
+                format,
+                output,
+            } => {
+                let owner_id = resolve_address(address).await?.parse()?;
+                let after_timestamp = since.or_else(|| {
+                    last.map(|d| Utc::now() - chrono::Duration::seconds(d.as_secs() as i64))
+                });
+                let filter = pay::StatementFilter {
+                    role,
+                    platform,
+                    after_timestamp,
+                    before_timestamp: until,
+                };
+                let entries = bus::service(pay::BUS_ID)
+                    .call(pay::ExportStatement { owner_id, filter })
+                    .await??;
+
+                let csv = matches!(format, StatementFormat::Csv) && !ctx.json_output;
+                match output {
+                    Some(path) => {
+                        let content = match csv {
+                            true => statement::to_csv(&entries),
+                            false => serde_json::to_string_pretty(&entries)?,
+                        };
+                        std::fs::write(&path, content)?;
+                        log::info!("Exported {} entries to {}", entries.len(), path.display());
+                        Ok(CommandOutput::NoOutput)
+                    }
+                    None if csv => CommandOutput::object(statement::to_csv(&entries)),
+                    None => CommandOutput::object(entries),
+                }
+            }
+            PaymentCli::Reconcile {
+                address,
+                peer,
+                verify,
+                verify_all,
+                resend,
+            } => {
+                let owner_id = resolve_address(address).await?.parse()?;
+                let options = pay::ReconcileOptions {
+                    verify,
+                    verify_all,
+                    resend,
+                    peer_id: peer,
+                };
+                let report = bus::service(pay::BUS_ID)
+                    .call(pay::Reconcile { owner_id, options })
+                    .await??;
+                if ctx.json_output {
+                    return CommandOutput::object(report);
+                }
+
+                let mut header = format!("\nDiscrepancies found: {}\n", report.discrepancies.len());
+                if verify || verify_all {
+                    header.push_str(&format!(
+                        "Payments verified by drivers: {}\n",
+                        report.verified_payments
+                    ));
+                    if let Some(since) = report.verified_since {
+                        header.push_str(&format!("Verified payments made after: {}\n", since));
+                    }
+                }
+                if resend {
+                    header.push_str(&format!(
+                        "Sync notifications delivered: {}, failed: {}\n",
+                        report.resent_to.len(),
+                        report.resend_failed.len()
+                    ));
+                }
+                Ok(ResponseTable {
+                    columns: vec![
+                        "kind".to_owned(),
+                        "id".to_owned(),
+                        "peer".to_owned(),
+                        "platform".to_owned(),
+                        "amount".to_owned(),
+                        "details".to_owned(),
+                    ],
+                    values: report
+                        .discrepancies
+                        .into_iter()
+                        .map(|d| {
+                            serde_json::json! {[
+                                d.kind.to_string(),
+                                d.id,
+                                d.peer_id.map(|p| p.to_string()).unwrap_or_default(),
+                                d.platform.unwrap_or_default(),
+                                d.amount.map(|a| a.to_string()).unwrap_or_default(),
+                                d.details,
+                            ]}
+                        })
+                        .collect(),
+                }
+                .with_header(header))
+            }
         }
     }
 }
@@ -1,10 +1,9 @@
 // External crates
-use anyhow::anyhow;
 use chrono::{DateTime, Utc};
 use erc20_payment_lib::rpc_pool::{VerifyEndpointResult, Web3ExternalSources, Web3FullNodeData};
 use serde_json::json;
 use std::str::FromStr;
-use ya_core_model::payment::local::{AccountCli, DriverName, NetworkName};
+use ya_core_model::payment::local::{AccountCli, DriverName};
 
 // Workspace uses
 use ya_core_model::payment::local as pay;
@@ -31,7 +30,7 @@ pub struct RpcCommandParams {
 
 pub fn run_command_rpc_entry(
     driver: &DriverName,
-    network: &NetworkName,
+    network: &str,
     sources: &Option<&Web3ExternalSources>,
     node_infos: &Vec<Web3FullNodeData>,
     ctx: &CliCtx,
@@ -168,18 +167,15 @@ pub async fn run_command_rpc(
         );
     }
 
+    // Networks were validated by the driver, which may report chains from its registry
     let v = result
         .endpoints
         .iter()
         .map(|(network, node_infos)| {
             let sources = result.sources.get(network);
-            let network = NetworkName::from_str(network)
-                .map_err(|_| anyhow!("Invalid network name {network}"))?;
-            Ok(run_command_rpc_entry(
-                &driver, &network, &sources, node_infos, ctx, &params,
-            ))
+            run_command_rpc_entry(&driver, network, &sources, node_infos, ctx, &params)
         })
-        .collect::<anyhow::Result<Vec<CommandOutput>>>()?;
+        .collect::<Vec<CommandOutput>>();
 
     Ok(CommandOutput::MultiTable { tables: v })
 }
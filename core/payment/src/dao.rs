@@ -1,12 +1,15 @@
 mod activity;
 mod agreement;
 mod allocation;
+mod allocation_event;
 mod debit_note;
 mod debit_note_event;
 mod invoice;
 mod invoice_event;
 mod order;
 mod payment;
+mod reconcile_checkpoint;
+mod spending_policy;
 mod sync_notifs;
 
 pub use self::activity::ActivityDao;
@@ -14,10 +17,15 @@ pub use self::agreement::AgreementDao;
 pub use self::allocation::AllocationDao;
 pub use self::allocation::AllocationReleaseStatus;
 pub use self::allocation::AllocationStatus;
+pub use self::allocation_event::AllocationEventDao;
 pub use self::debit_note::DebitNoteDao;
 pub use self::debit_note_event::DebitNoteEventDao;
 pub use self::invoice::InvoiceDao;
 pub use self::invoice_event::InvoiceEventDao;
 pub use self::order::OrderDao;
 pub use self::payment::PaymentDao;
+pub use self::reconcile_checkpoint::ReconcileCheckpointDao;
+pub use self::spending_policy::Spending;
+pub use self::spending_policy::SpendingPolicyDao;
+pub use self::spending_policy::SpendingStatus;
 pub use self::sync_notifs::SyncNotifsDao;
@@ -1,5 +1,7 @@
+use crate::dao::allocation_event;
 use crate::error::{DbError, DbResult};
 use crate::models::allocation::{ReadObj, WriteObj};
+use crate::models::allocation_event::{AllocationReleaseReason, WriteObj as EventWriteObj};
 use crate::schema::pay_allocation::dsl;
 use bigdecimal::BigDecimal;
 use chrono::NaiveDateTime;
@@ -166,10 +168,25 @@ impl<'c> AllocationDao<'c> {
         .await
     }
 
+    /// Not released allocations, which timeout passed before `now`.
+    pub async fn get_timed_out(&self, now: NaiveDateTime) -> DbResult<Vec<Allocation>> {
+        readonly_transaction(self.pool, "allocation_dao_get_timed_out", move |conn| {
+            let allocations: Vec<ReadObj> = dsl::pay_allocation
+                .filter(dsl::released.eq(false))
+                .filter(dsl::timeout.lt(now))
+                .order_by(dsl::timeout.asc())
+                .load(conn)?;
+            Ok(allocations.into_iter().map(Into::into).collect())
+        })
+        .await
+    }
+
+    /// Releases allocation and records `RELEASED` event with the `reason`.
     pub async fn release(
         &self,
         allocation_id: String,
         owner_id: Option<NodeId>,
+        reason: AllocationReleaseReason,
     ) -> DbResult<AllocationReleaseStatus> {
         let id = allocation_id.clone();
         do_with_transaction(self.pool, "allocation_dao_release", move |conn| {
@@ -178,7 +195,7 @@ impl<'c> AllocationDao<'c> {
                 .first(conn)
                 .optional()?;
 
-            match allocation {
+            let allocation = match allocation {
                 Some(allocation) => {
                     if let Some(owner_id) = owner_id {
                         if owner_id != allocation.owner_id {
@@ -189,9 +206,10 @@ impl<'c> AllocationDao<'c> {
                     if allocation.released {
                         return Ok(AllocationReleaseStatus::Gone);
                     }
+                    allocation
                 }
                 None => return Ok(AllocationReleaseStatus::NotFound),
-            }
+            };
 
             let num_released = diesel::update(dsl::pay_allocation)
                 .filter(dsl::released.eq(false))
@@ -200,7 +218,11 @@ impl<'c> AllocationDao<'c> {
                 .execute(conn)?;
 
             match num_released {
-                1 => Ok(AllocationReleaseStatus::Released),
+                1 => {
+                    let event = EventWriteObj::released(id, allocation.owner_id, reason);
+                    allocation_event::create(event, conn)?;
+                    Ok(AllocationReleaseStatus::Released(allocation.into()))
+                }
                 _ => Err(DbError::Query(format!(
                     "Update error occurred when releasing allocation {}",
                     allocation_id
@@ -243,8 +265,9 @@ pub enum AllocationStatus {
     NotFound,
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum AllocationReleaseStatus {
     Gone,
     NotFound,
-    Released,
+    Released(Allocation),
 }
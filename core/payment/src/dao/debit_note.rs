@@ -1,3 +1,4 @@
+use crate::dao::spending_policy::{self, Spending};
 use crate::dao::{activity, debit_note_event};
 use crate::error::{DbError, DbResult};
 use crate::models::debit_note::{ReadObj, WriteObj};
@@ -12,7 +13,9 @@ use diesel::{
 };
 use std::collections::HashMap;
 use std::convert::TryInto;
-use ya_client_model::payment::{DebitNote, DebitNoteEventType, DocumentStatus, NewDebitNote};
+use ya_client_model::payment::{
+    DebitNote, DebitNoteEventType, DocumentStatus, NewDebitNote, Rejection,
+};
 use ya_client_model::NodeId;
 use ya_persistence::executor::{
     do_with_transaction, readonly_transaction, AsDao, ConnType, PoolType,
@@ -199,6 +202,30 @@ impl<'c> DebitNoteDao<'c> {
         .await
     }
 
+    pub async fn get_last_for_activity(
+        &self,
+        activity_id: String,
+        owner_id: NodeId,
+    ) -> DbResult<Option<DebitNote>> {
+        readonly_transaction(
+            self.pool,
+            "debit_note_dao_get_last_for_activity",
+            move |conn| {
+                let debit_note: Option<ReadObj> = query!()
+                    .filter(dsl::activity_id.eq(activity_id))
+                    .filter(dsl::owner_id.eq(owner_id))
+                    .order_by(dsl::timestamp.desc())
+                    .first(conn)
+                    .optional()?;
+                match debit_note {
+                    Some(debit_note) => Ok(Some(debit_note.try_into()?)),
+                    None => Ok(None),
+                }
+            },
+        )
+        .await
+    }
+
     pub async fn list(
         &self,
         role: Option<Role>,
@@ -259,7 +286,13 @@ impl<'c> DebitNoteDao<'c> {
         .await
     }
 
-    pub async fn accept(&self, debit_note_id: String, owner_id: NodeId) -> DbResult<()> {
+    /// Accepts the document. Requestor records amount to pay with `spending`.
+    pub async fn accept(
+        &self,
+        debit_note_id: String,
+        owner_id: NodeId,
+        spending: Option<Spending>,
+    ) -> DbResult<()> {
         do_with_transaction(self.pool, "debit_note_dao_accept", move |conn| {
             let (activity_id, amount, role): (String, BigDecimalField, Role) = dsl::pay_debit_note
                 .find((&debit_note_id, &owner_id))
@@ -294,6 +327,10 @@ impl<'c> DebitNoteDao<'c> {
                 debit_note_event::create(debit_note_id.clone(), owner_id, event, conn)?;
             }
 
+            if let Some(spending) = spending {
+                spending_policy::record_spending(owner_id, spending, conn)?;
+            }
+
             Ok(())
         })
         .await
@@ -385,30 +422,27 @@ impl<'c> DebitNoteDao<'c> {
         .await
     }
 
-    // TODO: Implement reject debit note
-    // pub async fn reject(&self, debit_note_id: String, owner_id: NodeId) -> DbResult<()> {
-    //     do_with_transaction(self.pool, move |conn| {
-    //         let (activity_id, role): (String, Role) = dsl::pay_debit_note
-    //             .find((&debit_note_id, &owner_id))
-    //             .select((dsl::activity_id, dsl::role))
-    //             .first(conn)?;
-    //         update_status(
-    //             &vec![debit_note_id.clone()],
-    //             &owner_id,
-    //             &DocumentStatus::Rejected,
-    //             conn,
-    //         )?;
-    //         if let Role::Provider = role {
-    //             debit_note_event::create::<()>(
-    //                 debit_note_id,
-    //                 owner_id,
-    //                 DebitNoteEventType::DebitNoteRejectedEvent,
-    //                 None,
-    //                 conn,
-    //             )?;
-    //         }
-    //         Ok(())
-    //     })
-    //     .await
-    // }
+    pub async fn reject(
+        &self,
+        debit_note_id: String,
+        owner_id: NodeId,
+        rejection: Rejection,
+    ) -> DbResult<()> {
+        do_with_transaction(self.pool, "debit_note_dao_reject", move |conn| {
+            update_status(
+                &vec![debit_note_id.clone()],
+                &owner_id,
+                &DocumentStatus::Rejected,
+                conn,
+            )?;
+            debit_note_event::create(
+                debit_note_id,
+                owner_id,
+                DebitNoteEventType::DebitNoteRejectedEvent { rejection },
+                conn,
+            )?;
+            Ok(())
+        })
+        .await
+    }
 }
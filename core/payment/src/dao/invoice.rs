@@ -1,3 +1,4 @@
+use crate::dao::spending_policy::{self, Spending};
 use crate::dao::{agreement, invoice_event};
 use crate::error::{DbError, DbResult};
 use crate::models::invoice::{equivalent, InvoiceXActivity, ReadObj, WriteObj};
@@ -302,7 +303,13 @@ impl<'c> InvoiceDao<'c> {
         .await
     }
 
-    pub async fn accept(&self, invoice_id: String, owner_id: NodeId) -> DbResult<()> {
+    /// Accepts the document. Requestor records amount to pay with `spending`.
+    pub async fn accept(
+        &self,
+        invoice_id: String,
+        owner_id: NodeId,
+        spending: Option<Spending>,
+    ) -> DbResult<()> {
         do_with_transaction(self.pool, "invoice_dao_accept", move |conn| {
             let (agreement_id, amount, role): (String, BigDecimalField, Role) = dsl::pay_invoice
                 .find((&invoice_id, &owner_id))
@@ -338,6 +345,10 @@ impl<'c> InvoiceDao<'c> {
                 invoice_event::create(invoice_id.clone(), owner_id, event, conn)?;
             }
 
+            if let Some(spending) = spending {
+                spending_policy::record_spending(owner_id, spending, conn)?;
+            }
+
             Ok(())
         })
         .await
@@ -5,14 +5,47 @@ use crate::schema::pay_debit_note::dsl as debit_note_dsl;
 use crate::schema::pay_invoice::dsl as invoice_dsl;
 use crate::schema::pay_order::dsl;
 use diesel::{
-    self, BoolExpressionMethods, ExpressionMethods, JoinOnDsl, NullableExpressionMethods, QueryDsl,
-    RunQueryDsl,
+    self, BoolExpressionMethods, ExpressionMethods, JoinOnDsl, NullableExpressionMethods,
+    OptionalExtension, QueryDsl, RunQueryDsl,
 };
+use ya_client_model::NodeId;
 use ya_core_model::payment::local::{
     DebitNotePayment, InvoicePayment, PaymentTitle, SchedulePayment,
 };
 use ya_persistence::executor::{do_with_transaction, readonly_transaction, AsDao, PoolType};
 
+macro_rules! query {
+    () => {
+        dsl::pay_order
+            .left_join(
+                invoice_dsl::pay_invoice.on(dsl::invoice_id
+                    .eq(invoice_dsl::id.nullable())
+                    .and(dsl::payer_id.eq(invoice_dsl::owner_id))),
+            )
+            .left_join(
+                debit_note_dsl::pay_debit_note.on(dsl::debit_note_id
+                    .eq(debit_note_dsl::id.nullable())
+                    .and(dsl::payer_id.eq(debit_note_dsl::owner_id))),
+            )
+            .select((
+                dsl::id,
+                dsl::driver,
+                dsl::amount,
+                dsl::payee_id,
+                dsl::payer_id,
+                dsl::payee_addr,
+                dsl::payer_addr,
+                dsl::payment_platform,
+                dsl::invoice_id,
+                dsl::debit_note_id,
+                dsl::allocation_id,
+                dsl::is_paid,
+                invoice_dsl::agreement_id.nullable(),
+                debit_note_dsl::activity_id.nullable(),
+            ))
+    };
+}
+
 pub struct OrderDao<'c> {
     pool: &'c PoolType,
 }
@@ -56,38 +89,43 @@ impl<'c> OrderDao<'c> {
 
     pub async fn get_many(&self, ids: Vec<String>, driver: String) -> DbResult<Vec<ReadObj>> {
         readonly_transaction(self.pool, "order_dao_get_many", move |conn| {
-            let orders = dsl::pay_order
-                .left_join(
-                    invoice_dsl::pay_invoice.on(dsl::invoice_id
-                        .eq(invoice_dsl::id.nullable())
-                        .and(dsl::payer_id.eq(invoice_dsl::owner_id))),
-                )
-                .left_join(
-                    debit_note_dsl::pay_debit_note.on(dsl::debit_note_id
-                        .eq(debit_note_dsl::id.nullable())
-                        .and(dsl::payer_id.eq(debit_note_dsl::owner_id))),
-                )
+            let orders = query!()
                 .filter(dsl::id.eq_any(ids))
                 .filter(dsl::driver.eq(driver))
-                .select((
-                    dsl::id,
-                    dsl::driver,
-                    dsl::amount,
-                    dsl::payee_id,
-                    dsl::payer_id,
-                    dsl::payee_addr,
-                    dsl::payer_addr,
-                    dsl::payment_platform,
-                    dsl::invoice_id,
-                    dsl::debit_note_id,
-                    dsl::allocation_id,
-                    dsl::is_paid,
-                    invoice_dsl::agreement_id.nullable(),
-                    debit_note_dsl::activity_id.nullable(),
-                ))
                 .load(conn)?;
             Ok(orders)
         })
         .await
     }
+
+    /// Lists orders of the payer, which weren't confirmed by drivers yet.
+    pub async fn get_unpaid(&self, payer_id: NodeId) -> DbResult<Vec<ReadObj>> {
+        readonly_transaction(self.pool, "order_dao_get_unpaid", move |conn| {
+            let orders = query!()
+                .filter(dsl::payer_id.eq(payer_id))
+                .filter(dsl::is_paid.eq(false))
+                .load(conn)?;
+            Ok(orders)
+        })
+        .await
+    }
+
+    /// Allocation, which the payer chose when accepting the invoice. There is
+    /// no order for invoices accepted with nothing left to pay.
+    pub async fn get_invoice_allocation(
+        &self,
+        invoice_id: String,
+        payer_id: NodeId,
+    ) -> DbResult<Option<String>> {
+        readonly_transaction(self.pool, "order_dao_get_invoice_allocation", move |conn| {
+            let allocation_id = dsl::pay_order
+                .select(dsl::allocation_id)
+                .filter(dsl::invoice_id.eq(invoice_id))
+                .filter(dsl::payer_id.eq(payer_id))
+                .first(conn)
+                .optional()?;
+            Ok(allocation_id)
+        })
+        .await
+    }
 }
@@ -17,7 +17,6 @@ use diesel::{
 use std::collections::HashMap;
 use ya_client_model::payment::{ActivityPayment, AgreementPayment, Payment};
 use ya_client_model::NodeId;
-use ya_core_model::payment::local::{DriverName, NetworkName};
 use ya_persistence::executor::{
     do_with_transaction, readonly_transaction, AsDao, ConnType, PoolType,
 };
@@ -226,8 +225,8 @@ impl<'c> PaymentDao<'c> {
         after_timestamp: Option<NaiveDateTime>,
         max_events: Option<u32>,
         app_session_id: Option<String>,
-        network: Option<NetworkName>,
-        driver: Option<DriverName>,
+        network: Option<String>,
+        driver: Option<String>,
     ) -> DbResult<Vec<Payment>> {
         readonly_transaction(self.pool, "payment_dao_get_for_node_id", move |conn| {
             let mut query = dsl::pay_payment
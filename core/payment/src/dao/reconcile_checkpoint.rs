@@ -0,0 +1,52 @@
+use crate::error::DbResult;
+use crate::models::reconcile_checkpoint::ReconcileCheckpoint;
+use crate::schema::pay_reconcile_checkpoint::dsl;
+use chrono::NaiveDateTime;
+use diesel::{self, OptionalExtension, QueryDsl, RunQueryDsl};
+use ya_client_model::NodeId;
+use ya_persistence::executor::{do_with_transaction, readonly_transaction, AsDao, PoolType};
+
+pub struct ReconcileCheckpointDao<'c> {
+    pool: &'c PoolType,
+}
+
+impl<'c> AsDao<'c> for ReconcileCheckpointDao<'c> {
+    fn as_dao(pool: &'c PoolType) -> Self {
+        Self { pool }
+    }
+}
+
+impl<'c> ReconcileCheckpointDao<'c> {
+    pub async fn get(&self, owner_id: NodeId) -> DbResult<Option<NaiveDateTime>> {
+        readonly_transaction(self.pool, "reconcile_checkpoint_dao_get", move |conn| {
+            let checkpoint = dsl::pay_reconcile_checkpoint
+                .find(owner_id)
+                .select(dsl::verified_until)
+                .first(conn)
+                .optional()?;
+            Ok(checkpoint)
+        })
+        .await
+    }
+
+    pub async fn set(&self, owner_id: NodeId, verified_until: NaiveDateTime) -> DbResult<()> {
+        do_with_transaction(self.pool, "reconcile_checkpoint_dao_set", move |conn| {
+            diesel::replace_into(dsl::pay_reconcile_checkpoint)
+                .values(ReconcileCheckpoint {
+                    owner_id,
+                    verified_until,
+                })
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn clear(&self, owner_id: NodeId) -> DbResult<()> {
+        do_with_transaction(self.pool, "reconcile_checkpoint_dao_clear", move |conn| {
+            diesel::delete(dsl::pay_reconcile_checkpoint.find(owner_id)).execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+}
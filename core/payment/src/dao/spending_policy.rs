@@ -0,0 +1,169 @@
+use crate::error::DbResult;
+use crate::models::spending_policy::{PolicyObj, WriteSpendingObj};
+use crate::policy::{PolicyViolation, Spent};
+use crate::schema::pay_spending::dsl as spending_dsl;
+use crate::schema::pay_spending_policy::dsl;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::{self, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use ya_client_model::NodeId;
+use ya_core_model::payment::local::SpendingPolicy;
+use ya_persistence::executor::{
+    do_with_transaction, readonly_transaction, AsDao, ConnType, PoolType,
+};
+use ya_persistence::types::BigDecimalField;
+
+pub enum SpendingStatus {
+    Allowed,
+    LimitExceeded(PolicyViolation),
+}
+
+/// Amount to pay for accepted document. Recorded in the same transaction,
+/// which accepts the document.
+#[derive(Clone, Debug)]
+pub struct Spending {
+    pub peer_id: NodeId,
+    pub document_id: String,
+    pub amount: BigDecimal,
+}
+
+/// Replaces amount recorded for the document. Amounts are recorded also without
+/// policy, so limits set later take them into account.
+pub fn record_spending(owner_id: NodeId, spending: Spending, conn: &ConnType) -> DbResult<()> {
+    diesel::delete(
+        spending_dsl::pay_spending
+            .filter(spending_dsl::owner_id.eq(owner_id))
+            .filter(spending_dsl::document_id.eq(&spending.document_id)),
+    )
+    .execute(conn)?;
+    if spending.amount <= BigDecimal::zero() {
+        return Ok(());
+    }
+    diesel::insert_into(spending_dsl::pay_spending)
+        .values(WriteSpendingObj::new(
+            owner_id,
+            spending.document_id,
+            spending.peer_id,
+            spending.amount,
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+fn get_policy(owner_id: NodeId, conn: &ConnType) -> DbResult<Option<SpendingPolicy>> {
+    let policy: Option<PolicyObj> = dsl::pay_spending_policy
+        .find(owner_id)
+        .first(conn)
+        .optional()?;
+    Ok(policy.map(Into::into))
+}
+
+/// Sums amounts spent since given time. Amount recorded for `excluded_document`
+/// is skipped, because it's replaced when accepting the document again.
+fn spent_since(
+    owner_id: NodeId,
+    peer_id: Option<NodeId>,
+    since: NaiveDateTime,
+    excluded_document: Option<&str>,
+    conn: &ConnType,
+) -> DbResult<BigDecimal> {
+    let mut query = spending_dsl::pay_spending
+        .select(spending_dsl::amount)
+        .filter(spending_dsl::owner_id.eq(owner_id))
+        .filter(spending_dsl::timestamp.gt(since))
+        .into_boxed();
+    if let Some(peer_id) = peer_id {
+        query = query.filter(spending_dsl::peer_id.eq(peer_id));
+    }
+    if let Some(document_id) = excluded_document {
+        query = query.filter(spending_dsl::document_id.ne(document_id.to_string()));
+    }
+    let amounts: Vec<BigDecimalField> = query.load(conn)?;
+    Ok(amounts
+        .into_iter()
+        .fold(BigDecimal::zero(), |total, amount| total + amount.0))
+}
+
+fn day_ago() -> NaiveDateTime {
+    (Utc::now() - Duration::days(1)).naive_utc()
+}
+
+fn month_ago() -> NaiveDateTime {
+    (Utc::now() - Duration::days(30)).naive_utc()
+}
+
+pub struct SpendingPolicyDao<'c> {
+    pool: &'c PoolType,
+}
+
+impl<'c> AsDao<'c> for SpendingPolicyDao<'c> {
+    fn as_dao(pool: &'c PoolType) -> Self {
+        Self { pool }
+    }
+}
+
+impl<'c> SpendingPolicyDao<'c> {
+    pub async fn get(&self, owner_id: NodeId) -> DbResult<Option<SpendingPolicy>> {
+        readonly_transaction(self.pool, "spending_policy_dao_get", move |conn| {
+            get_policy(owner_id, conn)
+        })
+        .await
+    }
+
+    pub async fn set(&self, owner_id: NodeId, policy: SpendingPolicy) -> DbResult<()> {
+        let policy = PolicyObj::new(owner_id, policy);
+        do_with_transaction(self.pool, "spending_policy_dao_set", move |conn| {
+            diesel::delete(dsl::pay_spending_policy.find(owner_id)).execute(conn)?;
+            diesel::insert_into(dsl::pay_spending_policy)
+                .values(policy)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn clear(&self, owner_id: NodeId) -> DbResult<()> {
+        do_with_transaction(self.pool, "spending_policy_dao_clear", move |conn| {
+            diesel::delete(dsl::pay_spending_policy.find(owner_id)).execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Amounts spent by the identity in the last day and the last month.
+    pub async fn spent(&self, owner_id: NodeId) -> DbResult<(BigDecimal, BigDecimal)> {
+        readonly_transaction(self.pool, "spending_policy_dao_spent", move |conn| {
+            Ok((
+                spent_since(owner_id, None, day_ago(), None, conn)?,
+                spent_since(owner_id, None, month_ago(), None, conn)?,
+            ))
+        })
+        .await
+    }
+
+    /// Checks spending limits. Amount isn't recorded, so concurrent checks of the same
+    /// identity have to be serialized until the document is accepted.
+    pub async fn check(&self, owner_id: NodeId, spending: Spending) -> DbResult<SpendingStatus> {
+        readonly_transaction(self.pool, "spending_policy_dao_check", move |conn| {
+            if spending.amount <= BigDecimal::zero() {
+                return Ok(SpendingStatus::Allowed);
+            }
+
+            if let Some(policy) = get_policy(owner_id, conn)? {
+                let doc = Some(spending.document_id.as_str());
+                let peer_id = Some(spending.peer_id);
+                let spent = Spent {
+                    daily: spent_since(owner_id, None, day_ago(), doc, conn)?,
+                    monthly: spent_since(owner_id, None, month_ago(), doc, conn)?,
+                    provider_daily: spent_since(owner_id, peer_id, day_ago(), doc, conn)?,
+                    provider_monthly: spent_since(owner_id, peer_id, month_ago(), doc, conn)?,
+                };
+                if let Err(violation) = spent.check(&policy, &spending.amount) {
+                    return Ok(SpendingStatus::LimitExceeded(violation));
+                }
+            }
+            Ok(SpendingStatus::Allowed)
+        })
+        .await
+    }
+}
@@ -14,14 +14,20 @@ extern crate diesel;
 
 pub mod accounts;
 pub mod api;
+pub mod auto_accept;
 mod cli;
 pub mod dao;
 pub mod error;
 pub mod models;
 pub mod payment_sync;
+pub mod policy;
 pub mod processor;
+pub mod reconcile;
 pub mod schema;
 pub mod service;
+pub mod statement;
+#[cfg(test)]
+mod testing;
 pub mod utils;
 mod wallet;
 
@@ -39,6 +45,32 @@ lazy_static::lazy_static! {
                 .and_then(|x| x.parse().ok())
                 .unwrap_or(10),
         );
+    static ref ALLOCATION_RELEASE_INTERVAL: Duration = Duration::from_secs(
+            std::env::var("PAYMENT_ALLOCATION_RELEASE_INTERVAL_SECS")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(5),
+        );
+    static ref DRIVER_PING_INTERVAL: Duration = Duration::from_secs(
+            std::env::var("PAYMENT_DRIVER_PING_INTERVAL_SECS")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(30),
+        );
+    static ref DRIVER_PING_TIMEOUT: Duration = Duration::from_secs(
+            std::env::var("PAYMENT_DRIVER_PING_TIMEOUT_SECS")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(10),
+        );
+    pub(crate) static ref DEBIT_NOTE_AUTO_ACCEPT: bool = std::env::var("PAYMENT_DEBIT_NOTE_AUTO_ACCEPT")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(false);
+    pub(crate) static ref DEBIT_NOTE_TOLERANCE: f64 = std::env::var("PAYMENT_DEBIT_NOTE_TOLERANCE")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(0.01);
 }
 
 pub struct PaymentService;
@@ -53,10 +85,27 @@ impl PaymentService {
         db.apply_migration(migrations::run_with_output)?;
 
         let mut processor = PaymentProcessor::new(db.clone());
-        self::service::bind_service(&db, processor.clone(), BindOptions::default());
+        let shared_processor =
+            self::service::bind_service(&db, processor.clone(), BindOptions::default());
 
         tokio::task::spawn(async move {
-            processor.release_allocations(false).await;
+            let mut interval = tokio::time::interval(*ALLOCATION_RELEASE_INTERVAL);
+            loop {
+                interval.tick().await;
+                processor.release_allocations(false).await;
+            }
+        });
+
+        tokio::task::spawn_local(async move {
+            let mut interval = tokio::time::interval(*DRIVER_PING_INTERVAL);
+            loop {
+                interval.tick().await;
+                crate::processor::unregister_disconnected_drivers(
+                    &shared_processor,
+                    *DRIVER_PING_TIMEOUT,
+                )
+                .await;
+            }
         });
 
         Ok(())
@@ -1,10 +1,13 @@
 pub mod activity;
 pub mod agreement;
 pub mod allocation;
+pub mod allocation_event;
 pub mod debit_note;
 pub mod debit_note_event;
 pub mod invoice;
 pub mod invoice_event;
 pub mod order;
 pub mod payment;
+pub mod reconcile_checkpoint;
+pub mod spending_policy;
 pub mod sync_notifs;
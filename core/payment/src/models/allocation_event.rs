@@ -0,0 +1,94 @@
+use crate::error::{DbError, DbResult};
+use crate::schema::pay_allocation_event;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use strum::{Display, EnumString};
+use ya_client_model::NodeId;
+use ya_persistence::types::{AdaptTimestamp, TimestampAdapter};
+
+pub const RELEASED_EVENT: &str = "RELEASED";
+
+/// Why allocation was released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllocationReleaseReason {
+    /// Released by its owner.
+    Requested,
+    /// Allocation timeout passed.
+    Timeout,
+    /// Deposit for the allocation couldn't be made.
+    DepositFailed,
+    /// All allocations were released with `yagna payment release-allocations`.
+    Forced,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllocationEvent {
+    pub allocation_id: String,
+    pub event_date: DateTime<Utc>,
+    pub event_type: String,
+    pub reason: Option<AllocationReleaseReason>,
+}
+
+#[derive(Debug, Identifiable, Insertable)]
+#[table_name = "pay_allocation_event"]
+#[primary_key(allocation_id, event_type)]
+pub struct WriteObj {
+    pub allocation_id: String,
+    pub owner_id: NodeId,
+    pub event_type: String,
+    pub reason: Option<String>,
+    pub timestamp: TimestampAdapter,
+}
+
+impl WriteObj {
+    pub fn released(
+        allocation_id: String,
+        owner_id: NodeId,
+        reason: AllocationReleaseReason,
+    ) -> Self {
+        Self {
+            allocation_id,
+            owner_id,
+            event_type: RELEASED_EVENT.to_owned(),
+            reason: Some(reason.to_string()),
+            timestamp: Utc::now().adapt(),
+        }
+    }
+}
+
+#[derive(Queryable, Debug, Identifiable)]
+#[table_name = "pay_allocation_event"]
+#[primary_key(allocation_id, event_type)]
+pub struct ReadObj {
+    pub allocation_id: String,
+    pub owner_id: NodeId,
+    pub event_type: String,
+    pub reason: Option<String>,
+    pub timestamp: NaiveDateTime,
+}
+
+impl TryFrom<ReadObj> for AllocationEvent {
+    type Error = DbError;
+
+    fn try_from(event: ReadObj) -> DbResult<Self> {
+        let reason = match &event.reason {
+            Some(reason) => Some(
+                AllocationReleaseReason::from_str(reason)
+                    .map_err(|e| DbError::Integrity(format!("reason = {}: {}", reason, e)))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            allocation_id: event.allocation_id,
+            event_date: Utc.from_utc_datetime(&event.timestamp),
+            event_type: event.event_type,
+            reason,
+        })
+    }
+}
@@ -0,0 +1,12 @@
+use crate::schema::pay_reconcile_checkpoint;
+use chrono::NaiveDateTime;
+use ya_client_model::NodeId;
+
+/// Payments of the owner with timestamp up to `verified_until` were verified with drivers.
+#[derive(Queryable, Debug, Identifiable, Insertable)]
+#[table_name = "pay_reconcile_checkpoint"]
+#[primary_key(owner_id)]
+pub struct ReconcileCheckpoint {
+    pub owner_id: NodeId,
+    pub verified_until: NaiveDateTime,
+}
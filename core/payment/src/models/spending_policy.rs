@@ -0,0 +1,70 @@
+use crate::schema::{pay_spending, pay_spending_policy};
+use bigdecimal::BigDecimal;
+use ya_client_model::NodeId;
+use ya_core_model::payment::local::SpendingPolicy;
+use ya_persistence::types::BigDecimalField;
+
+#[derive(Queryable, Debug, Identifiable, Insertable, AsChangeset)]
+#[table_name = "pay_spending_policy"]
+#[primary_key(owner_id)]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct PolicyObj {
+    pub owner_id: NodeId,
+    pub daily_limit: Option<BigDecimalField>,
+    pub monthly_limit: Option<BigDecimalField>,
+    pub provider_daily_limit: Option<BigDecimalField>,
+    pub provider_monthly_limit: Option<BigDecimalField>,
+    pub max_debit_note_price_ratio: Option<f64>,
+    pub max_invoice_price_ratio: Option<f64>,
+    pub auto_reject: bool,
+}
+
+impl PolicyObj {
+    pub fn new(owner_id: NodeId, policy: SpendingPolicy) -> Self {
+        Self {
+            owner_id,
+            daily_limit: policy.daily_limit.map(Into::into),
+            monthly_limit: policy.monthly_limit.map(Into::into),
+            provider_daily_limit: policy.provider_daily_limit.map(Into::into),
+            provider_monthly_limit: policy.provider_monthly_limit.map(Into::into),
+            max_debit_note_price_ratio: policy.max_debit_note_price_ratio,
+            max_invoice_price_ratio: policy.max_invoice_price_ratio,
+            auto_reject: policy.auto_reject,
+        }
+    }
+}
+
+impl From<PolicyObj> for SpendingPolicy {
+    fn from(obj: PolicyObj) -> Self {
+        Self {
+            daily_limit: obj.daily_limit.map(|v| v.0),
+            monthly_limit: obj.monthly_limit.map(|v| v.0),
+            provider_daily_limit: obj.provider_daily_limit.map(|v| v.0),
+            provider_monthly_limit: obj.provider_monthly_limit.map(|v| v.0),
+            max_debit_note_price_ratio: obj.max_debit_note_price_ratio,
+            max_invoice_price_ratio: obj.max_invoice_price_ratio,
+            auto_reject: obj.auto_reject,
+        }
+    }
+}
+
+/// Amount scheduled for payment when accepting invoice or debit note.
+#[derive(Debug, Insertable)]
+#[table_name = "pay_spending"]
+pub struct WriteSpendingObj {
+    pub owner_id: NodeId,
+    pub document_id: String,
+    pub peer_id: NodeId,
+    pub amount: BigDecimalField,
+}
+
+impl WriteSpendingObj {
+    pub fn new(owner_id: NodeId, document_id: String, peer_id: NodeId, amount: BigDecimal) -> Self {
+        Self {
+            owner_id,
+            document_id,
+            peer_id,
+            amount: amount.into(),
+        }
+    }
+}
@@ -26,7 +26,7 @@ use crate::dao::{DebitNoteDao, InvoiceDao, InvoiceEventDao, PaymentDao, SyncNoti
 
 const SYNC_NOTIF_DELAY_0: Duration = Duration::from_secs(30);
 const SYNC_NOTIF_RATIO: u32 = 6;
-const SYNC_NOTIF_MAX_RETRIES: u32 = 7;
+pub(crate) const SYNC_NOTIF_MAX_RETRIES: u32 = 7;
 
 async fn payment_sync(db: &DbExecutor, peer_id: NodeId) -> anyhow::Result<PaymentSync> {
     let payment_dao: PaymentDao = db.as_dao();
@@ -137,6 +137,29 @@ async fn mark_all_sent(db: &DbExecutor, msg: PaymentSync) -> anyhow::Result<()>
     Ok(())
 }
 
+/// Sends `PaymentSync` with all undelivered payments and acceptances to the peer.
+/// Returns `false` if the peer couldn't be reached.
+pub(crate) async fn send_sync_notif(
+    db: &DbExecutor,
+    from: NodeId,
+    peer: NodeId,
+) -> anyhow::Result<bool> {
+    let msg = payment_sync(db, peer).await?;
+
+    let result = ya_net::from(from)
+        .to(peer)
+        .service(ya_core_model::payment::public::BUS_ID)
+        .call(msg.clone())
+        .await;
+
+    if matches!(&result, Ok(Ok(_))) {
+        mark_all_sent(db, msg).await?;
+        db.as_dao::<SyncNotifsDao>().drop(peer).await?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
 async fn send_sync_notifs(db: &DbExecutor) -> anyhow::Result<Option<Duration>> {
     let dao: SyncNotifsDao = db.as_dao();
 
@@ -174,18 +197,7 @@ async fn send_sync_notifs(db: &DbExecutor) -> anyhow::Result<Option<Duration>> {
         .collect::<Vec<_>>();
 
     for peer in peers_to_notify {
-        let msg = payment_sync(db, peer).await?;
-
-        let result = ya_net::from(default_identity)
-            .to(peer)
-            .service(ya_core_model::payment::public::BUS_ID)
-            .call(msg.clone())
-            .await;
-
-        if matches!(&result, Ok(Ok(_))) {
-            mark_all_sent(db, msg).await?;
-            dao.drop(peer).await?;
-        } else {
+        if !send_sync_notif(db, default_identity, peer).await? {
             dao.increment_retry(peer, cutoff.naive_utc()).await?;
         }
     }
@@ -0,0 +1,357 @@
+/*
+    Requestor-side spending policy.
+
+    Limits amounts scheduled for payment per identity and per Provider,
+    and prices of invoices and debit notes relative to Agreement's linear
+    pricing coefficients.
+*/
+// External crates
+use bigdecimal::{BigDecimal, FromPrimitive};
+use serde_json::Value;
+
+// Workspace uses
+use ya_agreement_utils::agreement::expand;
+use ya_client_model::market::{Agreement, Role};
+use ya_client_model::payment::{DebitNote, Invoice};
+use ya_client_model::NodeId;
+use ya_core_model::payment::local::SpendingPolicy;
+use ya_persistence::executor::DbExecutor;
+
+// Local uses
+use crate::dao::{DebitNoteDao, Spending, SpendingPolicyDao, SpendingStatus};
+use crate::error::Error;
+use crate::utils::get_agreement;
+
+const LINEAR_COEFFS_POINTER: &str = "/golem/com/pricing/model/linear/coeffs";
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum PolicyViolation {
+    #[error("daily limit {limit} exceeded (spent: {spent}, requested: {amount})")]
+    DailyLimit {
+        limit: BigDecimal,
+        spent: BigDecimal,
+        amount: BigDecimal,
+    },
+    #[error("monthly limit {limit} exceeded (spent: {spent}, requested: {amount})")]
+    MonthlyLimit {
+        limit: BigDecimal,
+        spent: BigDecimal,
+        amount: BigDecimal,
+    },
+    #[error("daily Provider limit {limit} exceeded (spent: {spent}, requested: {amount})")]
+    ProviderDailyLimit {
+        limit: BigDecimal,
+        spent: BigDecimal,
+        amount: BigDecimal,
+    },
+    #[error("monthly Provider limit {limit} exceeded (spent: {spent}, requested: {amount})")]
+    ProviderMonthlyLimit {
+        limit: BigDecimal,
+        spent: BigDecimal,
+        amount: BigDecimal,
+    },
+    #[error("amount {amount} exceeds {ratio} times the cost {cost} computed from Agreement's linear coefficients")]
+    Price {
+        amount: BigDecimal,
+        cost: BigDecimal,
+        ratio: f64,
+    },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PolicyError {
+    #[error("Spending policy violated: {0}")]
+    Violation(#[from] PolicyViolation),
+    #[error("Can't check spending policy: {0}")]
+    Internal(#[from] Error),
+}
+
+/// Amounts already spent in periods limited by `SpendingPolicy`.
+#[derive(Clone, Debug, Default)]
+pub struct Spent {
+    pub daily: BigDecimal,
+    pub monthly: BigDecimal,
+    pub provider_daily: BigDecimal,
+    pub provider_monthly: BigDecimal,
+}
+
+impl Spent {
+    pub fn check(
+        &self,
+        policy: &SpendingPolicy,
+        amount: &BigDecimal,
+    ) -> Result<(), PolicyViolation> {
+        let exceeds = |limit: &Option<BigDecimal>, spent: &BigDecimal| match limit {
+            Some(limit) if spent + amount > *limit => Some((limit.clone(), spent.clone())),
+            _ => None,
+        };
+        let amount = amount.clone();
+
+        if let Some((limit, spent)) = exceeds(&policy.daily_limit, &self.daily) {
+            return Err(PolicyViolation::DailyLimit {
+                limit,
+                spent,
+                amount,
+            });
+        }
+        if let Some((limit, spent)) = exceeds(&policy.monthly_limit, &self.monthly) {
+            return Err(PolicyViolation::MonthlyLimit {
+                limit,
+                spent,
+                amount,
+            });
+        }
+        if let Some((limit, spent)) = exceeds(&policy.provider_daily_limit, &self.provider_daily) {
+            return Err(PolicyViolation::ProviderDailyLimit {
+                limit,
+                spent,
+                amount,
+            });
+        }
+        if let Some((limit, spent)) =
+            exceeds(&policy.provider_monthly_limit, &self.provider_monthly)
+        {
+            return Err(PolicyViolation::ProviderMonthlyLimit {
+                limit,
+                spent,
+                amount,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Usage counters reported in debit note.
+pub fn usage_counters(usage_counter_vector: &Option<Value>) -> Option<Vec<f64>> {
+    usage_counter_vector
+        .as_ref()?
+        .as_array()?
+        .iter()
+        .map(Value::as_f64)
+        .collect()
+}
+
+fn linear_coeffs(agreement: &Agreement) -> Option<Vec<f64>> {
+    expand(agreement.offer.properties.clone())
+        .pointer(LINEAR_COEFFS_POINTER)?
+        .as_array()?
+        .iter()
+        .map(Value::as_f64)
+        .collect()
+}
+
+/// Cost of given usage computed from Agreement's linear coefficients.
+/// Last coefficient is a constant term. Returns `None` for Agreements
+/// not using linear pricing model, or if usage doesn't match coefficients.
+pub fn linear_cost(agreement: &Agreement, usage: &[f64]) -> Option<BigDecimal> {
+    let coeffs = linear_coeffs(agreement)?;
+    let (constant, coeffs) = coeffs.split_last()?;
+    if coeffs.len() != usage.len() {
+        return None;
+    }
+
+    let cost = coeffs
+        .iter()
+        .zip(usage.iter())
+        .fold(*constant, |cost, (coeff, value)| cost + coeff * value);
+    BigDecimal::from_f64(cost)
+}
+
+/// Cost of Activity, which didn't report any usage.
+fn linear_cost_constant(agreement: &Agreement) -> Option<BigDecimal> {
+    BigDecimal::from_f64(*linear_coeffs(agreement)?.last()?)
+}
+
+fn check_price(amount: &BigDecimal, cost: BigDecimal, ratio: f64) -> Result<(), PolicyViolation> {
+    let max_amount = &cost * BigDecimal::from_f64(ratio).unwrap_or_default();
+    if *amount > max_amount {
+        return Err(PolicyViolation::Price {
+            amount: amount.clone(),
+            cost,
+            ratio,
+        });
+    }
+    Ok(())
+}
+
+/// Checks debit note against spending policy. Returns amount to pay, which
+/// has to be recorded when accepting the debit note.
+pub async fn check_debit_note(
+    db: &DbExecutor,
+    owner_id: NodeId,
+    policy: Option<&SpendingPolicy>,
+    debit_note: &DebitNote,
+    amount_to_pay: &BigDecimal,
+) -> Result<Spending, PolicyError> {
+    if let Some(policy) = policy {
+        check_debit_note_price(policy, debit_note).await?;
+    }
+    let spending = Spending {
+        peer_id: debit_note.issuer_id,
+        document_id: debit_note.debit_note_id.clone(),
+        amount: amount_to_pay.clone(),
+    };
+    check_limits(db, owner_id, spending).await
+}
+
+/// Checks invoice against spending policy. Returns amount to pay, which
+/// has to be recorded when accepting the invoice.
+pub async fn check_invoice(
+    db: &DbExecutor,
+    owner_id: NodeId,
+    policy: Option<&SpendingPolicy>,
+    invoice: &Invoice,
+    amount_to_pay: &BigDecimal,
+) -> Result<Spending, PolicyError> {
+    if let Some(policy) = policy {
+        check_invoice_price(db, owner_id, policy, invoice).await?;
+    }
+    let spending = Spending {
+        peer_id: invoice.issuer_id,
+        document_id: invoice.invoice_id.clone(),
+        amount: amount_to_pay.clone(),
+    };
+    check_limits(db, owner_id, spending).await
+}
+
+async fn check_limits(
+    db: &DbExecutor,
+    owner_id: NodeId,
+    spending: Spending,
+) -> Result<Spending, PolicyError> {
+    let status = db
+        .as_dao::<SpendingPolicyDao>()
+        .check(owner_id, spending.clone())
+        .await
+        .map_err(Error::from)?;
+    match status {
+        SpendingStatus::Allowed => Ok(spending),
+        SpendingStatus::LimitExceeded(violation) => Err(violation.into()),
+    }
+}
+
+async fn check_debit_note_price(
+    policy: &SpendingPolicy,
+    debit_note: &DebitNote,
+) -> Result<(), PolicyError> {
+    let ratio = match policy.max_debit_note_price_ratio {
+        Some(ratio) => ratio,
+        None => return Ok(()),
+    };
+    let usage = match usage_counters(&debit_note.usage_counter_vector) {
+        Some(usage) => usage,
+        None => return Ok(()),
+    };
+    let agreement = match get_agreement(debit_note.agreement_id.clone(), Role::Requestor).await? {
+        Some(agreement) => agreement,
+        None => return Ok(()),
+    };
+
+    match linear_cost(&agreement, &usage) {
+        Some(cost) => Ok(check_price(&debit_note.total_amount_due, cost, ratio)?),
+        None => {
+            log::debug!(
+                "Agreement [{}] price can't be computed from linear coefficients. \
+                Skipping debit note [{}] price check.",
+                agreement.agreement_id,
+                debit_note.debit_note_id
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Invoice cost is computed from usage reported in the last debit note of each Activity.
+/// Activities without debit notes contribute only the constant term.
+async fn check_invoice_price(
+    db: &DbExecutor,
+    owner_id: NodeId,
+    policy: &SpendingPolicy,
+    invoice: &Invoice,
+) -> Result<(), PolicyError> {
+    let ratio = match policy.max_invoice_price_ratio {
+        Some(ratio) => ratio,
+        None => return Ok(()),
+    };
+    let agreement = match get_agreement(invoice.agreement_id.clone(), Role::Requestor).await? {
+        Some(agreement) => agreement,
+        None => return Ok(()),
+    };
+
+    let dao: DebitNoteDao = db.as_dao();
+    let mut cost = BigDecimal::from(0);
+    for activity_id in &invoice.activity_ids {
+        let last_debit_note = dao
+            .get_last_for_activity(activity_id.clone(), owner_id)
+            .await
+            .map_err(Error::from)?;
+        let usage =
+            last_debit_note.and_then(|debit_note| usage_counters(&debit_note.usage_counter_vector));
+        let activity_cost = match usage {
+            Some(usage) => linear_cost(&agreement, &usage),
+            None => linear_cost_constant(&agreement),
+        };
+        match activity_cost {
+            Some(activity_cost) => cost += activity_cost,
+            None => {
+                log::debug!(
+                    "Agreement [{}] price can't be computed from linear coefficients. \
+                    Skipping invoice [{}] price check.",
+                    agreement.agreement_id,
+                    invoice.invoice_id
+                );
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(check_price(&invoice.amount, cost, ratio)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::mock_agreement;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_linear_cost() {
+        let agreement = mock_agreement(json!({
+            "golem.com.pricing.model.linear.coeffs": [0.5, 0.25, 1.0]
+        }));
+
+        assert_eq!(linear_cost(&agreement, &[2.0, 4.0]), Some(dec("3")));
+        assert_eq!(linear_cost(&agreement, &[2.0]), None);
+        assert_eq!(linear_cost(&mock_agreement(json!({})), &[2.0]), None);
+    }
+
+    #[test]
+    fn test_limits() {
+        let policy = SpendingPolicy {
+            daily_limit: Some(dec("10")),
+            provider_monthly_limit: Some(dec("5")),
+            ..Default::default()
+        };
+        let spent = Spent {
+            daily: dec("4"),
+            monthly: dec("4"),
+            provider_daily: dec("3"),
+            provider_monthly: dec("3"),
+        };
+
+        assert!(spent.check(&policy, &dec("2")).is_ok());
+        assert!(matches!(
+            spent.check(&policy, &dec("3")),
+            Err(PolicyViolation::ProviderMonthlyLimit { .. })
+        ));
+        assert!(matches!(
+            spent.check(&policy, &dec("7")),
+            Err(PolicyViolation::DailyLimit { .. })
+        ));
+    }
+}
@@ -1,24 +1,27 @@
-use crate::api::allocations::{forced_release_allocation, release_allocation_after};
+use crate::api::allocations::forced_release_allocation;
 use crate::dao::{ActivityDao, AgreementDao, AllocationDao, OrderDao, PaymentDao, SyncNotifsDao};
 use crate::error::processor::{
     AccountNotRegistered, GetStatusError, NotifyPaymentError, OrderValidationError,
     SchedulePaymentError, ValidateAllocationError, VerifyPaymentError,
 };
+use crate::models::allocation_event::AllocationReleaseReason;
 use crate::models::order::ReadObj as DbOrder;
 use crate::payment_sync::SYNC_NOTIFS_NOTIFY;
 use actix_web::web::Data;
 use bigdecimal::{BigDecimal, Zero};
+use chrono::Utc;
 use futures::FutureExt;
 use metrics::counter;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::time::Duration;
+use tokio::sync::RwLock;
 use ya_client_model::payment::{
     Account, ActivityPayment, AgreementPayment, DriverDetails, Network, Payment,
 };
 use ya_core_model::driver::{
     self, driver_bus_id, AccountMode, GasDetails, GetRpcEndpointsResult, PaymentConfirmation,
-    PaymentDetails, ShutDown, ValidateAllocation,
+    PaymentDetails, Ping, ShutDown, ValidateAllocation,
 };
 use ya_core_model::payment::local::{
     NotifyPayment, RegisterAccount, RegisterAccountError, RegisterDriver, RegisterDriverError,
@@ -28,8 +31,9 @@ use ya_core_model::payment::public::{SendPayment, BUS_ID};
 use ya_net::RemoteEndpoint;
 use ya_persistence::executor::DbExecutor;
 use ya_persistence::types::Role;
+use ya_service_bus::timeout::IntoTimeoutFuture;
 use ya_service_bus::typed::Endpoint;
-use ya_service_bus::{typed as bus, RpcEndpoint};
+use ya_service_bus::{typed as bus, Error as BusError, RpcEndpoint};
 
 fn driver_endpoint(driver: &str) -> Endpoint {
     bus::service(driver_bus_id(driver))
@@ -336,6 +340,10 @@ impl PaymentProcessor {
         self.registry.get_drivers()
     }
 
+    pub fn driver_names(&self) -> Vec<String> {
+        self.registry.iter_drivers().cloned().collect()
+    }
+
     pub fn get_network(
         &self,
         driver: String,
@@ -661,6 +669,38 @@ impl PaymentProcessor {
         Ok(())
     }
 
+    /// Asks the driver to verify confirmation of already stored payment again
+    /// and compares returned details with the payment. No documents are updated.
+    pub async fn reverify_payment(&self, payment: &Payment) -> Result<(), VerifyPaymentError> {
+        let driver = self.registry.driver(
+            &payment.payment_platform,
+            &payment.payee_addr,
+            AccountMode::empty(),
+        )?;
+        let confirmation = match base64::decode(&payment.details) {
+            Ok(confirmation) => PaymentConfirmation { confirmation },
+            Err(_) => return Err(VerifyPaymentError::ConfirmationEncoding),
+        };
+        let details: PaymentDetails = driver_endpoint(&driver)
+            .send(driver::VerifyPayment::new(
+                confirmation,
+                payment.payment_platform.clone(),
+                payment.clone(),
+            ))
+            .await??;
+
+        if details.amount < payment.amount {
+            return VerifyPaymentError::amount(&details.amount, &payment.amount);
+        }
+        if details.recipient != payment.payee_addr {
+            return VerifyPaymentError::recipient(&payment.payee_addr, &details.recipient);
+        }
+        if details.sender != payment.payer_addr {
+            return VerifyPaymentError::sender(&payment.payer_addr, &details.sender);
+        }
+        Ok(())
+    }
+
     pub async fn get_status(
         &self,
         platform: String,
@@ -740,42 +780,42 @@ impl PaymentProcessor {
         Ok(result)
     }
 
-    /// This function releases allocations.
-    /// When `bool` is `true` all existing allocations are released immediately.
-    /// For `false` each allocation timestamp is respected.
+    /// Releases allocations, which timeout has passed. When `force` is `true`,
+    /// all existing allocations are released immediately.
     pub async fn release_allocations(&mut self, force: bool) {
         let db = Data::new(self.db_executor.clone());
-        let existing_allocations = db
-            .clone()
-            .as_dao::<AllocationDao>()
-            .get_filtered(None, None, None, None, None)
-            .await;
+        let dao = db.as_dao::<AllocationDao>();
+        let existing_allocations = if force {
+            log::info!("Releasing all allocations...");
+            dao.get_filtered(None, None, None, None, None).await
+        } else {
+            log::trace!("Checking for timed out allocations...");
+            dao.get_timed_out(Utc::now().naive_utc()).await
+        };
 
-        log::info!("Checking for allocations to be released...");
+        let reason = if force {
+            AllocationReleaseReason::Forced
+        } else {
+            AllocationReleaseReason::Timeout
+        };
 
         match existing_allocations {
             Ok(allocations) => {
-                if !allocations.is_empty() {
-                    for allocation in allocations {
-                        if force {
-                            forced_release_allocation(db.clone(), allocation.allocation_id, None)
-                                .await
-                        } else {
-                            release_allocation_after(
-                                db.clone(),
-                                allocation.allocation_id,
-                                allocation.timeout,
-                                None,
-                            )
-                            .await
-                        }
+                for allocation in allocations {
+                    if !force {
+                        log::info!(
+                            "Allocation {} timed out at {:?}.",
+                            allocation.allocation_id,
+                            allocation.timeout
+                        );
+                        counter!("payment.allocations.timed_out", 1);
                     }
-                } else {
-                    log::info!("No allocations to be released.")
+                    forced_release_allocation(db.clone(), allocation.allocation_id, None, reason)
+                        .await
                 }
             }
             Err(e) => {
-                log::error!("Allocations release failed. Restart yagna to retry allocations release. Db error occurred: {}.", e);
+                log::error!("Allocations release failed. Db error occurred: {}.", e);
             }
         }
     }
@@ -790,6 +830,37 @@ impl PaymentProcessor {
     }
 }
 
+/// Pings registered drivers and unregisters the ones, which are no longer bound to the service bus.
+/// External drivers run as separate processes and disappear from the bus when the process exits.
+/// They are registered again, when they restart and send `RegisterDriver`.
+pub async fn unregister_disconnected_drivers(
+    processor: &RwLock<PaymentProcessor>,
+    timeout: Duration,
+) {
+    let drivers = processor.read().await.driver_names();
+    for driver in drivers {
+        match driver_endpoint(&driver)
+            .call(Ping {})
+            .timeout(Some(timeout.as_secs_f64()))
+            .await
+        {
+            Ok(Ok(Ok(()))) => log::trace!("Driver '{}' is alive.", driver),
+            Ok(Ok(Err(e))) => log::warn!("Driver '{}' failed to answer ping: {}", driver, e),
+            Ok(Err(e @ (BusError::NoEndpoint(_) | BusError::Closed(_)))) => {
+                log::warn!("Driver '{}' disconnected: {}. Unregistering.", driver, e);
+                counter!("payment.drivers.disconnected", 1);
+                processor
+                    .write()
+                    .await
+                    .unregister_driver(UnregisterDriver(driver))
+                    .await;
+            }
+            Ok(Err(e)) => log::warn!("Can't ping driver '{}': {}", driver, e),
+            Err(_) => log::warn!("Driver '{}' didn't answer ping in {:?}.", driver, timeout),
+        }
+    }
+}
+
 fn shut_down_driver(
     driver: &str,
     timeout: Duration,
@@ -805,3 +876,90 @@ fn shut_down_driver(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dao::{AllocationEventDao, AllocationStatus};
+    use chrono::DateTime;
+    use ya_client_model::payment::NewAllocation;
+    use ya_client_model::NodeId;
+
+    fn new_allocation(timeout: Option<DateTime<Utc>>) -> NewAllocation {
+        NewAllocation {
+            address: None,
+            payment_platform: None,
+            total_amount: BigDecimal::from(10),
+            timeout,
+            make_deposit: false,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_release_timed_out_allocations() {
+        let db = DbExecutor::in_memory("processor-release-allocations").unwrap();
+        db.apply_migration(crate::migrations::run_with_output)
+            .unwrap();
+        let owner = NodeId::from(&[1; 20][..]);
+        let dao = db.as_dao::<AllocationDao>();
+
+        let mut allocation_ids = Vec::new();
+        for timeout in [
+            Some(Utc::now() - chrono::Duration::seconds(1)),
+            Some(Utc::now() + chrono::Duration::hours(1)),
+            None,
+        ] {
+            let allocation_id = dao
+                .create(
+                    new_allocation(timeout),
+                    owner,
+                    "dummy-glm".to_string(),
+                    "0x01".to_string(),
+                )
+                .await
+                .unwrap();
+            allocation_ids.push(allocation_id);
+        }
+
+        let mut processor = PaymentProcessor::new(db.clone());
+        processor.release_allocations(false).await;
+        // Released allocation isn't released again
+        processor.release_allocations(false).await;
+
+        let active = dao.get_for_owner(owner, None, None).await.unwrap();
+        assert_eq!(active.len(), 2);
+        assert!(active
+            .iter()
+            .all(|allocation| allocation.allocation_id != allocation_ids[0]));
+        assert!(matches!(
+            dao.get(allocation_ids[0].clone(), owner).await.unwrap(),
+            AllocationStatus::NotFound
+        ));
+
+        let events = db
+            .as_dao::<AllocationEventDao>()
+            .get_for_node_id(owner, None, None)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].allocation_id, allocation_ids[0]);
+        assert_eq!(events[0].event_type, "RELEASED");
+        assert_eq!(events[0].reason, Some(AllocationReleaseReason::Timeout));
+
+        processor.release_allocations(true).await;
+        assert!(dao
+            .get_for_owner(owner, None, None)
+            .await
+            .unwrap()
+            .is_empty());
+        let events = db
+            .as_dao::<AllocationEventDao>()
+            .get_for_node_id(owner, Some(events[0].event_date.naive_utc()), None)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .all(|event| event.reason == Some(AllocationReleaseReason::Forced)));
+    }
+}
@@ -0,0 +1,490 @@
+/*
+    Reconciliation of local payment state with drivers and peers.
+
+    Payment sync notifications are given up after `SYNC_NOTIF_MAX_RETRIES`,
+    so a Provider being offline for long may never learn it was paid.
+    Reconciliation lists such discrepancies and lets the operator resend
+    sync notifications or verify stored payments with drivers again.
+
+    Verification is resumed from a checkpoint stored per identity, so only
+    payments made since the last reconciliation are sent to drivers.
+*/
+// External crates
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use std::collections::HashSet;
+use tokio::sync::RwLock;
+
+// Workspace uses
+use ya_client_model::payment::DocumentStatus;
+use ya_client_model::NodeId;
+use ya_core_model::payment::local::{
+    Discrepancy, DiscrepancyKind, ReconcileOptions, ReconcileReport,
+};
+use ya_persistence::executor::DbExecutor;
+
+// Local uses
+use crate::dao::{
+    AgreementDao, InvoiceDao, OrderDao, PaymentDao, ReconcileCheckpointDao, SyncNotifsDao,
+};
+use crate::payment_sync::{send_sync_notif, SYNC_NOTIFS_NOTIFY, SYNC_NOTIF_MAX_RETRIES};
+use crate::processor::PaymentProcessor;
+
+fn is_selected(options: &ReconcileOptions, peer_id: &NodeId) -> bool {
+    options.peer_id.map(|p| p == *peer_id).unwrap_or(true)
+}
+
+async fn find_discrepancies(
+    db: &DbExecutor,
+    owner_id: NodeId,
+    options: &ReconcileOptions,
+) -> anyhow::Result<Vec<Discrepancy>> {
+    let mut discrepancies = Vec::new();
+
+    for order in db.as_dao::<OrderDao>().get_unpaid(owner_id).await? {
+        if !is_selected(options, &order.payee_id) {
+            continue;
+        }
+        let title = match (&order.invoice_id, &order.debit_note_id) {
+            (Some(invoice_id), _) => format!("Invoice [{}]", invoice_id),
+            (_, Some(debit_note_id)) => format!("DebitNote [{}]", debit_note_id),
+            _ => "unknown document".to_string(),
+        };
+        discrepancies.push(Discrepancy {
+            kind: DiscrepancyKind::UnconfirmedOrder,
+            id: order.id,
+            peer_id: Some(order.payee_id),
+            platform: Some(order.payment_platform),
+            amount: Some(order.amount.0),
+            details: format!(
+                "Payment for {} was not confirmed by {} driver",
+                title, order.driver
+            ),
+        });
+    }
+
+    for payment in db
+        .as_dao::<PaymentDao>()
+        .list_unsent(options.peer_id)
+        .await?
+    {
+        if payment.payer_id != owner_id {
+            continue;
+        }
+        discrepancies.push(Discrepancy {
+            kind: DiscrepancyKind::UnsentPayment,
+            id: payment.payment_id,
+            peer_id: Some(payment.payee_id),
+            platform: Some(payment.payment_platform),
+            amount: Some(payment.amount),
+            details: "Payment was not delivered to the Provider".to_string(),
+        });
+    }
+
+    for notif in db.as_dao::<SyncNotifsDao>().list().await? {
+        if notif.retries <= SYNC_NOTIF_MAX_RETRIES as i32 || !is_selected(options, &notif.id) {
+            continue;
+        }
+        discrepancies.push(Discrepancy {
+            kind: DiscrepancyKind::AbandonedSync,
+            id: notif.id.to_string(),
+            peer_id: Some(notif.id),
+            platform: None,
+            amount: None,
+            details: format!(
+                "Sync notifications abandoned after {} retries, last attempt at {}",
+                notif.retries,
+                notif.last_ping.and_utc()
+            ),
+        });
+    }
+
+    let agreement_dao: AgreementDao = db.as_dao();
+    for invoice in db
+        .as_dao::<InvoiceDao>()
+        .get_for_node_id(owner_id, None, None)
+        .await?
+    {
+        let peer_id = match invoice.issuer_id == owner_id {
+            true => invoice.recipient_id,
+            false => invoice.issuer_id,
+        };
+        if matches!(
+            invoice.status,
+            DocumentStatus::Settled | DocumentStatus::Cancelled
+        ) || !is_selected(options, &peer_id)
+        {
+            continue;
+        }
+        let agreement = match agreement_dao
+            .get(invoice.agreement_id.clone(), owner_id)
+            .await?
+        {
+            Some(agreement) => agreement,
+            None => continue,
+        };
+        if agreement.total_amount_paid.0 < invoice.amount {
+            continue;
+        }
+        discrepancies.push(Discrepancy {
+            kind: DiscrepancyKind::UnsettledInvoice,
+            id: invoice.invoice_id,
+            peer_id: Some(peer_id),
+            platform: Some(invoice.payment_platform),
+            amount: Some(invoice.amount),
+            details: format!(
+                "Agreement [{}] is paid ({}), but invoice status is {}",
+                invoice.agreement_id, agreement.total_amount_paid.0, invoice.status
+            ),
+        });
+    }
+
+    Ok(discrepancies)
+}
+
+/// New checkpoint given verification results ordered by payment timestamp.
+/// Checkpoint stops before the first payment, which wasn't verified,
+/// so it is verified again by the next reconciliation.
+fn next_checkpoint(results: &[(NaiveDateTime, bool)]) -> Option<NaiveDateTime> {
+    let failed_at = results
+        .iter()
+        .find(|(_, verified)| !verified)
+        .map(|(timestamp, _)| *timestamp);
+    results
+        .iter()
+        .filter(|(timestamp, verified)| *verified && failed_at.map_or(true, |f| *timestamp < f))
+        .map(|(timestamp, _)| *timestamp)
+        .max()
+}
+
+/// Verifies payments of the identity made since the last checkpoint with drivers,
+/// or all of them with `verify_all`. Returns number of verified payments,
+/// discrepancies found and the checkpoint verification started from.
+async fn verify_payments(
+    db: &DbExecutor,
+    processor: &RwLock<PaymentProcessor>,
+    owner_id: NodeId,
+    options: &ReconcileOptions,
+) -> anyhow::Result<(u32, Vec<Discrepancy>, Option<NaiveDateTime>)> {
+    let checkpoint_dao: ReconcileCheckpointDao = db.as_dao();
+    let since = match options.verify_all {
+        true => None,
+        false => checkpoint_dao.get(owner_id).await?,
+    };
+    let payments = db
+        .as_dao::<PaymentDao>()
+        .get_for_node_id(owner_id, since, None, None, None, None)
+        .await?;
+
+    let mut verified = 0;
+    let mut discrepancies = Vec::new();
+    let mut results = Vec::new();
+    for payment in payments {
+        let peer_id = match payment.payer_id == owner_id {
+            true => payment.payee_id,
+            false => payment.payer_id,
+        };
+        if !is_selected(options, &peer_id) {
+            continue;
+        }
+        let timestamp = payment.timestamp.naive_utc();
+        match processor.read().await.reverify_payment(&payment).await {
+            Ok(()) => {
+                verified += 1;
+                results.push((timestamp, true));
+            }
+            Err(e) => {
+                results.push((timestamp, false));
+                discrepancies.push(Discrepancy {
+                    kind: DiscrepancyKind::UnverifiedPayment,
+                    id: payment.payment_id,
+                    peer_id: Some(peer_id),
+                    platform: Some(payment.payment_platform),
+                    amount: Some(payment.amount),
+                    details: e.to_string(),
+                })
+            }
+        }
+    }
+
+    // Payments of other peers were skipped, so checkpoint is moved by full runs only.
+    if options.peer_id.is_none() {
+        match next_checkpoint(&results).or(since) {
+            Some(checkpoint) => checkpoint_dao.set(owner_id, checkpoint).await?,
+            None => checkpoint_dao.clear(owner_id).await?,
+        }
+    }
+    Ok((verified, discrepancies, since))
+}
+
+/// Sends sync notifications to peers with undelivered payments. Peers, which can't be
+/// reached, are scheduled for regular sync notification retries again.
+async fn resend(
+    db: &DbExecutor,
+    owner_id: NodeId,
+    discrepancies: &[Discrepancy],
+    report: &mut ReconcileReport,
+) -> anyhow::Result<()> {
+    let peers: HashSet<NodeId> = discrepancies
+        .iter()
+        .filter(|d| {
+            matches!(
+                d.kind,
+                DiscrepancyKind::UnsentPayment | DiscrepancyKind::AbandonedSync
+            )
+        })
+        .filter_map(|d| d.peer_id)
+        .collect();
+
+    for peer_id in peers {
+        match send_sync_notif(db, owner_id, peer_id).await {
+            Ok(true) => {
+                log::info!("Payment sync notification delivered to [{}]", peer_id);
+                report.resent_to.push(peer_id);
+                continue;
+            }
+            Ok(false) => log::info!("Peer [{}] can't be reached", peer_id),
+            Err(e) => log::warn!("Failed to resend payment sync to [{}]: {}", peer_id, e),
+        }
+        db.as_dao::<SyncNotifsDao>().upsert(peer_id).await?;
+        report.resend_failed.push(peer_id);
+    }
+    if !report.resend_failed.is_empty() {
+        SYNC_NOTIFS_NOTIFY.notify_one();
+    }
+    Ok(())
+}
+
+/// Compares local orders, payments and invoices with drivers' view and peers' sync state.
+/// Discrepancies are reported as found before resending sync notifications.
+pub async fn reconcile(
+    db: &DbExecutor,
+    processor: &RwLock<PaymentProcessor>,
+    owner_id: NodeId,
+    options: ReconcileOptions,
+) -> anyhow::Result<ReconcileReport> {
+    let mut report = ReconcileReport {
+        discrepancies: find_discrepancies(db, owner_id, &options).await?,
+        ..Default::default()
+    };
+
+    if options.verify || options.verify_all {
+        let (verified, discrepancies, since) =
+            verify_payments(db, processor, owner_id, &options).await?;
+        report.verified_payments = verified;
+        report.verified_since = since.map(|since| Utc.from_utc_datetime(&since));
+        report.discrepancies.extend(discrepancies);
+    }
+
+    if options.resend {
+        let discrepancies = report.discrepancies.clone();
+        resend(db, owner_id, &discrepancies, &mut report).await?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use chrono::Duration;
+
+    const OWNER: [u8; 20] = [1; 20];
+    const PEER: [u8; 20] = [2; 20];
+    const ABANDONED_PEER: [u8; 20] = [3; 20];
+
+    fn setup(name: &str) -> (DbExecutor, RwLock<PaymentProcessor>) {
+        let db = DbExecutor::in_memory(name).unwrap();
+        db.apply_migration(crate::migrations::run_with_output)
+            .unwrap();
+        let processor = RwLock::new(PaymentProcessor::new(db.clone()));
+        (db, processor)
+    }
+
+    /// Payment sent to the peer. No driver is registered in tests, so it can't be verified.
+    async fn create_payment(db: &DbExecutor) -> String {
+        let payment_id = db
+            .as_dao::<PaymentDao>()
+            .create_new(
+                NodeId::from(&OWNER[..]),
+                NodeId::from(&PEER[..]),
+                "0x01".to_string(),
+                "0x02".to_string(),
+                "dummy-glm".to_string(),
+                BigDecimal::from(1),
+                vec![],
+                vec![],
+                vec![],
+            )
+            .await
+            .unwrap();
+        // Payments of subsequent calls get distinct timestamps.
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        payment_id
+    }
+
+    async fn abandon_sync(db: &DbExecutor, peer_id: NodeId) {
+        let dao: SyncNotifsDao = db.as_dao();
+        dao.upsert(peer_id).await.unwrap();
+        for _ in 0..=SYNC_NOTIF_MAX_RETRIES {
+            dao.increment_retry(peer_id, Utc::now().naive_utc())
+                .await
+                .unwrap();
+        }
+    }
+
+    fn ids(report: &ReconcileReport, kind: DiscrepancyKind) -> Vec<String> {
+        report
+            .discrepancies
+            .iter()
+            .filter(|d| d.kind == kind)
+            .map(|d| d.id.clone())
+            .collect()
+    }
+
+    fn verify(verify_all: bool) -> ReconcileOptions {
+        ReconcileOptions {
+            verify: true,
+            verify_all,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_next_checkpoint() {
+        let t0 = Utc::now().naive_utc();
+        let t = |secs| t0 + Duration::seconds(secs);
+
+        assert_eq!(next_checkpoint(&[]), None);
+        assert_eq!(next_checkpoint(&[(t(0), true), (t(1), true)]), Some(t(1)));
+        assert_eq!(
+            next_checkpoint(&[(t(0), true), (t(1), false), (t(2), true)]),
+            Some(t(0))
+        );
+        // Verified payment with the same timestamp as failed one doesn't move the checkpoint.
+        assert_eq!(
+            next_checkpoint(&[(t(0), true), (t(1), true), (t(1), false)]),
+            Some(t(0))
+        );
+        assert_eq!(next_checkpoint(&[(t(0), false), (t(1), true)]), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_detect_discrepancies() {
+        let (db, processor) = setup("reconcile-detect");
+        let owner_id = NodeId::from(&OWNER[..]);
+        let payment_id = create_payment(&db).await;
+        abandon_sync(&db, NodeId::from(&ABANDONED_PEER[..])).await;
+
+        let report = reconcile(&db, &processor, owner_id, verify(false))
+            .await
+            .unwrap();
+        assert_eq!(
+            ids(&report, DiscrepancyKind::UnsentPayment),
+            vec![payment_id.clone()]
+        );
+        assert_eq!(
+            ids(&report, DiscrepancyKind::AbandonedSync),
+            vec![NodeId::from(&ABANDONED_PEER[..]).to_string()]
+        );
+        assert_eq!(
+            ids(&report, DiscrepancyKind::UnverifiedPayment),
+            vec![payment_id]
+        );
+        assert_eq!(report.verified_payments, 0);
+
+        // Discrepancies are limited to the selected peer.
+        let options = ReconcileOptions {
+            peer_id: Some(NodeId::from(&ABANDONED_PEER[..])),
+            ..verify(false)
+        };
+        let report = reconcile(&db, &processor, owner_id, options).await.unwrap();
+        assert_eq!(report.discrepancies.len(), 1);
+        assert_eq!(report.discrepancies[0].kind, DiscrepancyKind::AbandonedSync);
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_since_checkpoint() {
+        let (db, processor) = setup("reconcile-checkpoint");
+        let owner_id = NodeId::from(&OWNER[..]);
+        let checkpoint_dao: ReconcileCheckpointDao = db.as_dao();
+        let old_payment = create_payment(&db).await;
+
+        // Unverified payment doesn't move the checkpoint.
+        reconcile(&db, &processor, owner_id, verify(false))
+            .await
+            .unwrap();
+        assert_eq!(checkpoint_dao.get(owner_id).await.unwrap(), None);
+
+        let old_timestamp = db
+            .as_dao::<PaymentDao>()
+            .get(old_payment.clone(), owner_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .timestamp
+            .naive_utc();
+        checkpoint_dao.set(owner_id, old_timestamp).await.unwrap();
+        let new_payment = create_payment(&db).await;
+
+        let report = reconcile(&db, &processor, owner_id, verify(false))
+            .await
+            .unwrap();
+        assert_eq!(
+            ids(&report, DiscrepancyKind::UnverifiedPayment),
+            vec![new_payment.clone()]
+        );
+        assert_eq!(
+            report.verified_since,
+            Some(Utc.from_utc_datetime(&old_timestamp))
+        );
+        assert_eq!(
+            checkpoint_dao.get(owner_id).await.unwrap(),
+            Some(old_timestamp)
+        );
+
+        let report = reconcile(&db, &processor, owner_id, verify(true))
+            .await
+            .unwrap();
+        assert_eq!(
+            ids(&report, DiscrepancyKind::UnverifiedPayment),
+            vec![old_payment, new_payment]
+        );
+        assert_eq!(report.verified_since, None);
+        // Full verification failed on the first payment, so it starts from scratch next time.
+        assert_eq!(checkpoint_dao.get(owner_id).await.unwrap(), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_resend_reschedules_abandoned_sync() {
+        let (db, processor) = setup("reconcile-resend");
+        let owner_id = NodeId::from(&OWNER[..]);
+        let peer_id = NodeId::from(&PEER[..]);
+        let payment_id = create_payment(&db).await;
+        abandon_sync(&db, peer_id).await;
+
+        let options = ReconcileOptions {
+            resend: true,
+            ..Default::default()
+        };
+        let report = reconcile(&db, &processor, owner_id, options).await.unwrap();
+        // Peer can't be reached in tests.
+        assert!(report.resent_to.is_empty());
+        assert_eq!(report.resend_failed, vec![peer_id]);
+
+        // Sync notifications are retried by the regular job again.
+        let notifs = db.as_dao::<SyncNotifsDao>().list().await.unwrap();
+        assert_eq!(notifs.len(), 1);
+        assert_eq!(notifs[0].id, peer_id);
+        assert_eq!(notifs[0].retries, 0);
+
+        let report = reconcile(&db, &processor, owner_id, Default::default())
+            .await
+            .unwrap();
+        assert!(ids(&report, DiscrepancyKind::AbandonedSync).is_empty());
+        assert_eq!(
+            ids(&report, DiscrepancyKind::UnsentPayment),
+            vec![payment_id]
+        );
+    }
+}
@@ -64,6 +64,16 @@ table! {
     }
 }
 
+table! {
+    pay_allocation_event (allocation_id, event_type) {
+        allocation_id -> Text,
+        owner_id -> Text,
+        event_type -> Text,
+        reason -> Nullable<Text>,
+        timestamp -> Timestamp,
+    }
+}
+
 table! {
     pay_debit_note (id, owner_id) {
         id -> Text,
@@ -193,6 +203,36 @@ table! {
     }
 }
 
+table! {
+    pay_reconcile_checkpoint (owner_id) {
+        owner_id -> Text,
+        verified_until -> Timestamp,
+    }
+}
+
+table! {
+    pay_spending (owner_id, document_id) {
+        owner_id -> Text,
+        document_id -> Text,
+        peer_id -> Text,
+        amount -> Text,
+        timestamp -> Timestamp,
+    }
+}
+
+table! {
+    pay_spending_policy (owner_id) {
+        owner_id -> Text,
+        daily_limit -> Nullable<Text>,
+        monthly_limit -> Nullable<Text>,
+        provider_daily_limit -> Nullable<Text>,
+        provider_monthly_limit -> Nullable<Text>,
+        max_debit_note_price_ratio -> Nullable<Double>,
+        max_invoice_price_ratio -> Nullable<Double>,
+        auto_reject -> Bool,
+    }
+}
+
 table! {
     pay_sync_needed_notifs (id) {
         id -> Text,
@@ -203,6 +243,7 @@ table! {
 
 joinable!(pay_activity_payment -> pay_allocation (allocation_id));
 joinable!(pay_agreement_payment -> pay_allocation (allocation_id));
+joinable!(pay_allocation_event -> pay_allocation (allocation_id));
 joinable!(pay_debit_note -> pay_document_status (status));
 joinable!(pay_debit_note_event -> pay_event_type (event_type));
 joinable!(pay_invoice -> pay_document_status (status));
@@ -215,6 +256,7 @@ allow_tables_to_appear_in_same_query!(
     pay_agreement,
     pay_agreement_payment,
     pay_allocation,
+    pay_allocation_event,
     pay_debit_note,
     pay_debit_note_event,
     pay_debit_note_event_read,
@@ -226,4 +268,7 @@ allow_tables_to_appear_in_same_query!(
     pay_invoice_x_activity,
     pay_order,
     pay_payment,
+    pay_reconcile_checkpoint,
+    pay_spending,
+    pay_spending_policy,
 );
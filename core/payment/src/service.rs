@@ -36,21 +36,27 @@ impl Default for BindOptions {
     }
 }
 
-pub fn bind_service(db: &DbExecutor, processor: PaymentProcessor, opts: BindOptions) {
+/// Binds payment service and returns processor shared by its handlers.
+pub fn bind_service(
+    db: &DbExecutor,
+    processor: PaymentProcessor,
+    opts: BindOptions,
+) -> Arc<RwLock<PaymentProcessor>> {
     log::debug!("Binding payment service to service bus");
 
     let processor = Arc::new(RwLock::new(processor));
     local::bind_service(db, processor.clone());
-    public::bind_service(db, processor, opts);
+    public::bind_service(db, processor.clone(), opts);
 
     log::debug!("Successfully bound payment service to service bus");
+    processor
 }
 
 mod local {
     use super::*;
     use crate::dao::*;
+    use bigdecimal::{BigDecimal, Zero};
     use chrono::NaiveDateTime;
-    use std::str::FromStr;
     use std::sync::atomic::AtomicU64;
     use std::time::Duration;
     use std::{collections::BTreeMap, convert::TryInto};
@@ -78,6 +84,7 @@ mod local {
             .bind_with_processor(register_account)
             .bind_with_processor(unregister_account)
             .bind_with_processor(notify_payment)
+            .bind_with_processor(notify_payment_failed)
             .bind_with_processor(get_rpc_endpoints)
             .bind_with_processor(get_status)
             .bind_with_processor(get_invoice_stats)
@@ -85,6 +92,11 @@ mod local {
             .bind_with_processor(validate_allocation)
             .bind_with_processor(release_allocations)
             .bind_with_processor(get_drivers)
+            .bind_with_processor(get_spending_policy)
+            .bind_with_processor(set_spending_policy)
+            .bind_with_processor(clear_spending_policy)
+            .bind_with_processor(export_statement)
+            .bind_with_processor(reconcile)
             .bind_with_processor(payment_driver_status)
             .bind_with_processor(handle_status_change)
             .bind_with_processor(shut_down);
@@ -116,6 +128,9 @@ mod local {
         counter!("payment.invoices.provider.accepted", 0);
         counter!("payment.invoices.provider.accepted.call", 0);
         counter!("payment.invoices.requestor.not-enough-funds", 0);
+        counter!("payment.invoices.requestor.policy-violations", 0);
+        counter!("payment.orders.requestor.failed", 0);
+        counter!("payment.debit_notes.requestor.policy-violations", 0);
 
         counter!("payment.amount.received", 0, "platform" => "erc20-rinkeby-tglm");
         counter!("payment.amount.received", 0, "platform" => "erc20-mainnet-glm");
@@ -247,6 +262,37 @@ mod local {
         Ok(res?)
     }
 
+    async fn notify_payment_failed(
+        db: DbExecutor,
+        processor: Arc<RwLock<PaymentProcessor>>,
+        sender: String,
+        msg: NotifyPaymentFailed,
+    ) -> Result<(), GenericError> {
+        let orders = db
+            .as_dao::<OrderDao>()
+            .get_many(msg.order_ids, msg.driver.clone())
+            .await
+            .map_err(GenericError::new)?;
+        counter!("payment.orders.requestor.failed", orders.len() as u64);
+        for order in orders {
+            let title = match (&order.agreement_id, &order.activity_id) {
+                (Some(agreement_id), _) => format!("agreement [{agreement_id}]"),
+                (_, Some(activity_id)) => format!("activity [{activity_id}]"),
+                _ => "unknown title".to_string(),
+            };
+            log::error!(
+                "Driver [{}] failed to send payment of {} {} to {} for {}: {}",
+                msg.driver,
+                order.amount.0,
+                msg.platform,
+                order.payee_addr,
+                title,
+                msg.reason
+            );
+        }
+        Ok(())
+    }
+
     async fn get_rpc_endpoints(
         db: DbExecutor,
         processor: Arc<RwLock<PaymentProcessor>>,
@@ -262,12 +308,12 @@ mod local {
             no_wait,
         } = msg;
 
+        // Network name is validated against networks reported by the driver
         let (network2, network_details) = processor
             .read()
             .await
-            .get_network(driver.to_string(), network.as_ref().map(|s| s.to_string()))
+            .get_network(driver.to_string(), network.clone())
             .map_err(GenericError::new)?;
-        let network2 = NetworkName::from_str(&network2).map_err(GenericError::new)?;
 
         let token = network_details.default_token.clone();
         let platform = match network_details.tokens.get(&token) {
@@ -286,7 +332,7 @@ mod local {
             .get_rpc_endpoints_info(
                 platform,
                 address.to_string(),
-                network.as_ref().map(|s| s.to_string()),
+                network,
                 verify,
                 resolve,
                 no_wait,
@@ -457,6 +503,101 @@ mod local {
             .await?)
     }
 
+    async fn get_spending_policy(
+        db: DbExecutor,
+        processor: Arc<RwLock<PaymentProcessor>>,
+        _caller: String,
+        msg: GetSpendingPolicy,
+    ) -> Result<SpendingPolicyStatus, GenericError> {
+        let dao: SpendingPolicyDao = db.as_dao();
+        let policy = dao.get(msg.owner_id).await.map_err(GenericError::new)?;
+        let (daily_spent, monthly_spent) =
+            dao.spent(msg.owner_id).await.map_err(GenericError::new)?;
+        Ok(SpendingPolicyStatus {
+            policy,
+            daily_spent,
+            monthly_spent,
+        })
+    }
+
+    async fn set_spending_policy(
+        db: DbExecutor,
+        processor: Arc<RwLock<PaymentProcessor>>,
+        _caller: String,
+        msg: SetSpendingPolicy,
+    ) -> Result<(), GenericError> {
+        let policy = msg.policy;
+        let limits = [
+            &policy.daily_limit,
+            &policy.monthly_limit,
+            &policy.provider_daily_limit,
+            &policy.provider_monthly_limit,
+        ];
+        if limits
+            .iter()
+            .any(|limit| matches!(limit, Some(limit) if *limit < BigDecimal::zero()))
+        {
+            return Err(GenericError::new("Spending limits can't be negative"));
+        }
+        let ratios = [
+            policy.max_debit_note_price_ratio,
+            policy.max_invoice_price_ratio,
+        ];
+        if ratios
+            .iter()
+            .any(|ratio| matches!(ratio, Some(ratio) if *ratio <= 0.0))
+        {
+            return Err(GenericError::new(
+                "Maximum price ratios have to be positive",
+            ));
+        }
+
+        log::info!(
+            "Setting spending policy for [{}]: {:?}",
+            msg.owner_id,
+            policy
+        );
+        db.as_dao::<SpendingPolicyDao>()
+            .set(msg.owner_id, policy)
+            .await
+            .map_err(GenericError::new)
+    }
+
+    async fn clear_spending_policy(
+        db: DbExecutor,
+        processor: Arc<RwLock<PaymentProcessor>>,
+        _caller: String,
+        msg: ClearSpendingPolicy,
+    ) -> Result<(), GenericError> {
+        log::info!("Clearing spending policy for [{}]", msg.owner_id);
+        db.as_dao::<SpendingPolicyDao>()
+            .clear(msg.owner_id)
+            .await
+            .map_err(GenericError::new)
+    }
+
+    async fn export_statement(
+        db: DbExecutor,
+        processor: Arc<RwLock<PaymentProcessor>>,
+        _caller: String,
+        msg: ExportStatement,
+    ) -> Result<Vec<StatementEntry>, GenericError> {
+        crate::statement::build(&db, msg.owner_id, &msg.filter)
+            .await
+            .map_err(GenericError::new)
+    }
+
+    async fn reconcile(
+        db: DbExecutor,
+        processor: Arc<RwLock<PaymentProcessor>>,
+        _caller: String,
+        msg: Reconcile,
+    ) -> Result<ReconcileReport, GenericError> {
+        crate::reconcile::reconcile(&db, &processor, msg.owner_id, msg.options)
+            .await
+            .map_err(GenericError::new)
+    }
+
     async fn release_allocations(
         db: DbExecutor,
         processor: Arc<RwLock<PaymentProcessor>>,
@@ -847,6 +988,10 @@ mod public {
         }
 
         let node_id = *agreement.requestor_id();
+        let auto_accept = match *crate::DEBIT_NOTE_AUTO_ACCEPT {
+            true => Some((db.clone(), agreement.clone(), debit_note.clone())),
+            false => None,
+        };
         match async move {
             db.as_dao::<AgreementDao>()
                 .create_if_not_exists(agreement, node_id, Role::Requestor)
@@ -866,7 +1011,17 @@ mod public {
         }
         .await
         {
-            Ok(_) => Ok(Ack {}),
+            Ok(_) => {
+                if let Some((db, agreement, debit_note)) = auto_accept {
+                    tokio::task::spawn_local(crate::auto_accept::process_received(
+                        db,
+                        agreement,
+                        debit_note,
+                        *crate::DEBIT_NOTE_TOLERANCE,
+                    ));
+                }
+                Ok(Ack {})
+            }
             Err(DbError::Query(e)) => Err(SendError::BadRequest(e)),
             Err(e) => Err(SendError::ServiceError(e.to_string())),
         }
@@ -918,7 +1073,7 @@ mod public {
             _ => (),
         }
 
-        match dao.accept(debit_note_id.clone(), node_id).await {
+        match dao.accept(debit_note_id.clone(), node_id, None).await {
             Ok(_) => {
                 log::info!("Node [{sender_id}] accepted DebitNote [{debit_note_id}].");
                 counter!("payment.debit_notes.provider.accepted", 1);
@@ -931,10 +1086,57 @@ mod public {
 
     async fn reject_debit_note(
         db: DbExecutor,
-        sender: String,
+        sender_id: String,
         msg: RejectDebitNote,
     ) -> Result<Ack, AcceptRejectError> {
-        unimplemented!() // TODO
+        let debit_note_id = msg.debit_note_id;
+        let rejection = msg.rejection;
+        let owner_id = msg.issuer_id;
+
+        log::debug!(
+            "Got RejectDebitNote [{}] from Node [{}].",
+            debit_note_id,
+            sender_id
+        );
+        counter!("payment.debit_notes.provider.rejected.call", 1);
+
+        let dao: DebitNoteDao = db.as_dao();
+        let debit_note: DebitNote = match dao.get(debit_note_id.clone(), owner_id).await {
+            Ok(Some(debit_note)) => debit_note,
+            Ok(None) => return Err(AcceptRejectError::ObjectNotFound),
+            Err(e) => return Err(AcceptRejectError::ServiceError(e.to_string())),
+        };
+
+        if sender_id != debit_note.recipient_id.to_string() {
+            return Err(AcceptRejectError::Forbidden);
+        }
+
+        match debit_note.status {
+            status @ DocumentStatus::Accepted
+            | status @ DocumentStatus::Settled
+            | status @ DocumentStatus::Cancelled => {
+                return Err(AcceptRejectError::BadRequest(format!(
+                    "Cannot reject {status:?} debit note"
+                )));
+            }
+            DocumentStatus::Rejected => return Ok(Ack {}),
+            _ => (),
+        }
+
+        match dao.reject(debit_note_id.clone(), owner_id, rejection).await {
+            Ok(_) => {
+                log::info!(
+                    "Node [{}] rejected DebitNote [{}] for Activity [{}].",
+                    sender_id,
+                    debit_note_id,
+                    debit_note.activity_id
+                );
+                counter!("payment.debit_notes.provider.rejected", 1);
+                Ok(Ack {})
+            }
+            Err(DbError::Query(e)) => Err(AcceptRejectError::BadRequest(e)),
+            Err(e) => Err(AcceptRejectError::ServiceError(e.to_string())),
+        }
     }
 
     async fn cancel_debit_note(
@@ -1014,6 +1216,14 @@ mod public {
 
         let owner_id = *agreement.requestor_id();
         let sender_id = *agreement.provider_id();
+        // Provider sends invoice again, when it didn't learn about our decision.
+        let known_invoice = db
+            .as_dao::<InvoiceDao>()
+            .get(invoice_id.clone(), owner_id)
+            .await
+            .ok()
+            .flatten();
+        let decision_db = db.clone();
         match async move {
             db.as_dao::<AgreementDao>()
                 .create_if_not_exists(agreement, owner_id, Role::Requestor)
@@ -1040,12 +1250,103 @@ mod public {
         }
         .await
         {
-            Ok(_) => Ok(Ack {}),
+            Ok(_) => {
+                if let Some(invoice) = known_invoice {
+                    resend_invoice_decision(decision_db, invoice);
+                }
+                Ok(Ack {})
+            }
             Err(DbError::Query(e)) => Err(SendError::BadRequest(e)),
             Err(e) => Err(SendError::ServiceError(e.to_string())),
         }
     }
 
+    /// Delivers acceptance or rejection of the invoice to its issuer again.
+    /// Spawned, so the issuer gets reply to `SendInvoice` first.
+    fn resend_invoice_decision(db: DbExecutor, invoice: Invoice) {
+        if !matches!(
+            invoice.status,
+            DocumentStatus::Accepted | DocumentStatus::Settled | DocumentStatus::Rejected
+        ) {
+            return;
+        }
+        tokio::task::spawn_local(async move {
+            let invoice_id = invoice.invoice_id.clone();
+            let issuer_id = invoice.issuer_id;
+            let remote = ya_net::from(invoice.recipient_id)
+                .to(issuer_id)
+                .service(BUS_ID);
+            let result = match invoice.status {
+                DocumentStatus::Rejected => {
+                    let events = match db
+                        .as_dao::<InvoiceEventDao>()
+                        .get_for_invoice_id(
+                            invoice_id.clone(),
+                            None,
+                            None,
+                            None,
+                            vec!["REJECTED".into()],
+                            vec![],
+                        )
+                        .await
+                    {
+                        Ok(events) => events,
+                        Err(e) => {
+                            log::warn!("Failed to get rejection of invoice [{invoice_id}]: {e}");
+                            return;
+                        }
+                    };
+                    let rejection = match events.into_iter().last().map(|e| e.event_type) {
+                        Some(InvoiceEventType::InvoiceRejectedEvent { rejection }) => rejection,
+                        _ => return,
+                    };
+                    remote
+                        .call(RejectInvoiceV2 {
+                            invoice_id: invoice_id.clone(),
+                            rejection,
+                            issuer_id,
+                        })
+                        .await
+                }
+                _ => {
+                    let allocation_id = match db
+                        .as_dao::<OrderDao>()
+                        .get_invoice_allocation(invoice_id.clone(), invoice.recipient_id)
+                        .await
+                    {
+                        Ok(allocation_id) => allocation_id.unwrap_or_default(),
+                        Err(e) => {
+                            log::warn!("Failed to get allocation of invoice [{invoice_id}]: {e}");
+                            return;
+                        }
+                    };
+                    let acceptance = Acceptance {
+                        total_amount_accepted: invoice.amount,
+                        allocation_id,
+                    };
+                    remote
+                        .call(AcceptInvoice::new(
+                            invoice_id.clone(),
+                            acceptance,
+                            issuer_id,
+                        ))
+                        .await
+                }
+            };
+            match result {
+                Ok(Ok(_)) => log::info!(
+                    "Decision on invoice [{invoice_id}] sent again to node [{issuer_id}]."
+                ),
+                Ok(Err(e)) => log::warn!(
+                    "Node [{issuer_id}] didn't take decision on invoice [{invoice_id}]: {e}"
+                ),
+                Err(e) => log::warn!(
+                    "Failed to send decision on invoice [{invoice_id}] to node [{issuer_id}]: {e}"
+                ),
+            }
+        });
+    }
+
     async fn accept_invoice(
         db: DbExecutor,
         sender_id: String,
@@ -1092,7 +1393,7 @@ mod public {
             _ => (),
         }
 
-        match dao.accept(invoice_id.clone(), owner_id).await {
+        match dao.accept(invoice_id.clone(), owner_id, None).await {
             Ok(_) => {
                 log::info!(
                     "Node [{}] accepted invoice [{}] for Agreement [{}].",
@@ -0,0 +1,281 @@
+/*
+    Payment statements for accounting.
+
+    Statement is built from payments and invoices of the identity.
+    CSV columns are listed in `COLUMNS` and shouldn't be reordered or removed,
+    so exported files can be processed by external tools.
+*/
+// External crates
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use strum::{EnumString, EnumVariantNames};
+
+// Workspace uses
+use ya_client_model::payment::{InvoiceEventType, Payment};
+use ya_client_model::NodeId;
+use ya_core_model::payment::local::{
+    StatementEntry, StatementEntryKind, StatementFilter, StatementRole,
+};
+use ya_persistence::executor::DbExecutor;
+
+// Local uses
+use crate::dao::{ActivityDao, InvoiceDao, InvoiceEventDao, PaymentDao};
+use crate::error::DbResult;
+
+pub const COLUMNS: [&str; 13] = [
+    "kind",
+    "id",
+    "role",
+    "timestamp",
+    "agreement_id",
+    "activity_id",
+    "counterparty",
+    "platform",
+    "tx_hash",
+    "amount",
+    "status",
+    "accepted_at",
+    "settled_at",
+];
+
+#[derive(Clone, Copy, Debug, Deserialize, EnumString, EnumVariantNames)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum StatementFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Default)]
+struct InvoiceDates {
+    accepted_at: Option<DateTime<Utc>>,
+    settled_at: Option<DateTime<Utc>>,
+}
+
+fn is_selected(
+    filter: &StatementFilter,
+    role: StatementRole,
+    platform: &str,
+    timestamp: &DateTime<Utc>,
+) -> bool {
+    filter.role.map(|r| r == role).unwrap_or(true)
+        && filter
+            .platform
+            .as_deref()
+            .map(|p| p == platform)
+            .unwrap_or(true)
+        && filter
+            .before_timestamp
+            .map(|t| *timestamp < t)
+            .unwrap_or(true)
+}
+
+/// Transaction hash is stored by payment drivers as payment confirmation.
+fn payment_tx_hash(payment: &Payment) -> Option<String> {
+    base64::decode(&payment.details)
+        .ok()
+        .filter(|details| !details.is_empty())
+        .map(|details| format!("0x{}", hex::encode(details)))
+}
+
+/// Builds statement of payments and invoices of the identity, ordered by timestamp.
+pub async fn build(
+    db: &DbExecutor,
+    owner_id: NodeId,
+    filter: &StatementFilter,
+) -> DbResult<Vec<StatementEntry>> {
+    let after_timestamp = filter.after_timestamp.map(|t| t.naive_utc());
+    let payments = db
+        .as_dao::<PaymentDao>()
+        .get_for_node_id(owner_id, after_timestamp, None, None, None, None)
+        .await?;
+    let invoices = db
+        .as_dao::<InvoiceDao>()
+        .get_for_node_id(owner_id, after_timestamp, None)
+        .await?;
+    let events = vec!["ACCEPTED".into(), "SETTLED".into()];
+    let invoice_events = db
+        .as_dao::<InvoiceEventDao>()
+        .get_for_node_id(owner_id, None, None, None, events.clone(), events)
+        .await?;
+
+    let mut invoice_dates: HashMap<String, InvoiceDates> = HashMap::new();
+    for event in invoice_events {
+        let dates = invoice_dates.entry(event.invoice_id).or_default();
+        match event.event_type {
+            InvoiceEventType::InvoiceAcceptedEvent => dates.accepted_at = Some(event.event_date),
+            InvoiceEventType::InvoiceSettledEvent => dates.settled_at = Some(event.event_date),
+            _ => (),
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut activity_agreements: HashMap<String, Option<String>> = HashMap::new();
+
+    for payment in payments {
+        let (role, counterparty) = match payment.payer_id == owner_id {
+            true => (StatementRole::Requestor, payment.payee_id),
+            false => (StatementRole::Provider, payment.payer_id),
+        };
+        if !is_selected(filter, role, &payment.payment_platform, &payment.timestamp) {
+            continue;
+        }
+        let tx_hash = payment_tx_hash(&payment);
+        let entry =
+            |agreement_id: Option<String>, activity_id: Option<String>, amount| StatementEntry {
+                kind: StatementEntryKind::Payment,
+                id: payment.payment_id.clone(),
+                role,
+                timestamp: payment.timestamp,
+                agreement_id,
+                activity_id,
+                counterparty,
+                platform: payment.payment_platform.clone(),
+                tx_hash: tx_hash.clone(),
+                amount,
+                status: None,
+                accepted_at: None,
+                settled_at: Some(payment.timestamp),
+            };
+
+        for activity_payment in &payment.activity_payments {
+            let activity_id = activity_payment.activity_id.clone();
+            let agreement_id = match activity_agreements.get(&activity_id) {
+                Some(agreement_id) => agreement_id.clone(),
+                None => {
+                    let agreement_id = db
+                        .as_dao::<ActivityDao>()
+                        .get(activity_id.clone(), owner_id)
+                        .await?
+                        .map(|activity| activity.agreement_id);
+                    activity_agreements.insert(activity_id.clone(), agreement_id.clone());
+                    agreement_id
+                }
+            };
+            entries.push(entry(
+                agreement_id,
+                Some(activity_id),
+                activity_payment.amount.clone(),
+            ));
+        }
+        for agreement_payment in &payment.agreement_payments {
+            entries.push(entry(
+                Some(agreement_payment.agreement_id.clone()),
+                None,
+                agreement_payment.amount.clone(),
+            ));
+        }
+    }
+
+    for invoice in invoices {
+        let (role, counterparty) = match invoice.issuer_id == owner_id {
+            true => (StatementRole::Provider, invoice.recipient_id),
+            false => (StatementRole::Requestor, invoice.issuer_id),
+        };
+        if !is_selected(filter, role, &invoice.payment_platform, &invoice.timestamp) {
+            continue;
+        }
+        let dates = invoice_dates
+            .remove(&invoice.invoice_id)
+            .unwrap_or_default();
+        entries.push(StatementEntry {
+            kind: StatementEntryKind::Invoice,
+            id: invoice.invoice_id,
+            role,
+            timestamp: invoice.timestamp,
+            agreement_id: Some(invoice.agreement_id),
+            activity_id: match invoice.activity_ids.is_empty() {
+                true => None,
+                false => Some(invoice.activity_ids.join(";")),
+            },
+            counterparty,
+            platform: invoice.payment_platform,
+            tx_hash: None,
+            amount: invoice.amount,
+            status: Some(invoice.status.to_string()),
+            accepted_at: dates.accepted_at,
+            settled_at: dates.settled_at,
+        });
+    }
+
+    entries.sort_by_key(|entry| entry.timestamp);
+    Ok(entries)
+}
+
+fn escape(value: &str) -> Cow<str> {
+    if value.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Formats statement as CSV with header listing `COLUMNS`.
+pub fn to_csv(entries: &[StatementEntry]) -> String {
+    let format_date =
+        |date: &Option<DateTime<Utc>>| date.map(|d| d.to_rfc3339()).unwrap_or_default();
+
+    let mut csv = COLUMNS.join(",");
+    csv.push('\n');
+    for entry in entries {
+        let row = [
+            entry.kind.to_string(),
+            entry.id.clone(),
+            entry.role.to_string(),
+            entry.timestamp.to_rfc3339(),
+            entry.agreement_id.clone().unwrap_or_default(),
+            entry.activity_id.clone().unwrap_or_default(),
+            entry.counterparty.to_string(),
+            entry.platform.clone(),
+            entry.tx_hash.clone().unwrap_or_default(),
+            entry.amount.to_string(),
+            entry.status.clone().unwrap_or_default(),
+            format_date(&entry.accepted_at),
+            format_date(&entry.settled_at),
+        ];
+        let row: Vec<Cow<str>> = row.iter().map(|value| escape(value)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_to_csv() {
+        let entry = StatementEntry {
+            kind: StatementEntryKind::Invoice,
+            id: "invoice_id".to_string(),
+            role: StatementRole::Requestor,
+            timestamp: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            agreement_id: Some("agreement_id".to_string()),
+            activity_id: Some("a,\"b\"".to_string()),
+            counterparty: NodeId::default(),
+            platform: "erc20-holesky-tglm".to_string(),
+            tx_hash: None,
+            amount: bigdecimal::BigDecimal::from_str("1.5").unwrap(),
+            status: Some("ACCEPTED".to_string()),
+            accepted_at: Some(Utc.timestamp_opt(1_700_000_060, 0).unwrap()),
+            settled_at: None,
+        };
+
+        let csv = to_csv(&[entry]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            format!(
+                "invoice,invoice_id,requestor,2023-11-14T22:13:20+00:00,agreement_id,\"a,\"\"b\"\"\",{},\
+                erc20-holesky-tglm,,1.5,ACCEPTED,2023-11-14T22:14:20+00:00,",
+                NodeId::default()
+            )
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! Fixtures shared by unit tests.
+
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use ya_client_model::market::agreement::State;
+use ya_client_model::market::{Agreement, Demand, Offer};
+
+/// Approved Agreement with given Offer properties and empty Demand.
+pub fn mock_agreement(offer_properties: Value) -> Agreement {
+    let demand = Demand::new(
+        json!({}),
+        "()".to_string(),
+        "demand_id".to_string(),
+        Default::default(),
+        Default::default(),
+    );
+    let offer = Offer::new(
+        offer_properties,
+        "()".to_string(),
+        "offer_id".to_string(),
+        Default::default(),
+        Default::default(),
+    );
+    Agreement::new(
+        "agreement_id".to_string(),
+        demand,
+        offer,
+        Utc::now() + Duration::days(1),
+        State::Approved,
+        Utc::now(),
+    )
+}
@@ -2,9 +2,12 @@ use crate::error::{DbError, DbResult, Error, ExternalServiceError};
 use actix_web::HttpResponse;
 use futures::Future;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 use ya_client_model::market::{Agreement, Role};
+use ya_client_model::payment::DriverDetails;
 use ya_core_model::market;
+use ya_core_model::payment::local::{GetDrivers, BUS_ID as PAYMENT_BUS_ID};
 use ya_service_bus::{typed as bus, RpcEndpoint};
 
 pub fn fake_get_agreement(agreement_id: String, agreement: Agreement) {
@@ -112,6 +115,39 @@ pub mod provider {
     }
 }
 
+/// Network is supported, if any registered driver reports it. Drivers report
+/// built-in networks together with networks added by their chain registry.
+pub fn network_registered(network: &str, drivers: &HashMap<String, DriverDetails>) -> bool {
+    drivers
+        .values()
+        .any(|details| details.networks.contains_key(network))
+}
+
+pub async fn validate_network(network: &str) -> anyhow::Result<String> {
+    // Unwrap is provably safe because NoError can't be instanciated
+    let drivers = bus::service(PAYMENT_BUS_ID)
+        .call(GetDrivers {})
+        .await?
+        .unwrap();
+    match network_registered(network, &drivers) {
+        true => Ok(network.to_string()),
+        false => anyhow::bail!("Network {} isn't supported by any payment driver", network),
+    }
+}
+
+/// Driver is supported, if it's registered, either built-in or external one.
+pub async fn validate_driver(driver: &str) -> anyhow::Result<String> {
+    // Unwrap is provably safe because NoError can't be instanciated
+    let drivers = bus::service(PAYMENT_BUS_ID)
+        .call(GetDrivers {})
+        .await?
+        .unwrap();
+    match drivers.contains_key(driver) {
+        true => Ok(driver.to_string()),
+        false => anyhow::bail!("Payment driver {} isn't registered", driver),
+    }
+}
+
 pub async fn with_timeout<Work: Future<Output = HttpResponse>>(
     timeout_secs: impl Into<f64>,
     work: Work,
@@ -196,9 +196,17 @@ impl ServiceContext {
         Ok((TypeId::of::<S>(), DbExecutor::from_data_dir(path, name)?))
     }
 
-    fn make_mixed_entry<S: 'static>(path: &Path, name: &str) -> Result<(TypeId, DbMixedExecutor)> {
+    fn make_mixed_entry<S: 'static>(
+        path: &Path,
+        name: &str,
+        persistent: bool,
+    ) -> Result<(TypeId, DbMixedExecutor)> {
         let disk_db = DbExecutor::from_data_dir(path, name)?;
-        let ram_db = DbExecutor::in_memory(name)?;
+        let ram_db = if persistent {
+            DbExecutor::from_data_dir(path, &format!("{}-state", name))?
+        } else {
+            DbExecutor::in_memory(name)?
+        };
 
         Ok((TypeId::of::<S>(), DbMixedExecutor::new(disk_db, ram_db)))
     }
@@ -222,7 +230,11 @@ impl TryFrom<CliCtx> for ServiceContext {
         .cloned()
         .collect();
 
-        let market_db = Self::make_mixed_entry::<MarketService>(&ctx.data_dir, "market")?;
+        let market_db = Self::make_mixed_entry::<MarketService>(
+            &ctx.data_dir,
+            "market",
+            MarketService::persistent_state()?,
+        )?;
         let mixed_dbs = [market_db.clone()].iter().cloned().collect();
         let activity_tracker = TrackerRef::create();
 
@@ -267,15 +279,22 @@ enum Services {
     GsbApi(GsbApiService),
 }
 
-#[cfg(not(any(feature = "dummy-driver", feature = "erc20-driver",)))]
-compile_error!("At least one payment driver needs to be enabled in order to make payments.");
-
-async fn start_payment_drivers(data_dir: &Path) -> anyhow::Result<Vec<String>> {
-    let mut drivers = vec![];
+/// Starts drivers compiled into yagna. External drivers are started as extensions
+/// and register in the payment service on their own.
+#[cfg_attr(
+    not(any(feature = "dummy-driver", feature = "erc20-driver")),
+    allow(unused_variables)
+)]
+async fn start_payment_drivers(
+    data_dir: &Path,
+    #[cfg(feature = "dummy-driver")] dummy_driver: ya_dummy_driver::DummyDriverConfig,
+) -> anyhow::Result<Vec<String>> {
+    #[allow(unused_mut)]
+    let mut drivers: Vec<String> = vec![];
     #[cfg(feature = "dummy-driver")]
     {
         use ya_dummy_driver::{PaymentDriverService, DRIVER_NAME};
-        PaymentDriverService::gsb(&()).await?;
+        PaymentDriverService::gsb(data_dir.to_path_buf(), dummy_driver).await?;
         drivers.push(DRIVER_NAME.to_owned());
     }
     #[cfg(feature = "erc20-driver")]
@@ -284,6 +303,12 @@ async fn start_payment_drivers(data_dir: &Path) -> anyhow::Result<Vec<String>> {
         PaymentDriverService::gsb(data_dir.to_path_buf()).await?;
         drivers.push(DRIVER_NAME.to_owned());
     }
+    if drivers.is_empty() {
+        log::warn!(
+            "No payment drivers compiled in. Payments require an external driver \
+            registered with `yagna extension register`."
+        );
+    }
     Ok(drivers)
 }
 
@@ -440,6 +465,10 @@ struct ServiceCommandOpts {
 
     #[structopt(flatten)]
     cors: CorsConfig,
+
+    #[cfg(feature = "dummy-driver")]
+    #[structopt(flatten)]
+    dummy_driver: ya_dummy_driver::DummyDriverConfig,
 }
 
 #[cfg(unix)]
@@ -483,6 +512,8 @@ impl ServiceCommand {
                 log_dir,
                 debug,
                 cors,
+                #[cfg(feature = "dummy-driver")]
+                dummy_driver,
             }) => {
                 let is_rust_log_default =
                     env::var("RUST_LOG").map(|s| s.is_empty()).unwrap_or(true);
@@ -550,7 +581,12 @@ impl ServiceCommand {
 
                 ya_compile_time_utils::report_version_to_metrics();
 
-                start_payment_drivers(&ctx.data_dir).await?;
+                start_payment_drivers(
+                    &ctx.data_dir,
+                    #[cfg(feature = "dummy-driver")]
+                    dummy_driver.clone(),
+                )
+                .await?;
 
                 let api_host_port = rest_api_host_port(api_url.clone());
                 let rest_address = api_host_port.clone();
@@ -1,6 +1,6 @@
 use std::fmt::{self, Formatter};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Constraints {
     pub constraints: Vec<ConstraintExpr>,
     pub operator: ClauseOperator,
@@ -71,6 +71,8 @@ impl Constraints {
 impl fmt::Display for Constraints {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self.constraints.len() {
+            // Empty OR is never satisfied, while empty AND is neutral and can be omitted.
+            0 if self.operator == ClauseOperator::Or => write!(f, "(|)"),
             0 => Ok(()),
             1 => write!(f, "{}", self.constraints[0]),
             _ => {
@@ -93,7 +95,7 @@ impl std::iter::IntoIterator for Constraints {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ClauseOperator {
     And,
     Or,
@@ -112,7 +114,7 @@ impl fmt::Display for ClauseOperator {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ConstraintOperator {
     Equal,
     NotEqual,
@@ -135,7 +137,7 @@ impl fmt::Display for ConstraintOperator {
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ConstraintKey(serde_json::Value);
 
 impl ConstraintKey {
@@ -153,7 +155,7 @@ impl<T: AsRef<str>> From<T> for ConstraintKey {
 pub type ConstraintValue = ConstraintKey;
 
 /* expression, e.g. key > value */
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ConstraintExpr {
     KeyValue {
         /* ops_values length is 0 or 1 now, but it's ready for expressions like k: > v1, < v2 */